@@ -0,0 +1,22 @@
+//! Sets `GIT_COMMIT_HASH` to the commit the node is built from, reported by `era_getNodeInfo`. The variable may also
+//! be set when building outside of a git checkout, e.g. from a source archive.
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT_HASH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    if std::env::var("GIT_COMMIT_HASH").is_ok() {
+        return;
+    }
+
+    let commit = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=GIT_COMMIT_HASH={}", commit.trim());
+    }
+}
@@ -0,0 +1,71 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use jsonrpc_http_server::hyper::{header::AUTHORIZATION, Body, Request};
+
+/// Returns true if the request is authenticated with the given token, sent either as a bearer token
+/// (`Authorization: Bearer <token>`) or as the password of the basic authentication (with any username),
+/// so the node may also be used via URLs like `http://user:<token>@localhost:8011`.
+pub fn is_authorized(request: &Request<Body>, token: &str) -> bool {
+    is_authorized_header(
+        request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok()),
+        token,
+    )
+}
+
+/// Returns true if the value of the `Authorization` header holds the given token, see [is_authorized].
+pub fn is_authorized_header(header: Option<&str>, token: &str) -> bool {
+    let header = match header {
+        Some(header) => header.trim(),
+        None => return false,
+    };
+
+    if let Some(bearer) = header.strip_prefix("Bearer ") {
+        return bearer.trim() == token;
+    }
+    if let Some(basic) = header.strip_prefix("Basic ") {
+        return STANDARD
+            .decode(basic.trim())
+            .ok()
+            .and_then(|credentials| String::from_utf8(credentials).ok())
+            .and_then(|credentials| {
+                credentials
+                    .split_once(':')
+                    .map(|(_, password)| password == token)
+            })
+            .unwrap_or(false);
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(authorization: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri("/");
+        if let Some(authorization) = authorization {
+            builder = builder.header(AUTHORIZATION, authorization);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn test_is_authorized() {
+        assert!(is_authorized(&request(Some("Bearer secret")), "secret"));
+        assert!(is_authorized(
+            &request(Some(&format!("Basic {}", STANDARD.encode("user:secret")))),
+            "secret"
+        ));
+
+        assert!(!is_authorized(&request(None), "secret"));
+        assert!(!is_authorized(&request(Some("Bearer wrong")), "secret"));
+        assert!(!is_authorized(
+            &request(Some(&format!("Basic {}", STANDARD.encode("secret:wrong")))),
+            "secret"
+        ));
+        assert!(!is_authorized(&request(Some("Basic not-base64")), "secret"));
+    }
+}
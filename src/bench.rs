@@ -0,0 +1,186 @@
+//! Benchmark of the node, run by the `bench` command: floods an in-process node with synthetic transactions
+//! and reports the throughput, the latency percentiles and the time spent in the VM, so that performance
+//! regressions between releases are measurable.
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use multivm::interface::TxExecutionMode;
+use serde::Serialize;
+use zksync_basic_types::{web3::signing::keccak256, Address, Nonce, H256, U256};
+use zksync_types::{fee::Fee, l2::L2Tx, PackedEthSignature, CONTRACT_DEPLOYER_ADDRESS};
+use zksync_utils::bytecode::hash_bytecode;
+
+use crate::{
+    deps::system_contracts::bytecode_from_slice,
+    fork::ForkSource,
+    metrics::{percentile, METRICS},
+    node::{InMemoryNode, L2_GAS_PRICE},
+};
+
+/// Gas limit of the synthetic transactions.
+const BENCH_GAS_LIMIT: u64 = 50_000_000;
+
+/// Workload of the benchmark. The transactions are sent round-robin from `accounts` funded accounts, and
+/// each one is executed in its own block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchConfig {
+    /// Number of ETH transfers to random addresses.
+    pub transfers: usize,
+    /// Number of deployments of an empty contract.
+    pub deploys: usize,
+    /// Number of the sending accounts.
+    pub accounts: usize,
+}
+
+/// Results of the benchmark.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchReport {
+    pub transactions: usize,
+    /// Transactions rejected by the node, e.g. because they halted.
+    pub failed: usize,
+    pub duration_ms: f64,
+    /// Transactions per second.
+    pub tps: f64,
+    /// Latency percentiles of a single transaction, from its submission until its block is sealed.
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    /// Time spent executing the transactions in the VM.
+    pub vm_ms: f64,
+    /// Time spent outside of the VM, e.g. validating the transactions and sealing the blocks.
+    pub other_ms: f64,
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Runs the benchmark workload against the node.
+    pub fn run_bench(&self, config: &BenchConfig) -> anyhow::Result<BenchReport> {
+        let chain_id = self
+            .get_inner()
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?
+            .fork_storage
+            .chain_id;
+        let mut accounts = (1..=config.accounts.max(1) as u64)
+            .map(|index| {
+                let private_key = H256::from_low_u64_be(index);
+                let address = PackedEthSignature::address_from_private_key(&private_key)
+                    .map_err(|err| anyhow!("invalid private key: {:?}", err))?;
+                self.set_rich_account(address);
+                Ok((private_key, Nonce(0)))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let bytecode = bytecode_from_slice(
+            "EmptyContract",
+            include_bytes!("deps/contracts/EmptyContract.json"),
+        );
+        let deploy_calldata = [
+            keccak256(b"create(bytes32,bytes32,bytes)")[..4].to_vec(),
+            ethabi::encode(&[
+                ethabi::Token::FixedBytes(vec![0; 32]),
+                ethabi::Token::FixedBytes(hash_bytecode(&bytecode).as_bytes().to_vec()),
+                ethabi::Token::Bytes(vec![]),
+            ]),
+        ]
+        .concat();
+
+        let total = config.transfers + config.deploys;
+        let vm_time_before = METRICS.vm_execution_time();
+        let started_at = Instant::now();
+        let mut latencies = Vec::with_capacity(total);
+        let mut failed = 0;
+        for index in 0..total {
+            let (private_key, nonce) = &mut accounts[index % config.accounts.max(1)];
+            let (to, value, calldata, factory_deps) = if index < config.transfers {
+                (Address::random(), U256::one(), vec![], None)
+            } else {
+                (
+                    CONTRACT_DEPLOYER_ADDRESS,
+                    U256::zero(),
+                    deploy_calldata.clone(),
+                    Some(vec![bytecode.clone()]),
+                )
+            };
+            let tx = L2Tx::new_signed(
+                to,
+                calldata,
+                *nonce,
+                Fee {
+                    gas_limit: U256::from(BENCH_GAS_LIMIT),
+                    max_fee_per_gas: U256::from(L2_GAS_PRICE),
+                    max_priority_fee_per_gas: U256::zero(),
+                    gas_per_pubdata_limit: U256::from(50_000),
+                },
+                value,
+                chain_id,
+                private_key,
+                factory_deps,
+                Default::default(),
+            )
+            .map_err(|err| anyhow!("failed signing transaction: {:?}", err))?;
+
+            let tx_started_at = Instant::now();
+            match self.run_l2_tx(tx, TxExecutionMode::VerifyExecute) {
+                Ok(()) => *nonce = Nonce(nonce.0 + 1),
+                Err(err) => {
+                    tracing::warn!("benchmark transaction #{} failed: {}", index, err);
+                    failed += 1;
+                }
+            }
+            latencies.push(millis(tx_started_at.elapsed()));
+        }
+        let duration = started_at.elapsed();
+        let vm_time = METRICS
+            .vm_execution_time()
+            .saturating_sub(vm_time_before)
+            .min(duration);
+
+        latencies.sort_by(|a, b| a.total_cmp(b));
+        Ok(BenchReport {
+            transactions: total,
+            failed,
+            duration_ms: millis(duration),
+            tps: if duration.is_zero() {
+                0.0
+            } else {
+                total as f64 / duration.as_secs_f64()
+            },
+            p50_ms: percentile(&latencies, 50),
+            p90_ms: percentile(&latencies, 90),
+            p99_ms: percentile(&latencies, 99),
+            max_ms: latencies.last().copied().unwrap_or_default(),
+            vm_ms: millis(vm_time),
+            other_ms: millis(duration - vm_time),
+        })
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+
+    #[test]
+    fn test_bench_executes_transfers_and_deploys() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let report = node
+            .run_bench(&BenchConfig {
+                transfers: 4,
+                deploys: 2,
+                accounts: 2,
+            })
+            .expect("failed running benchmark");
+
+        assert_eq!(6, report.transactions);
+        assert_eq!(0, report.failed);
+        assert!(report.p50_ms <= report.max_ms);
+        assert!(report.vm_ms <= report.duration_ms);
+    }
+}
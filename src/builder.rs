@@ -0,0 +1,369 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    ops::Deref,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use anyhow::anyhow;
+use tracing_subscriber::filter::LevelFilter;
+use zksync_basic_types::{Address, H256};
+use zksync_types::transaction_request::CallRequest;
+
+use crate::{
+    cache::CacheConfig,
+    fork::ForkDetails,
+    http_fork_source::HttpForkSource,
+    multiplex::{serve_multiplexed, MultiplexTargets, RouterHandle},
+    node::{InMemoryNode, InMemoryNodeConfig, NodeHook},
+    rpc_filter::RpcFilterMiddleware,
+    server::{build_json_http, build_json_ws, RpcMethod, ServerHandle, WsServerHandle},
+    ws_connections::WsConfig,
+};
+
+/// Builder of a [Node] running in-process, e.g. to drive it from Rust integration tests.
+///
+/// Example:
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// let node = era_test_node::Node::builder()
+///     .fork("testnet")
+///     .port(8012)
+///     .spawn()
+///     .await?;
+/// node.mine_blocks(None, None)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct NodeBuilder {
+    config: InMemoryNodeConfig,
+    fork: Option<String>,
+    fork_at: Option<u64>,
+    port: Option<u16>,
+    rich_accounts: Vec<Address>,
+    hooks: Vec<Arc<dyn NodeHook>>,
+    extensions: Vec<RpcMethod>,
+}
+
+impl NodeBuilder {
+    /// Sets the configuration of the node.
+    pub fn config(mut self, config: InMemoryNodeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Forks the latest block of the network, either a known network name (`mainnet`, `testnet`, ...) or an URL.
+    pub fn fork(mut self, network: &str) -> Self {
+        self.fork = Some(network.to_string());
+        self
+    }
+
+    /// Forks the network at the given block.
+    pub fn fork_at(mut self, network: &str, block: u64) -> Self {
+        self.fork = Some(network.to_string());
+        self.fork_at = Some(block);
+        self
+    }
+
+    /// Serves the JSON-RPC API at `127.0.0.1:<port>`, over HTTP and WebSocket. By default the node is only
+    /// reachable in-process.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Funds the account with a large amount of ETH.
+    pub fn rich_account(mut self, address: Address) -> Self {
+        self.rich_accounts.push(address);
+        self
+    }
+
+    /// Serves additional RPC methods next to the built-in namespaces, e.g. a project-specific namespace
+    /// via `builder.extend_with(MyNamespaceT::to_delegate(handler))`. Only used if a port is set, and served over
+    /// both HTTP and WebSocket.
+    pub fn extend_with<F>(mut self, methods: F) -> Self
+    where
+        F: IntoIterator<Item = RpcMethod>,
+    {
+        self.extensions.extend(methods);
+        self
+    }
+
+    /// Registers a hook called on the transaction lifecycle events.
+    pub fn hook(mut self, hook: Arc<dyn NodeHook>) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    /// Creates the node and, if a port was set, starts serving its JSON-RPC API. Fails if the server can't be
+    /// started, e.g. as the port is in use.
+    pub async fn spawn(self) -> anyhow::Result<Node> {
+        let fork = match &self.fork {
+            Some(network) => {
+                Some(ForkDetails::from_network(network, self.fork_at, CacheConfig::Memory).await)
+            }
+            None => None,
+        };
+        let node = InMemoryNode::new(fork, None, self.config);
+        for address in self.rich_accounts {
+            node.set_rich_account(address);
+        }
+        for hook in self.hooks {
+            node.add_hook(hook);
+        }
+
+        let server = match self.port {
+            Some(port) => Some(Arc::new(
+                NodeServer::start(node.clone(), port, self.extensions).await?,
+            )),
+            None => None,
+        };
+
+        Ok(Node { node, server })
+    }
+
+    /// Creates `instances` isolated nodes with the same configuration, e.g. one per parallel test shard.
+    /// If a port was set, the instances serve their JSON-RPC API on sequential ports starting with it.
+    pub async fn spawn_pool(self, instances: u16) -> anyhow::Result<Vec<Node>> {
+        let mut nodes = vec![];
+        for index in 0..instances {
+            let port = self
+                .port
+                .map(|port| {
+                    port.checked_add(index)
+                        .ok_or_else(|| anyhow!("no port available for instance #{}", index))
+                })
+                .transpose()?;
+            let builder = NodeBuilder {
+                config: self.config.clone(),
+                fork: self.fork.clone(),
+                fork_at: self.fork_at,
+                port,
+                rich_accounts: self.rich_accounts.clone(),
+                hooks: self.hooks.clone(),
+                extensions: self.extensions.clone(),
+            };
+            nodes.push(builder.spawn().await?);
+        }
+
+        Ok(nodes)
+    }
+}
+
+/// Handle of a node running in-process. Dereferences to the [InMemoryNode], so its state can be queried
+/// and modified directly (e.g. via `mine_blocks`, `set_balance` or the [crate::namespaces::EthNamespaceT]
+/// methods).
+#[derive(Clone)]
+pub struct Node {
+    node: InMemoryNode<HttpForkSource>,
+    server: Option<Arc<NodeServer>>,
+}
+
+/// JSON-RPC servers of a [Node], closed once the last clone of the node is dropped. The HTTP and WebSocket servers
+/// listen on the loopback, behind the router of the port.
+struct NodeServer {
+    http: ServerHandle,
+    ws: WsServerHandle,
+    router: RouterHandle,
+    url: String,
+}
+
+impl NodeServer {
+    async fn start(
+        node: InMemoryNode<HttpForkSource>,
+        port: u16,
+        extensions: Vec<RpcMethod>,
+    ) -> anyhow::Result<Self> {
+        let loopback = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        let http = build_json_http(
+            loopback,
+            LevelFilter::INFO,
+            false,
+            RpcFilterMiddleware::default(),
+            None,
+            node.clone(),
+            vec![],
+            extensions.clone(),
+            Arc::new(AtomicBool::new(true)),
+            false,
+            false,
+        )
+        .await?;
+        let ws = build_json_ws(
+            loopback,
+            LevelFilter::INFO,
+            false,
+            RpcFilterMiddleware::default(),
+            None,
+            node,
+            vec![],
+            extensions,
+            WsConfig::default(),
+        )?;
+        let router = match serve_multiplexed(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port),
+            MultiplexTargets {
+                http: http.addr,
+                ws: ws.addr,
+            },
+        )
+        .await
+        {
+            Ok(router) => router,
+            Err(err) => {
+                http.close();
+                ws.close();
+                return Err(err);
+            }
+        };
+
+        Ok(Self {
+            http,
+            ws,
+            router,
+            url: format!("http://127.0.0.1:{}", port),
+        })
+    }
+}
+
+impl Drop for NodeServer {
+    fn drop(&mut self) {
+        self.router.close();
+        self.http.close();
+        self.ws.close();
+    }
+}
+
+impl Node {
+    pub fn builder() -> NodeBuilder {
+        NodeBuilder::default()
+    }
+
+    /// Returns the URL of the JSON-RPC API, if it is served.
+    pub fn url(&self) -> Option<&str> {
+        self.server.as_deref().map(|server| server.url.as_str())
+    }
+
+    /// Sends an unsigned transaction on behalf of its `from` account, filling in the missing nonce,
+    /// gas price and gas limit.
+    pub async fn send_transaction(&self, request: CallRequest) -> anyhow::Result<H256> {
+        self.node
+            .send_impersonated_request(request)
+            .await?
+            .map_err(|err| anyhow!("failed sending transaction: {}", err.message))
+    }
+}
+
+impl Deref for Node {
+    type Target = InMemoryNode<HttpForkSource>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{logging_middleware::Meta, namespaces::EthNamespaceT};
+    use jsonrpc_core::{BoxFuture, IoDelegate, Params, Value};
+    use zksync_basic_types::U256;
+    use zksync_types::transaction_request::CallRequestBuilder;
+
+    #[tokio::test]
+    async fn test_spawned_node_executes_transactions() {
+        let from = Address::repeat_byte(0x1);
+        let to = Address::repeat_byte(0x2);
+        let node = Node::builder()
+            .rich_account(from)
+            .spawn()
+            .await
+            .expect("failed spawning node");
+
+        node.send_transaction(
+            CallRequestBuilder::default()
+                .from(from)
+                .to(to)
+                .value(U256::from(100))
+                .build(),
+        )
+        .await
+        .expect("failed sending transaction");
+        let block_number = node.get_block_number().await.unwrap();
+        node.mine_blocks(Some(2.into()), None)
+            .expect("failed mining blocks");
+
+        assert_eq!(None, node.url());
+        assert_eq!(U256::from(100), node.get_balance(to, None).await.unwrap());
+        assert_eq!(block_number + 2, node.get_block_number().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_spawned_pool_instances_are_isolated() {
+        let address = Address::repeat_byte(0x1);
+        let nodes = Node::builder()
+            .spawn_pool(2)
+            .await
+            .expect("failed spawning pool");
+
+        nodes[0]
+            .set_balance(address, U256::from(100))
+            .expect("failed setting balance");
+
+        assert_eq!(2, nodes.len());
+        assert_eq!(
+            U256::from(100),
+            nodes[0].get_balance(address, None).await.unwrap()
+        );
+        assert_eq!(
+            U256::zero(),
+            nodes[1].get_balance(address, None).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spawned_node_serves_extensions() {
+        let mut delegate = IoDelegate::<(), Meta>::new(Arc::new(()));
+        delegate.add_method(
+            "custom_hello",
+            |_: &(), _: Params| -> BoxFuture<jsonrpc_core::Result<Value>> {
+                Box::pin(futures::future::ready(Ok(Value::String("hello".into()))))
+            },
+        );
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+        let node = Node::builder()
+            .port(port)
+            .extend_with(delegate)
+            .spawn()
+            .await
+            .expect("failed spawning node");
+
+        let response = reqwest::Client::new()
+            .post(node.url().expect("missing url"))
+            .header("content-type", "application/json")
+            .body(r#"{"jsonrpc":"2.0","method":"custom_hello","params":[],"id":1}"#)
+            .send()
+            .await
+            .expect("failed sending request")
+            .text()
+            .await
+            .expect("failed reading response");
+
+        assert_eq!(
+            r#"{"jsonrpc":"2.0","result":"hello","id":1}"#,
+            response.trim()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spawn_fails_on_port_in_use() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let result = Node::builder().port(port).spawn().await;
+
+        assert!(result.is_err());
+    }
+}
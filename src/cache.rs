@@ -1,14 +1,19 @@
+use crate::metrics::METRICS;
 use rustc_hash::FxHashMap;
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fs;
 use std::fs::File;
+use std::hash::Hash;
 use std::io::{BufReader, BufWriter};
-use std::path::Path;
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
 use std::result::Result;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use zksync_basic_types::H256;
 use zksync_types::api::{Block, BridgeAddresses, Transaction, TransactionVariant};
-use zksync_types::Transaction as RawTransaction;
+use zksync_types::{StorageKey, Transaction as RawTransaction};
 
 /// Caches full blocks by their hashes
 const CACHE_TYPE_BLOCKS_FULL: &str = "blocks_full";
@@ -21,8 +26,27 @@ const CACHE_TYPE_TRANSACTIONS: &str = "transactions";
 /// Caches arbitrary values by their keys
 const CACHE_TYPE_KEY_VALUE: &str = "key_value";
 
+/// Holds the storage values and factory deps evicted from the memory of the fork storage
+const CACHE_TYPE_FORK_DATA: &str = "fork_data";
+/// Storage values in the fork data directory of a block, by their addresses and keys
+const FORK_DATA_STORAGE_VALUES: &str = "storage_values";
+/// Factory deps in the fork data directory of a block, by their hashes
+const FORK_DATA_FACTORY_DEPS: &str = "factory_deps";
+
 /// Caching key for bridge addresses
 const CACHE_KEY_BRIDGE_ADDRESSES: &str = "bridge_addresses";
+/// Caches confirmed tokens, in memory only
+const CACHE_TYPE_CONFIRMED_TOKENS: &str = "confirmed_tokens";
+
+/// Names of the caches, as reported by `era_getCacheStats` and accepted by `era_clearCache`.
+pub(crate) const CACHE_KINDS: [&str; 6] = [
+    CACHE_TYPE_BLOCKS_FULL,
+    CACHE_TYPE_BLOCKS_MIN,
+    CACHE_TYPE_BLOCK_RAW_TRANSACTIONS,
+    CACHE_TYPE_TRANSACTIONS,
+    CACHE_KEY_BRIDGE_ADDRESSES,
+    CACHE_TYPE_CONFIRMED_TOKENS,
+];
 
 /// Cache configuration. Can be one of:
 ///
@@ -40,25 +64,268 @@ pub enum CacheConfig {
     },
 }
 
+/// Clock shared by the [LruCache]s, so that the entries of different caches can be compared by their last use.
+static LRU_CLOCK: AtomicU64 = AtomicU64::new(0);
+
+/// Estimates the memory used by an entry of an [LruCache] from the sizes of its key and value types, counting the
+/// key twice as it's also kept in the usage order.
+pub fn entry_memory<K, V>(_key: &K, _value: &V) -> usize {
+    2 * (size_of::<K>() + size_of::<u64>()) + size_of::<V>()
+}
+
+/// Map that holds at most `max_size` entries. Inserting into a full map evicts the least recently used entry.
+/// The number of entries and their estimated memory are reported to the [METRICS] under the cache `name`.
+#[derive(Debug, Clone)]
+pub struct LruCache<K: Eq + Hash, V> {
+    name: &'static str,
+    /// The cached values, along with the tick of the [LRU_CLOCK] at which they were last used.
+    entries: FxHashMap<K, (V, u64)>,
+    /// The cached keys, ordered by the tick at which they were last used.
+    recently_used: BTreeMap<u64, K>,
+    max_size: Option<usize>,
+    /// Estimates the memory used by an entry, [entry_memory] by default.
+    weigher: fn(&K, &V) -> usize,
+    memory_usage: usize,
+}
+
+impl<K: Eq + Hash + Clone, V> LruCache<K, V> {
+    /// Creates a new cache that holds at most `max_size` entries, unbounded if `None`.
+    pub fn new(name: &'static str, max_size: Option<usize>) -> Self {
+        Self {
+            name,
+            entries: Default::default(),
+            recently_used: Default::default(),
+            max_size,
+            weigher: entry_memory::<K, V>,
+            memory_usage: 0,
+        }
+    }
+
+    /// Sets how the memory used by an entry is estimated, e.g. to count the bytes the value holds on the heap.
+    pub fn with_weigher(mut self, weigher: fn(&K, &V) -> usize) -> Self {
+        self.weigher = weigher;
+        self
+    }
+
+    /// Returns the value cached for the key, marking it as the most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let tick = next_tick();
+        let (value, last_used) = self.entries.get_mut(key)?;
+        let key = self
+            .recently_used
+            .remove(last_used)
+            .expect("cache entry missing from usage order");
+        self.recently_used.insert(tick, key);
+        *last_used = tick;
+        Some(value)
+    }
+
+    /// Caches the value for the key, evicting the least recently used entries if the cache is full.
+    ///
+    /// # Returns
+    /// The evicted entries.
+    pub fn insert(&mut self, key: K, value: V) -> Vec<(K, V)> {
+        let tick = next_tick();
+        self.memory_usage += (self.weigher)(&key, &value);
+        if let Some((value, last_used)) = self.entries.insert(key.clone(), (value, tick)) {
+            self.recently_used.remove(&last_used);
+            self.memory_usage -= (self.weigher)(&key, &value);
+        }
+        self.recently_used.insert(tick, key);
+        let evicted = self.evict();
+        self.report();
+        evicted
+    }
+
+    /// Sets the maximum number of entries, evicting the least recently used entries that don't fit.
+    pub fn set_max_size(&mut self, max_size: Option<usize>) {
+        self.max_size = max_size;
+        self.evict();
+        self.report();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the estimated memory used by the entries, in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.memory_usage
+    }
+
+    /// Returns the tick of the [LRU_CLOCK] at which the least recently used entry was last used, to compare it
+    /// with the entries of other caches.
+    pub fn least_recent_use(&self) -> Option<u64> {
+        self.recently_used.keys().next().copied()
+    }
+
+    /// Removes the least recently used entry and returns it.
+    pub fn pop_least_recently_used(&mut self) -> Option<(K, V)> {
+        let (_, key) = self.recently_used.pop_first()?;
+        let (value, _) = self.entries.remove(&key)?;
+        self.memory_usage -= (self.weigher)(&key, &value);
+        METRICS.observe_cache_evictions(self.name, 1);
+        self.report();
+        Some((key, value))
+    }
+
+    /// Returns the cached entries, from the most recently used, without marking them as used.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.recently_used
+            .values()
+            .rev()
+            .map(move |key| (key, &self.entries[key].0))
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recently_used.clear();
+        self.memory_usage = 0;
+        self.report();
+    }
+
+    fn evict(&mut self) -> Vec<(K, V)> {
+        let max_size = match self.max_size {
+            Some(max_size) => max_size,
+            None => return vec![],
+        };
+        let mut evicted = vec![];
+        while self.entries.len() > max_size {
+            let key = match self.recently_used.pop_first() {
+                Some((_, key)) => key,
+                None => break,
+            };
+            if let Some((value, _)) = self.entries.remove(&key) {
+                self.memory_usage -= (self.weigher)(&key, &value);
+                evicted.push((key, value));
+            }
+        }
+        METRICS.observe_cache_evictions(self.name, evicted.len());
+        evicted
+    }
+
+    fn report(&self) {
+        METRICS.set_cache_entries(self.name, self.entries.len());
+        METRICS.set_cache_memory(self.name, self.memory_usage);
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq> PartialEq for LruCache<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.entries == other.entries
+            && self.recently_used == other.recently_used
+            && self.max_size == other.max_size
+            && self.memory_usage == other.memory_usage
+    }
+}
+
+fn next_tick() -> u64 {
+    LRU_CLOCK.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Directory of the disk cache the storage values and the factory deps read from the forked network are written to
+/// when they are evicted from memory, so that they're read back from the disk instead of the network. The data of
+/// every fork block is kept apart, in a directory named after the block hash.
+#[derive(Debug, Clone)]
+pub(crate) struct ForkDataDir {
+    dir: PathBuf,
+}
+
+impl ForkDataDir {
+    /// Opens the directory of the fork data of the block in the disk cache directory `cache_dir`.
+    pub(crate) fn open(cache_dir: &Path, block_hash: H256) -> std::io::Result<Self> {
+        let dir = cache_dir
+            .join(CACHE_TYPE_FORK_DATA)
+            .join(format!("{:#x}", block_hash));
+        for kind in [FORK_DATA_STORAGE_VALUES, FORK_DATA_FACTORY_DEPS] {
+            fs::create_dir_all(dir.join(kind))?;
+        }
+        Ok(Self { dir })
+    }
+
+    fn value_path(&self, key: &StorageKey) -> PathBuf {
+        self.dir.join(FORK_DATA_STORAGE_VALUES).join(format!(
+            "{:#x}_{:#x}",
+            key.address(),
+            key.key()
+        ))
+    }
+
+    fn factory_dep_path(&self, hash: H256) -> PathBuf {
+        self.dir
+            .join(FORK_DATA_FACTORY_DEPS)
+            .join(format!("{:#x}", hash))
+    }
+
+    /// Writes the storage value evicted from memory.
+    pub(crate) fn write_value(&self, key: &StorageKey, value: H256) {
+        let path = self.value_path(key);
+        if let Err(err) = fs::write(&path, value.as_bytes()) {
+            tracing::error!("failed writing fork data {:?}: {:?}", path, err);
+        }
+    }
+
+    /// Reads a storage value previously evicted from memory.
+    pub(crate) fn read_value(&self, key: &StorageKey) -> Option<H256> {
+        fs::read(self.value_path(key))
+            .ok()
+            .filter(|bytes| bytes.len() == 32)
+            .map(|bytes| H256::from_slice(&bytes))
+    }
+
+    /// Writes the factory dep evicted from memory.
+    pub(crate) fn write_factory_dep(&self, hash: H256, bytecode: &[u8]) {
+        let path = self.factory_dep_path(hash);
+        if let Err(err) = fs::write(&path, bytecode) {
+            tracing::error!("failed writing fork data {:?}: {:?}", path, err);
+        }
+    }
+
+    /// Reads a factory dep previously evicted from memory.
+    pub(crate) fn read_factory_dep(&self, hash: H256) -> Option<Vec<u8>> {
+        fs::read(self.factory_dep_path(hash)).ok()
+    }
+
+    /// Removes the storage values, or the factory deps, written to the directory.
+    pub(crate) fn clear(&self, storage_values: bool) {
+        let path = self.dir.join(if storage_values {
+            FORK_DATA_STORAGE_VALUES
+        } else {
+            FORK_DATA_FACTORY_DEPS
+        });
+        if let Err(err) = fs::remove_dir_all(&path).and_then(|_| fs::create_dir_all(&path)) {
+            tracing::warn!("failed clearing fork data {:?}: {:?}", path, err);
+        }
+    }
+}
+
 /// A general purpose cache.
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub(crate) struct Cache {
     config: CacheConfig,
     block_hashes: FxHashMap<u64, H256>,
-    blocks_full: FxHashMap<H256, Block<TransactionVariant>>,
-    blocks_min: FxHashMap<H256, Block<TransactionVariant>>,
-    block_raw_transactions: FxHashMap<u64, Vec<RawTransaction>>,
-    transactions: FxHashMap<H256, Transaction>,
+    blocks_full: LruCache<H256, Block<TransactionVariant>>,
+    blocks_min: LruCache<H256, Block<TransactionVariant>>,
+    block_raw_transactions: LruCache<u64, Vec<RawTransaction>>,
+    transactions: LruCache<H256, Transaction>,
     bridge_addresses: Option<BridgeAddresses>,
-    confirmed_tokens: FxHashMap<(u32, u8), Vec<zksync_web3_decl::types::Token>>,
+    confirmed_tokens: LruCache<(u32, u8), Vec<zksync_web3_decl::types::Token>>,
 }
 
 impl Cache {
     /// Creates a new cache with the provided config.
+    /// The in-memory caches are unbounded until [Cache::set_max_size] is called.
     pub(crate) fn new(config: CacheConfig) -> Self {
         let mut cache = Cache {
             config: config.clone(),
-            ..Default::default()
+            block_hashes: Default::default(),
+            blocks_full: LruCache::new(CACHE_TYPE_BLOCKS_FULL, None),
+            blocks_min: LruCache::new(CACHE_TYPE_BLOCKS_MIN, None),
+            block_raw_transactions: LruCache::new(CACHE_TYPE_BLOCK_RAW_TRANSACTIONS, None),
+            transactions: LruCache::new(CACHE_TYPE_TRANSACTIONS, None),
+            bridge_addresses: None,
+            confirmed_tokens: LruCache::new(CACHE_TYPE_CONFIRMED_TOKENS, None),
         };
 
         if let CacheConfig::Disk { dir, reset } = &config {
@@ -69,6 +336,7 @@ impl Cache {
                     CACHE_TYPE_BLOCK_RAW_TRANSACTIONS,
                     CACHE_TYPE_TRANSACTIONS,
                     CACHE_TYPE_KEY_VALUE,
+                    CACHE_TYPE_FORK_DATA,
                 ] {
                     fs::remove_dir_all(Path::new(dir).join(cache_type)).unwrap_or_else(|err| {
                         tracing::warn!(
@@ -90,6 +358,7 @@ impl Cache {
                 CACHE_TYPE_BLOCK_RAW_TRANSACTIONS,
                 CACHE_TYPE_TRANSACTIONS,
                 CACHE_TYPE_KEY_VALUE,
+                CACHE_TYPE_FORK_DATA,
             ] {
                 fs::create_dir_all(Path::new(dir).join(cache_type)).unwrap_or_else(|err| {
                     panic!("failed creating directory {}: {:?}", cache_type, err)
@@ -103,9 +372,17 @@ impl Cache {
         cache
     }
 
+    /// Returns the directory of the cache if it's persisted on disk.
+    pub(crate) fn disk_dir(&self) -> Option<&Path> {
+        match &self.config {
+            CacheConfig::Disk { dir, .. } => Some(Path::new(dir)),
+            _ => None,
+        }
+    }
+
     /// Returns the cached full/minimal block for the provided hash.
     pub(crate) fn get_block(
-        &self,
+        &mut self,
         hash: &H256,
         full_transactions: bool,
     ) -> Option<&Block<TransactionVariant>> {
@@ -114,9 +391,9 @@ impl Cache {
         }
 
         if full_transactions {
-            self.blocks_full.get(hash)
+            observe_lookup(CACHE_TYPE_BLOCKS_FULL, self.blocks_full.get(hash))
         } else {
-            self.blocks_min.get(hash)
+            observe_lookup(CACHE_TYPE_BLOCKS_MIN, self.blocks_min.get(hash))
         }
     }
 
@@ -151,24 +428,33 @@ impl Cache {
     }
 
     /// Returns the cached raw transactions for the provided block number.
-    pub(crate) fn get_block_raw_transactions(&self, number: &u64) -> Option<&Vec<RawTransaction>> {
+    pub(crate) fn get_block_raw_transactions(
+        &mut self,
+        number: &u64,
+    ) -> Option<&Vec<RawTransaction>> {
         if matches!(self.config, CacheConfig::None) {
             return None;
         }
 
-        self.block_raw_transactions.get(number)
+        observe_lookup(
+            CACHE_TYPE_BLOCK_RAW_TRANSACTIONS,
+            self.block_raw_transactions.get(number),
+        )
     }
 
     /// Returns the cached confirmed tokens.
     pub(crate) fn get_confirmed_tokens(
-        &self,
+        &mut self,
         from: u32,
         limit: u8,
     ) -> Option<&Vec<zksync_web3_decl::types::Token>> {
         if matches!(self.config, CacheConfig::None) {
             return None;
         }
-        self.confirmed_tokens.get(&(from, limit))
+        observe_lookup(
+            CACHE_TYPE_CONFIRMED_TOKENS,
+            self.confirmed_tokens.get(&(from, limit)),
+        )
     }
 
     /// Cache confirmed tokens
@@ -204,12 +490,12 @@ impl Cache {
     }
 
     /// Returns the cached transaction for the provided hash.
-    pub(crate) fn get_transaction(&self, hash: &H256) -> Option<&Transaction> {
+    pub(crate) fn get_transaction(&mut self, hash: &H256) -> Option<&Transaction> {
         if matches!(self.config, CacheConfig::None) {
             return None;
         }
 
-        self.transactions.get(hash)
+        observe_lookup(CACHE_TYPE_TRANSACTIONS, self.transactions.get(hash))
     }
 
     /// Cache a transaction for the provided hash.
@@ -232,7 +518,7 @@ impl Cache {
             return None;
         }
 
-        self.bridge_addresses.as_ref()
+        observe_lookup(CACHE_KEY_BRIDGE_ADDRESSES, self.bridge_addresses.as_ref())
     }
 
     /// Cache default bridge addresses.
@@ -249,6 +535,71 @@ impl Cache {
         self.bridge_addresses = Some(bridge_addresses);
     }
 
+    /// Sets the maximum number of entries of every type held in memory, evicting the least recently used
+    /// entries that don't fit.
+    pub(crate) fn set_max_size(&mut self, max_size: Option<usize>) {
+        self.blocks_full.set_max_size(max_size);
+        self.blocks_min.set_max_size(max_size);
+        self.block_raw_transactions.set_max_size(max_size);
+        self.transactions.set_max_size(max_size);
+        self.confirmed_tokens.set_max_size(max_size);
+    }
+
+    /// Returns the number of entries of every cache, keyed by the names in [CACHE_KINDS].
+    pub(crate) fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            (CACHE_TYPE_BLOCKS_FULL, self.blocks_full.len()),
+            (CACHE_TYPE_BLOCKS_MIN, self.blocks_min.len()),
+            (
+                CACHE_TYPE_BLOCK_RAW_TRANSACTIONS,
+                self.block_raw_transactions.len(),
+            ),
+            (CACHE_TYPE_TRANSACTIONS, self.transactions.len()),
+            (
+                CACHE_KEY_BRIDGE_ADDRESSES,
+                self.bridge_addresses.iter().count(),
+            ),
+            (CACHE_TYPE_CONFIRMED_TOKENS, self.confirmed_tokens.len()),
+        ]
+    }
+
+    /// Removes the entries of the cache named `kind` (one of [CACHE_KINDS]), both from memory and disk.
+    pub(crate) fn clear(&mut self, kind: &str) {
+        match kind {
+            CACHE_TYPE_BLOCKS_FULL => {
+                self.blocks_full.clear();
+                self.block_hashes.clear();
+            }
+            CACHE_TYPE_BLOCKS_MIN => {
+                self.blocks_min.clear();
+                self.block_hashes.clear();
+            }
+            CACHE_TYPE_BLOCK_RAW_TRANSACTIONS => self.block_raw_transactions.clear(),
+            CACHE_TYPE_TRANSACTIONS => self.transactions.clear(),
+            CACHE_KEY_BRIDGE_ADDRESSES => self.bridge_addresses = None,
+            CACHE_TYPE_CONFIRMED_TOKENS => self.confirmed_tokens.clear(),
+            _ => return,
+        }
+
+        if let CacheConfig::Disk { dir, .. } = &self.config {
+            let path = match kind {
+                CACHE_KEY_BRIDGE_ADDRESSES => Path::new(dir)
+                    .join(CACHE_TYPE_KEY_VALUE)
+                    .join(CACHE_KEY_BRIDGE_ADDRESSES),
+                CACHE_TYPE_CONFIRMED_TOKENS => return,
+                _ => Path::new(dir).join(kind),
+            };
+            let result = if path.is_dir() {
+                fs::remove_dir_all(&path).and_then(|_| fs::create_dir_all(&path))
+            } else {
+                fs::remove_file(&path)
+            };
+            if let Err(err) = result {
+                tracing::warn!("failed clearing cache {:?}: {:?}", path, err);
+            }
+        }
+    }
+
     /// Reads the cache contents from the disk, if available.
     fn read_all_from_disk(&mut self, dir: &str) -> Result<(), String> {
         for cache_type in [
@@ -355,6 +706,12 @@ impl Cache {
     }
 }
 
+/// Records the outcome of a lookup in the cache named `kind` in the [METRICS].
+fn observe_lookup<T>(kind: &'static str, value: Option<T>) -> Option<T> {
+    METRICS.observe_cache_lookup(kind, value.is_some());
+    value
+}
+
 #[cfg(test)]
 mod tests {
     use tempdir::TempDir;
@@ -518,7 +875,7 @@ mod tests {
             cache.get_bridge_addresses().expect("expected addresses"),
         );
 
-        let new_cache = Cache::new(CacheConfig::Disk {
+        let mut new_cache = Cache::new(CacheConfig::Disk {
             dir: cache_dir_path,
             reset: false,
         });
@@ -616,7 +973,7 @@ mod tests {
             cache.get_bridge_addresses().expect("expected addresses"),
         );
 
-        let new_cache = Cache::new(CacheConfig::Disk {
+        let mut new_cache = Cache::new(CacheConfig::Disk {
             dir: cache_dir_path,
             reset: true,
         });
@@ -666,4 +1023,78 @@ mod tests {
         );
         assert!(random_file_path.exists(), "random file was reset from disk");
     }
+
+    #[test]
+    fn test_cache_clear_removes_data_in_memory_and_on_disk() {
+        let cache_dir = TempDir::new("cache-test").expect("failed creating temporary dir");
+        let cache_dir_path = cache_dir
+            .path()
+            .to_str()
+            .expect("invalid dir name")
+            .to_string();
+        let mut cache = Cache::new(CacheConfig::Disk {
+            dir: cache_dir_path.clone(),
+            reset: true,
+        });
+        cache.insert_transaction(H256::zero(), Default::default());
+        cache.insert_block_raw_transactions(0, vec![]);
+
+        cache.clear(CACHE_TYPE_TRANSACTIONS);
+
+        assert_eq!(None, cache.get_transaction(&H256::zero()));
+        assert!(cache.get_block_raw_transactions(&0).is_some());
+        assert!(cache
+            .sizes()
+            .contains(&(CACHE_TYPE_BLOCK_RAW_TRANSACTIONS, 1)));
+        let mut new_cache = Cache::new(CacheConfig::Disk {
+            dir: cache_dir_path,
+            reset: false,
+        });
+        assert_eq!(None, new_cache.get_transaction(&H256::zero()));
+        assert!(new_cache.get_block_raw_transactions(&0).is_some());
+    }
+
+    #[test]
+    fn test_lru_cache_evicts_least_recently_used() {
+        let mut cache = LruCache::new("test", Some(2));
+        cache.insert(1, "one");
+        cache.insert(2, "two");
+        assert_eq!(Some(&"one"), cache.get(&1));
+
+        cache.insert(3, "three");
+
+        assert_eq!(2, cache.len());
+        assert_eq!(None, cache.get(&2));
+        assert_eq!(Some(&"one"), cache.get(&1));
+        assert_eq!(Some(&"three"), cache.get(&3));
+
+        cache.set_max_size(Some(1));
+
+        assert_eq!(1, cache.len());
+        assert_eq!(Some(&"three"), cache.get(&3));
+    }
+
+    #[test]
+    fn test_lru_cache_tracks_memory_usage() {
+        let mut cache: LruCache<u64, Vec<u8>> =
+            LruCache::new("test", Some(2)).with_weigher(|_, value| value.len());
+        assert_eq!(Vec::<(u64, Vec<u8>)>::new(), cache.insert(1, vec![0; 10]));
+        cache.insert(2, vec![0; 20]);
+        cache.insert(2, vec![0; 5]);
+        assert_eq!(15, cache.memory_usage());
+
+        assert_eq!(vec![(1, vec![0; 10])], cache.insert(3, vec![0; 30]));
+        assert_eq!(35, cache.memory_usage());
+
+        let other: LruCache<u64, Vec<u8>> = {
+            let mut other = LruCache::new("other", None);
+            other.insert(4, vec![]);
+            other
+        };
+        assert!(cache.least_recent_use() < other.least_recent_use());
+        assert_eq!(Some((2, vec![0; 5])), cache.pop_least_recently_used());
+        assert_eq!(30, cache.memory_usage());
+        cache.clear();
+        assert_eq!(0, cache.memory_usage());
+    }
 }
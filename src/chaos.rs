@@ -0,0 +1,172 @@
+//! Chaos testing of the RPC clients: artificial latency and transient errors injected into the responses of the
+//! selected RPC methods, set with `config_setChaos`, so that the retry logic of frontends and SDKs can be tested.
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use futures::future::{self, Either};
+use jsonrpc_core::{middleware, Call, Error, ErrorCode, Metadata, Middleware, Output};
+use serde::{Deserialize, Serialize};
+
+use crate::rpc_filter::method_matches;
+
+/// Faults injected into the RPC responses.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChaosConfig {
+    /// Delay of every response, in milliseconds.
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Fraction of the requests failing with a transient error, between 0 and 1. The failures are spread evenly
+    /// rather than randomly, so that the runs are reproducible: with `0.25`, every 4th request fails.
+    #[serde(default)]
+    pub error_rate: f64,
+    /// The affected methods, matched by their exact name or by a prefix ending with `*` (e.g. `eth_*`).
+    /// All the methods are affected if empty.
+    #[serde(default)]
+    pub methods: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct ChaosState {
+    config: Option<ChaosConfig>,
+    /// Number of the affected requests since the config was set.
+    requests: u64,
+}
+
+/// Middleware injecting the faults of the [ChaosConfig]. The clones of the middleware share the config, so that
+/// it can be changed while the server is running.
+#[derive(Debug, Clone, Default)]
+pub struct ChaosMiddleware {
+    state: Arc<Mutex<ChaosState>>,
+}
+
+impl ChaosMiddleware {
+    /// Sets the injected faults, or disables them with `None`.
+    pub fn set_config(&self, config: Option<ChaosConfig>) -> anyhow::Result<()> {
+        if let Some(config) = &config {
+            if !(0.0..=1.0).contains(&config.error_rate) {
+                return Err(anyhow!(
+                    "error rate {} is not between 0 and 1",
+                    config.error_rate
+                ));
+            }
+        }
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        *state = ChaosState {
+            config,
+            requests: 0,
+        };
+        Ok(())
+    }
+
+    /// Returns whether faults are injected into the responses.
+    pub fn is_enabled(&self) -> bool {
+        self.state
+            .lock()
+            .map_or(false, |state| state.config.is_some())
+    }
+
+    /// Returns the delay of the response to a request to the method and whether it fails, or `None` if the
+    /// method is not affected.
+    fn next_fault(&self, method: &str) -> Option<(Duration, bool)> {
+        let mut state = self.state.lock().ok()?;
+        let config = state.config.clone()?;
+        if !config.methods.is_empty()
+            && !config
+                .methods
+                .iter()
+                .any(|pattern| method_matches(pattern, method))
+        {
+            return None;
+        }
+
+        // the request fails whenever the expected number of failures reaches the next integer
+        state.requests += 1;
+        let failures = |requests: u64| (requests as f64 * config.error_rate).floor();
+        let fails = failures(state.requests) > failures(state.requests - 1);
+        Some((Duration::from_millis(config.latency_ms), fails))
+    }
+}
+
+impl<M: Metadata> Middleware<M> for ChaosMiddleware {
+    type Future = middleware::NoopFuture;
+    type CallFuture = middleware::NoopCallFuture;
+
+    fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X>
+    where
+        F: FnOnce(Call, M) -> X + Send,
+        X: future::Future<Output = Option<Output>> + Send + 'static,
+    {
+        let method_call = match &call {
+            Call::MethodCall(method_call) => method_call,
+            _ => return Either::Right(next(call, meta)),
+        };
+        let (delay, fails) = match self.next_fault(&method_call.method) {
+            Some(fault) => fault,
+            None => return Either::Right(next(call, meta)),
+        };
+
+        let response = if fails {
+            tracing::warn!("injecting error into call to {}", method_call.method);
+            let error = Error {
+                code: ErrorCode::ServerError(-32005),
+                message: "Transient error injected by the chaos mode".to_string(),
+                data: None,
+            };
+            let output = Output::from(Err(error), method_call.id.clone(), method_call.jsonrpc);
+            Either::Left(future::ready(Some(output)))
+        } else {
+            Either::Right(next(call, meta))
+        };
+        Either::Left(Box::pin(async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            response.await
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chaos_fails_selected_methods_at_error_rate() {
+        let chaos = ChaosMiddleware::default();
+        assert_eq!(None, chaos.next_fault("eth_call"));
+
+        chaos
+            .set_config(Some(ChaosConfig {
+                latency_ms: 50,
+                error_rate: 0.25,
+                methods: vec!["eth_*".to_string()],
+            }))
+            .expect("failed setting config");
+
+        assert_eq!(None, chaos.next_fault("net_version"));
+        let faults = (0..8)
+            .map(|_| chaos.next_fault("eth_call").expect("method not affected"))
+            .collect::<Vec<_>>();
+        assert!(faults
+            .iter()
+            .all(|(delay, _)| *delay == Duration::from_millis(50)));
+        assert_eq!(
+            vec![false, false, false, true, false, false, false, true],
+            faults.iter().map(|(_, fails)| *fails).collect::<Vec<_>>()
+        );
+
+        assert!(chaos
+            .set_config(Some(ChaosConfig {
+                error_rate: 1.5,
+                ..Default::default()
+            }))
+            .is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Periodic checkpoints of the state of the node, written with `--checkpoint-interval` and restored with the
+//! `restore` command, so that long manual testing sessions survive a crash of the node.
+//!
+//! The checkpoints are written to the checkpoint directory in the binary state format of `--dump-state`. The first
+//! checkpoint holds the whole local state, and every following one only the storage slots changed since the previous
+//! one, so that the state at a checkpoint is restored by applying all the checkpoints up to it in order. The
+//! `manifest.json` file of the directory lists the checkpoints, along with the forked network.
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use zksync_basic_types::H256;
+use zksync_types::StorageKey;
+
+use crate::{fork::ForkSource, genesis::GenesisFile, node::InMemoryNode};
+
+/// Name of the manifest file in the checkpoint directory.
+const MANIFEST_FILE: &str = "manifest.json";
+/// Interval at which the block number of the node is checked for a new checkpoint.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Network the checkpointed node was forked from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointFork {
+    pub url: String,
+    pub block: u64,
+}
+
+/// Checkpoint written to the checkpoint directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    pub index: u64,
+    /// The latest block of the node when the checkpoint was written.
+    pub block: u64,
+    /// Name of the checkpoint file in the checkpoint directory.
+    pub file: String,
+}
+
+/// Content of the `manifest.json` file of the checkpoint directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointManifest {
+    /// The forked network, which must be forked again at the same block to restore the checkpoints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork: Option<CheckpointFork>,
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+impl CheckpointManifest {
+    /// Reads the manifest of the checkpoint directory.
+    pub fn read(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("failed reading checkpoints manifest {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("failed parsing checkpoints manifest {}", path.display()))
+    }
+
+    fn write(&self, dir: &Path) -> anyhow::Result<()> {
+        let path = dir.join(MANIFEST_FILE);
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?)
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .with_context(|| format!("failed writing checkpoints manifest {}", path.display()))
+    }
+
+    /// Returns the checkpoints to apply in order to restore the given one, the latest one if `None`.
+    pub fn checkpoints_up_to(&self, index: Option<u64>) -> anyhow::Result<&[Checkpoint]> {
+        let index =
+            match index.or_else(|| self.checkpoints.last().map(|checkpoint| checkpoint.index)) {
+                Some(index) => index,
+                None => anyhow::bail!("no checkpoint was written"),
+            };
+        let position = self
+            .checkpoints
+            .iter()
+            .position(|checkpoint| checkpoint.index == index)
+            .with_context(|| format!("checkpoint {} not found", index))?;
+        Ok(&self.checkpoints[..=position])
+    }
+}
+
+/// Writes the checkpoints of a node to the checkpoint directory.
+pub struct Checkpointer {
+    dir: PathBuf,
+    manifest: CheckpointManifest,
+    /// The state of the node at the latest checkpoint.
+    state: HashMap<StorageKey, H256>,
+}
+
+impl Checkpointer {
+    /// Starts a new series of checkpoints in the directory, removing the checkpoints of a previous session.
+    pub fn create(dir: PathBuf, fork: Option<CheckpointFork>) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed creating checkpoint directory {}", dir.display()))?;
+        let mut checkpointer = Self {
+            dir,
+            manifest: CheckpointManifest {
+                fork,
+                checkpoints: vec![],
+            },
+            state: HashMap::new(),
+        };
+        if let Ok(previous) = CheckpointManifest::read(&checkpointer.dir) {
+            checkpointer.remove_files(&previous.checkpoints);
+        }
+        checkpointer.manifest.write(&checkpointer.dir)?;
+        Ok(checkpointer)
+    }
+
+    /// Continues the series of checkpoints in the directory after the restored `checkpoint`, for a node whose
+    /// current state is the one of the checkpoint. The later checkpoints are removed.
+    pub fn resume<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static>(
+        dir: PathBuf,
+        checkpoint: u64,
+        node: &InMemoryNode<S>,
+    ) -> anyhow::Result<Self> {
+        let mut manifest = CheckpointManifest::read(&dir)?;
+        let position = manifest
+            .checkpoints
+            .iter()
+            .position(|existing| existing.index == checkpoint)
+            .with_context(|| format!("checkpoint {} not found", checkpoint))?;
+        let removed = manifest.checkpoints.split_off(position + 1);
+        let mut checkpointer = Self {
+            dir,
+            manifest,
+            state: node.storage_state()?,
+        };
+        checkpointer.remove_files(&removed);
+        checkpointer.manifest.write(&checkpointer.dir)?;
+        Ok(checkpointer)
+    }
+
+    fn remove_files(&self, checkpoints: &[Checkpoint]) {
+        for checkpoint in checkpoints {
+            let _ = fs::remove_file(self.dir.join(&checkpoint.file));
+        }
+    }
+
+    /// Writes a checkpoint of the node at the given block.
+    pub fn write<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static>(
+        &mut self,
+        node: &InMemoryNode<S>,
+        block: u64,
+    ) -> anyhow::Result<&Checkpoint> {
+        let index = self
+            .manifest
+            .checkpoints
+            .last()
+            .map_or(0, |checkpoint| checkpoint.index + 1);
+        let file = format!("checkpoint-{}.bin", index);
+        self.state = node.dump_state_changes_to_file(self.dir.join(&file), &self.state)?;
+        self.manifest
+            .checkpoints
+            .push(Checkpoint { index, block, file });
+        self.manifest.write(&self.dir)?;
+        Ok(self.manifest.checkpoints.last().expect("checkpoint added"))
+    }
+
+    /// Writes a checkpoint whenever the node produced `interval` blocks since the previous one, until the node
+    /// stops.
+    pub async fn run<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static>(
+        mut self,
+        node: InMemoryNode<S>,
+        interval: u64,
+    ) {
+        let mut checked = tokio::time::interval(CHECK_INTERVAL);
+        let mut last_block = match node.get_inner().read() {
+            Ok(reader) => reader.current_miniblock,
+            Err(_) => return,
+        };
+        loop {
+            checked.tick().await;
+            let block = match node.get_inner().read() {
+                Ok(reader) => reader.current_miniblock,
+                Err(_) => continue,
+            };
+            if block < last_block.saturating_add(interval) {
+                continue;
+            }
+            last_block = block;
+            match self.write(&node, block) {
+                Ok(checkpoint) => {
+                    tracing::info!("Wrote checkpoint {} at block #{}", checkpoint.index, block)
+                }
+                Err(err) => tracing::error!("Failed writing checkpoint: {:?}", err),
+            }
+        }
+    }
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Applies the checkpoints of the directory up to the given one, the latest one if `None`, to the node.
+    ///
+    /// # Returns
+    /// The index of the restored checkpoint.
+    pub fn restore_checkpoint(&self, dir: &Path, checkpoint: Option<u64>) -> anyhow::Result<u64> {
+        let manifest = CheckpointManifest::read(dir)?;
+        let checkpoints = manifest.checkpoints_up_to(checkpoint)?;
+        for checkpoint in checkpoints {
+            self.apply_genesis_file(GenesisFile::open(dir.join(&checkpoint.file))?)
+                .with_context(|| format!("failed restoring checkpoint {}", checkpoint.index))?;
+        }
+        Ok(checkpoints.last().expect("checkpoints not empty").index)
+    }
+
+    /// Returns a copy of the local storage of the node.
+    fn storage_state(&self) -> anyhow::Result<HashMap<StorageKey, H256>> {
+        let inner = self.get_inner();
+        let reader = inner
+            .read()
+            .map_err(|err| anyhow::anyhow!("failed acquiring lock: {:?}", err))?;
+        let storage = reader
+            .fork_storage
+            .inner
+            .read()
+            .map_err(|err| anyhow::anyhow!("failed acquiring lock: {:?}", err))?;
+        Ok(storage.raw_storage.state.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_fork_source::HttpForkSource, namespaces::EthNamespaceT};
+    use zksync_basic_types::{Address, U256};
+
+    #[tokio::test]
+    async fn test_restore_incremental_checkpoints() {
+        let dir = tempdir::TempDir::new("checkpoints").unwrap();
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let mut checkpointer =
+            Checkpointer::create(dir.path().to_path_buf(), None).expect("failed creating");
+
+        let first = Address::repeat_byte(0x1);
+        let second = Address::repeat_byte(0x2);
+        node.set_balance(first, U256::from(100))
+            .expect("failed setting balance");
+        checkpointer.write(&node, 1).expect("failed writing");
+        node.set_balance(second, U256::from(200))
+            .expect("failed setting balance");
+        checkpointer.write(&node, 2).expect("failed writing");
+        node.set_balance(first, U256::from(300))
+            .expect("failed setting balance");
+        checkpointer.write(&node, 3).expect("failed writing");
+
+        let manifest = CheckpointManifest::read(dir.path()).expect("failed reading manifest");
+        assert_eq!(
+            vec![0, 1, 2],
+            manifest
+                .checkpoints
+                .iter()
+                .map(|checkpoint| checkpoint.index)
+                .collect::<Vec<_>>()
+        );
+
+        let restored = InMemoryNode::<HttpForkSource>::default();
+        assert_eq!(
+            1,
+            restored
+                .restore_checkpoint(dir.path(), Some(1))
+                .expect("failed restoring")
+        );
+        assert_eq!(
+            U256::from(100),
+            restored.get_balance(first, None).await.unwrap()
+        );
+        assert_eq!(
+            U256::from(200),
+            restored.get_balance(second, None).await.unwrap()
+        );
+
+        let restored = InMemoryNode::<HttpForkSource>::default();
+        assert_eq!(
+            2,
+            restored
+                .restore_checkpoint(dir.path(), None)
+                .expect("failed restoring")
+        );
+        assert_eq!(
+            U256::from(300),
+            restored.get_balance(first, None).await.unwrap()
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Context};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use crate::{
+    fork::ForkSource,
+    node::{InMemoryNode, ShowCalls, ShowGasDetails, ShowStorageLogs, ShowVMDetails},
+    observability::LogLevel,
+    subscriptions::NodeEvent,
+};
+
+/// Options of the configuration file that are applied to the running node when the file changes.
+/// Changing any other option requires a restart.
+const RELOADABLE_OPTIONS: [&str; 13] = [
+    "log",
+    "show-calls",
+    "show-storage-logs",
+    "show-vm-details",
+    "show-gas-details",
+    "show-precompiles",
+    "show-outputs",
+    "resolve-hashes",
+    "block-gas-limit",
+    "tx-gas-limit",
+    "disable-block-gas-limit",
+    "vm-execution-timeout",
+    "vm-max-steps",
+];
+/// How often the configuration file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reads the TOML configuration file passed with `--config`. Its keys are the names of the CLI options,
+/// either in kebab or snake case, and are returned in kebab case.
+///
+/// Example:
+/// ```toml
+/// port = 8012
+/// log = "debug"
+/// show-calls = "user"
+/// rpc-deny = ["hardhat_setBalance", "hardhat_setCode"]
+/// ```
+pub fn read_config_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Table> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed reading config file {}", path.display()))?;
+    let config: Table = contents
+        .parse()
+        .with_context(|| format!("failed parsing config file {}", path.display()))?;
+
+    Ok(config
+        .into_iter()
+        .map(|(key, value)| (key.replace('_', "-"), value))
+        .collect())
+}
+
+/// Converts the options of the configuration file into CLI arguments, e.g. `port = 8012` into `--port=8012`.
+/// Flags set to `false` are omitted and arrays are passed as repeated arguments.
+///
+/// Options that are also set via their environment variable (see [env_var_name]) are omitted too,
+/// so that the environment takes precedence over the file.
+pub fn to_cli_args(config: &Table) -> anyhow::Result<Vec<String>> {
+    let mut args = vec![];
+    for (key, value) in config {
+        if env::var_os(env_var_name(key)).is_some() {
+            continue;
+        }
+        match value {
+            Value::Boolean(true) => args.push(format!("--{}", key)),
+            Value::Boolean(false) => {}
+            Value::Array(values) => {
+                for value in values {
+                    args.push(format!("--{}={}", key, to_cli_value(key, value)?));
+                }
+            }
+            value => args.push(format!("--{}={}", key, to_cli_value(key, value)?)),
+        }
+    }
+
+    Ok(args)
+}
+
+/// Named bundle of options passed with `--preset`, for a sensible setup without going through every option.
+/// The options of the preset are overridden by the ones in the configuration file, environment and command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// Forks mainnet (unless another network is given), with the fork data cached on disk.
+    MainnetFork,
+    /// Forks the Sepolia testnet (unless another network is given), with the fork data cached on disk.
+    TestnetFork,
+    /// Reproducible and quiet runs for test suites: deterministic timestamps, no disk cache and no block gas limit.
+    Ci,
+    /// Logs everything there is to know about the executed transactions and RPC requests.
+    HeavyDebug,
+}
+
+impl Preset {
+    /// Returns the network forked by default with this preset.
+    pub fn fork_url(&self) -> Option<&'static str> {
+        match self {
+            Preset::MainnetFork => Some("mainnet"),
+            Preset::TestnetFork => Some("sepolia-testnet"),
+            Preset::Ci | Preset::HeavyDebug => None,
+        }
+    }
+
+    /// Returns the options of the preset, in the format of the configuration file.
+    pub fn options(&self) -> Table {
+        let options = match self {
+            Preset::MainnetFork | Preset::TestnetFork => {
+                r#"
+                    cache = "disk"
+                    resolve-hashes = true
+                    show-calls = "user"
+                "#
+            }
+            Preset::Ci => {
+                r#"
+                    log = "warn"
+                    cache = "memory"
+                    deterministic = true
+                    disable-block-gas-limit = true
+                "#
+            }
+            Preset::HeavyDebug => {
+                r#"
+                    log = "debug"
+                    log-rpc-requests = true
+                    show-calls = "all"
+                    show-storage-logs = "all"
+                    show-vm-details = "all"
+                    show-gas-details = "all"
+                    show-outputs = true
+                    resolve-hashes = true
+                "#
+            }
+        };
+
+        options.parse().expect("invalid preset options")
+    }
+}
+
+/// Returns the name of the environment variable mirroring the CLI option, e.g. `ERA_TEST_NODE_CACHE_DIR`
+/// for `cache-dir`.
+pub fn env_var_name(option: &str) -> String {
+    format!("ERA_TEST_NODE_{}", option.replace('-', "_").to_uppercase())
+}
+
+fn to_cli_value(key: &str, value: &Value) -> anyhow::Result<String> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Integer(value) => Ok(value.to_string()),
+        Value::Float(value) => Ok(value.to_string()),
+        _ => Err(anyhow!("unsupported value of `{}` in config file", key)),
+    }
+}
+
+/// Options of the configuration file that can be changed while the node is running, see [RELOADABLE_OPTIONS].
+/// Options missing from the file keep their current value.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RuntimeConfig {
+    pub log: Option<LogLevel>,
+    pub show_calls: Option<String>,
+    pub show_storage_logs: Option<String>,
+    pub show_vm_details: Option<String>,
+    pub show_gas_details: Option<String>,
+    pub show_precompiles: Option<bool>,
+    pub show_outputs: Option<bool>,
+    pub resolve_hashes: Option<bool>,
+    pub block_gas_limit: Option<u64>,
+    pub tx_gas_limit: Option<u64>,
+    pub disable_block_gas_limit: Option<bool>,
+    pub vm_execution_timeout: Option<u64>,
+    pub vm_max_steps: Option<u64>,
+}
+
+impl RuntimeConfig {
+    /// Extracts the runtime options from the configuration file contents.
+    pub fn from_table(config: &Table) -> anyhow::Result<Self> {
+        Value::Table(config.clone())
+            .try_into()
+            .context("invalid option in config file")
+    }
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Applies the options of the [RuntimeConfig] to the running node, notifying the `era_subscribe`
+    /// subscriptions of each option set.
+    pub fn apply_runtime_config(&self, config: &RuntimeConfig) -> anyhow::Result<()> {
+        let show_calls = config
+            .show_calls
+            .as_deref()
+            .map(str::parse::<ShowCalls>)
+            .transpose()
+            .map_err(|err| anyhow!(err))?;
+        let show_storage_logs = config
+            .show_storage_logs
+            .as_deref()
+            .map(str::parse::<ShowStorageLogs>)
+            .transpose()
+            .map_err(|err| anyhow!(err))?;
+        let show_vm_details = config
+            .show_vm_details
+            .as_deref()
+            .map(str::parse::<ShowVMDetails>)
+            .transpose()
+            .map_err(|err| anyhow!(err))?;
+        let show_gas_details = config
+            .show_gas_details
+            .as_deref()
+            .map(str::parse::<ShowGasDetails>)
+            .transpose()
+            .map_err(|err| anyhow!(err))?;
+
+        let inner = self.get_inner();
+        let mut writer = inner
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        if let (Some(level), Some(observability)) = (&config.log, &writer.observability) {
+            observability.set_log_level(level.clone())?;
+        }
+        if let Some(show_calls) = show_calls {
+            writer.show_calls = show_calls;
+        }
+        if let Some(show_storage_logs) = show_storage_logs {
+            writer.show_storage_logs = show_storage_logs;
+        }
+        if let Some(show_vm_details) = show_vm_details {
+            writer.show_vm_details = show_vm_details;
+        }
+        if let Some(show_gas_details) = show_gas_details {
+            writer.show_gas_details = show_gas_details;
+        }
+        if let Some(show_precompiles) = config.show_precompiles {
+            writer.show_precompiles = show_precompiles;
+        }
+        if let Some(show_outputs) = config.show_outputs {
+            writer.show_outputs = show_outputs;
+        }
+        if let Some(resolve_hashes) = config.resolve_hashes {
+            writer.resolve_hashes = resolve_hashes;
+        }
+        if let Some(block_gas_limit) = config.block_gas_limit {
+            writer.block_gas_limit = block_gas_limit;
+        }
+        if config.tx_gas_limit.is_some() {
+            writer.tx_gas_limit = config.tx_gas_limit;
+        }
+        if let Some(disable_block_gas_limit) = config.disable_block_gas_limit {
+            writer.disable_block_gas_limit = disable_block_gas_limit;
+        }
+        if config.vm_execution_timeout.is_some() {
+            writer.execution_limits.timeout_ms = config.vm_execution_timeout;
+        }
+        if config.vm_max_steps.is_some() {
+            writer.execution_limits.max_steps = config.vm_max_steps;
+        }
+        if let Ok(serde_json::Value::Object(options)) = serde_json::to_value(config) {
+            for (option, value) in options.into_iter().filter(|(_, value)| !value.is_null()) {
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::ConfigChanged { option, value });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks the configuration file at `path` for changes every [WATCH_INTERVAL] and applies its [RuntimeConfig]
+/// to the node. Changes of the other options are only reported, as they require a restart.
+pub async fn watch_config_file<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static>(
+    path: PathBuf,
+    node: InMemoryNode<S>,
+    mut current: Table,
+) {
+    let mut modified = modified_at(&path);
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let last_modified = modified_at(&path);
+        if last_modified == modified {
+            continue;
+        }
+        modified = last_modified;
+
+        let config = match read_config_file(&path).and_then(|config| {
+            node.apply_runtime_config(&RuntimeConfig::from_table(&config)?)?;
+            Ok(config)
+        }) {
+            Ok(config) => config,
+            Err(err) => {
+                tracing::error!("Failed reloading config file: {:?}", err);
+                continue;
+            }
+        };
+
+        let mut restart_required: Vec<&String> = config
+            .keys()
+            .chain(current.keys())
+            .filter(|key| !RELOADABLE_OPTIONS.contains(&key.as_str()))
+            .filter(|key| config.get(*key) != current.get(*key))
+            .collect();
+        restart_required.sort();
+        restart_required.dedup();
+        for key in restart_required {
+            tracing::warn!("Changing `{}` in the config file requires a restart", key);
+        }
+        tracing::info!("Reloaded config file {}", path.display());
+        current = config;
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_to_cli_args() {
+        let dir = TempDir::new("config-file").unwrap();
+        let path = dir.path().join("era_test_node.toml");
+        fs::write(
+            &path,
+            r#"
+                port = 8012
+                show_calls = "user"
+                resolve-hashes = true
+                no-automine = false
+                rpc-deny = ["hardhat_setBalance", "hardhat_setCode"]
+                log-file-max-files = 3
+            "#,
+        )
+        .unwrap();
+
+        env::set_var(env_var_name("log-file-max-files"), "10");
+        let config = read_config_file(&path).expect("failed reading config");
+        let mut args = to_cli_args(&config).expect("failed converting config");
+        args.sort();
+
+        assert_eq!(
+            vec![
+                "--port=8012",
+                "--resolve-hashes",
+                "--rpc-deny=hardhat_setBalance",
+                "--rpc-deny=hardhat_setCode",
+                "--show-calls=user",
+            ],
+            args
+        );
+    }
+
+    #[test]
+    fn test_preset_options_are_valid_cli_args() {
+        for preset in Preset::value_variants() {
+            to_cli_args(&preset.options()).expect("invalid preset options");
+            RuntimeConfig::from_table(&preset.options()).expect("invalid preset options");
+        }
+    }
+
+    #[test]
+    fn test_apply_runtime_config() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let config: Table = r#"
+            port = 8012
+            show-calls = "all"
+            tx-gas-limit = 1000000
+        "#
+        .parse()
+        .unwrap();
+
+        node.apply_runtime_config(&RuntimeConfig::from_table(&config).unwrap())
+            .expect("failed applying config");
+
+        let inner = node.get_inner().read().unwrap();
+        assert_eq!(ShowCalls::All, inner.show_calls);
+        assert_eq!(Some(1_000_000), inner.tx_gas_limit);
+        assert!(node
+            .apply_runtime_config(&RuntimeConfig {
+                show_calls: Some(String::from("everything")),
+                ..Default::default()
+            })
+            .is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! JSON-RPC conformance suite, run with `--selftest` and by the tests: sends the requests recorded in
+//! `data/conformance.json` to the RPC methods of the node and checks the responses against the recorded
+//! expectations, so that a build or a configuration can be verified to behave like the documented API.
+//!
+//! Every RPC method has at least one case. The methods changing the state of the node are only called with invalid
+//! parameters, which are rejected before reaching the node, except for a few cases at the end of the suite.
+use anyhow::Context;
+use jsonrpc_core::{MetaIoHandler, Middleware};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::logging_middleware::Meta;
+
+/// The recorded conformance cases, in the order they are run.
+const CONFORMANCE_CASES: &str = include_str!("data/conformance.json");
+
+/// Expected JSON type of the result of a method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResultKind {
+    Null,
+    Bool,
+    Number,
+    String,
+    /// A hex encoded number, e.g. `0x1a`.
+    Quantity,
+    /// Hex encoded bytes, e.g. `0x` or `0x1a2b`.
+    Data,
+    Hash,
+    Address,
+    Array,
+    Object,
+}
+
+impl ResultKind {
+    fn matches(&self, value: &Value) -> bool {
+        let hex_digits = || {
+            value
+                .as_str()
+                .and_then(|value| value.strip_prefix("0x"))
+                .filter(|digits| digits.chars().all(|c| c.is_ascii_hexdigit()))
+        };
+        match self {
+            ResultKind::Null => value.is_null(),
+            ResultKind::Bool => value.is_boolean(),
+            ResultKind::Number => value.is_number(),
+            ResultKind::String => value.is_string(),
+            ResultKind::Quantity => hex_digits().map_or(false, |digits| !digits.is_empty()),
+            ResultKind::Data => hex_digits().map_or(false, |digits| digits.len() % 2 == 0),
+            ResultKind::Hash => hex_digits().map_or(false, |digits| digits.len() == 64),
+            ResultKind::Address => hex_digits().map_or(false, |digits| digits.len() == 40),
+            ResultKind::Array => value.is_array(),
+            ResultKind::Object => value.is_object(),
+        }
+    }
+}
+
+/// Request to a method along with the expectations on its response. A case without expectations only expects the
+/// request to succeed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConformanceCase {
+    pub method: String,
+    #[serde(default)]
+    pub params: Vec<Value>,
+    /// The exact expected result. A `null` result is expected with the `null` kind instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ResultKind>,
+    /// The fields the object result must have.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<String>,
+    /// The code of the expected error, e.g. `-32602` for the invalid parameters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<i64>,
+}
+
+impl ConformanceCase {
+    /// Checks the JSON-RPC response to the request of the case.
+    ///
+    /// # Returns
+    /// The reason the response does not meet the expectations, if it does not.
+    fn check(&self, response: &Value) -> Result<(), String> {
+        match (self.error, response.get("error")) {
+            (Some(code), Some(error)) if error["code"] == code => Ok(()),
+            (Some(code), Some(error)) => {
+                Err(format!("expected error {}, got error {}", code, error))
+            }
+            (Some(code), None) => Err(format!(
+                "expected error {}, got result {}",
+                code, response["result"]
+            )),
+            (None, Some(error)) => Err(format!("unexpected error {}", error)),
+            (None, None) => {
+                let result = &response["result"];
+                if let Some(expected) = self.result.as_ref().filter(|expected| *expected != result)
+                {
+                    return Err(format!("expected result {}, got {}", expected, result));
+                }
+                if let Some(kind) = self.kind.filter(|kind| !kind.matches(result)) {
+                    return Err(format!("expected a {:?} result, got {}", kind, result));
+                }
+                match self.fields.iter().find(|field| result.get(field).is_none()) {
+                    Some(field) => Err(format!("missing field {} in result {}", field, result)),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// Case of the conformance suite whose response did not meet the expectations.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConformanceFailure {
+    pub method: String,
+    pub params: Vec<Value>,
+    pub reason: String,
+}
+
+/// Results of the conformance suite, printed by `--selftest`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConformanceReport {
+    pub passed: usize,
+    /// The cases of the methods not served by the node, e.g. of the namespaces not enabled.
+    pub skipped: usize,
+    pub failures: Vec<ConformanceFailure>,
+}
+
+/// Returns the recorded conformance cases.
+pub fn conformance_cases() -> anyhow::Result<Vec<ConformanceCase>> {
+    serde_json::from_str(CONFORMANCE_CASES).context("failed parsing the conformance cases")
+}
+
+/// Sends the requests of the cases in order to the RPC methods of the `io` handler, and checks the responses.
+pub async fn run_conformance_suite<T: Middleware<Meta>>(
+    io: &MetaIoHandler<Meta, T>,
+    cases: &[ConformanceCase],
+) -> ConformanceReport {
+    let mut report = ConformanceReport::default();
+    for (id, case) in cases.iter().enumerate() {
+        if !io.iter().any(|(method, _)| *method == case.method) {
+            report.skipped += 1;
+            continue;
+        }
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": case.method,
+            "params": case.params,
+        });
+        let response = io
+            .handle_request(&request.to_string(), Meta::default())
+            .await;
+        let checked = match response.map(|response| serde_json::from_str::<Value>(&response)) {
+            Some(Ok(response)) => case.check(&response),
+            Some(Err(err)) => Err(format!("invalid response: {}", err)),
+            None => Err("no response".to_string()),
+        };
+        match checked {
+            Ok(()) => report.passed += 1,
+            Err(reason) => report.failures.push(ConformanceFailure {
+                method: case.method.clone(),
+                params: case.params.clone(),
+                reason,
+            }),
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http_fork_source::HttpForkSource, logging_middleware::LoggingMiddleware,
+        node::InMemoryNode, rpc_filter::RpcFilterMiddleware, server::build_io_handler,
+    };
+    use std::collections::BTreeSet;
+    use tracing_subscriber::filter::LevelFilter;
+
+    fn io_handler() -> MetaIoHandler<
+        Meta,
+        (
+            LoggingMiddleware,
+            RpcFilterMiddleware,
+            crate::chaos::ChaosMiddleware,
+        ),
+    > {
+        build_io_handler(
+            InMemoryNode::<HttpForkSource>::default(),
+            LoggingMiddleware::new(LevelFilter::INFO, false),
+            RpcFilterMiddleware::default(),
+            &[],
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_conformance_cases_cover_all_methods() {
+        let io = io_handler();
+        let methods: BTreeSet<_> = io.iter().map(|(method, _)| method.clone()).collect();
+        let covered: BTreeSet<_> = conformance_cases()
+            .expect("failed parsing cases")
+            .into_iter()
+            .map(|case| case.method)
+            .collect();
+
+        assert_eq!(
+            Vec::<&String>::new(),
+            methods.difference(&covered).collect::<Vec<_>>(),
+            "methods without conformance cases"
+        );
+        assert_eq!(
+            Vec::<&String>::new(),
+            covered.difference(&methods).collect::<Vec<_>>(),
+            "conformance cases of unknown methods"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conformance_suite_passes() {
+        let cases = conformance_cases().expect("failed parsing cases");
+        let report = run_conformance_suite(&io_handler(), &cases).await;
+
+        assert_eq!(Vec::<ConformanceFailure>::new(), report.failures);
+        assert_eq!(cases.len(), report.passed);
+    }
+
+    #[test]
+    fn test_conformance_case_checks_response() {
+        let case = ConformanceCase {
+            method: "eth_getBlockByNumber".to_string(),
+            params: vec![],
+            result: None,
+            kind: Some(ResultKind::Object),
+            fields: vec!["hash".to_string()],
+            error: None,
+        };
+
+        assert_eq!(
+            Ok(()),
+            case.check(&serde_json::json!({"result": {"hash": "0x1"}}))
+        );
+        assert!(case.check(&serde_json::json!({"result": {}})).is_err());
+        assert!(case.check(&serde_json::json!({"result": null})).is_err());
+        assert!(case
+            .check(&serde_json::json!({"error": {"code": -32602}}))
+            .is_err());
+        assert!(ResultKind::Quantity.matches(&serde_json::json!("0x1a")));
+        assert!(!ResultKind::Quantity.matches(&serde_json::json!("0x")));
+        assert!(!ResultKind::Address.matches(&serde_json::json!("0x1a")));
+    }
+}
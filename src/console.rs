@@ -0,0 +1,279 @@
+use std::io::{self, BufRead, Write};
+
+use anyhow::{anyhow, Context};
+use colored::Colorize;
+use serde_json::{json, Value};
+use zksync_basic_types::U256;
+
+const HELP: &str = "Available commands:
+  balance <address>                  Get the balance of an account
+  setBalance <address> <wei>         Set the balance of an account
+  nonce <address>                    Get the nonce of an account
+  code <address>                     Get the code deployed at an address
+  storage <address> <slot>           Read a storage slot of an account
+  send <from> <to> <wei> [data]      Send a transaction from an impersonated account
+  call <to> <data>                   Execute a call without creating a transaction
+  tx <hash>                          Get the receipt of a transaction
+  block [number]                     Get a block (latest by default)
+  mine [blocks]                      Mine one or more blocks
+  snapshot                           Snapshot the state of the node
+  revert <id>                        Revert the node to a snapshot
+  rpc <method> [params]              Call any RPC method with JSON encoded params
+  help                               Show this message
+  exit                               Leave the console";
+
+/// Single command entered in the console.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConsoleCommand {
+    Help,
+    Exit,
+    /// A JSON-RPC request that is sent to the node.
+    Rpc {
+        method: String,
+        params: Value,
+    },
+}
+
+/// Parses a line entered in the console. Returns `None` for empty lines.
+pub fn parse_command(line: &str) -> anyhow::Result<Option<ConsoleCommand>> {
+    let line = line.trim();
+    let (name, rest) = match line.split_once(char::is_whitespace) {
+        Some((name, rest)) => (name, rest.trim()),
+        None => (line, ""),
+    };
+    if name.is_empty() {
+        return Ok(None);
+    }
+    if name == "rpc" {
+        let (method, params) = match rest.split_once(char::is_whitespace) {
+            Some((method, params)) => (
+                method,
+                serde_json::from_str(params).context("params must be valid JSON")?,
+            ),
+            None => (rest, json!([])),
+        };
+        if method.is_empty() {
+            return Err(anyhow!("usage: rpc <method> [params]"));
+        }
+        return Ok(Some(rpc(method, params)));
+    }
+
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let command = match (name, args.as_slice()) {
+        ("help", []) => ConsoleCommand::Help,
+        ("exit", []) | ("quit", []) => ConsoleCommand::Exit,
+        ("balance", [address]) => rpc("eth_getBalance", json!([address, "latest"])),
+        ("setBalance", [address, wei]) => {
+            rpc("hardhat_setBalance", json!([address, to_hex_quantity(wei)?]))
+        }
+        ("nonce", [address]) => rpc("eth_getTransactionCount", json!([address, "latest"])),
+        ("code", [address]) => rpc("eth_getCode", json!([address, "latest"])),
+        ("storage", [address, slot]) => rpc(
+            "eth_getStorageAt",
+            json!([address, to_hex_quantity(slot)?, "latest"]),
+        ),
+        ("send", [from, to, wei]) => rpc(
+            "eth_sendTransaction",
+            json!([{ "from": from, "to": to, "value": to_hex_quantity(wei)? }]),
+        ),
+        ("send", [from, to, wei, data]) => rpc(
+            "eth_sendTransaction",
+            json!([{ "from": from, "to": to, "value": to_hex_quantity(wei)?, "data": data }]),
+        ),
+        ("call", [to, data]) => rpc("eth_call", json!([{ "to": to, "data": data }, "latest"])),
+        ("tx", [hash]) => rpc("eth_getTransactionReceipt", json!([hash])),
+        ("block", []) => rpc("eth_getBlockByNumber", json!(["latest", false])),
+        ("block", [number]) => rpc(
+            "eth_getBlockByNumber",
+            json!([to_block_tag(number)?, false]),
+        ),
+        ("mine", []) => rpc("evm_mine", json!([])),
+        ("mine", [blocks]) => rpc("hardhat_mine", json!([to_hex_quantity(blocks)?])),
+        ("snapshot", []) => rpc("evm_snapshot", json!([])),
+        ("revert", [id]) => rpc("evm_revert", json!([to_hex_quantity(id)?])),
+        _ => {
+            return Err(anyhow!(
+                "unknown command or wrong number of arguments: `{}` (type `help` for the list of commands)",
+                line
+            ))
+        }
+    };
+
+    Ok(Some(command))
+}
+
+fn rpc(method: &str, params: Value) -> ConsoleCommand {
+    ConsoleCommand::Rpc {
+        method: method.to_string(),
+        params,
+    }
+}
+
+/// Converts a decimal or `0x` prefixed number into a hex encoded quantity.
+fn to_hex_quantity(value: &str) -> anyhow::Result<String> {
+    let number = match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(value).ok(),
+    };
+    number
+        .map(|number| format!("{:#x}", number))
+        .ok_or_else(|| anyhow!("`{}` is not a valid number", value))
+}
+
+fn to_block_tag(value: &str) -> anyhow::Result<String> {
+    match value {
+        "latest" | "earliest" | "pending" | "committed" | "finalized" => Ok(value.to_string()),
+        _ => to_hex_quantity(value),
+    }
+}
+
+/// Client for the JSON-RPC API of a running node.
+pub struct Console {
+    client: reqwest::Client,
+    url: String,
+    next_id: u64,
+}
+
+impl Console {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.to_string(),
+            next_id: 1,
+        }
+    }
+
+    /// Sends a JSON-RPC request to the node and returns its result.
+    pub async fn request(&mut self, method: &str, params: Value) -> anyhow::Result<Value> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": self.next_id,
+            "method": method,
+            "params": params,
+        });
+        self.next_id += 1;
+
+        let response = self
+            .client
+            .post(&self.url)
+            .header("content-type", "application/json")
+            .body(request.to_string())
+            .send()
+            .await
+            .with_context(|| format!("failed connecting to {}", self.url))?
+            .text()
+            .await?;
+        let mut response: Value =
+            serde_json::from_str(&response).context("failed parsing response")?;
+
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!(
+                "{}",
+                error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown error")
+            ));
+        }
+        Ok(response
+            .get_mut("result")
+            .map(Value::take)
+            .unwrap_or(Value::Null))
+    }
+}
+
+/// Runs an interactive console against the node listening at `url`, until `exit` is entered.
+pub async fn run(url: &str) -> anyhow::Result<()> {
+    let mut console = Console::new(url);
+    let chain_id = console
+        .request("eth_chainId", json!([]))
+        .await
+        .with_context(|| format!("no node is running at {}", url))?;
+    println!(
+        "Connected to {} (chain id {}). Type `help` for the list of commands.",
+        url.bold(),
+        chain_id
+    );
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+
+        match parse_command(&line) {
+            Ok(None) => {}
+            Ok(Some(ConsoleCommand::Help)) => println!("{}", HELP),
+            Ok(Some(ConsoleCommand::Exit)) => break,
+            Ok(Some(ConsoleCommand::Rpc { method, params })) => {
+                match console.request(&method, params).await {
+                    Ok(result) => println!("{}", serde_json::to_string_pretty(&result)?),
+                    Err(err) => println!("{}", format!("Error: {}", err).red()),
+                }
+            }
+            Err(err) => println!("{}", format!("Error: {}", err).red()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks a sample of the fork caches of the node running at `url` with `era_verifyFork` and prints the report.
+/// Fails if any of the cached values differs from the forked network.
+pub async fn verify_fork(url: &str, sample_size: usize) -> anyhow::Result<()> {
+    let report = Console::new(url)
+        .request("era_verifyFork", json!([sample_size]))
+        .await
+        .with_context(|| format!("failed verifying the fork of the node at {}", url))?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    let divergences = report["divergences"].as_array().map_or(0, Vec::len);
+    if divergences > 0 {
+        return Err(anyhow!(
+            "{} cached values differ from the forked network, clear them with era_clearCache",
+            divergences
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(None, parse_command("  ").unwrap());
+        assert_eq!(Some(ConsoleCommand::Exit), parse_command("exit").unwrap());
+        assert_eq!(
+            Some(rpc(
+                "hardhat_setBalance",
+                json!(["0x36615Cf349d7F6344891B1e7CA7C72883F5dc049", "0x3e8"])
+            )),
+            parse_command("setBalance 0x36615Cf349d7F6344891B1e7CA7C72883F5dc049 1000").unwrap()
+        );
+        assert_eq!(
+            Some(rpc("hardhat_mine", json!(["0xa"]))),
+            parse_command("mine 0xa").unwrap()
+        );
+        assert_eq!(
+            Some(rpc("eth_getBlockByNumber", json!(["0x5", false]))),
+            parse_command("block 5").unwrap()
+        );
+        assert_eq!(
+            Some(rpc("eth_blockNumber", json!([]))),
+            parse_command("rpc eth_blockNumber").unwrap()
+        );
+        assert_eq!(
+            Some(rpc("evm_increaseTime", json!([60]))),
+            parse_command("rpc evm_increaseTime [60]").unwrap()
+        );
+
+        assert!(parse_command("balance").is_err());
+        assert!(parse_command("mine ten").is_err());
+        assert!(parse_command("unknown").is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use std::time::{Duration, Instant};
+
+use multivm::interface::{
+    dyn_tracers::vm_1_4_1::DynTracer,
+    tracer::{TracerExecutionStatus, TracerExecutionStopReason},
+    Halt,
+};
+use multivm::vm_latest::{BootloaderState, HistoryMode, SimpleMemory, VmTracer, ZkSyncVmState};
+use zksync_state::WriteStorage;
+
+/// The elapsed time is only checked every this many VM steps, to keep the overhead of the tracer low.
+const TIMEOUT_CHECK_INTERVAL: u64 = 1024;
+
+/// Limits of a single VM execution (e.g. of an `eth_call` or a transaction), protecting the node against
+/// runaway transactions such as infinite loops. Both are unlimited by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    /// Maximum wall-clock time of the execution, in milliseconds.
+    pub timeout_ms: Option<u64>,
+    /// Maximum number of VM steps of the execution, including the ones of the bootloader.
+    pub max_steps: Option<u64>,
+}
+
+/// The role of this tracer is to abort the VM execution with a [Halt::TracerCustom] once it exceeds the
+/// [ExecutionLimits].
+pub struct ExecutionLimitTracer {
+    limits: ExecutionLimits,
+    started_at: Instant,
+    steps: u64,
+}
+
+impl ExecutionLimitTracer {
+    pub fn new(limits: ExecutionLimits) -> Self {
+        Self {
+            limits,
+            started_at: Instant::now(),
+            steps: 0,
+        }
+    }
+
+    /// Returns the reason to abort the execution after another step, if it exceeded a limit.
+    fn exceeded_limit(&mut self) -> Option<String> {
+        self.steps += 1;
+        if let Some(max_steps) = self.limits.max_steps {
+            if self.steps > max_steps {
+                return Some(format!(
+                    "execution exceeded the limit of {} VM steps",
+                    max_steps
+                ));
+            }
+        }
+        if let Some(timeout_ms) = self.limits.timeout_ms {
+            if self.steps % TIMEOUT_CHECK_INTERVAL == 0
+                && self.started_at.elapsed() > Duration::from_millis(timeout_ms)
+            {
+                return Some(format!("execution timed out after {}ms", timeout_ms));
+            }
+        }
+        None
+    }
+}
+
+impl<S, H: HistoryMode> DynTracer<S, SimpleMemory<H>> for ExecutionLimitTracer {}
+
+impl<S: WriteStorage, H: HistoryMode> VmTracer<S, H> for ExecutionLimitTracer {
+    fn finish_cycle(
+        &mut self,
+        _state: &mut ZkSyncVmState<S, H>,
+        _bootloader_state: &mut BootloaderState,
+    ) -> TracerExecutionStatus {
+        match self.exceeded_limit() {
+            Some(reason) => {
+                tracing::warn!("aborting VM execution: {}", reason);
+                TracerExecutionStatus::Stop(TracerExecutionStopReason::Abort(Halt::TracerCustom(
+                    reason,
+                )))
+            }
+            None => TracerExecutionStatus::Continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_execution_limit_tracer_stops_after_max_steps() {
+        let mut tracer = ExecutionLimitTracer::new(ExecutionLimits {
+            timeout_ms: None,
+            max_steps: Some(3),
+        });
+
+        assert_eq!(None, tracer.exceeded_limit());
+        assert_eq!(None, tracer.exceeded_limit());
+        assert_eq!(None, tracer.exceeded_limit());
+        assert_eq!(
+            Some("execution exceeded the limit of 3 VM steps".to_string()),
+            tracer.exceeded_limit()
+        );
+    }
+
+    #[test]
+    fn test_execution_limit_tracer_stops_after_timeout() {
+        let mut tracer = ExecutionLimitTracer::new(ExecutionLimits {
+            timeout_ms: Some(0),
+            max_steps: None,
+        });
+        std::thread::sleep(Duration::from_millis(1));
+
+        let reason = (0..TIMEOUT_CHECK_INTERVAL).find_map(|_| tracer.exceeded_limit());
+        assert_eq!(Some("execution timed out after 0ms".to_string()), reason);
+    }
+}
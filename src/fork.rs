@@ -4,26 +4,34 @@
 //! And ForkDetails - that parses network address and fork height from arguments.
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     convert::{TryFrom, TryInto},
     future::Future,
+    path::PathBuf,
     sync::{Arc, RwLock},
 };
 
+use serde::Serialize;
 use tokio::runtime::Builder;
-use zksync_basic_types::{Address, L1BatchNumber, L2ChainId, MiniblockNumber, H256, U256, U64};
+use zksync_basic_types::{
+    Address, Bytes, L1BatchNumber, L2ChainId, MiniblockNumber, H256, U256, U64,
+};
 
 use zksync_types::{
     api::{
-        Block, BlockDetails, BlockIdVariant, BlockNumber, BridgeAddresses, Transaction,
-        TransactionDetails, TransactionVariant,
+        Block, BlockDetails, BlockIdVariant, BlockNumber, BridgeAddresses, L1BatchDetails,
+        Transaction, TransactionDetails, TransactionVariant,
     },
     l2::L2Tx,
+    transaction_request::CallRequest,
     ProtocolVersionId, StorageKey,
 };
 
 use zksync_state::ReadStorage;
-use zksync_utils::{bytecode::hash_bytecode, h256_to_u256};
+use zksync_utils::{
+    bytecode::{compress_bytecode, hash_bytecode},
+    h256_to_u256,
+};
 
 use zksync_web3_decl::{
     jsonrpsee::http_client::HttpClient, namespaces::EthNamespaceClient, types::Index,
@@ -31,7 +39,11 @@ use zksync_web3_decl::{
 use zksync_web3_decl::{jsonrpsee::http_client::HttpClientBuilder, namespaces::ZksNamespaceClient};
 
 use crate::system_contracts;
-use crate::{cache::CacheConfig, node::TEST_NODE_NETWORK_ID};
+use crate::{
+    cache::{entry_memory, CacheConfig, ForkDataDir, LruCache, CACHE_KINDS},
+    metrics::{CacheStats, METRICS},
+    node::TEST_NODE_NETWORK_ID,
+};
 use crate::{deps::InMemoryStorage, http_fork_source::HttpForkSource};
 
 pub fn block_on<F: Future + Send + 'static>(future: F) -> F::Output
@@ -49,6 +61,25 @@ where
     .unwrap()
 }
 
+/// Name of the cache of storage values read from the forked network.
+const CACHE_STORAGE_VALUES: &str = "storage_values";
+/// Name of the cache of factory deps read from the forked network.
+const CACHE_FACTORY_DEPS: &str = "factory_deps";
+/// Name of the cache of the published lengths of the bytecodes, by their hashes.
+const CACHE_COMPRESSED_BYTECODES: &str = "compressed_bytecodes";
+/// Names of the caches held by the [ForkStorage].
+const STORAGE_CACHE_KINDS: [&str; 3] = [
+    CACHE_STORAGE_VALUES,
+    CACHE_FACTORY_DEPS,
+    CACHE_COMPRESSED_BYTECODES,
+];
+/// Maximum number of entries of the compressed bytecodes cache, unless `--cache-max-size` is set.
+const COMPRESSED_BYTECODES_CACHE_SIZE: usize = 4096;
+/// Name of the set of the accounts whose storage values were read from the forked network.
+const CACHE_LOADED_ACCOUNTS: &str = "loaded_accounts";
+/// Maximum number of the tracked loaded accounts, unless `--cache-max-size` is set.
+const LOADED_ACCOUNTS_CACHE_SIZE: usize = 65536;
+
 /// In memory storage, that allows 'forking' from other network.
 /// If forking is enabled, it reads missing data from remote location.
 /// S - is a struct that is used for source of the fork.
@@ -56,6 +87,8 @@ where
 pub struct ForkStorage<S> {
     pub inner: Arc<RwLock<ForkStorageInner<S>>>,
     pub chain_id: L2ChainId,
+    // The system contracts the local storage starts with, needed when it is reset.
+    system_contracts_options: system_contracts::Options,
 }
 
 #[derive(Debug)]
@@ -63,92 +96,486 @@ pub struct ForkStorageInner<S> {
     // Underlying local storage
     pub raw_storage: InMemoryStorage,
     // Cache of data that was read from remote location.
-    pub value_read_cache: HashMap<StorageKey, H256>,
+    pub value_read_cache: LruCache<StorageKey, H256>,
     // Cache of factory deps that were read from remote location.
-    pub factory_dep_cache: HashMap<H256, Option<Vec<u8>>>,
+    pub factory_dep_cache: LruCache<H256, Option<Vec<u8>>>,
+    // Maximum estimated memory of the storage values and factory deps read from remote location, in bytes. The least
+    // recently used ones are evicted above it.
+    pub max_memory: Option<usize>,
+    // Directory of the disk cache the evicted storage values and factory deps are written to, if caching on disk.
+    pub fork_data_dir: Option<ForkDataDir>,
+    // Accounts whose storage values were read from remote location, the least recently loaded ones being forgotten.
+    pub loaded_accounts: LruCache<Address, ()>,
+    // Cache of the number of bytes published for the bytecodes, which are costly to compress.
+    pub compressed_bytecode_cache: LruCache<H256, usize>,
     // If set - it hold the necessary information on where to fetch the data.
     // If not set - it will simply read from underlying storage.
     pub fork: Option<ForkDetails<S>>,
+    // Journals of the local writes made in the open isolation contexts, the innermost last.
+    pub journals: Vec<StorageJournal>,
+}
+
+/// Original values of the storage slots and factory deps written locally since an isolation context began
+/// (see `era_beginContext`), so that ending the context only costs as much as the writes made in it.
+#[derive(Debug, Default)]
+pub struct StorageJournal {
+    values: HashMap<StorageKey, Option<zksync_types::StorageValue>>,
+    factory_deps: HashMap<H256, Option<Vec<u8>>>,
+}
+
+impl<S> ForkStorageInner<S> {
+    fn new(
+        fork_data_dir: Option<ForkDataDir>,
+        fork: Option<ForkDetails<S>>,
+        chain_id: L2ChainId,
+        system_contracts_options: &system_contracts::Options,
+    ) -> Self {
+        let cache_max_size = fork.as_ref().and_then(|d| d.cache_max_size);
+        ForkStorageInner {
+            raw_storage: InMemoryStorage::with_system_contracts_and_chain_id(
+                chain_id,
+                hash_bytecode,
+                system_contracts_options,
+            ),
+            value_read_cache: LruCache::new(CACHE_STORAGE_VALUES, cache_max_size),
+            max_memory: fork.as_ref().and_then(|d| d.max_memory),
+            fork_data_dir,
+            loaded_accounts: LruCache::new(
+                CACHE_LOADED_ACCOUNTS,
+                Some(cache_max_size.unwrap_or(LOADED_ACCOUNTS_CACHE_SIZE)),
+            ),
+            fork,
+            journals: vec![],
+            factory_dep_cache: LruCache::new(CACHE_FACTORY_DEPS, cache_max_size).with_weigher(
+                |hash, bytecode| {
+                    entry_memory(hash, bytecode) + bytecode.as_ref().map_or(0, Vec::len)
+                },
+            ),
+            compressed_bytecode_cache: LruCache::new(
+                CACHE_COMPRESSED_BYTECODES,
+                Some(cache_max_size.unwrap_or(COMPRESSED_BYTECODES_CACHE_SIZE)),
+            ),
+        }
+    }
+
+    /// Caches a storage value read from the forked network, or from the disk after its eviction.
+    fn cache_value(&mut self, key: StorageKey, value: H256) {
+        for (key, value) in self.value_read_cache.insert(key, value) {
+            self.spill_value(&key, value);
+        }
+        self.enforce_max_memory();
+    }
+
+    /// Caches a factory dep read from the forked network, or from the disk after its eviction.
+    fn cache_factory_dep(&mut self, hash: H256, bytecode: Option<Vec<u8>>) {
+        for (hash, bytecode) in self.factory_dep_cache.insert(hash, bytecode) {
+            self.spill_factory_dep(hash, bytecode);
+        }
+        self.enforce_max_memory();
+    }
+
+    /// Evicts the least recently used storage values and factory deps, across both caches, until their estimated
+    /// memory fits in the [ForkStorageInner::max_memory].
+    fn enforce_max_memory(&mut self) {
+        let max_memory = match self.max_memory {
+            Some(max_memory) => max_memory,
+            None => return,
+        };
+        while self.value_read_cache.memory_usage() + self.factory_dep_cache.memory_usage()
+            > max_memory
+        {
+            let evict_value = match (
+                self.value_read_cache.least_recent_use(),
+                self.factory_dep_cache.least_recent_use(),
+            ) {
+                (Some(value_use), Some(factory_dep_use)) => value_use < factory_dep_use,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            if evict_value {
+                if let Some((key, value)) = self.value_read_cache.pop_least_recently_used() {
+                    self.spill_value(&key, value);
+                }
+            } else if let Some((hash, bytecode)) = self.factory_dep_cache.pop_least_recently_used()
+            {
+                self.spill_factory_dep(hash, bytecode);
+            }
+        }
+    }
+
+    /// Writes an evicted storage value to the disk cache, if any.
+    fn spill_value(&self, key: &StorageKey, value: H256) {
+        if let Some(dir) = &self.fork_data_dir {
+            dir.write_value(key, value);
+        }
+    }
+
+    /// Writes an evicted factory dep to the disk cache, if any. The unknown bytecodes are fetched again.
+    fn spill_factory_dep(&self, hash: H256, bytecode: Option<Vec<u8>>) {
+        if let (Some(dir), Some(bytecode)) = (&self.fork_data_dir, bytecode) {
+            dir.write_factory_dep(hash, &bytecode);
+        }
+    }
+
+    /// Replaces the local storage slots (e.g. when restoring a snapshot), journaling the ones that change.
+    pub fn replace_state(&mut self, state: HashMap<StorageKey, zksync_types::StorageValue>) {
+        if let Some(journal) = self.journals.last_mut() {
+            let old_state = &self.raw_storage.state;
+            for key in old_state.keys().chain(state.keys()) {
+                if old_state.get(key) != state.get(key) {
+                    journal
+                        .values
+                        .entry(*key)
+                        .or_insert_with(|| old_state.get(key).copied());
+                }
+            }
+        }
+        self.raw_storage.state = state;
+    }
+
+    /// Replaces the local factory deps, journaling the ones that change.
+    pub fn replace_factory_deps(&mut self, factory_deps: HashMap<H256, Vec<u8>>) {
+        if let Some(journal) = self.journals.last_mut() {
+            let old_deps = &self.raw_storage.factory_deps;
+            for hash in old_deps.keys().chain(factory_deps.keys()) {
+                if old_deps.get(hash) != factory_deps.get(hash) {
+                    journal
+                        .factory_deps
+                        .entry(*hash)
+                        .or_insert_with(|| old_deps.get(hash).cloned());
+                }
+            }
+        }
+        self.raw_storage.factory_deps = factory_deps;
+    }
+
+    /// Undoes the local writes made since the innermost isolation context began, and closes it.
+    /// Returns `false` if no context is open.
+    pub fn revert_journal(&mut self) -> bool {
+        let journal = match self.journals.pop() {
+            Some(journal) => journal,
+            None => return false,
+        };
+        for (key, value) in journal.values {
+            match value {
+                Some(value) => self.raw_storage.state.insert(key, value),
+                None => self.raw_storage.state.remove(&key),
+            };
+        }
+        for (hash, bytecode) in journal.factory_deps {
+            match bytecode {
+                Some(bytecode) => self.raw_storage.factory_deps.insert(hash, bytecode),
+                None => self.raw_storage.factory_deps.remove(&hash),
+            };
+        }
+        true
+    }
+}
+
+/// Opens the directory of the disk cache of the fork source the evicted fork data of the fork block is written to, if
+/// the source caches on disk.
+fn fork_data_dir<S: ForkSource>(fork: Option<&ForkDetails<S>>) -> Option<ForkDataDir> {
+    let fork = fork?;
+    let cache_dir = fork.fork_source.disk_cache_dir()?;
+    ForkDataDir::open(&cache_dir, fork.l2_miniblock_hash)
+        .map_err(|err| tracing::error!("failed opening fork data directory: {:?}", err))
+        .ok()
+}
+
+/// Result of checking the storage slots and bytecodes cached from the forked network against the network, as
+/// returned by `era_verifyFork`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkVerification {
+    /// The miniblock the network was queried at.
+    pub miniblock: u64,
+    pub checked_slots: usize,
+    pub checked_bytecodes: usize,
+    /// The cached values that differ from the network.
+    pub divergences: Vec<ForkDivergence>,
+}
+
+/// Cached value that differs from the forked network.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ForkDivergence {
+    #[serde(rename_all = "camelCase")]
+    StorageSlot {
+        address: Address,
+        slot: H256,
+        cached: H256,
+        upstream: H256,
+    },
+    /// The lengths are `None` if the bytecode is unknown.
+    #[serde(rename_all = "camelCase")]
+    Bytecode {
+        hash: H256,
+        cached_len: Option<usize>,
+        upstream_len: Option<usize>,
+    },
 }
 
 impl<S: ForkSource> ForkStorage<S> {
     pub fn new(
         fork: Option<ForkDetails<S>>,
         system_contracts_options: &system_contracts::Options,
+        override_chain_id: Option<u32>,
     ) -> Self {
         let chain_id = fork
             .as_ref()
             .and_then(|d| d.overwrite_chain_id)
-            .unwrap_or(L2ChainId::from(TEST_NODE_NETWORK_ID));
+            .unwrap_or(L2ChainId::from(
+                override_chain_id.unwrap_or(TEST_NODE_NETWORK_ID),
+            ));
         tracing::info!("Starting network with chain id: {:?}", chain_id);
 
         ForkStorage {
-            inner: Arc::new(RwLock::new(ForkStorageInner {
-                raw_storage: InMemoryStorage::with_system_contracts_and_chain_id(
-                    chain_id,
-                    hash_bytecode,
-                    system_contracts_options,
-                ),
-                value_read_cache: Default::default(),
+            inner: Arc::new(RwLock::new(ForkStorageInner::new(
+                fork_data_dir(fork.as_ref()),
                 fork,
-                factory_dep_cache: Default::default(),
-            })),
+                chain_id,
+                system_contracts_options,
+            ))),
             chain_id,
+            system_contracts_options: system_contracts_options.clone(),
         }
     }
 
+    /// Forks the network at another block, discarding the local state and the cached fork data.
+    pub fn reset_fork(&self, fork: ForkDetails<S>) {
+        *self.inner.write().unwrap() = ForkStorageInner::new(
+            fork_data_dir(Some(&fork)),
+            Some(fork),
+            self.chain_id,
+            &self.system_contracts_options,
+        );
+    }
+
     fn read_value_internal(&self, key: &StorageKey) -> zksync_types::StorageValue {
-        let mut mutator = self.inner.write().unwrap();
-        let local_storage = mutator.raw_storage.read_value(key);
+        let local_storage = self.inner.write().unwrap().raw_storage.read_value(key);
+        if !H256::is_zero(&local_storage) {
+            return local_storage;
+        }
+        self.read_fork_value(key)
+    }
 
-        if let Some(fork) = &mutator.fork {
-            if !H256::is_zero(&local_storage) {
-                return local_storage;
-            }
+    /// Returns the value of the key at the fork point, ignoring the local writes. Zero when not forking.
+    pub fn read_fork_value(&self, key: &StorageKey) -> zksync_types::StorageValue {
+        let mut guard = self.inner.write().unwrap();
+        // Borrow the fields separately, the fork details are used while the caches are updated.
+        let mutator = &mut *guard;
 
-            if let Some(value) = mutator.value_read_cache.get(key) {
+        if let Some(fork) = &mutator.fork {
+            let cached = mutator.value_read_cache.get(key);
+            METRICS.observe_cache_lookup(CACHE_STORAGE_VALUES, cached.is_some());
+            if let Some(value) = cached {
                 return *value;
             }
             let l2_miniblock = fork.l2_miniblock;
             let key_ = *key;
 
-            let result = fork
-                .fork_source
-                .get_storage_at(
-                    *key_.account().address(),
-                    h256_to_u256(*key_.key()),
-                    Some(BlockIdVariant::BlockNumber(BlockNumber::Number(U64::from(
-                        l2_miniblock,
-                    )))),
-                )
-                .unwrap();
-
-            mutator.value_read_cache.insert(*key, result);
+            let spilled = mutator
+                .fork_data_dir
+                .as_ref()
+                .and_then(|dir| dir.read_value(key));
+            let result = match spilled {
+                Some(value) => {
+                    METRICS.observe_cache_disk_read(CACHE_STORAGE_VALUES);
+                    value
+                }
+                None => {
+                    let value = fork
+                        .fork_source
+                        .get_storage_at(
+                            *key_.account().address(),
+                            h256_to_u256(*key_.key()),
+                            Some(BlockIdVariant::BlockNumber(BlockNumber::Number(U64::from(
+                                l2_miniblock,
+                            )))),
+                        )
+                        .unwrap();
+                    METRICS.observe_fork_load(CACHE_STORAGE_VALUES, value.as_bytes().len());
+                    if mutator.loaded_accounts.get(key_.address()).is_none() {
+                        mutator.loaded_accounts.insert(*key_.address(), ());
+                        METRICS.observe_fork_loaded_account();
+                    }
+                    value
+                }
+            };
+
+            mutator.cache_value(*key, result);
             result
         } else {
-            local_storage
+            H256::zero()
         }
     }
 
     fn load_factory_dep_internal(&self, hash: H256) -> Option<Vec<u8>> {
-        let mut mutator = self.inner.write().unwrap();
+        let mut guard = self.inner.write().unwrap();
+        // Borrow the fields separately, the fork details are used while the caches are updated.
+        let mutator = &mut *guard;
         let local_storage = mutator.raw_storage.load_factory_dep(hash);
         if let Some(fork) = &mutator.fork {
             if local_storage.is_some() {
                 return local_storage;
             }
-            if let Some(value) = mutator.factory_dep_cache.get(&hash) {
+            let cached = mutator.factory_dep_cache.get(&hash);
+            METRICS.observe_cache_lookup(CACHE_FACTORY_DEPS, cached.is_some());
+            if let Some(value) = cached {
                 return value.clone();
             }
 
-            let result = fork.fork_source.get_bytecode_by_hash(hash).unwrap();
-            mutator.factory_dep_cache.insert(hash, result.clone());
+            let spilled = mutator
+                .fork_data_dir
+                .as_ref()
+                .and_then(|dir| dir.read_factory_dep(hash));
+            let result = match spilled {
+                Some(bytecode) => {
+                    METRICS.observe_cache_disk_read(CACHE_FACTORY_DEPS);
+                    Some(bytecode)
+                }
+                None => {
+                    let bytecode = fork.fork_source.get_bytecode_by_hash(hash).unwrap();
+                    METRICS.observe_fork_load(
+                        CACHE_FACTORY_DEPS,
+                        bytecode.as_ref().map_or(0, Vec::len),
+                    );
+                    bytecode
+                }
+            };
+            mutator.cache_factory_dep(hash, result.clone());
             result
         } else {
             local_storage
         }
     }
 
+    /// Returns the lookup statistics and the number of entries of every cache of the data read
+    /// from the forked network, keyed by the cache name.
+    pub fn cache_stats(&self) -> BTreeMap<String, CacheStats> {
+        let mut sizes: HashMap<&str, usize> = HashMap::new();
+        let mut memory: HashMap<&str, usize> = HashMap::new();
+        if let Ok(reader) = self.inner.read() {
+            sizes.insert(CACHE_STORAGE_VALUES, reader.value_read_cache.len());
+            sizes.insert(CACHE_FACTORY_DEPS, reader.factory_dep_cache.len());
+            sizes.insert(
+                CACHE_COMPRESSED_BYTECODES,
+                reader.compressed_bytecode_cache.len(),
+            );
+            memory.insert(CACHE_STORAGE_VALUES, reader.value_read_cache.memory_usage());
+            memory.insert(CACHE_FACTORY_DEPS, reader.factory_dep_cache.memory_usage());
+            memory.insert(
+                CACHE_COMPRESSED_BYTECODES,
+                reader.compressed_bytecode_cache.memory_usage(),
+            );
+            if let Some(fork) = &reader.fork {
+                sizes.extend(fork.fork_source.cache_sizes());
+            }
+        }
+
+        STORAGE_CACHE_KINDS
+            .iter()
+            .chain(CACHE_KINDS.iter())
+            .map(|kind| {
+                let stats = CacheStats {
+                    size: sizes.get(kind).copied().unwrap_or_default(),
+                    memory_bytes: memory.get(kind).copied().unwrap_or_default(),
+                    ..METRICS.cache_stats(kind)
+                };
+                (kind.to_string(), stats)
+            })
+            .collect()
+    }
+
+    /// Removes the entries of the cache named `kind`, or of all the caches if `None`.
+    /// Returns `false` if there's no cache with that name.
+    pub fn clear_cache(&self, kind: Option<&str>) -> bool {
+        let kinds: Vec<&str> = match kind {
+            Some(kind) if STORAGE_CACHE_KINDS.contains(&kind) => vec![kind],
+            Some(kind) if CACHE_KINDS.contains(&kind) => vec![kind],
+            Some(_) => return false,
+            None => STORAGE_CACHE_KINDS
+                .iter()
+                .chain(CACHE_KINDS.iter())
+                .copied()
+                .collect(),
+        };
+
+        let mut writer = self.inner.write().unwrap();
+        for kind in kinds {
+            match kind {
+                CACHE_STORAGE_VALUES => {
+                    writer.value_read_cache.clear();
+                    if let Some(dir) = &writer.fork_data_dir {
+                        dir.clear(true);
+                    }
+                }
+                CACHE_FACTORY_DEPS => {
+                    writer.factory_dep_cache.clear();
+                    if let Some(dir) = &writer.fork_data_dir {
+                        dir.clear(false);
+                    }
+                }
+                CACHE_COMPRESSED_BYTECODES => writer.compressed_bytecode_cache.clear(),
+                _ => {
+                    if let Some(fork) = &writer.fork {
+                        fork.fork_source.clear_cache(kind);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Checks up to `sample_size` of the most recently used storage slots and bytecodes each, cached from the
+    /// forked network, against the network at the fork miniblock, to detect stale or corrupted caches.
+    pub fn verify_cache(&self, sample_size: usize) -> eyre::Result<ForkVerification> {
+        let reader = self.inner.read().unwrap();
+        let fork = reader
+            .fork
+            .as_ref()
+            .ok_or_else(|| eyre::eyre!("the node is not forking a network"))?;
+        let block = || {
+            Some(BlockIdVariant::BlockNumber(BlockNumber::Number(U64::from(
+                fork.l2_miniblock,
+            ))))
+        };
+
+        let mut verification = ForkVerification {
+            miniblock: fork.l2_miniblock,
+            ..Default::default()
+        };
+        for (key, cached) in reader.value_read_cache.iter().take(sample_size) {
+            let upstream = fork.fork_source.get_storage_at(
+                *key.address(),
+                h256_to_u256(*key.key()),
+                block(),
+            )?;
+            verification.checked_slots += 1;
+            if upstream != *cached {
+                verification.divergences.push(ForkDivergence::StorageSlot {
+                    address: *key.address(),
+                    slot: *key.key(),
+                    cached: *cached,
+                    upstream,
+                });
+            }
+        }
+        for (hash, cached) in reader.factory_dep_cache.iter().take(sample_size) {
+            let upstream = fork.fork_source.get_bytecode_by_hash(*hash)?;
+            verification.checked_bytecodes += 1;
+            if upstream != *cached {
+                verification.divergences.push(ForkDivergence::Bytecode {
+                    hash: *hash,
+                    cached_len: cached.as_ref().map(Vec::len),
+                    upstream_len: upstream.as_ref().map(Vec::len),
+                });
+            }
+        }
+        Ok(verification)
+    }
+
     /// Check if this is the first time when we're ever writing to this key.
     /// This has impact on amount of pubdata that we have to spend for the write.
     fn is_write_initial_internal(&self, key: &StorageKey) -> bool {
@@ -215,19 +642,73 @@ impl<S: std::fmt::Debug + ForkSource> ReadStorage for &ForkStorage<S> {
 
 impl<S> ForkStorage<S> {
     pub fn set_value(&mut self, key: StorageKey, value: zksync_types::StorageValue) {
-        let mut mutator = self.inner.write().unwrap();
+        let mut guard = self.inner.write().unwrap();
+        let mutator = &mut *guard;
+        if let Some(journal) = mutator.journals.last_mut() {
+            let state = &mutator.raw_storage.state;
+            journal
+                .values
+                .entry(key)
+                .or_insert_with(|| state.get(&key).copied());
+        }
         mutator.raw_storage.set_value(key, value)
     }
     pub fn store_factory_dep(&mut self, hash: H256, bytecode: Vec<u8>) {
-        let mut mutator = self.inner.write().unwrap();
+        let mut guard = self.inner.write().unwrap();
+        let mutator = &mut *guard;
+        if let Some(journal) = mutator.journals.last_mut() {
+            let factory_deps = &mutator.raw_storage.factory_deps;
+            journal
+                .factory_deps
+                .entry(hash)
+                .or_insert_with(|| factory_deps.get(&hash).cloned());
+        }
         mutator.raw_storage.store_factory_dep(hash, bytecode)
     }
+
+    /// Returns the number of bytes published for the bytecode with the given hash: the length of its compressed
+    /// form, or of the bytecode itself if it can't be compressed. Compressing large contracts is costly, so the
+    /// result is cached across transactions.
+    pub fn published_bytecode_len(&self, hash: H256, bytecode: &[u8]) -> usize {
+        let cached = self
+            .inner
+            .write()
+            .unwrap()
+            .compressed_bytecode_cache
+            .get(&hash)
+            .copied();
+        METRICS.observe_cache_lookup(CACHE_COMPRESSED_BYTECODES, cached.is_some());
+        if let Some(len) = cached {
+            return len;
+        }
+
+        // compressed without holding the lock, the cached length is the same for concurrent callers
+        let len = compress_bytecode(bytecode)
+            .map(|compressed| compressed.len())
+            .unwrap_or(bytecode.len());
+        self.inner
+            .write()
+            .unwrap()
+            .compressed_bytecode_cache
+            .insert(hash, len);
+        len
+    }
 }
 
 /// Trait that provides necessary data when
 /// forking a remote chain.
 /// The method signatures are similar to methods from ETHNamespace and ZKNamespace.
 pub trait ForkSource {
+    /// Returns the URL of the forked network.
+    fn get_fork_url(&self) -> eyre::Result<String> {
+        Err(unsupported("get_fork_url"))
+    }
+
+    /// Replaces the URL of the forked network, e.g. to switch to another provider of the same network.
+    fn set_fork_url(&mut self, _url: String) -> eyre::Result<()> {
+        Err(unsupported("set_fork_url"))
+    }
+
     /// Returns the Storage value at a given index for given address.
     fn get_storage_at(
         &self,
@@ -239,6 +720,11 @@ pub trait ForkSource {
     /// Returns the bytecode stored under this hash (if available).
     fn get_bytecode_by_hash(&self, hash: H256) -> eyre::Result<Option<Vec<u8>>>;
 
+    /// Returns the code deployed at the address at the given block.
+    fn get_code(&self, _address: Address, _block: Option<BlockIdVariant>) -> eyre::Result<Bytes> {
+        Err(unsupported("get_code"))
+    }
+
     /// Returns the transaction for a given hash.
     fn get_transaction_by_hash(&self, hash: H256) -> eyre::Result<Option<Transaction>>;
 
@@ -268,6 +754,16 @@ pub trait ForkSource {
     /// Returns the block details for a given miniblock number.
     fn get_block_details(&self, miniblock: MiniblockNumber) -> eyre::Result<Option<BlockDetails>>;
 
+    /// Returns the numbers of the first and the last block of an L1 batch.
+    fn get_block_range(&self, _batch: L1BatchNumber) -> eyre::Result<Option<(U64, U64)>> {
+        Err(unsupported("get_block_range"))
+    }
+
+    /// Returns the details of an L1 batch.
+    fn get_l1_batch_details(&self, _batch: L1BatchNumber) -> eyre::Result<Option<L1BatchDetails>> {
+        Err(unsupported("get_l1_batch_details"))
+    }
+
     /// Returns the  transaction count for a given block hash.
     fn get_block_transaction_count_by_hash(&self, block_hash: H256) -> eyre::Result<Option<U256>>;
 
@@ -300,6 +796,38 @@ pub trait ForkSource {
         from: u32,
         limit: u8,
     ) -> eyre::Result<Vec<zksync_web3_decl::types::Token>>;
+
+    /// Executes a call on the forked network at the given block, e.g. at a block preceding the fork.
+    fn call(&self, _request: CallRequest, _block: Option<BlockIdVariant>) -> eyre::Result<Bytes> {
+        Err(unsupported("call"))
+    }
+
+    /// Estimates the gas of a transaction on the forked network at the given block.
+    fn estimate_gas(
+        &self,
+        _request: CallRequest,
+        _block: Option<BlockNumber>,
+    ) -> eyre::Result<U256> {
+        Err(unsupported("estimate_gas"))
+    }
+
+    /// Returns the number of entries in every cache of the source, keyed by the cache name.
+    fn cache_sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![]
+    }
+
+    /// Removes the entries of the source cache named `kind`.
+    fn clear_cache(&self, _kind: &str) {}
+
+    /// Returns the directory of the cache of the source, if it's persisted on disk.
+    fn disk_cache_dir(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Returns the error of a [ForkSource] method the source doesn't implement.
+fn unsupported(method: &str) -> eyre::Report {
+    eyre::eyre!("{} is not supported by the fork source", method)
 }
 
 /// Holds the information about the original chain.
@@ -317,6 +845,10 @@ pub struct ForkDetails<S> {
     pub block_timestamp: u64,
     pub overwrite_chain_id: Option<L2ChainId>,
     pub l1_gas_price: u64,
+    // Maximum number of entries of every type kept in the in-memory caches of the fork data.
+    pub cache_max_size: Option<usize>,
+    // Maximum estimated memory of the storage values and factory deps kept in memory, in bytes.
+    pub max_memory: Option<usize>,
 }
 
 const SUPPORTED_VERSIONS: &[ProtocolVersionId] = &[
@@ -402,8 +934,25 @@ impl ForkDetails<HttpForkSource> {
             l2_miniblock_hash: root_hash,
             overwrite_chain_id: chain_id,
             l1_gas_price: block_details.base.l1_gas_price,
+            cache_max_size: None,
+            max_memory: None,
         }
     }
+
+    /// Limits the number of entries of every type kept in the in-memory caches of the fork data,
+    /// evicting the least recently used ones.
+    pub fn with_cache_max_size(mut self, max_size: Option<usize>) -> Self {
+        self.fork_source.set_cache_max_size(max_size);
+        self.cache_max_size = max_size;
+        self
+    }
+
+    /// Limits the estimated memory of the storage values and factory deps read from the forked network, evicting
+    /// the least recently used ones to the disk cache, if any.
+    pub fn with_max_memory(mut self, max_memory: Option<usize>) -> Self {
+        self.max_memory = max_memory;
+        self
+    }
     /// Create a fork from a given network at a given height.
     pub async fn from_network(fork: &str, fork_at: Option<u64>, cache_config: CacheConfig) -> Self {
         let (url, client) = Self::fork_to_url_and_client(fork);
@@ -440,6 +989,49 @@ impl ForkDetails<HttpForkSource> {
     }
 }
 
+impl<S: ForkSource + Clone> ForkDetails<S> {
+    /// Returns the details of the fork of the same network at another block, e.g. to warp to it.
+    pub fn at_miniblock(&self, miniblock: u64) -> eyre::Result<Self> {
+        let block_details = self
+            .fork_source
+            .get_block_details(MiniblockNumber(miniblock as u32))?
+            .ok_or_else(|| eyre::eyre!("could not find block {}", miniblock))?;
+        let root_hash = block_details
+            .base
+            .root_hash
+            .ok_or_else(|| eyre::eyre!("block {} is missing the root hash", miniblock))?;
+        let block = self
+            .fork_source
+            .get_block_by_hash(root_hash, true)?
+            .ok_or_else(|| eyre::eyre!("could not find block {} ({:#x})", miniblock, root_hash))?;
+        if !block_details
+            .protocol_version
+            .map(supported_protocol_versions)
+            .unwrap_or(false)
+        {
+            return Err(eyre::eyre!(
+                "block {} is using the unsupported protocol version: {:?}. This binary supports versions {}.",
+                miniblock,
+                block_details.protocol_version,
+                supported_versions_to_string()
+            ));
+        }
+
+        Ok(ForkDetails {
+            fork_source: self.fork_source.clone(),
+            l1_block: block_details.l1_batch_number,
+            l2_block: block,
+            block_timestamp: block_details.base.timestamp,
+            l2_miniblock: miniblock,
+            l2_miniblock_hash: root_hash,
+            overwrite_chain_id: self.overwrite_chain_id,
+            l1_gas_price: block_details.base.l1_gas_price,
+            cache_max_size: self.cache_max_size,
+            max_memory: self.max_memory,
+        })
+    }
+}
+
 impl<S: ForkSource> ForkDetails<S> {
     /// Return URL and HTTP client for a given fork name.
     pub fn fork_to_url_and_client(fork: &str) -> (&str, HttpClient) {
@@ -492,13 +1084,14 @@ impl<S: ForkSource> ForkDetails<S> {
 
 #[cfg(test)]
 mod tests {
-    use zksync_basic_types::{AccountTreeId, L1BatchNumber, H256};
+    use zksync_basic_types::{AccountTreeId, Address, L1BatchNumber, H256};
     use zksync_state::ReadStorage;
     use zksync_types::{api::TransactionVariant, StorageKey};
 
     use crate::{deps::InMemoryStorage, system_contracts, testing};
 
-    use super::{ForkDetails, ForkStorage};
+    use super::{ForkDetails, ForkDivergence, ForkSource, ForkStorage};
+    use crate::cache::{entry_memory, ForkDataDir};
 
     #[test]
     fn test_initial_writes() {
@@ -525,9 +1118,11 @@ mod tests {
             block_timestamp: 0,
             overwrite_chain_id: None,
             l1_gas_price: 100,
+            cache_max_size: None,
+            max_memory: None,
         };
 
-        let mut fork_storage = ForkStorage::new(Some(fork_details), &options);
+        let mut fork_storage = ForkStorage::new(Some(fork_details), &options, None);
 
         assert_eq!(fork_storage.is_write_initial(&never_written_key), true);
         assert_eq!(fork_storage.is_write_initial(&key_with_some_value), false);
@@ -539,4 +1134,216 @@ mod tests {
         fork_storage.set_value(key_with_value_0, H256::zero());
         assert_eq!(fork_storage.is_write_initial(&key_with_value_0), false);
     }
+
+    #[test]
+    fn test_published_bytecode_len_is_cached() {
+        let options = system_contracts::Options::default();
+        let fork_storage = ForkStorage::<&testing::ExternalStorage>::new(None, &options, None);
+        let bytecode = vec![0u8; 64];
+        let hash = zksync_utils::bytecode::hash_bytecode(&bytecode);
+
+        let len = fork_storage.published_bytecode_len(hash, &bytecode);
+        assert!(len > 0);
+        assert_eq!(
+            Some(&len),
+            fork_storage
+                .inner
+                .write()
+                .unwrap()
+                .compressed_bytecode_cache
+                .get(&hash)
+        );
+
+        // the cached length is returned without compressing the bytecode again
+        fork_storage
+            .inner
+            .write()
+            .unwrap()
+            .compressed_bytecode_cache
+            .insert(hash, 1);
+        assert_eq!(1, fork_storage.published_bytecode_len(hash, &bytecode));
+    }
+
+    #[test]
+    fn test_verify_cache_reports_divergences() {
+        let account = AccountTreeId::default();
+        let key = StorageKey::new(account, H256::from_low_u64_be(1));
+        let stale_key = StorageKey::new(account, H256::from_low_u64_be(2));
+        let mut in_memory_storage = InMemoryStorage::default();
+        in_memory_storage.set_value(key, H256::from_low_u64_be(13));
+        in_memory_storage.set_value(stale_key, H256::from_low_u64_be(14));
+        let external_storage = testing::ExternalStorage {
+            raw_storage: in_memory_storage,
+        };
+        let fork_details = ForkDetails {
+            fork_source: &external_storage,
+            l1_block: L1BatchNumber(1),
+            l2_block: zksync_types::api::Block::<TransactionVariant>::default(),
+            l2_miniblock: 1,
+            l2_miniblock_hash: H256::zero(),
+            block_timestamp: 0,
+            overwrite_chain_id: None,
+            l1_gas_price: 100,
+            cache_max_size: None,
+            max_memory: None,
+        };
+        let fork_storage = ForkStorage::new(
+            Some(fork_details),
+            &system_contracts::Options::default(),
+            None,
+        );
+
+        assert_eq!(
+            H256::from_low_u64_be(13),
+            fork_storage.read_fork_value(&key)
+        );
+        {
+            let mut writer = fork_storage.inner.write().unwrap();
+            writer
+                .value_read_cache
+                .insert(stale_key, H256::from_low_u64_be(7));
+            writer
+                .factory_dep_cache
+                .insert(H256::repeat_byte(0x1), Some(vec![0u8; 32]));
+        }
+
+        let verification = fork_storage
+            .verify_cache(10)
+            .expect("failed verifying cache");
+        assert_eq!(1, verification.miniblock);
+        assert_eq!(2, verification.checked_slots);
+        assert_eq!(1, verification.checked_bytecodes);
+        assert_eq!(
+            vec![
+                ForkDivergence::StorageSlot {
+                    address: *stale_key.address(),
+                    slot: *stale_key.key(),
+                    cached: H256::from_low_u64_be(7),
+                    upstream: H256::from_low_u64_be(14),
+                },
+                ForkDivergence::Bytecode {
+                    hash: H256::repeat_byte(0x1),
+                    cached_len: Some(32),
+                    upstream_len: None,
+                },
+            ],
+            verification.divergences
+        );
+        assert_eq!(1, fork_storage.verify_cache(1).unwrap().checked_slots);
+    }
+
+    #[test]
+    fn test_max_memory_evicts_fork_data_to_disk() {
+        let account = AccountTreeId::default();
+        let keys: Vec<StorageKey> = (0..3)
+            .map(|index| StorageKey::new(account, H256::from_low_u64_be(index)))
+            .collect();
+        let mut in_memory_storage = InMemoryStorage::default();
+        for (index, key) in keys.iter().enumerate() {
+            in_memory_storage.set_value(*key, H256::from_low_u64_be(index as u64 + 10));
+        }
+        let external_storage = testing::ExternalStorage {
+            raw_storage: in_memory_storage,
+        };
+        let fork_details = ForkDetails {
+            fork_source: &external_storage,
+            l1_block: L1BatchNumber(1),
+            l2_block: zksync_types::api::Block::<TransactionVariant>::default(),
+            l2_miniblock: 1,
+            l2_miniblock_hash: H256::zero(),
+            block_timestamp: 0,
+            overwrite_chain_id: None,
+            l1_gas_price: 100,
+            cache_max_size: None,
+            max_memory: Some(2 * entry_memory(&keys[0], &H256::zero())),
+        };
+        let fork_storage = ForkStorage::new(
+            Some(fork_details),
+            &system_contracts::Options::default(),
+            None,
+        );
+        let dir = tempdir::TempDir::new("fork-data").unwrap();
+        let fork_data_dir = ForkDataDir::open(dir.path(), H256::zero()).unwrap();
+        fork_storage.inner.write().unwrap().fork_data_dir = Some(fork_data_dir.clone());
+
+        for key in &keys {
+            fork_storage.read_fork_value(key);
+        }
+        {
+            let reader = fork_storage.inner.read().unwrap();
+            assert_eq!(2, reader.value_read_cache.len());
+            assert_eq!(1, reader.loaded_accounts.len());
+        }
+        assert_eq!(
+            Some(H256::from_low_u64_be(10)),
+            fork_data_dir.read_value(&keys[0])
+        );
+
+        // the evicted value is read back from the disk rather than the network
+        fork_data_dir.write_value(&keys[0], H256::repeat_byte(0xff));
+        assert_eq!(
+            H256::repeat_byte(0xff),
+            fork_storage.read_fork_value(&keys[0])
+        );
+        assert_eq!(
+            Some(H256::from_low_u64_be(11)),
+            fork_data_dir.read_value(&keys[1])
+        );
+    }
+
+    #[test]
+    fn test_loaded_accounts_are_bounded_by_cache_max_size() {
+        let keys: Vec<StorageKey> = (1..=3)
+            .map(|byte| {
+                StorageKey::new(AccountTreeId::new(Address::repeat_byte(byte)), H256::zero())
+            })
+            .collect();
+        let external_storage = testing::ExternalStorage {
+            raw_storage: InMemoryStorage::default(),
+        };
+        let fork_details = ForkDetails {
+            fork_source: &external_storage,
+            l1_block: L1BatchNumber(1),
+            l2_block: zksync_types::api::Block::<TransactionVariant>::default(),
+            l2_miniblock: 1,
+            l2_miniblock_hash: H256::zero(),
+            block_timestamp: 0,
+            overwrite_chain_id: None,
+            l1_gas_price: 100,
+            cache_max_size: Some(2),
+            max_memory: None,
+        };
+        let fork_storage = ForkStorage::new(
+            Some(fork_details),
+            &system_contracts::Options::default(),
+            None,
+        );
+
+        for key in &keys {
+            fork_storage.read_fork_value(key);
+        }
+
+        let reader = fork_storage.inner.read().unwrap();
+        assert_eq!(2, reader.loaded_accounts.len());
+    }
+
+    #[test]
+    fn test_fork_source_optional_methods_are_unsupported_by_default() {
+        let external_storage = testing::ExternalStorage {
+            raw_storage: InMemoryStorage::default(),
+        };
+        let mut fork_source = &external_storage;
+
+        let err = fork_source
+            .get_fork_url()
+            .expect_err("get_fork_url unexpectedly supported");
+        assert_eq!(
+            "get_fork_url is not supported by the fork source",
+            err.to_string()
+        );
+        assert!(fork_source.set_fork_url("http://localhost".into()).is_err());
+        assert!(fork_source.get_code(Address::zero(), None).is_err());
+        assert!(fork_source.get_block_range(L1BatchNumber(1)).is_err());
+        assert!(fork_source.get_l1_batch_details(L1BatchNumber(1)).is_err());
+    }
 }
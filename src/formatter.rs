@@ -1,5 +1,5 @@
 //! Helper methods to display transaction data in more human readable way.
-use crate::{node::ShowCalls, resolver};
+use crate::{node::ShowCalls, resolver, verification::VerifiedContracts};
 
 use colored::Colorize;
 
@@ -54,10 +54,53 @@ fn address_to_human_readable(address: H160) -> Option<String> {
         })
 }
 
+/// Names of the addresses, set with `config_setAddressLabels` and for the dev accounts, displayed as `[name]` in
+/// the call traces, events and storage logs instead of the raw addresses.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AddressLabels(HashMap<H160, String>);
+
+impl AddressLabels {
+    /// Labels the addresses, replacing their previous labels. The addresses with an empty name are unlabeled.
+    pub fn extend(&mut self, labels: HashMap<H160, String>) {
+        for (address, name) in labels {
+            if name.is_empty() {
+                self.0.remove(&address);
+            } else {
+                self.0.insert(address, name);
+            }
+        }
+    }
+
+    /// Returns the label of the address, or the name of the known contract at the address.
+    fn name(&self, address: H160) -> Option<String> {
+        self.0
+            .get(&address)
+            .map(|name| format!("[{}]", name))
+            .or_else(|| address_to_human_readable(address))
+    }
+}
+
+/// Pads `text` with spaces to `width` visible characters, i.e. ignoring the ANSI color codes it may contain.
+fn pad_right(text: &str, width: usize) -> String {
+    let mut visible = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // skip the escape sequence up to and including its final `m`
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            visible += 1;
+        }
+    }
+
+    format!("{}{}", text, " ".repeat(width.saturating_sub(visible)))
+}
+
 /// Pretty-prints event object
 /// if skip_resolve is false, will try to contact openchain to resolve the topic hashes.
-pub fn print_event(event: &VmEvent, resolve_hashes: bool) {
+pub fn print_event(event: &VmEvent, labels: &AddressLabels, resolve_hashes: bool) {
     let event = event.clone();
+    let name = labels.name(event.address);
     block_on(async move {
         let mut tt: Vec<String> = vec![];
         if !resolve_hashes {
@@ -77,9 +120,12 @@ pub fn print_event(event: &VmEvent, resolve_hashes: bool) {
 
         tracing::info!(
             "{}",
-            address_to_human_readable(event.address)
-                .map(|x| format!("{:42}", x.blue()))
-                .unwrap_or(format!("{:42}", format!("{:?}", event.address).blue()))
+            pad_right(
+                &name
+                    .map(|x| x.blue().to_string())
+                    .unwrap_or_else(|| format!("{:?}", event.address).blue().to_string()),
+                42
+            )
         );
 
         tracing::info!("{}", "  Topics:".truecolor(128, 128, 128));
@@ -121,25 +167,29 @@ pub fn print_event(event: &VmEvent, resolve_hashes: bool) {
 
 /// Pretty-prints contents of a 'call' - including subcalls.
 /// If skip_resolve is false, will try to contact openchain to resolve the ABI names.
-pub fn print_call(call: &Call, padding: usize, show_calls: &ShowCalls, resolve_hashes: bool) {
+/// The calls to the precompiles are only printed with `show_precompiles` (or [ShowCalls::All]).
+/// The subcalls nested deeper than `max_depth` levels are replaced by a single line with their count.
+/// With `show_outputs`, the return data of the successful calls is printed as well, decoded if the called
+/// contract is verified. The labeled addresses are printed by their labels.
+#[allow(clippy::too_many_arguments)]
+pub fn print_call(
+    call: &Call,
+    padding: usize,
+    show_calls: &ShowCalls,
+    show_precompiles: bool,
+    max_depth: Option<usize>,
+    show_outputs: bool,
+    verified_contracts: &VerifiedContracts,
+    labels: &AddressLabels,
+    resolve_hashes: bool,
+) {
     let contract_type = KNOWN_ADDRESSES
         .get(&call.to)
         .cloned()
         .map(|known_address| known_address.contract_type)
         .unwrap_or(ContractType::Unknown);
 
-    let should_print = match (&contract_type, &show_calls) {
-        (_, ShowCalls::All) => true,
-        (_, ShowCalls::None) => false,
-        // now we're left only with 'user' and 'system'
-        (ContractType::Unknown, _) => true,
-        (ContractType::Popular, _) => true,
-        (ContractType::Precompile, _) => false,
-        // Now we're left with System
-        (ContractType::System, ShowCalls::User) => false,
-        (ContractType::System, ShowCalls::System) => true,
-    };
-    if should_print {
+    if should_print(&contract_type, show_calls, show_precompiles) {
         let function_signature = if call.input.len() >= 4 {
             let sig = call.input.as_slice()[..4]
                 .iter()
@@ -148,11 +198,11 @@ pub fn print_call(call: &Call, padding: usize, show_calls: &ShowCalls, resolve_h
                 .join("");
 
             if contract_type == ContractType::Precompile || !resolve_hashes {
-                format!("{:>16}", sig)
+                format!("0x{}", sig)
             } else {
                 block_on(async move {
                     let fetch = resolver::decode_function_selector(&sig).await.unwrap();
-                    fetch.unwrap_or(format!("{:>16}", format!("0x{}", sig).dimmed()))
+                    fetch.unwrap_or(format!("{}", format!("0x{}", sig).dimmed()))
                 })
             }
         } else {
@@ -167,24 +217,36 @@ pub fn print_call(call: &Call, padding: usize, show_calls: &ShowCalls, resolve_h
             )
         };
 
-        let pretty_print = format!(
-            "{}{:?} {} {} {} {} {}",
-            " ".repeat(padding),
-            call.r#type,
-            address_to_human_readable(call.to)
-                .map(|x| format!("{:<52}", x))
-                .unwrap_or(format!("{:<52}", format!("{:?}", call.to).bold())),
+        // The columns are padded by their visible width, so that they stay aligned whether the output
+        // is colored or not. The ones of unbounded width come last.
+        let mut pretty_print = format!(
+            "{} {} {:>12} {}",
+            pad_right(&format!("{}{:?}", " ".repeat(padding), call.r#type), 24),
+            pad_right(
+                &labels
+                    .name(call.to)
+                    .unwrap_or_else(|| format!("{:?}", call.to).bold().to_string()),
+                42
+            ),
+            call.gas,
             function_signature,
-            call.revert_reason
-                .as_ref()
-                .map(|s| format!("Revert: {}", s))
-                .unwrap_or_default(),
-            call.error
-                .as_ref()
-                .map(|s| format!("Error: {}", s))
-                .unwrap_or_default(),
-            call.gas
         );
+        if let Some(revert_reason) = &call.revert_reason {
+            pretty_print.push_str(&format!(" Revert: {}", revert_reason));
+        }
+        if let Some(error) = &call.error {
+            pretty_print.push_str(&format!(" Error: {}", error));
+        }
+        if show_outputs
+            && !call.output.is_empty()
+            && call.revert_reason.is_none()
+            && call.error.is_none()
+        {
+            let output = verified_contracts
+                .decode_output(call.to, &call.input, &call.output)
+                .unwrap_or_else(|| format!("0x{}", hex::encode(&call.output)));
+            pretty_print.push_str(&format!(" Output: {}", output));
+        }
 
         if call.revert_reason.as_ref().is_some() || call.error.as_ref().is_some() {
             tracing::info!("{}", pretty_print.on_red());
@@ -192,11 +254,60 @@ pub fn print_call(call: &Call, padding: usize, show_calls: &ShowCalls, resolve_h
             tracing::info!("{}", pretty_print);
         }
     }
+    if max_depth.map_or(false, |max_depth| padding / 2 + 1 >= max_depth) {
+        let hidden = count_calls(&call.calls);
+        if hidden > 0 && *show_calls != ShowCalls::None {
+            tracing::info!(
+                "{}",
+                format!(
+                    "{}... {} nested calls hidden",
+                    " ".repeat(padding + 2),
+                    hidden
+                )
+                .dimmed()
+            );
+        }
+        return;
+    }
     for subcall in &call.calls {
-        print_call(subcall, padding + 2, show_calls, resolve_hashes);
+        print_call(
+            subcall,
+            padding + 2,
+            show_calls,
+            show_precompiles,
+            max_depth,
+            show_outputs,
+            verified_contracts,
+            labels,
+            resolve_hashes,
+        );
     }
 }
 
+/// Returns whether the calls to the contracts of the given type are printed.
+fn should_print(
+    contract_type: &ContractType,
+    show_calls: &ShowCalls,
+    show_precompiles: bool,
+) -> bool {
+    match (contract_type, show_calls) {
+        (_, ShowCalls::All) => true,
+        (_, ShowCalls::None) => false,
+        // now we're left only with 'user' and 'system'
+        (ContractType::Unknown, _) => true,
+        (ContractType::Popular, _) => true,
+        (ContractType::Precompile, _) => show_precompiles,
+        // Now we're left with System
+        (ContractType::System, ShowCalls::User) => false,
+        (ContractType::System, ShowCalls::System) => true,
+    }
+}
+
+/// Returns the number of the calls, including all of their subcalls.
+fn count_calls(calls: &[Call]) -> usize {
+    calls.iter().map(|call| 1 + count_calls(&call.calls)).sum()
+}
+
 /// Amount of pubdata that given write has cost.
 pub enum PubdataBytesInfo {
     // This slot is free
@@ -237,13 +348,18 @@ impl PubdataBytesInfo {
     }
 }
 
-pub fn print_logs(log_query: &StorageLogQuery, pubdata_bytes: Option<PubdataBytesInfo>) {
+pub fn print_logs(
+    log_query: &StorageLogQuery,
+    labels: &AddressLabels,
+    pubdata_bytes: Option<PubdataBytesInfo>,
+) {
     let separator = "─".repeat(82);
     tracing::info!("{:<15} {:?}", "Type:", log_query.log_type);
     tracing::info!(
         "{:<15} {}",
         "Address:",
-        address_to_human_readable(log_query.log_query.address)
+        labels
+            .name(log_query.log_query.address)
             .unwrap_or(format!("{}", log_query.log_query.address))
     );
     tracing::info!("{:<15} {:#066x}", "Key:", log_query.log_query.key);
@@ -300,3 +416,77 @@ pub fn print_vm_details(result: &VmExecutionResultAndLogs) {
 
     tracing::info!("════════════════════════════");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_print_precompiles_only_when_enabled() {
+        assert!(!should_print(
+            &ContractType::Precompile,
+            &ShowCalls::User,
+            false
+        ));
+        assert!(should_print(
+            &ContractType::Precompile,
+            &ShowCalls::User,
+            true
+        ));
+        assert!(should_print(
+            &ContractType::Precompile,
+            &ShowCalls::All,
+            false
+        ));
+        assert!(!should_print(
+            &ContractType::Precompile,
+            &ShowCalls::None,
+            true
+        ));
+        assert!(!should_print(&ContractType::System, &ShowCalls::User, true));
+    }
+
+    #[test]
+    fn test_count_calls_includes_nested_calls() {
+        let call = |calls| Call {
+            calls,
+            ..Default::default()
+        };
+
+        assert_eq!(0, count_calls(&[]));
+        assert_eq!(
+            4,
+            count_calls(&[call(vec![call(vec![call(vec![])])]), call(vec![])])
+        );
+    }
+
+    #[test]
+    fn test_pad_right_ignores_color_codes() {
+        colored::control::set_override(true);
+        let colored = pad_right(&"0x01".bold().to_string(), 8);
+        colored::control::unset_override();
+
+        assert_eq!("0x01    ", pad_right("0x01", 8));
+        assert!(colored.ends_with("m    "));
+        assert_eq!("0x0102", pad_right("0x0102", 4));
+    }
+
+    #[test]
+    fn test_address_labels_override_known_names() {
+        let deployer = H160::from_low_u64_be(0x8006);
+        let account = H160::repeat_byte(0x1);
+        let mut labels = AddressLabels::default();
+        assert_eq!(Some("L2 deployer".to_string()), labels.name(deployer));
+        assert_eq!(None, labels.name(account));
+
+        labels.extend(HashMap::from([
+            (deployer, "Deployer".to_string()),
+            (account, "RichAccount#0".to_string()),
+        ]));
+        assert_eq!(Some("[Deployer]".to_string()), labels.name(deployer));
+        assert_eq!(Some("[RichAccount#0]".to_string()), labels.name(account));
+
+        labels.extend(HashMap::from([(deployer, String::new())]));
+        assert_eq!(Some("L2 deployer".to_string()), labels.name(deployer));
+    }
+}
@@ -0,0 +1,605 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use anyhow::Context;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use zksync_basic_types::{AccountTreeId, Address, H256, U256};
+use zksync_core::fee_model::BatchFeeModelInputProvider;
+use zksync_types::{fee_model::FeeParams, Bytes, StorageKey, ACCOUNT_CODE_STORAGE_ADDRESS};
+
+use crate::{
+    fork::ForkSource,
+    node::{fee_model::TestNodeFeeInputProvider, InMemoryNode},
+    utils::is_system_contract,
+};
+
+/// Leading bytes of the gzip streams, used to tell the binary state files from the JSON ones.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Version of the binary state format, written at the start of the compressed stream.
+const BINARY_STATE_VERSION: u8 = 1;
+
+/// Describes a custom genesis state that is applied on top of the default (or forked) state
+/// before the node starts serving requests.
+///
+/// Example:
+/// ```json
+/// {
+///   "chainId": 270,
+///   "l1GasPrice": 50000000000,
+///   "accounts": {
+///     "0x36615Cf349d7F6344891B1e7CA7C72883F5dc049": {
+///       "balance": "0xde0b6b3a7640000",
+///       "nonce": 5
+///     }
+///   }
+/// }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Genesis {
+    /// Chain id of the network. Ignored when forking.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u32>,
+    /// L1 gas price (in wei) used by the fee model. Ignored when forking.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_gas_price: Option<u64>,
+    /// Accounts to initialize, keyed by address.
+    #[serde(default)]
+    pub accounts: HashMap<Address, GenesisAccount>,
+}
+
+/// Initial state of a single account in the [Genesis].
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenesisAccount {
+    /// Balance in wei (hex encoded).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    /// Account and deployment nonce.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    /// Deployed EraVM bytecode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// Storage slots of the account.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub storage: HashMap<H256, H256>,
+}
+
+impl Genesis {
+    /// Reads and parses a genesis file in JSON format.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("failed opening genesis file {}", path.display()))?;
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("failed parsing genesis file {}", path.display()))
+    }
+
+    /// Writes the genesis to a file in JSON format.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        write_atomically(path.as_ref(), |writer| {
+            serde_json::to_writer_pretty(writer, self).map_err(Into::into)
+        })
+    }
+}
+
+/// Format of the state files read with `--genesis` and written with `--dump-state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateFormat {
+    /// The [Genesis] serialized as JSON.
+    Json,
+    /// Gzip compressed stream of [StateRecord]s, which is a fraction of the size of the JSON for large
+    /// (e.g. forked) states. It is written and read record by record, without holding a copy of the state.
+    Binary,
+}
+
+impl StateFormat {
+    /// Returns the format to write the state to `path` in: binary for the `.bin` files, JSON otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension == "bin" => StateFormat::Binary,
+            _ => StateFormat::Json,
+        }
+    }
+}
+
+/// Header of the binary state files.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BinaryStateHeader {
+    pub version: u8,
+    /// Same as [Genesis::chain_id].
+    pub chain_id: Option<u32>,
+    /// Same as [Genesis::l1_gas_price].
+    pub l1_gas_price: Option<u64>,
+}
+
+/// Entry of the binary state files, following the [BinaryStateHeader].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum StateRecord {
+    /// Value of a storage slot written locally.
+    Storage {
+        address: [u8; 20],
+        key: [u8; 32],
+        value: [u8; 32],
+    },
+    /// Bytecode of a deployed contract.
+    FactoryDep { hash: [u8; 32], bytecode: Vec<u8> },
+    /// Marks the end of the file, so that truncated files are detected.
+    End,
+}
+
+/// State read from a `--genesis` file.
+pub enum GenesisFile {
+    Json(Genesis),
+    /// Only the header of the binary files is read upfront, the records are read while they are applied.
+    Binary {
+        header: BinaryStateHeader,
+        reader: GzDecoder<BufReader<File>>,
+    },
+}
+
+impl GenesisFile {
+    /// Opens a state file, in either [StateFormat].
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let mut file = File::open(path)
+            .with_context(|| format!("failed opening genesis file {}", path.display()))?;
+        let mut magic = [0u8; 2];
+        let is_binary = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+        if !is_binary {
+            return Genesis::from_file(path).map(GenesisFile::Json);
+        }
+
+        file.seek(SeekFrom::Start(0))
+            .with_context(|| format!("failed reading genesis file {}", path.display()))?;
+        let mut reader = GzDecoder::new(BufReader::new(file));
+        let header: BinaryStateHeader = bincode::deserialize_from(&mut reader)
+            .with_context(|| format!("failed parsing genesis file {}", path.display()))?;
+        if header.version != BINARY_STATE_VERSION {
+            anyhow::bail!(
+                "unsupported version {} of genesis file {}",
+                header.version,
+                path.display()
+            );
+        }
+        Ok(GenesisFile::Binary { header, reader })
+    }
+
+    pub fn chain_id(&self) -> Option<u32> {
+        match self {
+            GenesisFile::Json(genesis) => genesis.chain_id,
+            GenesisFile::Binary { header, .. } => header.chain_id,
+        }
+    }
+}
+
+/// Writes a file next to `path` first and then moves it in place, so an interrupted write never leaves a
+/// truncated file behind.
+fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    let file = File::create(&tmp_path)
+        .with_context(|| format!("failed creating genesis file {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer)
+        .with_context(|| format!("failed writing genesis file {}", path.display()))?;
+    writer
+        .into_inner()
+        .map_err(|err| err.into_error())
+        .and_then(|mut file| file.flush().and_then(|_| file.sync_all()))
+        .with_context(|| format!("failed writing genesis file {}", path.display()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("failed writing genesis file {}", path.display()))
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Applies the accounts and chain parameters of the given [Genesis] to the node state.
+    ///
+    /// The chain id is not applied here as it must be known when the node is constructed,
+    /// see [crate::node::InMemoryNodeConfig::chain_id].
+    pub fn apply_genesis(&self, genesis: &Genesis) -> anyhow::Result<()> {
+        if let Some(l1_gas_price) = genesis.l1_gas_price {
+            self.apply_genesis_l1_gas_price(l1_gas_price)?;
+        }
+
+        for (address, account) in genesis.accounts.iter() {
+            if let Some(balance) = account.balance {
+                self.set_balance(*address, balance)?;
+            }
+            if let Some(nonce) = account.nonce {
+                if nonce > 0 {
+                    self.set_nonce(*address, U256::from(nonce))?;
+                }
+            }
+            if let Some(code) = &account.code {
+                self.set_code(*address, code.0.clone())?;
+            }
+            if !account.storage.is_empty() {
+                let inner = self.get_inner();
+                let mut writer = inner
+                    .write()
+                    .map_err(|err| anyhow::anyhow!("failed acquiring lock: {:?}", err))?;
+                for (slot, value) in account.storage.iter() {
+                    let key = StorageKey::new(AccountTreeId::new(*address), *slot);
+                    writer.fork_storage.set_value(key, *value);
+                }
+            }
+        }
+
+        tracing::info!("Applied genesis with {} account(s)", genesis.accounts.len());
+        Ok(())
+    }
+
+    /// Applies a state file in either [StateFormat], see [InMemoryNode::apply_genesis].
+    pub fn apply_genesis_file(&self, genesis: GenesisFile) -> anyhow::Result<()> {
+        let (header, mut reader) = match genesis {
+            GenesisFile::Json(genesis) => return self.apply_genesis(&genesis),
+            GenesisFile::Binary { header, reader } => (header, reader),
+        };
+        if let Some(l1_gas_price) = header.l1_gas_price {
+            self.apply_genesis_l1_gas_price(l1_gas_price)?;
+        }
+
+        let inner = self.get_inner();
+        let mut writer = inner
+            .write()
+            .map_err(|err| anyhow::anyhow!("failed acquiring lock: {:?}", err))?;
+        let mut records = 0;
+        loop {
+            let record: StateRecord = bincode::deserialize_from(&mut reader)
+                .context("failed reading genesis record, the file may be truncated")?;
+            match record {
+                StateRecord::Storage {
+                    address,
+                    key,
+                    value,
+                } => {
+                    let key = StorageKey::new(
+                        AccountTreeId::new(Address::from(address)),
+                        H256::from(key),
+                    );
+                    writer.fork_storage.set_value(key, H256::from(value));
+                }
+                StateRecord::FactoryDep { hash, bytecode } => {
+                    writer
+                        .fork_storage
+                        .store_factory_dep(H256::from(hash), bytecode);
+                }
+                StateRecord::End => break,
+            }
+            records += 1;
+        }
+
+        tracing::info!("Applied binary genesis with {} record(s)", records);
+        Ok(())
+    }
+
+    fn apply_genesis_l1_gas_price(&self, l1_gas_price: u64) -> anyhow::Result<()> {
+        let inner = self.get_inner();
+        let mut writer = inner
+            .write()
+            .map_err(|err| anyhow::anyhow!("failed acquiring lock: {:?}", err))?;
+        if writer.fork_storage.inner.read().unwrap().fork.is_none() {
+            writer.fee_input_provider = TestNodeFeeInputProvider::new(l1_gas_price);
+        }
+        Ok(())
+    }
+
+    /// Writes the state of the node to `path`, in the [StateFormat] given by its extension.
+    pub fn dump_state_to_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        match StateFormat::from_path(path) {
+            StateFormat::Json => self.dump_state()?.to_file(path),
+            StateFormat::Binary => write_atomically(path, |writer| {
+                let mut encoder = GzEncoder::new(writer, Compression::default());
+                self.dump_binary_state(&mut encoder, None)?;
+                encoder.finish()?;
+                Ok(())
+            }),
+        }
+    }
+
+    /// Writes the storage slots changed since the `previous` state to `path` in the binary format, along with the
+    /// bytecodes of the contracts deployed since, so that applying the file on top of the `previous` state restores
+    /// the current one.
+    ///
+    /// # Returns
+    /// The current state, to be passed as the `previous` one of the next changes.
+    pub fn dump_state_changes_to_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        previous: &HashMap<StorageKey, H256>,
+    ) -> anyhow::Result<HashMap<StorageKey, H256>> {
+        let mut state = None;
+        write_atomically(path.as_ref(), |writer| {
+            let mut encoder = GzEncoder::new(writer, Compression::default());
+            state = self.dump_binary_state(&mut encoder, Some(previous))?;
+            encoder.finish()?;
+            Ok(())
+        })?;
+        Ok(state.unwrap_or_default())
+    }
+
+    /// Streams the same state as [InMemoryNode::dump_state] to the writer in the binary format, record by record.
+    /// If a `previous` state is given, only the storage slots changed since are written, and the current state
+    /// is returned.
+    fn dump_binary_state(
+        &self,
+        mut writer: impl Write,
+        previous: Option<&HashMap<StorageKey, H256>>,
+    ) -> anyhow::Result<Option<HashMap<StorageKey, H256>>> {
+        let inner = self.get_inner();
+        let reader = inner
+            .read()
+            .map_err(|err| anyhow::anyhow!("failed acquiring lock: {:?}", err))?;
+        let storage = reader
+            .fork_storage
+            .inner
+            .read()
+            .map_err(|err| anyhow::anyhow!("failed acquiring lock: {:?}", err))?;
+
+        let (chain_id, l1_gas_price) = if storage.fork.is_none() {
+            let l1_gas_price = match reader.fee_input_provider.get_fee_model_params() {
+                FeeParams::V1(params) => params.l1_gas_price,
+                FeeParams::V2(params) => params.l1_gas_price,
+            };
+            (
+                Some(reader.fork_storage.chain_id.as_u64() as u32),
+                Some(l1_gas_price),
+            )
+        } else {
+            (None, None)
+        };
+        let header = BinaryStateHeader {
+            version: BINARY_STATE_VERSION,
+            chain_id,
+            l1_gas_price,
+        };
+        bincode::serialize_into(&mut writer, &header)?;
+
+        let mut written_deps = HashSet::new();
+        for (key, value) in storage.raw_storage.state.iter() {
+            if previous.map_or(false, |previous| previous.get(key) == Some(value)) {
+                continue;
+            }
+            let address = *key.account().address();
+            bincode::serialize_into(
+                &mut writer,
+                &StateRecord::Storage {
+                    address: address.to_fixed_bytes(),
+                    key: key.key().to_fixed_bytes(),
+                    value: value.to_fixed_bytes(),
+                },
+            )?;
+
+            if address == ACCOUNT_CODE_STORAGE_ADDRESS {
+                let contract = Address::from_slice(&key.key().as_bytes()[12..]);
+                if is_system_contract(&contract) || !written_deps.insert(*value) {
+                    continue;
+                }
+                if let Some(bytecode) = storage.raw_storage.factory_deps.get(value) {
+                    bincode::serialize_into(
+                        &mut writer,
+                        &StateRecord::FactoryDep {
+                            hash: value.to_fixed_bytes(),
+                            bytecode: bytecode.clone(),
+                        },
+                    )?;
+                }
+            }
+        }
+        bincode::serialize_into(&mut writer, &StateRecord::End)?;
+        Ok(previous.map(|_| storage.raw_storage.state.clone()))
+    }
+
+    /// Captures the state of the node as a [Genesis], so that it can be restored by starting a node with it:
+    /// every storage slot written locally (balances and nonces live in the storage of the system contracts)
+    /// and the bytecode of the deployed contracts. The bytecode of the system contracts is not included.
+    pub fn dump_state(&self) -> anyhow::Result<Genesis> {
+        let inner = self.get_inner();
+        let reader = inner
+            .read()
+            .map_err(|err| anyhow::anyhow!("failed acquiring lock: {:?}", err))?;
+        let storage = reader
+            .fork_storage
+            .inner
+            .read()
+            .map_err(|err| anyhow::anyhow!("failed acquiring lock: {:?}", err))?;
+
+        let mut accounts: HashMap<Address, GenesisAccount> = HashMap::new();
+        for (key, value) in storage.raw_storage.state.iter() {
+            let address = *key.account().address();
+            accounts
+                .entry(address)
+                .or_default()
+                .storage
+                .insert(*key.key(), *value);
+
+            if address == ACCOUNT_CODE_STORAGE_ADDRESS {
+                let contract = Address::from_slice(&key.key().as_bytes()[12..]);
+                if is_system_contract(&contract) {
+                    continue;
+                }
+                if let Some(code) = storage.raw_storage.factory_deps.get(value) {
+                    accounts.entry(contract).or_default().code = Some(Bytes::from(code.clone()));
+                }
+            }
+        }
+
+        let (chain_id, l1_gas_price) = if storage.fork.is_none() {
+            let l1_gas_price = match reader.fee_input_provider.get_fee_model_params() {
+                FeeParams::V1(params) => params.l1_gas_price,
+                FeeParams::V2(params) => params.l1_gas_price,
+            };
+            (
+                Some(reader.fork_storage.chain_id.as_u64() as u32),
+                Some(l1_gas_price),
+            )
+        } else {
+            (None, None)
+        };
+
+        Ok(Genesis {
+            chain_id,
+            l1_gas_price,
+            accounts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_fork_source::HttpForkSource, namespaces::EthNamespaceT};
+    use std::str::FromStr;
+    use zksync_types::api::{BlockIdVariant, BlockNumber};
+
+    #[test]
+    fn test_genesis_deserializes_from_json() {
+        let genesis: Genesis = serde_json::from_str(
+            r#"{
+                "chainId": 270,
+                "accounts": {
+                    "0x36615Cf349d7F6344891B1e7CA7C72883F5dc049": {
+                        "balance": "0x539",
+                        "nonce": 3,
+                        "storage": {
+                            "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000002"
+                        }
+                    }
+                }
+            }"#,
+        )
+        .expect("failed parsing genesis");
+
+        assert_eq!(Some(270), genesis.chain_id);
+        assert_eq!(None, genesis.l1_gas_price);
+        let account = genesis
+            .accounts
+            .get(&Address::from_str("0x36615Cf349d7F6344891B1e7CA7C72883F5dc049").unwrap())
+            .expect("account missing");
+        assert_eq!(Some(U256::from(1337)), account.balance);
+        assert_eq!(Some(3), account.nonce);
+        assert_eq!(1, account.storage.len());
+    }
+
+    #[tokio::test]
+    async fn test_apply_genesis_sets_account_state() {
+        let address = Address::from_str("0x36615Cf349d7F6344891B1e7CA7C72883F5dc049").unwrap();
+        let slot = H256::from_low_u64_be(1);
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            address,
+            GenesisAccount {
+                balance: Some(U256::from(1337)),
+                nonce: Some(3),
+                code: None,
+                storage: std::iter::once((slot, H256::from_low_u64_be(2))).collect(),
+            },
+        );
+        node.apply_genesis(&Genesis {
+            accounts,
+            ..Default::default()
+        })
+        .expect("failed applying genesis");
+
+        let balance = node.get_balance(address, None).await.unwrap();
+        assert_eq!(U256::from(1337), balance);
+        let nonce = node.get_transaction_count(address, None).await.unwrap();
+        assert_eq!(U256::from(3), nonce);
+        let value = node
+            .get_storage(
+                address,
+                U256::from(1),
+                Some(BlockIdVariant::BlockNumber(BlockNumber::Latest)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(H256::from_low_u64_be(2), value);
+    }
+
+    #[tokio::test]
+    async fn test_dump_state_restores_accounts_in_new_node() {
+        let address = Address::repeat_byte(0x1);
+        let contract = Address::repeat_byte(0x2);
+        let code = vec![0x1u8; 32];
+        let node = InMemoryNode::<HttpForkSource>::default();
+        node.set_balance(address, U256::from(1337))
+            .expect("failed setting balance");
+        node.set_code(contract, code.clone())
+            .expect("failed setting code");
+
+        let dir = tempdir::TempDir::new("genesis-dump").unwrap();
+        let path = dir.path().join("state.json");
+        node.dump_state()
+            .expect("failed dumping state")
+            .to_file(&path)
+            .expect("failed writing state");
+        let genesis = Genesis::from_file(&path).expect("failed reading state");
+        let restored = InMemoryNode::<HttpForkSource>::default();
+        restored
+            .apply_genesis(&genesis)
+            .expect("failed applying genesis");
+
+        assert_eq!(Some(260), genesis.chain_id);
+        assert_eq!(
+            U256::from(1337),
+            restored.get_balance(address, None).await.unwrap()
+        );
+        assert_eq!(code, restored.get_code(contract, None).await.unwrap().0);
+        let system_contract = genesis
+            .accounts
+            .get(&ACCOUNT_CODE_STORAGE_ADDRESS)
+            .expect("system contract storage missing");
+        assert_eq!(None, system_contract.code);
+    }
+
+    #[tokio::test]
+    async fn test_binary_state_dump_restores_accounts_in_new_node() {
+        let address = Address::repeat_byte(0x1);
+        let contract = Address::repeat_byte(0x2);
+        let code = vec![0x1u8; 32];
+        let node = InMemoryNode::<HttpForkSource>::default();
+        node.set_balance(address, U256::from(1337))
+            .expect("failed setting balance");
+        node.set_code(contract, code.clone())
+            .expect("failed setting code");
+
+        let dir = tempdir::TempDir::new("genesis-dump").unwrap();
+        let json_path = dir.path().join("state.json");
+        let binary_path = dir.path().join("state.bin");
+        node.dump_state_to_file(&json_path)
+            .expect("failed dumping JSON state");
+        node.dump_state_to_file(&binary_path)
+            .expect("failed dumping binary state");
+        assert!(
+            fs::metadata(&binary_path).unwrap().len() < fs::metadata(&json_path).unwrap().len()
+        );
+
+        let genesis = GenesisFile::open(&binary_path).expect("failed reading state");
+        assert!(matches!(genesis, GenesisFile::Binary { .. }));
+        assert_eq!(Some(260), genesis.chain_id());
+        let restored = InMemoryNode::<HttpForkSource>::default();
+        restored
+            .apply_genesis_file(genesis)
+            .expect("failed applying genesis");
+
+        assert_eq!(
+            U256::from(1337),
+            restored.get_balance(address, None).await.unwrap()
+        );
+        assert_eq!(code, restored.get_code(contract, None).await.unwrap().0);
+    }
+}
@@ -0,0 +1,786 @@
+//! GraphQL endpoint implementing the standard Ethereum GraphQL schema ([EIP-1767](https://eips.ethereum.org/EIPS/eip-1767)),
+//! served at `/graphql` with the `--graphql` option.
+//!
+//! The resolvers query the node via its `eth` namespace methods, so the results are consistent with the JSON-RPC API.
+use std::{str::FromStr, sync::Arc};
+
+use async_graphql::{
+    Context, EmptySubscription, InputObject, InputValueError, InputValueResult, Object, Scalar,
+    ScalarType, Schema, ServerError, Value,
+};
+use zksync_basic_types::{H160, H256, U256, U64};
+use zksync_types::api::{self, BlockIdVariant, BlockNumber, TransactionVariant};
+use zksync_web3_decl::types::{Filter, ValueOrArray};
+
+use crate::{
+    fork::ForkSource,
+    namespaces::{EthNamespaceT, RpcResult},
+    node::InMemoryNode,
+    rpc_filter::RpcFilterMiddleware,
+};
+
+/// Schema of the `/graphql` endpoint.
+pub type GraphQlSchema = Schema<Query, Mutation, EmptySubscription>;
+
+/// Builds the GraphQL schema resolving the queries against the `node`. The mutations are rejected like the
+/// JSON-RPC methods they call by the `rpc_filter`.
+pub fn build_schema<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static>(
+    node: InMemoryNode<S>,
+    rpc_filter: RpcFilterMiddleware,
+) -> GraphQlSchema {
+    Schema::build(Query, Mutation, EmptySubscription)
+        .data::<Arc<dyn NodeApi>>(Arc::new(node))
+        .data(rpc_filter)
+        .finish()
+}
+
+/// Returns the GraphiQL page served on GET requests, to explore the schema and run queries from the browser.
+pub fn graphiql_html() -> String {
+    async_graphql::http::GraphiQLSource::build()
+        .endpoint("/graphql")
+        .finish()
+}
+
+/// Executes the GraphQL request in the `body` of a POST request, and returns the serialized response.
+pub async fn execute(schema: &GraphQlSchema, body: &[u8]) -> String {
+    let response = match serde_json::from_slice::<async_graphql::Request>(body) {
+        Ok(request) => schema.execute(request).await,
+        Err(err) => async_graphql::Response::from_errors(vec![ServerError::new(
+            format!("invalid request: {}", err),
+            None,
+        )]),
+    };
+
+    serde_json::to_string(&response).unwrap_or_default()
+}
+
+/// The `eth` namespace methods used by the resolvers. Unlike [EthNamespaceT], it can be used as a trait object
+/// in the schema data.
+trait NodeApi: Send + Sync {
+    fn block_by_number(
+        &self,
+        number: BlockNumber,
+    ) -> RpcResult<Option<api::Block<TransactionVariant>>>;
+    fn block_by_hash(&self, hash: H256) -> RpcResult<Option<api::Block<TransactionVariant>>>;
+    fn transaction(&self, hash: H256) -> RpcResult<Option<api::Transaction>>;
+    fn receipt(&self, hash: H256) -> RpcResult<Option<api::TransactionReceipt>>;
+    fn logs(&self, filter: Filter) -> RpcResult<Vec<api::Log>>;
+    fn balance(&self, address: H160, block: Option<BlockIdVariant>) -> RpcResult<U256>;
+    fn transaction_count(&self, address: H160, block: Option<BlockIdVariant>) -> RpcResult<U256>;
+    fn code(&self, address: H160, block: Option<BlockIdVariant>) -> RpcResult<zksync_types::Bytes>;
+    fn storage(&self, address: H160, slot: U256, block: Option<BlockIdVariant>) -> RpcResult<H256>;
+    fn gas_price(&self) -> RpcResult<U256>;
+    fn chain_id(&self) -> RpcResult<U64>;
+    fn send_raw_transaction(&self, data: zksync_types::Bytes) -> RpcResult<H256>;
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> NodeApi for InMemoryNode<S> {
+    fn block_by_number(
+        &self,
+        number: BlockNumber,
+    ) -> RpcResult<Option<api::Block<TransactionVariant>>> {
+        EthNamespaceT::get_block_by_number(self, number, true)
+    }
+
+    fn block_by_hash(&self, hash: H256) -> RpcResult<Option<api::Block<TransactionVariant>>> {
+        EthNamespaceT::get_block_by_hash(self, hash, true)
+    }
+
+    fn transaction(&self, hash: H256) -> RpcResult<Option<api::Transaction>> {
+        EthNamespaceT::get_transaction_by_hash(self, hash)
+    }
+
+    fn receipt(&self, hash: H256) -> RpcResult<Option<api::TransactionReceipt>> {
+        EthNamespaceT::get_transaction_receipt(self, hash)
+    }
+
+    fn logs(&self, filter: Filter) -> RpcResult<Vec<api::Log>> {
+        EthNamespaceT::get_logs(self, filter)
+    }
+
+    fn balance(&self, address: H160, block: Option<BlockIdVariant>) -> RpcResult<U256> {
+        EthNamespaceT::get_balance(self, address, block)
+    }
+
+    fn transaction_count(&self, address: H160, block: Option<BlockIdVariant>) -> RpcResult<U256> {
+        EthNamespaceT::get_transaction_count(self, address, block)
+    }
+
+    fn code(&self, address: H160, block: Option<BlockIdVariant>) -> RpcResult<zksync_types::Bytes> {
+        EthNamespaceT::get_code(self, address, block)
+    }
+
+    fn storage(&self, address: H160, slot: U256, block: Option<BlockIdVariant>) -> RpcResult<H256> {
+        EthNamespaceT::get_storage(self, address, slot, block)
+    }
+
+    fn gas_price(&self) -> RpcResult<U256> {
+        EthNamespaceT::gas_price(self)
+    }
+
+    fn chain_id(&self) -> RpcResult<U64> {
+        EthNamespaceT::chain_id(self)
+    }
+
+    fn send_raw_transaction(&self, data: zksync_types::Bytes) -> RpcResult<H256> {
+        EthNamespaceT::send_raw_transaction(self, data)
+    }
+}
+
+fn node<'a>(ctx: &Context<'a>) -> &'a Arc<dyn NodeApi> {
+    ctx.data_unchecked::<Arc<dyn NodeApi>>()
+}
+
+fn into_graphql_error(err: jsonrpc_core::Error) -> async_graphql::Error {
+    async_graphql::Error::new(err.message)
+}
+
+fn block_id(number: Option<Long>) -> Option<BlockIdVariant> {
+    number.map(|number| BlockIdVariant::BlockNumber(BlockNumber::Number(U64::from(number.0))))
+}
+
+/// 32 byte hex encoded value, e.g. a hash.
+pub struct Bytes32(H256);
+
+#[Scalar]
+impl ScalarType for Bytes32 {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match &value {
+            Value::String(s) => Ok(Bytes32(H256::from_str(s.trim_start_matches("0x"))?)),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(format!("{:#x}", self.0))
+    }
+}
+
+/// 20 byte hex encoded account address.
+pub struct Address(H160);
+
+#[Scalar]
+impl ScalarType for Address {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match &value {
+            Value::String(s) => Ok(Address(H160::from_str(s.trim_start_matches("0x"))?)),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(format!("{:#x}", self.0))
+    }
+}
+
+/// Arbitrary length hex encoded byte array.
+pub struct Bytes(Vec<u8>);
+
+#[Scalar]
+impl ScalarType for Bytes {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match &value {
+            Value::String(s) => Ok(Bytes(hex::decode(s.trim_start_matches("0x"))?)),
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(format!("0x{}", hex::encode(&self.0)))
+    }
+}
+
+/// 256 bit integer, accepted as a number or a decimal or `0x` prefixed hex string, and returned as a hex string.
+pub struct BigInt(U256);
+
+#[Scalar]
+impl ScalarType for BigInt {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match &value {
+            Value::Number(n) => n
+                .as_u64()
+                .map(|n| BigInt(U256::from(n)))
+                .ok_or_else(|| InputValueError::custom("expected a non-negative integer")),
+            Value::String(s) => match s.strip_prefix("0x") {
+                Some(hex) => Ok(BigInt(U256::from_str_radix(hex, 16)?)),
+                None => Ok(BigInt(U256::from_dec_str(s)?)),
+            },
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(format!("{:#x}", self.0))
+    }
+}
+
+/// 64 bit integer, accepted as a number or a decimal or `0x` prefixed hex string, and returned as a number.
+#[derive(Clone, Copy)]
+pub struct Long(u64);
+
+#[Scalar]
+impl ScalarType for Long {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        match &value {
+            Value::Number(n) => n
+                .as_u64()
+                .map(Long)
+                .ok_or_else(|| InputValueError::custom("expected a non-negative integer")),
+            Value::String(s) => match s.strip_prefix("0x") {
+                Some(hex) => Ok(Long(u64::from_str_radix(hex, 16)?)),
+                None => Ok(Long(s.parse()?)),
+            },
+            _ => Err(InputValueError::expected_type(value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Number(self.0.into())
+    }
+}
+
+/// Filter of the logs of a range of blocks.
+#[derive(InputObject)]
+pub struct FilterCriteria {
+    /// First block to return the logs of, defaults to the latest block.
+    from_block: Option<Long>,
+    /// Last block to return the logs of, defaults to the latest block.
+    to_block: Option<Long>,
+    /// Addresses the logs must be emitted by, any if empty.
+    addresses: Option<Vec<Address>>,
+    /// Alternatives of each topic position, any if empty.
+    topics: Option<Vec<Vec<Bytes32>>>,
+}
+
+/// Filter of the logs of a single block.
+#[derive(InputObject)]
+pub struct BlockFilterCriteria {
+    /// Addresses the logs must be emitted by, any if empty.
+    addresses: Option<Vec<Address>>,
+    /// Alternatives of each topic position, any if empty.
+    topics: Option<Vec<Vec<Bytes32>>>,
+}
+
+fn log_filter(
+    from_block: BlockNumber,
+    to_block: BlockNumber,
+    addresses: Option<Vec<Address>>,
+    topics: Option<Vec<Vec<Bytes32>>>,
+) -> Filter {
+    Filter {
+        from_block: Some(from_block),
+        to_block: Some(to_block),
+        address: addresses
+            .filter(|addresses| !addresses.is_empty())
+            .map(|addresses| ValueOrArray(addresses.into_iter().map(|a| a.0).collect())),
+        topics: topics.map(|topics| {
+            topics
+                .into_iter()
+                .map(|alternatives| {
+                    (!alternatives.is_empty())
+                        .then(|| ValueOrArray(alternatives.into_iter().map(|t| t.0).collect()))
+                })
+                .collect()
+        }),
+        ..Default::default()
+    }
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// Block by number or hash, the latest one if neither is given.
+    async fn block(
+        &self,
+        ctx: &Context<'_>,
+        number: Option<Long>,
+        hash: Option<Bytes32>,
+    ) -> async_graphql::Result<Option<Block>> {
+        let block = match (hash, number) {
+            (Some(hash), _) => node(ctx).block_by_hash(hash.0).await,
+            (None, Some(number)) => {
+                node(ctx)
+                    .block_by_number(BlockNumber::Number(U64::from(number.0)))
+                    .await
+            }
+            (None, None) => node(ctx).block_by_number(BlockNumber::Latest).await,
+        }
+        .map_err(into_graphql_error)?;
+
+        Ok(block.map(Block))
+    }
+
+    /// Blocks in the range `from..=to`, up to the latest one.
+    async fn blocks(
+        &self,
+        ctx: &Context<'_>,
+        from: Long,
+        to: Option<Long>,
+    ) -> async_graphql::Result<Vec<Block>> {
+        let mut blocks = vec![];
+        for number in from.0..=to.map_or(u64::MAX, |to| to.0) {
+            match node(ctx)
+                .block_by_number(BlockNumber::Number(U64::from(number)))
+                .await
+                .map_err(into_graphql_error)?
+            {
+                Some(block) => blocks.push(Block(block)),
+                None => break,
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    /// Transaction by hash.
+    async fn transaction(
+        &self,
+        ctx: &Context<'_>,
+        hash: Bytes32,
+    ) -> async_graphql::Result<Option<Transaction>> {
+        let tx = node(ctx)
+            .transaction(hash.0)
+            .await
+            .map_err(into_graphql_error)?;
+        Ok(tx.map(Transaction))
+    }
+
+    /// Logs matching the filter.
+    async fn logs(
+        &self,
+        ctx: &Context<'_>,
+        filter: FilterCriteria,
+    ) -> async_graphql::Result<Vec<Log>> {
+        let block_number = |number: Option<Long>| {
+            number.map_or(BlockNumber::Latest, |n| BlockNumber::Number(n.0.into()))
+        };
+        let logs = node(ctx)
+            .logs(log_filter(
+                block_number(filter.from_block),
+                block_number(filter.to_block),
+                filter.addresses,
+                filter.topics,
+            ))
+            .await
+            .map_err(into_graphql_error)?;
+
+        Ok(logs.into_iter().map(Log).collect())
+    }
+
+    /// Current gas price.
+    async fn gas_price(&self, ctx: &Context<'_>) -> async_graphql::Result<BigInt> {
+        let price = node(ctx).gas_price().await.map_err(into_graphql_error)?;
+        Ok(BigInt(price))
+    }
+
+    /// Chain id of the node.
+    #[graphql(name = "chainID")]
+    async fn chain_id(&self, ctx: &Context<'_>) -> async_graphql::Result<BigInt> {
+        let chain_id = node(ctx).chain_id().await.map_err(into_graphql_error)?;
+        Ok(BigInt(chain_id.as_u64().into()))
+    }
+}
+
+pub struct Mutation;
+
+#[Object]
+impl Mutation {
+    /// Sends a signed raw transaction, returning its hash.
+    async fn send_raw_transaction(
+        &self,
+        ctx: &Context<'_>,
+        data: Bytes,
+    ) -> async_graphql::Result<Bytes32> {
+        if let Some(rejection) = ctx
+            .data_unchecked::<RpcFilterMiddleware>()
+            .rejection("eth_sendRawTransaction")
+        {
+            return Err(async_graphql::Error::new(rejection));
+        }
+        let hash = node(ctx)
+            .send_raw_transaction(zksync_types::Bytes(data.0))
+            .await
+            .map_err(into_graphql_error)?;
+        Ok(Bytes32(hash))
+    }
+}
+
+/// Account state at a given block, the latest one if not set.
+pub struct Account {
+    address: H160,
+    block: Option<BlockIdVariant>,
+}
+
+#[Object]
+impl Account {
+    async fn address(&self) -> Address {
+        Address(self.address)
+    }
+
+    async fn balance(&self, ctx: &Context<'_>) -> async_graphql::Result<BigInt> {
+        let balance = node(ctx)
+            .balance(self.address, self.block.clone())
+            .await
+            .map_err(into_graphql_error)?;
+        Ok(BigInt(balance))
+    }
+
+    async fn transaction_count(&self, ctx: &Context<'_>) -> async_graphql::Result<Long> {
+        let nonce = node(ctx)
+            .transaction_count(self.address, self.block.clone())
+            .await
+            .map_err(into_graphql_error)?;
+        Ok(Long(nonce.low_u64()))
+    }
+
+    async fn code(&self, ctx: &Context<'_>) -> async_graphql::Result<Bytes> {
+        let code = node(ctx)
+            .code(self.address, self.block.clone())
+            .await
+            .map_err(into_graphql_error)?;
+        Ok(Bytes(code.0))
+    }
+
+    async fn storage(&self, ctx: &Context<'_>, slot: Bytes32) -> async_graphql::Result<Bytes32> {
+        let value = node(ctx)
+            .storage(
+                self.address,
+                U256::from_big_endian(slot.0.as_bytes()),
+                self.block.clone(),
+            )
+            .await
+            .map_err(into_graphql_error)?;
+        Ok(Bytes32(value))
+    }
+}
+
+pub struct Block(api::Block<TransactionVariant>);
+
+impl Block {
+    fn block_id(&self) -> Option<BlockIdVariant> {
+        block_id(Some(Long(self.0.number.as_u64())))
+    }
+
+    async fn transactions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Transaction>> {
+        let mut transactions = Vec::with_capacity(self.0.transactions.len());
+        for tx in &self.0.transactions {
+            let tx = match tx {
+                TransactionVariant::Full(tx) => Some(tx.clone()),
+                TransactionVariant::Hash(hash) => node(ctx)
+                    .transaction(*hash)
+                    .await
+                    .map_err(into_graphql_error)?,
+            };
+            transactions.extend(tx.map(Transaction));
+        }
+
+        Ok(transactions)
+    }
+}
+
+#[Object]
+impl Block {
+    async fn number(&self) -> Long {
+        Long(self.0.number.as_u64())
+    }
+
+    async fn hash(&self) -> Bytes32 {
+        Bytes32(self.0.hash)
+    }
+
+    async fn parent(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Block>> {
+        if self.0.number.is_zero() {
+            return Ok(None);
+        }
+        let parent = node(ctx)
+            .block_by_hash(self.0.parent_hash)
+            .await
+            .map_err(into_graphql_error)?;
+        Ok(parent.map(Block))
+    }
+
+    async fn timestamp(&self) -> Long {
+        Long(self.0.timestamp.low_u64())
+    }
+
+    async fn gas_limit(&self) -> Long {
+        Long(self.0.gas_limit.low_u64())
+    }
+
+    async fn gas_used(&self) -> Long {
+        Long(self.0.gas_used.low_u64())
+    }
+
+    async fn base_fee_per_gas(&self) -> BigInt {
+        BigInt(self.0.base_fee_per_gas)
+    }
+
+    async fn logs_bloom(&self) -> Bytes {
+        Bytes(self.0.logs_bloom.as_bytes().to_vec())
+    }
+
+    /// The operator account collecting the fees.
+    async fn miner(&self, block: Option<Long>) -> Account {
+        Account {
+            address: self.0.author,
+            block: block_id(block),
+        }
+    }
+
+    async fn transaction_count(&self) -> Long {
+        Long(self.0.transactions.len() as u64)
+    }
+
+    #[graphql(name = "transactions")]
+    async fn transactions_field(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Vec<Transaction>> {
+        self.transactions(ctx).await
+    }
+
+    async fn transaction_at(
+        &self,
+        ctx: &Context<'_>,
+        index: Long,
+    ) -> async_graphql::Result<Option<Transaction>> {
+        let mut transactions = self.transactions(ctx).await?;
+        let index = index.0 as usize;
+        Ok((index < transactions.len()).then(|| transactions.swap_remove(index)))
+    }
+
+    async fn logs(
+        &self,
+        ctx: &Context<'_>,
+        filter: BlockFilterCriteria,
+    ) -> async_graphql::Result<Vec<Log>> {
+        let number = BlockNumber::Number(self.0.number);
+        let logs = node(ctx)
+            .logs(log_filter(number, number, filter.addresses, filter.topics))
+            .await
+            .map_err(into_graphql_error)?;
+
+        Ok(logs.into_iter().map(Log).collect())
+    }
+
+    /// State of the account at this block.
+    async fn account(&self, address: Address) -> Account {
+        Account {
+            address: address.0,
+            block: self.block_id(),
+        }
+    }
+}
+
+pub struct Transaction(api::Transaction);
+
+impl Transaction {
+    async fn receipt(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<api::TransactionReceipt>> {
+        node(ctx)
+            .receipt(self.0.hash)
+            .await
+            .map_err(into_graphql_error)
+    }
+}
+
+#[Object]
+impl Transaction {
+    async fn hash(&self) -> Bytes32 {
+        Bytes32(self.0.hash)
+    }
+
+    async fn nonce(&self) -> Long {
+        Long(self.0.nonce.low_u64())
+    }
+
+    async fn index(&self) -> Option<Long> {
+        self.0.transaction_index.map(|index| Long(index.as_u64()))
+    }
+
+    async fn from(&self, block: Option<Long>) -> Option<Account> {
+        self.0.from.map(|address| Account {
+            address,
+            block: block_id(block),
+        })
+    }
+
+    async fn to(&self, block: Option<Long>) -> Option<Account> {
+        self.0.to.map(|address| Account {
+            address,
+            block: block_id(block),
+        })
+    }
+
+    async fn value(&self) -> BigInt {
+        BigInt(self.0.value)
+    }
+
+    async fn gas_price(&self) -> BigInt {
+        BigInt(self.0.gas_price.unwrap_or_default())
+    }
+
+    async fn max_fee_per_gas(&self) -> Option<BigInt> {
+        self.0.max_fee_per_gas.map(BigInt)
+    }
+
+    async fn max_priority_fee_per_gas(&self) -> Option<BigInt> {
+        self.0.max_priority_fee_per_gas.map(BigInt)
+    }
+
+    async fn gas(&self) -> Long {
+        Long(self.0.gas.low_u64())
+    }
+
+    async fn input_data(&self) -> Bytes {
+        Bytes(self.0.input.0.clone())
+    }
+
+    async fn block(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Block>> {
+        let block = match self.0.block_hash {
+            Some(hash) => node(ctx)
+                .block_by_hash(hash)
+                .await
+                .map_err(into_graphql_error)?,
+            None => None,
+        };
+        Ok(block.map(Block))
+    }
+
+    /// 1 if the transaction succeeded, 0 if it failed, or null if it is pending.
+    async fn status(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Long>> {
+        let receipt = self.receipt(ctx).await?;
+        Ok(receipt.map(|receipt| Long(receipt.status.as_u64())))
+    }
+
+    async fn gas_used(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Long>> {
+        let receipt = self.receipt(ctx).await?;
+        Ok(receipt
+            .and_then(|receipt| receipt.gas_used)
+            .map(|gas| Long(gas.low_u64())))
+    }
+
+    async fn cumulative_gas_used(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Long>> {
+        let receipt = self.receipt(ctx).await?;
+        Ok(receipt.map(|receipt| Long(receipt.cumulative_gas_used.low_u64())))
+    }
+
+    async fn effective_gas_price(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Option<BigInt>> {
+        let receipt = self.receipt(ctx).await?;
+        Ok(receipt
+            .and_then(|receipt| receipt.effective_gas_price)
+            .map(BigInt))
+    }
+
+    /// The account created by the transaction, if any.
+    async fn created_contract(
+        &self,
+        ctx: &Context<'_>,
+        block: Option<Long>,
+    ) -> async_graphql::Result<Option<Account>> {
+        let receipt = self.receipt(ctx).await?;
+        Ok(receipt
+            .and_then(|receipt| receipt.contract_address)
+            .map(|address| Account {
+                address,
+                block: block_id(block),
+            }))
+    }
+
+    async fn logs(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Vec<Log>>> {
+        let receipt = self.receipt(ctx).await?;
+        Ok(receipt.map(|receipt| receipt.logs.into_iter().map(Log).collect()))
+    }
+}
+
+pub struct Log(api::Log);
+
+#[Object]
+impl Log {
+    async fn index(&self) -> Long {
+        Long(self.0.log_index.unwrap_or_default().low_u64())
+    }
+
+    /// The account that emitted the log.
+    async fn account(&self, block: Option<Long>) -> Account {
+        Account {
+            address: self.0.address,
+            block: block_id(block),
+        }
+    }
+
+    async fn topics(&self) -> Vec<Bytes32> {
+        self.0.topics.iter().copied().map(Bytes32).collect()
+    }
+
+    async fn data(&self) -> Bytes {
+        Bytes(self.0.data.0.clone())
+    }
+
+    async fn transaction(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Transaction>> {
+        let tx = match self.0.transaction_hash {
+            Some(hash) => node(ctx)
+                .transaction(hash)
+                .await
+                .map_err(into_graphql_error)?,
+            None => None,
+        };
+        Ok(tx.map(Transaction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+    use zksync_basic_types::Address as L2Address;
+
+    #[tokio::test]
+    async fn test_graphql_queries_blocks_and_accounts() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let address = L2Address::repeat_byte(0x1);
+        node.set_rich_account(address);
+        let schema = build_schema(node, RpcFilterMiddleware::default());
+
+        let response = execute(
+            &schema,
+            serde_json::json!({
+                "query": format!(
+                    r#"{{ chainID block(number: 0) {{ number account(address: "{:#x}") {{ balance }} }} }}"#,
+                    address
+                )
+            })
+            .to_string()
+            .as_bytes(),
+        )
+        .await;
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+
+        assert_eq!(None, response.get("errors"), "{}", response);
+        assert_eq!("0x104", response["data"]["chainID"]);
+        assert_eq!(0, response["data"]["block"]["number"]);
+        assert_ne!("0x0", response["data"]["block"]["account"]["balance"]);
+    }
+
+    #[tokio::test]
+    async fn test_graphql_reports_invalid_requests() {
+        let schema = build_schema(
+            InMemoryNode::<HttpForkSource>::default(),
+            RpcFilterMiddleware::default(),
+        );
+
+        let response: serde_json::Value =
+            serde_json::from_str(&execute(&schema, b"not json").await).unwrap();
+
+        assert!(response["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid request"));
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use serde::Serialize;
+use zksync_types::api::BlockNumber;
+
+use crate::{fork::ForkSource, node::InMemoryNode};
+
+/// Health report of the node, served at the `/health` and `/ready` HTTP endpoints.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeHealth {
+    /// Whether the node finished its initialization (genesis, predeploys and init scripts).
+    pub initialized: bool,
+    /// Number of the latest block.
+    pub block_number: u64,
+    /// Whether the forked network is reachable, or `None` if the node is not forked.
+    pub fork_connected: Option<bool>,
+}
+
+/// Time the connectivity of the forked network is cached for, so that frequent probes don't each make a request to
+/// the forked network.
+const FORK_STATUS_TTL: Duration = Duration::from_secs(10);
+
+/// Last connectivity status of the forked network, along with the time it was checked at.
+#[derive(Debug, Clone, Default)]
+pub struct ForkStatusCache(Arc<Mutex<Option<(Instant, bool)>>>);
+
+impl ForkStatusCache {
+    /// Returns the cached status if checked within the last `ttl`, otherwise runs the `check` and caches its result.
+    /// Concurrent callers wait for the running check instead of making their own.
+    pub fn get_or_check(&self, ttl: Duration, check: impl FnOnce() -> bool) -> bool {
+        let mut status = match self.0.lock() {
+            Ok(status) => status,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match *status {
+            Some((checked_at, connected)) if checked_at.elapsed() < ttl => connected,
+            _ => {
+                let connected = check();
+                *status = Some((Instant::now(), connected));
+                connected
+            }
+        }
+    }
+}
+
+impl NodeHealth {
+    /// The node is ready to serve requests once initialized, with the forked network (if any) reachable.
+    pub fn is_ready(&self) -> bool {
+        self.initialized && self.fork_connected.unwrap_or(true)
+    }
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Reports the health of the node. If forked, the connectivity is checked by fetching the latest
+    /// block of the forked network, at most once per [FORK_STATUS_TTL].
+    pub fn health(&self, initialized: bool) -> anyhow::Result<NodeHealth> {
+        let (block_number, fork_source) = {
+            let inner = self
+                .get_inner()
+                .read()
+                .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+            let fork_source = inner
+                .fork_storage
+                .inner
+                .read()
+                .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?
+                .fork
+                .as_ref()
+                .map(|fork| fork.fork_source.clone());
+            (inner.current_miniblock, fork_source)
+        };
+
+        let fork_connected = fork_source.map(|fork_source| {
+            self.fork_status.get_or_check(FORK_STATUS_TTL, || {
+                fork_source
+                    .get_block_by_number(BlockNumber::Latest, false)
+                    .map_or(false, |block| block.is_some())
+            })
+        });
+
+        Ok(NodeHealth {
+            initialized,
+            block_number,
+            fork_connected,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+
+    #[test]
+    fn test_health_of_non_forked_node() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let health = node.health(true).expect("failed getting health");
+
+        assert_eq!(
+            NodeHealth {
+                initialized: true,
+                block_number: 0,
+                fork_connected: None,
+            },
+            health
+        );
+        assert!(health.is_ready());
+        assert!(!node.health(false).unwrap().is_ready());
+    }
+
+    #[test]
+    fn test_fork_status_cache_checks_once_per_ttl() {
+        let cache = ForkStatusCache::default();
+        let checks = std::cell::Cell::new(0);
+        let check = |connected| {
+            checks.set(checks.get() + 1);
+            connected
+        };
+
+        assert!(cache.get_or_check(Duration::from_secs(60), || check(true)));
+        assert!(cache.get_or_check(Duration::from_secs(60), || check(false)));
+        assert_eq!(1, checks.get());
+
+        assert!(!cache.get_or_check(Duration::ZERO, || check(false)));
+        assert_eq!(2, checks.get());
+    }
+}
@@ -1,12 +1,20 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 
 use crate::{
     cache::{Cache, CacheConfig},
     fork::{block_on, ForkSource},
+    metrics::METRICS,
+    observability::SPAN_TARGET,
 };
 use eyre::Context;
 use zksync_basic_types::{H256, U256};
-use zksync_types::api::{BridgeAddresses, Transaction};
+use zksync_types::{
+    api::{BlockIdVariant, BlockNumber, BridgeAddresses, Transaction},
+    transaction_request::CallRequest,
+};
 use zksync_web3_decl::types::Token;
 use zksync_web3_decl::{
     jsonrpsee::http_client::{HttpClient, HttpClientBuilder},
@@ -31,6 +39,14 @@ impl HttpForkSource {
         }
     }
 
+    /// Sets the maximum number of entries of every type kept in the in-memory cache.
+    pub fn set_cache_max_size(&self, max_size: Option<usize>) {
+        match self.cache.write() {
+            Ok(mut guard) => guard.set_max_size(max_size),
+            Err(err) => tracing::error!("failed acquiring cache lock: {:?}", err),
+        }
+    }
+
     pub fn create_client(&self) -> HttpClient {
         HttpClientBuilder::default()
             .build(self.fork_url.clone())
@@ -38,13 +54,29 @@ impl HttpForkSource {
     }
 }
 
+/// Records a request made to the forked network and enters the span covering it.
+fn observe_fork_request(method: &'static str) -> tracing::span::EnteredSpan {
+    METRICS.observe_fork_request(method);
+    tracing::info_span!(target: SPAN_TARGET, "fork_request", method).entered()
+}
+
 impl ForkSource for HttpForkSource {
+    fn get_fork_url(&self) -> eyre::Result<String> {
+        Ok(self.fork_url.clone())
+    }
+
+    fn set_fork_url(&mut self, url: String) -> eyre::Result<()> {
+        self.fork_url = url;
+        Ok(())
+    }
+
     fn get_storage_at(
         &self,
         address: zksync_basic_types::Address,
         idx: zksync_basic_types::U256,
         block: Option<zksync_types::api::BlockIdVariant>,
     ) -> eyre::Result<zksync_basic_types::H256> {
+        let _span = observe_fork_request("get_storage_at");
         let client = self.create_client();
         block_on(async move { client.get_storage_at(address, idx, block).await })
             .wrap_err("fork http client failed")
@@ -54,24 +86,37 @@ impl ForkSource for HttpForkSource {
         &self,
         hash: zksync_basic_types::H256,
     ) -> eyre::Result<Option<Vec<u8>>> {
+        let _span = observe_fork_request("get_bytecode_by_hash");
         let client = self.create_client();
         block_on(async move { client.get_bytecode_by_hash(hash).await })
             .wrap_err("fork http client failed")
     }
 
+    fn get_code(
+        &self,
+        address: zksync_basic_types::Address,
+        block: Option<BlockIdVariant>,
+    ) -> eyre::Result<zksync_basic_types::Bytes> {
+        let _span = observe_fork_request("get_code");
+        let client = self.create_client();
+        block_on(async move { client.get_code(address, block).await })
+            .wrap_err("fork http client failed")
+    }
+
     fn get_transaction_by_hash(
         &self,
         hash: zksync_basic_types::H256,
     ) -> eyre::Result<Option<zksync_types::api::Transaction>> {
         if let Ok(Some(transaction)) = self
             .cache
-            .read()
-            .map(|guard| guard.get_transaction(&hash).cloned())
+            .write()
+            .map(|mut guard| guard.get_transaction(&hash).cloned())
         {
             tracing::debug!("using cached transaction for {hash}");
             return Ok(Some(transaction));
         }
 
+        let _span = observe_fork_request("get_transaction_by_hash");
         let client = self.create_client();
         block_on(async move { client.get_transaction_by_hash(hash).await })
             .map(|maybe_transaction| {
@@ -95,6 +140,7 @@ impl ForkSource for HttpForkSource {
         &self,
         hash: H256,
     ) -> eyre::Result<Option<zksync_types::api::TransactionDetails>> {
+        let _span = observe_fork_request("get_transaction_details");
         let client = self.create_client();
         // n.b- We don't cache these responses as they will change through the lifecycle of the transaction
         // and caching could be error-prone. in theory we could cache responses once the txn status
@@ -110,13 +156,14 @@ impl ForkSource for HttpForkSource {
         let number = block_number.0 as u64;
         if let Ok(Some(transaction)) = self
             .cache
-            .read()
-            .map(|guard| guard.get_block_raw_transactions(&number).cloned())
+            .write()
+            .map(|mut guard| guard.get_block_raw_transactions(&number).cloned())
         {
             tracing::debug!("using cached raw transactions for block {block_number}");
             return Ok(transaction);
         }
 
+        let _span = observe_fork_request("get_raw_block_transactions");
         let client = self.create_client();
         block_on(async move { client.get_raw_block_transactions(block_number).await })
             .wrap_err("fork http client failed")
@@ -145,13 +192,14 @@ impl ForkSource for HttpForkSource {
     ) -> eyre::Result<Option<zksync_types::api::Block<zksync_types::api::TransactionVariant>>> {
         if let Ok(Some(block)) = self
             .cache
-            .read()
-            .map(|guard| guard.get_block(&hash, full_transactions).cloned())
+            .write()
+            .map(|mut guard| guard.get_block(&hash, full_transactions).cloned())
         {
             tracing::debug!("using cached block for {hash}");
             return Ok(Some(block));
         }
 
+        let _span = observe_fork_request("get_block_by_hash");
         let client = self.create_client();
         block_on(async move { client.get_block_by_hash(hash, full_transactions).await })
             .map(|block| {
@@ -182,16 +230,18 @@ impl ForkSource for HttpForkSource {
         };
 
         if let Some(block) = maybe_number.and_then(|number| {
-            self.cache.read().ok().and_then(|guard| {
+            self.cache.write().ok().and_then(|mut guard| {
                 guard
                     .get_block_hash(&number.as_u64())
-                    .and_then(|hash| guard.get_block(hash, full_transactions).cloned())
+                    .copied()
+                    .and_then(|hash| guard.get_block(&hash, full_transactions).cloned())
             })
         }) {
             tracing::debug!("using cached block for {block_number}");
             return Ok(Some(block));
         }
 
+        let _span = observe_fork_request("get_block_by_number");
         let client = self.create_client();
         block_on(async move {
             client
@@ -219,6 +269,7 @@ impl ForkSource for HttpForkSource {
 
     /// Returns the  transaction count for a given block hash.
     fn get_block_transaction_count_by_hash(&self, block_hash: H256) -> eyre::Result<Option<U256>> {
+        let _span = observe_fork_request("get_block_transaction_count_by_hash");
         let client = self.create_client();
         block_on(async move { client.get_block_transaction_count_by_hash(block_hash).await })
             .wrap_err("fork http client failed")
@@ -229,6 +280,7 @@ impl ForkSource for HttpForkSource {
         &self,
         block_number: zksync_types::api::BlockNumber,
     ) -> eyre::Result<Option<U256>> {
+        let _span = observe_fork_request("get_block_transaction_count_by_number");
         let client = self.create_client();
         block_on(async move {
             client
@@ -244,6 +296,7 @@ impl ForkSource for HttpForkSource {
         block_hash: H256,
         index: Index,
     ) -> eyre::Result<Option<Transaction>> {
+        let _span = observe_fork_request("get_transaction_by_block_hash_and_index");
         let client = self.create_client();
         block_on(async move {
             client
@@ -259,6 +312,7 @@ impl ForkSource for HttpForkSource {
         block_number: zksync_types::api::BlockNumber,
         index: Index,
     ) -> eyre::Result<Option<Transaction>> {
+        let _span = observe_fork_request("get_transaction_by_block_number_and_index");
         let client = self.create_client();
         block_on(async move {
             client
@@ -273,11 +327,34 @@ impl ForkSource for HttpForkSource {
         &self,
         miniblock: zksync_basic_types::MiniblockNumber,
     ) -> eyre::Result<Option<zksync_types::api::BlockDetails>> {
+        let _span = observe_fork_request("get_block_details");
         let client = self.create_client();
         block_on(async move { client.get_block_details(miniblock).await })
             .wrap_err("fork http client failed")
     }
 
+    /// Returns the numbers of the first and the last block of an L1 batch.
+    fn get_block_range(
+        &self,
+        batch: zksync_basic_types::L1BatchNumber,
+    ) -> eyre::Result<Option<(zksync_basic_types::U64, zksync_basic_types::U64)>> {
+        let _span = observe_fork_request("get_block_range");
+        let client = self.create_client();
+        block_on(async move { client.get_miniblock_range(batch).await })
+            .wrap_err("fork http client failed")
+    }
+
+    /// Returns the details of an L1 batch.
+    fn get_l1_batch_details(
+        &self,
+        batch: zksync_basic_types::L1BatchNumber,
+    ) -> eyre::Result<Option<zksync_types::api::L1BatchDetails>> {
+        let _span = observe_fork_request("get_l1_batch_details");
+        let client = self.create_client();
+        block_on(async move { client.get_l1_batch_details(batch).await })
+            .wrap_err("fork http client failed")
+    }
+
     /// Returns addresses of the default bridge contracts.
     fn get_bridge_contracts(&self) -> eyre::Result<BridgeAddresses> {
         if let Some(bridge_addresses) = self
@@ -290,6 +367,7 @@ impl ForkSource for HttpForkSource {
             return Ok(bridge_addresses);
         };
 
+        let _span = observe_fork_request("get_bridge_contracts");
         let client = self.create_client();
         block_on(async move { client.get_bridge_contracts().await })
             .map(|bridge_addresses| {
@@ -311,14 +389,15 @@ impl ForkSource for HttpForkSource {
     fn get_confirmed_tokens(&self, from: u32, limit: u8) -> eyre::Result<Vec<Token>> {
         if let Some(confirmed_tokens) = self
             .cache
-            .read()
+            .write()
             .ok()
-            .and_then(|guard| guard.get_confirmed_tokens(from, limit).cloned())
+            .and_then(|mut guard| guard.get_confirmed_tokens(from, limit).cloned())
         {
             tracing::debug!("using cached confirmed_tokens");
             return Ok(confirmed_tokens);
         };
 
+        let _span = observe_fork_request("get_confirmed_tokens");
         let client = self.create_client();
         block_on(async move { client.get_confirmed_tokens(from, limit).await })
             .map(|confirmed_tokens| {
@@ -337,6 +416,46 @@ impl ForkSource for HttpForkSource {
             })
             .wrap_err("fork http client failed")
     }
+
+    fn call(
+        &self,
+        request: CallRequest,
+        block: Option<BlockIdVariant>,
+    ) -> eyre::Result<zksync_basic_types::Bytes> {
+        // n.b- Not cached, as the result depends on the whole request.
+        let _span = observe_fork_request("call");
+        let client = self.create_client();
+        block_on(async move { client.call(request, block).await })
+            .wrap_err("fork http client failed")
+    }
+
+    fn estimate_gas(&self, request: CallRequest, block: Option<BlockNumber>) -> eyre::Result<U256> {
+        let _span = observe_fork_request("estimate_gas");
+        let client = self.create_client();
+        block_on(async move { client.estimate_gas(request, block).await })
+            .wrap_err("fork http client failed")
+    }
+
+    fn cache_sizes(&self) -> Vec<(&'static str, usize)> {
+        self.cache
+            .read()
+            .map(|guard| guard.sizes())
+            .unwrap_or_default()
+    }
+
+    fn clear_cache(&self, kind: &str) {
+        match self.cache.write() {
+            Ok(mut guard) => guard.clear(kind),
+            Err(err) => tracing::error!("failed acquiring cache lock: {:?}", err),
+        }
+    }
+
+    fn disk_cache_dir(&self) -> Option<PathBuf> {
+        self.cache
+            .read()
+            .ok()
+            .and_then(|guard| guard.disk_dir().map(Path::to_path_buf))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,240 @@
+use std::{env, fs, path::Path};
+
+use anyhow::{anyhow, Context};
+use multivm::interface::ExecutionResult;
+use serde::Deserialize;
+use zksync_basic_types::{H256, U256};
+use zksync_types::{transaction_request::CallRequest, Bytes};
+
+use crate::{
+    fork::ForkSource,
+    namespaces::EthNamespaceT,
+    node::{InMemoryNode, L2_GAS_PRICE},
+};
+
+/// Single transaction of an init script. Either a raw signed transaction, or an unsigned
+/// transaction request that is sent on behalf of its `from` account (which is temporarily impersonated).
+///
+/// Example:
+/// ```json
+/// [
+///   "0x02f87082010480840ee6b28083...",
+///   { "from": "0x36615Cf349d7F6344891B1e7CA7C72883F5dc049", "to": "${RECIPIENT}", "value": "0xde0b6b3a7640000" }
+/// ]
+/// ```
+///
+/// `${NAME}` placeholders are substituted with the values of the respective environment variables, escaped to fit
+/// in a JSON string.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum InitTransaction {
+    Raw(Bytes),
+    Request(Box<CallRequest>),
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Executes the transactions listed in the init script at `path`, in order.
+    /// Stops at the first transaction that fails or reverts.
+    ///
+    /// # Returns
+    /// The hashes of the executed transactions.
+    pub async fn run_init_script<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<Vec<H256>> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed reading init script {}", path.display()))?;
+        let transactions: Vec<InitTransaction> =
+            serde_json::from_str(&substitute_env_vars(&contents)?)
+                .with_context(|| format!("failed parsing init script {}", path.display()))?;
+
+        let mut hashes = Vec::with_capacity(transactions.len());
+        for (index, tx) in transactions.into_iter().enumerate() {
+            let hash = match tx {
+                InitTransaction::Raw(bytes) => self.send_raw_transaction(bytes).await,
+                InitTransaction::Request(request) => {
+                    self.send_impersonated_request(*request).await?
+                }
+            }
+            .map_err(|err| anyhow!("init transaction #{} failed: {}", index, err.message))?;
+            if let Some(reason) = self.revert_reason(hash)? {
+                anyhow::bail!(
+                    "init transaction #{} ({:#x}) reverted: {}",
+                    index,
+                    hash,
+                    reason
+                );
+            }
+            hashes.push(hash);
+        }
+
+        Ok(hashes)
+    }
+
+    /// Returns the revert reason of the executed transaction, or `None` if it succeeded or is still pending.
+    fn revert_reason(&self, hash: H256) -> anyhow::Result<Option<String>> {
+        let inner = self.get_inner();
+        let reader = inner
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        Ok(reader
+            .tx_results
+            .get(&hash)
+            .filter(|tx_result| tx_result.receipt.status.is_zero())
+            .map(|tx_result| match &tx_result.info.result.result {
+                ExecutionResult::Revert { output } => output.to_string(),
+                ExecutionResult::Halt { reason } => reason.to_string(),
+                ExecutionResult::Success { .. } => "unknown reason".to_string(),
+            }))
+    }
+
+    /// Fills in the missing nonce, gas price and gas limit and sends the request on behalf of `from`.
+    pub(crate) async fn send_impersonated_request(
+        &self,
+        mut request: CallRequest,
+    ) -> anyhow::Result<jsonrpc_core::Result<H256>> {
+        let from = request
+            .from
+            .ok_or_else(|| anyhow!("init transaction is missing the `from` field"))?;
+
+        if request.nonce.is_none() {
+            let nonce = self
+                .get_transaction_count(from, None)
+                .await
+                .map_err(|err| anyhow!("failed fetching nonce: {}", err.message))?;
+            request.nonce = Some(nonce);
+        }
+        if request.gas_price.is_none() && request.max_fee_per_gas.is_none() {
+            request.gas_price = Some(U256::from(L2_GAS_PRICE));
+        }
+        if request.gas.is_none() {
+            let gas = self
+                .estimate_gas(request.clone(), None)
+                .await
+                .map_err(|err| anyhow!("failed estimating gas: {}", err.message))?;
+            request.gas = Some(gas);
+        }
+
+        let was_impersonated = !self.impersonate_account(from)?;
+        let result = self.send_transaction(request).await;
+        if !was_impersonated {
+            self.stop_impersonating_account(from)?;
+        }
+
+        Ok(result)
+    }
+}
+
+/// Replaces every `${NAME}` placeholder with the value of the `NAME` environment variable, JSON-escaped so that
+/// quotes and backslashes in the value keep the script valid.
+fn substitute_env_vars(input: &str) -> anyhow::Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("unterminated placeholder in init script"))?;
+        let name = &rest[start + 2..start + end];
+        let value = env::var(name)
+            .with_context(|| format!("environment variable `{}` is not set", name))?;
+        let escaped = serde_json::to_string(&value)?;
+        output.push_str(&rest[..start]);
+        // without the quotes, as the placeholder is within a JSON string
+        output.push_str(&escaped[1..escaped.len() - 1]);
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+    use tempdir::TempDir;
+    use zksync_basic_types::Address;
+
+    #[test]
+    fn test_substitute_env_vars() {
+        env::set_var("ERA_TEST_NODE_INIT_SCRIPT_TEST", "0x1234");
+
+        assert_eq!(
+            r#"{"to": "0x1234"}"#,
+            substitute_env_vars(r#"{"to": "${ERA_TEST_NODE_INIT_SCRIPT_TEST}"}"#).unwrap()
+        );
+        env::set_var("ERA_TEST_NODE_INIT_SCRIPT_ESCAPED", r#"a "quoted" \ value"#);
+        let substituted =
+            substitute_env_vars(r#"{"data": "${ERA_TEST_NODE_INIT_SCRIPT_ESCAPED}"}"#).unwrap();
+        assert_eq!(r#"{"data": "a \"quoted\" \\ value"}"#, substituted);
+        let parsed: serde_json::Value = serde_json::from_str(&substituted).unwrap();
+        assert_eq!(r#"a "quoted" \ value"#, parsed["data"]);
+        assert_eq!(
+            "no placeholders",
+            substitute_env_vars("no placeholders").unwrap()
+        );
+        assert!(substitute_env_vars("${ERA_TEST_NODE_INIT_SCRIPT_MISSING}").is_err());
+        assert!(substitute_env_vars("${UNTERMINATED").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_init_script_executes_unsigned_requests() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let from = Address::repeat_byte(0x1);
+        let to = Address::repeat_byte(0x2);
+        node.set_rich_account(from);
+
+        let dir = TempDir::new("init-script").unwrap();
+        let path = dir.path().join("init.json");
+        fs::write(
+            &path,
+            format!(
+                r#"[
+                    {{"from": "{from:#x}", "to": "{to:#x}", "value": "0x64"}},
+                    {{"from": "{from:#x}", "to": "{to:#x}", "value": "0x64"}}
+                ]"#
+            ),
+        )
+        .unwrap();
+
+        let hashes = node.run_init_script(&path).await.expect("failed running");
+
+        assert_eq!(2, hashes.len());
+        assert_eq!(U256::from(200), node.get_balance(to, None).await.unwrap());
+        assert!(!node
+            .get_inner()
+            .read()
+            .unwrap()
+            .impersonated_accounts
+            .contains(&from));
+    }
+
+    #[tokio::test]
+    async fn test_run_init_script_aborts_on_revert() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let from = Address::repeat_byte(0x1);
+        node.set_rich_account(from);
+
+        let dir = TempDir::new("init-script").unwrap();
+        let path = dir.path().join("init.json");
+        // the deployer has no such function, so the call reverts
+        fs::write(
+            &path,
+            format!(
+                r#"[
+                    {{"from": "{from:#x}", "to": "{from:#x}", "value": "0x64"}},
+                    {{"from": "{from:#x}", "to": "0x0000000000000000000000000000000000008006", "data": "0x12345678", "gas": "0x1000000"}}
+                ]"#
+            ),
+        )
+        .unwrap();
+
+        let err = node
+            .run_init_script(&path)
+            .await
+            .expect_err("reverted script succeeded");
+        assert!(
+            err.to_string().starts_with("init transaction #1"),
+            "unexpected error: {}",
+            err
+        );
+    }
+}
@@ -30,30 +30,58 @@
 //! - No communication between Layer 1 and Layer 2.
 //! - Many APIs are not yet implemented.
 //! - No support for accessing historical data.
-//! - Only one transaction allowed per Layer 1 batch.
+//! - Only one transaction allowed per Layer 1 batch, unless automine is disabled.
+//! - No EVM bytecode emulation: the bundled system contracts do not ship an EVM interpreter, so only
+//!   EraVM bytecode (compiled with `zksolc`/`zkvyper`) can be deployed and executed.
 //!
 //! ## Usage
 //!
 //! To start the node, use the command `era_test_node run`. For more advanced functionalities like forking or
 //! replaying transactions, refer to the [official documentation](https://era.zksync.io/docs/tools/testing/era-test-node.html).
 //!
+//! The node can also be started in-process, e.g. from Rust integration tests, via [Node::builder].
+//!
 //! ## Contributions
 //!
 //! Contributions to improve `era-test-node` are welcome. Please refer to the [contribution guidelines](https://github.com/matter-labs/era-test-node/blob/main/.github/CONTRIBUTING.md) for more details.
 
+pub mod bench;
 pub mod bootloader_debug;
+pub mod builder;
+pub mod chaos;
+pub mod checkpoint;
+pub mod config_file;
+pub mod conformance;
+pub mod console;
 pub mod console_log;
 pub mod deps;
+pub mod execution_limit;
 pub mod filters;
 pub mod fork;
 pub mod formatter;
+pub mod genesis;
+pub mod graphql;
+pub mod health;
 pub mod http_fork_source;
+pub mod init_script;
+pub mod logging_middleware;
+pub mod metrics;
+pub mod multiplex;
 pub mod namespaces;
 pub mod node;
 pub mod observability;
+pub mod predeploy;
 pub mod resolver;
+pub mod rpc_filter;
+pub mod server;
+pub mod subscriptions;
 pub mod system_contracts;
 pub mod utils;
+pub mod verification;
+pub mod ws_connections;
 
+mod auth;
 mod cache;
 mod testing;
+
+pub use builder::{Node, NodeBuilder};
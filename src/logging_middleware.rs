@@ -1,23 +1,76 @@
+use std::{sync::Arc, time::Instant};
+
 use colored::Colorize;
 use futures::Future;
 use futures::{future::Either, FutureExt};
 use itertools::Itertools;
 use jsonrpc_core::{
-    middleware, Call, FutureResponse, Metadata, Middleware, Params, Request, Response,
+    middleware, Call, FutureResponse, Metadata, Middleware, Output, Params, Request, Response,
 };
+use jsonrpc_pubsub::{PubSubMetadata, Session};
+use tracing::Instrument;
 use tracing_subscriber::filter::LevelFilter;
 
+use crate::{
+    metrics::METRICS,
+    observability::{SPAN_TARGET, STRUCTURED_LOG_TARGET},
+    ws_connections::WsActivity,
+};
+
+/// Metadata of the RPC requests, holding the session of the WebSocket connections for the subscriptions.
 #[derive(Clone, Debug, Default)]
-pub struct Meta();
+pub struct Meta {
+    pub session: Option<Arc<Session>>,
+    /// Activity of the WebSocket connection, touched on each of its requests for its idle timeout.
+    pub activity: Option<WsActivity>,
+}
 impl Metadata for Meta {}
+impl PubSubMetadata for Meta {
+    fn session(&self) -> Option<Arc<Session>> {
+        self.session.clone()
+    }
+}
 
 pub struct LoggingMiddleware {
     log_level_filter: LevelFilter,
+    /// If true, every handled request is logged with its duration and result status.
+    log_requests: bool,
 }
 
 impl LoggingMiddleware {
-    pub fn new(log_level_filter: LevelFilter) -> Self {
-        Self { log_level_filter }
+    pub fn new(log_level_filter: LevelFilter, log_requests: bool) -> Self {
+        Self {
+            log_level_filter,
+            log_requests,
+        }
+    }
+}
+
+/// Formats the params of a request, truncating the ones with massive payloads.
+fn truncated_params(params: &Params) -> String {
+    match params {
+        Params::Array(values) => {
+            if values.is_empty() {
+                String::default()
+            } else {
+                format!(
+                    "with [{}]",
+                    values
+                        .iter()
+                        .map(|s| {
+                            let s_str = s.to_string();
+                            if s_str.len() > 70 {
+                                format!("{:.67}...", s_str)
+                            } else {
+                                s_str
+                            }
+                        })
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }
+        }
+        _ => String::default(),
     }
 }
 
@@ -33,6 +86,9 @@ impl Middleware<Meta> for LoggingMiddleware {
         F: FnOnce(Request, Meta) -> X + Send,
         X: Future<Output = Option<Response>> + Send + 'static,
     {
+        if let Some(activity) = &meta.activity {
+            activity.touch();
+        }
         if let Request::Single(Call::MethodCall(method_call)) = &request {
             match self.log_level_filter {
                 LevelFilter::TRACE => {
@@ -50,43 +106,60 @@ impl Middleware<Meta> for LoggingMiddleware {
                     tracing::trace!("{} was called {}", method_call.method.cyan(), full_params);
                 }
                 _ => {
-                    // Generate truncated params for requests with massive payloads
-                    let truncated_params = match &method_call.params {
-                        Params::Array(values) => {
-                            if values.is_empty() {
-                                String::default()
-                            } else {
-                                format!(
-                                    "with [{}]",
-                                    values
-                                        .iter()
-                                        .map(|s| {
-                                            let s_str = s.to_string();
-                                            if s_str.len() > 70 {
-                                                format!("{:.67}...", s_str)
-                                            } else {
-                                                s_str
-                                            }
-                                        })
-                                        .collect::<Vec<String>>()
-                                        .join(", ")
-                                )
-                            }
-                        }
-                        _ => String::default(),
-                    };
-
                     tracing::debug!(
                         "{} was called {}",
                         method_call.method.cyan(),
-                        truncated_params
+                        truncated_params(&method_call.params)
                     );
                 }
             }
         };
 
-        Either::Left(Box::pin(next(request, meta).map(move |res| {
+        let method = match &request {
+            Request::Single(Call::MethodCall(method_call)) => Some(method_call.method.clone()),
+            _ => None,
+        };
+        let logged_params = match &request {
+            Request::Single(Call::MethodCall(method_call)) if self.log_requests => {
+                Some(truncated_params(&method_call.params))
+            }
+            _ => None,
+        };
+        let started_at = Instant::now();
+        let span = tracing::info_span!(
+            target: SPAN_TARGET,
+            "rpc_request",
+            method = method.as_deref().unwrap_or("batch")
+        );
+        // the handlers may do their work before returning the future, so the call is made within the span as well
+        let response = span.in_scope(|| next(request, meta)).instrument(span);
+
+        Either::Left(Box::pin(response.map(move |res| {
             tracing::trace!("API response => {:?}", res);
+            if let Some(method) = method {
+                let failed = matches!(res, Some(Response::Single(Output::Failure(_))));
+                METRICS.observe_rpc_request(&method, started_at.elapsed(), failed);
+                tracing::debug!(
+                    target: STRUCTURED_LOG_TARGET,
+                    method = %method,
+                    success = !failed,
+                    duration_ms = started_at.elapsed().as_millis() as u64,
+                    "rpc request handled"
+                );
+                if let Some(params) = logged_params {
+                    tracing::info!(
+                        "{} {} in {:?} {}",
+                        method.cyan(),
+                        if failed {
+                            "failed".red()
+                        } else {
+                            "succeeded".green()
+                        },
+                        started_at.elapsed(),
+                        params
+                    );
+                }
+            }
             res
         })))
     }
@@ -1,53 +1,82 @@
+use crate::bench::BenchConfig;
 use crate::cache::CacheConfig;
+use crate::checkpoint::{CheckpointFork, CheckpointManifest, Checkpointer};
+use crate::conformance::{conformance_cases, run_conformance_suite};
+use crate::execution_limit::ExecutionLimits;
 use crate::node::{InMemoryNodeConfig, ShowGasDetails, ShowStorageLogs, ShowVMDetails};
 use crate::observability::Observability;
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{
+    parser::ValueSource, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand,
+    ValueEnum,
+};
 use colored::Colorize;
-use fork::{ForkDetails, ForkSource};
+use fork::ForkDetails;
+use genesis::GenesisFile;
 use logging_middleware::LoggingMiddleware;
 use node::ShowCalls;
-use observability::LogLevel;
+use observability::{LogFormat, LogLevel, LogRotation, LogRotationInterval, RotatingLogFile};
+use rpc_filter::RpcFilterMiddleware;
 use tracing_subscriber::filter::LevelFilter;
 
+mod auth;
+mod bench;
 mod bootloader_debug;
 mod cache;
+mod chaos;
+mod checkpoint;
+mod config_file;
+mod conformance;
+mod console;
 mod console_log;
 mod deps;
+mod execution_limit;
 mod filters;
 mod fork;
 mod formatter;
+mod genesis;
+mod graphql;
+mod health;
 mod http_fork_source;
+mod init_script;
 mod logging_middleware;
+mod metrics;
+mod multiplex;
 mod namespaces;
 mod node;
 pub mod observability;
+mod predeploy;
 mod resolver;
+mod rpc_filter;
+mod server;
+mod subscriptions;
 mod system_contracts;
 mod testing;
 mod utils;
+mod verification;
+mod ws_connections;
 
+use http_fork_source::HttpForkSource;
 use node::InMemoryNode;
 
-use std::fs::File;
+use std::path::PathBuf;
 use std::{
+    collections::HashMap,
     env,
     net::{IpAddr, Ipv4Addr, SocketAddr},
     str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
-use futures::{
-    channel::oneshot,
-    future::{self},
-    FutureExt,
-};
-use jsonrpc_core::MetaIoHandler;
+use futures::future::{self};
+use multiplex::{serve_multiplexed, MultiplexTargets};
+use server::{build_io_handler, build_json_http, build_json_ws, RpcNamespace};
+use ws_connections::WsConfig;
 use zksync_basic_types::{H160, H256};
 
-use crate::namespaces::{
-    ConfigurationApiNamespaceT, DebugNamespaceT, EthNamespaceT, EthTestNodeNamespaceT,
-    EvmNamespaceT, HardhatNamespaceT, NetNamespaceT, Web3NamespaceT, ZksNamespaceT,
-};
-
 /// List of legacy wallets (address, private key) that we seed with tokens at start.
 pub const LEGACY_RICH_WALLETS: [(&str, &str); 10] = [
     (
@@ -146,51 +175,6 @@ pub const RICH_WALLETS: [(&str, &str, &str); 10] = [
     ),
 ];
 
-#[allow(clippy::too_many_arguments)]
-async fn build_json_http<
-    S: std::marker::Sync + std::marker::Send + 'static + ForkSource + std::fmt::Debug + Clone,
->(
-    addr: SocketAddr,
-    log_level_filter: LevelFilter,
-    node: InMemoryNode<S>,
-) -> tokio::task::JoinHandle<()> {
-    let (sender, recv) = oneshot::channel::<()>();
-
-    let io_handler = {
-        let mut io = MetaIoHandler::with_middleware(LoggingMiddleware::new(log_level_filter));
-
-        io.extend_with(NetNamespaceT::to_delegate(node.clone()));
-        io.extend_with(Web3NamespaceT::to_delegate(node.clone()));
-        io.extend_with(ConfigurationApiNamespaceT::to_delegate(node.clone()));
-        io.extend_with(DebugNamespaceT::to_delegate(node.clone()));
-        io.extend_with(EthNamespaceT::to_delegate(node.clone()));
-        io.extend_with(EthTestNodeNamespaceT::to_delegate(node.clone()));
-        io.extend_with(EvmNamespaceT::to_delegate(node.clone()));
-        io.extend_with(HardhatNamespaceT::to_delegate(node.clone()));
-        io.extend_with(ZksNamespaceT::to_delegate(node));
-        io
-    };
-
-    std::thread::spawn(move || {
-        let runtime = tokio::runtime::Builder::new_multi_thread()
-            .enable_all()
-            .worker_threads(1)
-            .build()
-            .unwrap();
-
-        let server = jsonrpc_http_server::ServerBuilder::new(io_handler)
-            .threads(1)
-            .event_loop_executor(runtime.handle().clone())
-            .start_http(&addr)
-            .unwrap();
-
-        server.wait();
-        let _ = sender;
-    });
-
-    tokio::spawn(recv.map(drop))
-}
-
 /// Cache type config for the node.
 #[derive(ValueEnum, Debug, Clone)]
 enum CacheType {
@@ -209,55 +193,369 @@ enum DevSystemContracts {
 
 #[derive(Debug, Parser)]
 #[command(author = "Matter Labs", version, about = "Test Node", long_about = None)]
+#[command(args_override_self = true)]
 struct Cli {
     #[command(subcommand)]
     command: Command,
-    #[arg(long, default_value = "8011")]
+    /// Path to a TOML file with the values of the command line options, e.g. `port = 8012`.
+    /// Options given on the command line take precedence. Logging, tracing and gas settings
+    /// are reloaded when the file changes.
+    #[arg(long, env = "ERA_TEST_NODE_CONFIG")]
+    config: Option<PathBuf>,
+    /// Bundle of options for a common setup, overridden by the options set otherwise:
+    /// `mainnet-fork` or `testnet-fork` (fork by default, cache on disk), `ci` (deterministic and quiet)
+    /// or `heavy-debug` (show all the details).
+    #[arg(long, env = "ERA_TEST_NODE_PRESET")]
+    preset: Option<config_file::Preset>,
+    /// Increase the verbosity: `-v` shows the calls of the user contracts, `-vv` also the storage writes, gas
+    /// details and debug logs, `-vvv` all the calls, storage logs, VM details and trace logs.
+    /// Options set explicitly take precedence.
+    #[arg(short, long, env = "ERA_TEST_NODE_VERBOSE", action = ArgAction::Count)]
+    verbose: u8,
+    /// Only log errors, e.g. in CI.
+    #[arg(short, long, env = "ERA_TEST_NODE_QUIET", conflicts_with = "verbose")]
+    quiet: bool,
+    #[arg(long, env = "ERA_TEST_NODE_PORT", default_value = "8011")]
     /// Port to listen on - default: 8011
     port: u16,
-    #[arg(long, default_value = "none")]
+    /// Port of a dedicated WebSocket server, serving the RPC methods along with the `eth_subscribe` subscriptions.
+    /// Not started if not set, the WebSocket connections are then only accepted on `--port`.
+    #[arg(long, env = "ERA_TEST_NODE_WS_PORT")]
+    ws_port: Option<u16>,
+    /// Only serve HTTP on `--port`. By default the WebSocket upgrade requests to `--port` are also accepted, so that
+    /// the clients taking a single URL can use both.
+    #[arg(long, env = "ERA_TEST_NODE_NO_WS_UPGRADE")]
+    no_ws_upgrade: bool,
+    /// Maximum number of WebSocket connections, the new ones are refused beyond it - default: 100
+    #[arg(long, env = "ERA_TEST_NODE_WS_MAX_CONNECTIONS", default_value = "100")]
+    ws_max_connections: usize,
+    /// Send a keepalive message to the WebSocket connections every this many seconds, so that proxies don't close
+    /// the quiet ones. The pings of the clients are always answered with pongs.
+    #[arg(long, env = "ERA_TEST_NODE_WS_KEEPALIVE")]
+    ws_keepalive: Option<u64>,
+    /// Close the WebSocket connections that sent no request for this many seconds.
+    #[arg(long, env = "ERA_TEST_NODE_WS_IDLE_TIMEOUT")]
+    ws_idle_timeout: Option<u64>,
+    #[arg(long, env = "ERA_TEST_NODE_SHOW_CALLS", default_value = "none")]
     /// Show call debug information
     show_calls: ShowCalls,
-    #[arg(long, default_value = "none")]
+    #[arg(long, env = "ERA_TEST_NODE_SHOW_STORAGE_LOGS", default_value = "none")]
     /// Show storage log information
     show_storage_logs: ShowStorageLogs,
-    #[arg(long, default_value = "none")]
+    #[arg(long, env = "ERA_TEST_NODE_SHOW_VM_DETAILS", default_value = "none")]
     /// Show VM details information
     show_vm_details: ShowVMDetails,
 
-    #[arg(long, default_value = "none")]
+    #[arg(long, env = "ERA_TEST_NODE_SHOW_GAS_DETAILS", default_value = "none")]
     /// Show Gas details information
     show_gas_details: ShowGasDetails,
 
-    #[arg(long)]
+    #[arg(long, env = "ERA_TEST_NODE_SHOW_PRECOMPILES")]
+    /// Show the calls to the precompiles (e.g. keccak256 or ecrecover) with `--show-calls=user|system`.
+    show_precompiles: bool,
+
+    #[arg(long, env = "ERA_TEST_NODE_SHOW_OUTPUTS")]
+    /// Show the return data of the calls in the call traces, decoded for the verified contracts.
+    show_outputs: bool,
+
+    #[arg(long, env = "ERA_TEST_NODE_RESOLVE_HASHES")]
     /// If true, the tool will try to contact openchain to resolve the ABI & topic names.
     /// It will make debug log more readable, but will decrease the performance.
     resolve_hashes: bool,
 
     /// Specifies the option for the system contracts (use compiled built-in with or without signature verification, or load locally).
     /// Default: built-in
-    #[arg(long, default_value = "built-in")]
+    #[arg(
+        long,
+        env = "ERA_TEST_NODE_DEV_SYSTEM_CONTRACTS",
+        default_value = "built-in"
+    )]
     dev_system_contracts: DevSystemContracts,
 
     /// Log filter level - default: info
-    #[arg(long, default_value = "info")]
+    #[arg(long, env = "ERA_TEST_NODE_LOG", default_value = "info")]
     log: LogLevel,
 
+    /// Disables the colors of the output, e.g. when it is piped into a file. Also disabled when the `NO_COLOR`
+    /// environment variable is set.
+    #[arg(long, env = "ERA_TEST_NODE_NO_COLOR")]
+    no_color: bool,
+
+    /// Log output format, can be one of `plain` or `json` - default: plain
+    #[arg(long, env = "ERA_TEST_NODE_LOG_FORMAT", default_value = "plain")]
+    log_format: LogFormat,
+
+    /// If true, every RPC request is logged with its params, duration and result status.
+    #[arg(long, env = "ERA_TEST_NODE_LOG_RPC_REQUESTS")]
+    log_rpc_requests: bool,
+
+    /// Comma separated RPC methods that may be called, all the others are rejected. A trailing `*` matches
+    /// any method with the given prefix, e.g. `eth_*,net_*`. By default all the methods may be called.
+    #[arg(long, env = "ERA_TEST_NODE_RPC_ALLOW", value_delimiter = ',')]
+    rpc_allow: Vec<String>,
+
+    /// Comma separated RPC methods that are rejected, e.g. `hardhat_setBalance,hardhat_impersonateAccount`.
+    /// A trailing `*` matches any method with the given prefix. Takes precedence over `--rpc-allow`.
+    #[arg(long, env = "ERA_TEST_NODE_RPC_DENY", value_delimiter = ',')]
+    rpc_deny: Vec<String>,
+
+    /// Comma separated RPC namespaces served by the node, e.g. `eth,zks,net,web3` to leave out the cheatcodes and
+    /// the `config_*` methods of a shared node. The methods of the other namespaces are not registered at all.
+    /// By default all the namespaces are served.
+    #[arg(long, env = "ERA_TEST_NODE_ENABLE_NAMESPACES", value_delimiter = ',')]
+    enable_namespaces: Vec<RpcNamespace>,
+
+    /// Reject the RPC methods changing the state or the configuration of the node, e.g. sending transactions,
+    /// the cheatcodes and the `config_set*` methods, to share a forked node as a query and simulation endpoint.
+    #[arg(long, env = "ERA_TEST_NODE_READ_ONLY")]
+    read_only: bool,
+
+    /// If true, serves a block explorer UI listing the recent blocks, transactions, call traces and
+    /// account balances at `/explorer`.
+    #[arg(long, env = "ERA_TEST_NODE_EXPLORER")]
+    explorer: bool,
+
+    /// If true, serves the standard Ethereum GraphQL schema (EIP-1767) at `/graphql`.
+    #[arg(long, env = "ERA_TEST_NODE_GRAPHQL")]
+    graphql: bool,
+
+    /// Comma separated private keys imported as local signers, funded like the rich wallets. `eth_sendTransaction`
+    /// signs the transactions of their accounts and `eth_sign` their messages. Can be repeated.
+    #[arg(
+        long,
+        value_name = "PRIVATE_KEY",
+        env = "ERA_TEST_NODE_IMPORT_KEY",
+        value_delimiter = ','
+    )]
+    import_key: Vec<H256>,
+
+    /// Instead of starting the server, runs the JSON-RPC conformance suite against the configured node, prints the
+    /// results as JSON and fails if any method does not behave like the documented API.
+    #[arg(long)]
+    selftest: bool,
+
+    /// Require the requests to be authenticated with this token, sent either as a bearer token or as the
+    /// password of the basic authentication. The `/health` and `/ready` endpoints remain public.
+    #[arg(long, env = "ERA_TEST_NODE_AUTH_TOKEN")]
+    auth_token: Option<String>,
+
     /// Log file path - default: era_test_node.log
-    #[arg(long, default_value = "era_test_node.log")]
+    #[arg(
+        long,
+        env = "ERA_TEST_NODE_LOG_FILE_PATH",
+        alias = "log-file",
+        default_value = "era_test_node.log"
+    )]
     log_file_path: String,
 
+    /// Rotate the log file once it grows over this many megabytes.
+    #[arg(long, env = "ERA_TEST_NODE_LOG_FILE_MAX_SIZE")]
+    log_file_max_size: Option<u64>,
+
+    /// Rotate the log file periodically, can be one of `never`, `hourly` or `daily` - default: never
+    #[arg(long, env = "ERA_TEST_NODE_LOG_FILE_ROTATION", default_value = "never")]
+    log_file_rotation: LogRotationInterval,
+
+    /// Number of rotated log files to keep - default: 5
+    #[arg(long, env = "ERA_TEST_NODE_LOG_FILE_MAX_FILES", default_value = "5")]
+    log_file_max_files: usize,
+
+    /// Export the spans covering RPC requests, VM execution and fork fetches to this OTLP (gRPC) endpoint,
+    /// e.g. `http://localhost:4317`. Requires the node to be built with the `otlp` feature.
+    #[arg(long, env = "ERA_TEST_NODE_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
+
     /// Cache type, can be one of `none`, `memory`, or `disk` - default: "disk"
-    #[arg(long, default_value = "disk")]
+    #[arg(long, env = "ERA_TEST_NODE_CACHE", default_value = "disk")]
     cache: CacheType,
 
     /// If true, will reset the local `disk` cache.
-    #[arg(long)]
+    #[arg(long, env = "ERA_TEST_NODE_RESET_CACHE")]
     reset_cache: bool,
 
     /// Cache directory location for `disk` cache - default: ".cache"
-    #[arg(long, default_value = ".cache")]
+    #[arg(long, env = "ERA_TEST_NODE_CACHE_DIR", default_value = ".cache")]
     cache_dir: String,
+
+    /// Maximum number of entries of every type (storage values, blocks, transactions, ...) kept in the in-memory
+    /// fork caches. The least recently used entries are evicted first. Unbounded if not set.
+    #[arg(long, env = "ERA_TEST_NODE_CACHE_MAX_SIZE")]
+    cache_max_size: Option<usize>,
+
+    /// Maximum memory, in megabytes, of the storage values and bytecodes read from the forked network and kept in
+    /// memory. The least recently used ones are evicted above it, to the disk cache with `--cache=disk` (and read
+    /// back from there), otherwise they are fetched again when needed. Unbounded if not set.
+    #[arg(long, value_name = "MEGABYTES", env = "ERA_TEST_NODE_MAX_FORK_MEMORY")]
+    max_fork_memory: Option<usize>,
+
+    /// The maximum amount of gas a block may use. Transactions with a higher gas limit are rejected.
+    #[arg(long, env = "ERA_TEST_NODE_BLOCK_GAS_LIMIT")]
+    block_gas_limit: Option<u64>,
+
+    /// The maximum gas limit of a single transaction.
+    #[arg(long, env = "ERA_TEST_NODE_TX_GAS_LIMIT")]
+    tx_gas_limit: Option<u64>,
+
+    /// The minimum max fee per gas of a transaction, in wei. Transactions offering less are rejected.
+    #[arg(long, env = "ERA_TEST_NODE_MIN_GAS_PRICE")]
+    min_gas_price: Option<u64>,
+
+    /// The maximum max fee per gas of a transaction, in wei. Transactions offering more are rejected.
+    #[arg(long, env = "ERA_TEST_NODE_MAX_GAS_PRICE")]
+    max_gas_price: Option<u64>,
+
+    /// The maximum wall-clock time of a single VM execution (e.g. an `eth_call`), in milliseconds, after
+    /// which it is aborted.
+    #[arg(long, value_name = "MS", env = "ERA_TEST_NODE_VM_EXECUTION_TIMEOUT")]
+    vm_execution_timeout: Option<u64>,
+
+    /// The maximum number of VM steps of a single execution, after which it is aborted.
+    #[arg(long, env = "ERA_TEST_NODE_VM_MAX_STEPS")]
+    vm_max_steps: Option<u64>,
+
+    /// Number of the threads running the VM for the read-only RPC methods (`eth_call`, `eth_estimateGas` and
+    /// `debug_traceCall`), which then run in parallel. Defaults to 1.
+    #[arg(long, value_name = "N", env = "ERA_TEST_NODE_WORKERS")]
+    workers: Option<usize>,
+
+    /// If true, transactions exceeding the block gas limit are not rejected.
+    #[arg(long, env = "ERA_TEST_NODE_DISABLE_BLOCK_GAS_LIMIT")]
+    disable_block_gas_limit: bool,
+
+    /// The operator address that collects the transaction fees and is reported as the block `miner`
+    /// (and by `eth_coinbase`). Defaults to the zero address.
+    #[arg(long, env = "ERA_TEST_NODE_FEE_ACCOUNT")]
+    fee_account: Option<H160>,
+
+    /// The network `era_importContract` imports the contracts from (mainnet, sepolia-testnet or a URL).
+    /// Defaults to the forked network, if any.
+    #[arg(long, env = "ERA_TEST_NODE_IMPORT_FROM", value_name = "NETWORK")]
+    import_from: Option<String>,
+
+    /// If true, submitted transactions stay pending until a block is mined (via `evm_mine` or `hardhat_mine`),
+    /// and are then packed into a single block.
+    #[arg(long, env = "ERA_TEST_NODE_NO_AUTOMINE")]
+    no_automine: bool,
+
+    /// Computes the state root of each block from the storage, e.g. for the tools checking the state root linkage of
+    /// the blocks. Slows down every block, so the state roots are zero by default.
+    #[arg(long, env = "ERA_TEST_NODE_STATE_ROOTS")]
+    state_roots: bool,
+
+    /// Makes repeated runs reproducible: the wall-clock time is never used, and (in non-fork mode) the first block
+    /// starts at the given timestamp (default: 1000), with each following block 1 second later.
+    #[arg(
+        long,
+        env = "ERA_TEST_NODE_DETERMINISTIC",
+        value_name = "START_TIMESTAMP",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1000"
+    )]
+    deterministic: Option<u64>,
+
+    /// Number of the first block (ignored when forking), for protocols that expect a deployment at a given
+    /// height - default: 0
+    #[arg(long, env = "ERA_TEST_NODE_INIT_BLOCK_NUMBER")]
+    init_block_number: Option<u64>,
+
+    /// Timestamp of the first block in seconds (ignored when forking), for protocols that expect a deployment
+    /// at a given time. Takes precedence over the start timestamp of `--deterministic`.
+    #[arg(long, env = "ERA_TEST_NODE_INIT_TIMESTAMP")]
+    init_timestamp: Option<u64>,
+
+    /// The chain id reported by the node (ignored when forking). Takes precedence over the `chainId` of the
+    /// `--genesis` file - default: 260
+    #[arg(long, env = "ERA_TEST_NODE_CHAIN_ID")]
+    chain_id: Option<u32>,
+
+    /// Path to a JSON genesis file with initial accounts (balance, nonce, code, storage)
+    /// and chain parameters to apply at startup, or to a binary state file written with `--dump-state`.
+    #[arg(long, env = "ERA_TEST_NODE_GENESIS")]
+    genesis: Option<PathBuf>,
+
+    /// Path of the file the state of the node is written to when it shuts down (on SIGINT or SIGTERM),
+    /// in the `--genesis` format so that it can be loaded on the next start. Files with the `.bin` extension
+    /// are written in the compressed binary format.
+    #[arg(long, env = "ERA_TEST_NODE_DUMP_STATE")]
+    dump_state: Option<PathBuf>,
+
+    /// Write an incremental checkpoint of the state to `--checkpoint-dir` every BLOCKS blocks, so that the state
+    /// can be recovered with the `restore` command after a crash.
+    #[arg(long, env = "ERA_TEST_NODE_CHECKPOINT_INTERVAL", value_name = "BLOCKS")]
+    checkpoint_interval: Option<u64>,
+
+    /// Directory of the checkpoints written with `--checkpoint-interval` and read by the `restore` command.
+    #[arg(
+        long,
+        env = "ERA_TEST_NODE_CHECKPOINT_DIR",
+        default_value = ".checkpoints"
+    )]
+    checkpoint_dir: PathBuf,
+
+    /// Path to a directory of compiled zksolc artifacts, or to a JSON manifest listing them,
+    /// to deploy at deterministic addresses before the node starts serving requests.
+    #[arg(long, env = "ERA_TEST_NODE_DEPLOY")]
+    deploy: Option<PathBuf>,
+
+    /// Path to a JSON file with a list of raw or unsigned transactions to execute at startup.
+    #[arg(long, env = "ERA_TEST_NODE_INIT")]
+    init: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Applies the `--verbose` or `--quiet` level to the logging and tracing options that were left at their default.
+    fn apply_verbosity(&mut self, matches: &ArgMatches) {
+        let (log, show_calls, show_storage_logs, show_vm_details, show_gas_details) =
+            match (self.quiet, self.verbose) {
+                (true, _) => (
+                    LogLevel::Error,
+                    ShowCalls::None,
+                    ShowStorageLogs::None,
+                    ShowVMDetails::None,
+                    ShowGasDetails::None,
+                ),
+                (false, 0) => return,
+                (false, 1) => (
+                    LogLevel::Info,
+                    ShowCalls::User,
+                    ShowStorageLogs::None,
+                    ShowVMDetails::None,
+                    ShowGasDetails::None,
+                ),
+                (false, 2) => (
+                    LogLevel::Debug,
+                    ShowCalls::User,
+                    ShowStorageLogs::Write,
+                    ShowVMDetails::None,
+                    ShowGasDetails::All,
+                ),
+                (false, _) => (
+                    LogLevel::Trace,
+                    ShowCalls::All,
+                    ShowStorageLogs::All,
+                    ShowVMDetails::All,
+                    ShowGasDetails::All,
+                ),
+            };
+
+        let is_default = |id| matches.value_source(id) == Some(ValueSource::DefaultValue);
+        if is_default("log") {
+            self.log = log;
+        }
+        if is_default("show_calls") {
+            self.show_calls = show_calls;
+        }
+        if is_default("show_storage_logs") {
+            self.show_storage_logs = show_storage_logs;
+        }
+        if is_default("show_vm_details") {
+            self.show_vm_details = show_vm_details;
+        }
+        if is_default("show_gas_details") {
+            self.show_gas_details = show_gas_details;
+        }
+    }
 }
 
 #[derive(Debug, Subcommand)]
@@ -271,6 +569,25 @@ enum Command {
     /// Starts a local network that is a fork of another network, and replays a given TX on it.
     #[command(name = "replay_tx")]
     ReplayTx(ReplayArgs),
+    /// Opens an interactive console attached to a running node.
+    #[command(name = "console")]
+    Console(ConsoleArgs),
+    /// Starts several isolated local networks on sequential ports starting with `--port`, e.g. one per
+    /// parallel test shard, and prints their manifest as JSON.
+    #[command(name = "spawn-pool")]
+    SpawnPool(SpawnPoolArgs),
+    /// Executes synthetic transactions against a new local network and prints the throughput, latency
+    /// percentiles and VM time as JSON.
+    #[command(name = "bench")]
+    Bench(BenchArgs),
+    /// Checks a sample of the storage slots and bytecodes a running forked node cached from the network against
+    /// the network at the fork block, prints the divergences as JSON and fails if there are any.
+    #[command(name = "verify-fork")]
+    VerifyFork(VerifyForkArgs),
+    /// Starts the node from a checkpoint written with `--checkpoint-interval` to `--checkpoint-dir`, forking the
+    /// same network at the same block if the checkpointed node was forked.
+    #[command(name = "restore")]
+    Restore(RestoreArgs),
 }
 
 #[derive(Debug, Parser)]
@@ -281,12 +598,77 @@ struct ForkArgs {
     ///  - mainnet
     ///  - testnet
     ///  - http://XXX:YY
+    #[arg(env = "ERA_TEST_NODE_FORK_URL")]
     network: String,
-    #[arg(long)]
+    #[arg(long, env = "ERA_TEST_NODE_FORK_AT")]
     // Fork at a given L2 miniblock height.
     // If not set - will use the current finalized block from the network.
     fork_at: Option<u64>,
+    /// Pull the new blocks of the forked network every SECONDS (default: 5) and advance the fork point to the
+    /// latest one, as long as no transactions were executed or submitted locally since the fork.
+    #[arg(
+        long,
+        env = "ERA_TEST_NODE_FORK_FOLLOW",
+        value_name = "SECONDS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "5"
+    )]
+    fork_follow: Option<u64>,
 }
+#[derive(Debug, Parser)]
+struct ConsoleArgs {
+    /// URL of the running node.
+    #[arg(env = "ERA_TEST_NODE_URL", default_value = "http://localhost:8011")]
+    url: String,
+}
+
+#[derive(Debug, Parser)]
+struct SpawnPoolArgs {
+    /// Number of node instances to start.
+    #[arg(long, env = "ERA_TEST_NODE_INSTANCES", default_value = "2")]
+    instances: u16,
+    /// Fork this network in every instance, see the `fork` command. If not set, each instance starts a new network.
+    #[arg(long, env = "ERA_TEST_NODE_FORK_URL")]
+    fork: Option<String>,
+    /// Fork at a given L2 miniblock height.
+    #[arg(long, env = "ERA_TEST_NODE_FORK_AT", requires = "fork")]
+    fork_at: Option<u64>,
+    /// Also write the manifest of the instances to this file.
+    #[arg(long, env = "ERA_TEST_NODE_MANIFEST")]
+    manifest: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+struct BenchArgs {
+    /// Number of ETH transfers to send.
+    #[arg(long, default_value = "1000")]
+    transfers: usize,
+    /// Number of contract deployments to send.
+    #[arg(long, default_value = "100")]
+    deploys: usize,
+    /// Number of the accounts the transactions are sent from.
+    #[arg(long, default_value = "10")]
+    accounts: usize,
+}
+
+#[derive(Debug, Parser)]
+struct VerifyForkArgs {
+    /// URL of the running node.
+    #[arg(env = "ERA_TEST_NODE_URL", default_value = "http://localhost:8011")]
+    url: String,
+    /// Number of the most recently used storage slots and bytecodes to check.
+    #[arg(long, default_value = "100")]
+    sample: usize,
+}
+
+#[derive(Debug, Parser)]
+struct RestoreArgs {
+    /// Index of the checkpoint to restore. If not set - will restore the latest one.
+    #[arg(long)]
+    checkpoint: Option<u64>,
+}
+
 #[derive(Debug, Parser)]
 struct ReplayArgs {
     /// Whether to fork from existing network.
@@ -296,6 +678,7 @@ struct ReplayArgs {
     ///  - sepolia-testnet
     ///  - goerli-testnet
     ///  - http://XXX:YY
+    #[arg(env = "ERA_TEST_NODE_FORK_URL")]
     network: String,
     /// Transaction hash to replay.
     tx: H256,
@@ -303,14 +686,64 @@ struct ReplayArgs {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let opt = Cli::parse();
+    let config = early_arg(env::args(), "config")
+        .map(config_file::read_config_file)
+        .transpose()?;
+    // An invalid preset is reported when parsing the rest of the arguments.
+    let preset = early_arg(env::args(), "preset")
+        .and_then(|preset| config_file::Preset::from_str(&preset, true).ok());
+
+    if let Some(fork_url) = preset.and_then(|preset| preset.fork_url()) {
+        if env::var_os("ERA_TEST_NODE_FORK_URL").is_none() {
+            env::set_var("ERA_TEST_NODE_FORK_URL", fork_url);
+        }
+    }
+    let mut options = preset.map(|preset| preset.options()).unwrap_or_default();
+    if let Some(config) = &config {
+        options.extend(config.clone());
+    }
+    // The options of the preset and config file come first, so that the command line ones override them.
+    // The ones set via environment variables are left out, see `config_file::to_cli_args`.
+    let mut args = env::args();
+    let options_args = config_file::to_cli_args(&options)?;
+    let matches =
+        Cli::command().get_matches_from(args.next().into_iter().chain(options_args).chain(args));
+    let mut opt = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    opt.apply_verbosity(&matches);
+    if let Command::Console(console) = &opt.command {
+        return console::run(&console.url).await;
+    }
+    if let Command::VerifyFork(verify_fork) = &opt.command {
+        return console::verify_fork(&verify_fork.url, verify_fork.sample).await;
+    }
+
+    if opt.no_color || env::var_os("NO_COLOR").map_or(false, |value| !value.is_empty()) {
+        colored::control::set_override(false);
+    }
     let log_level_filter = LevelFilter::from(opt.log);
-    let log_file = File::create(opt.log_file_path)?;
+    let log_file = RotatingLogFile::create(
+        &opt.log_file_path,
+        LogRotation {
+            max_size: opt
+                .log_file_max_size
+                .map(|megabytes| megabytes * 1024 * 1024),
+            interval: opt.log_file_rotation,
+            max_files: opt.log_file_max_files,
+        },
+    )?;
 
     // Initialize the tracing subscriber
-    let observability =
-        Observability::init(String::from("era_test_node"), log_level_filter, log_file)?;
+    let observability = Observability::init(
+        String::from("era_test_node"),
+        log_level_filter,
+        opt.log_format,
+        log_file,
+        opt.otlp_endpoint.clone(),
+    )?;
 
+    if let Some(preset) = opt.preset {
+        tracing::info!("Using the {:?} preset", preset);
+    }
     if matches!(opt.dev_system_contracts, DevSystemContracts::Local) {
         if let Some(path) = env::var_os("ZKSYNC_HOME") {
             tracing::info!("+++++ Reading local contracts from {:?} +++++", path);
@@ -324,15 +757,57 @@ async fn main() -> anyhow::Result<()> {
             reset: opt.reset_cache,
         },
     };
+    let max_fork_memory = opt.max_fork_memory.map(|megabytes| megabytes * 1024 * 1024);
 
     let fork_details = match &opt.command {
-        Command::Run => None,
+        Command::Run
+        | Command::Console(_)
+        | Command::VerifyFork(_)
+        | Command::SpawnPool(_)
+        | Command::Bench(_) => None,
         Command::Fork(fork) => {
-            Some(ForkDetails::from_network(&fork.network, fork.fork_at, cache_config).await)
+            if opt.init_block_number.is_some() || opt.init_timestamp.is_some() {
+                tracing::warn!("--init-block-number and --init-timestamp are ignored when forking");
+            }
+            if opt.deterministic.is_some() && fork.fork_at.is_none() {
+                tracing::warn!(
+                    "Forking the latest block in deterministic mode, use --fork-at to pin the fork block"
+                );
+            }
+            Some(
+                ForkDetails::from_network(&fork.network, fork.fork_at, cache_config.clone())
+                    .await
+                    .with_cache_max_size(opt.cache_max_size)
+                    .with_max_memory(max_fork_memory),
+            )
         }
-        Command::ReplayTx(replay_tx) => {
-            Some(ForkDetails::from_network_tx(&replay_tx.network, replay_tx.tx, cache_config).await)
+        Command::ReplayTx(replay_tx) => Some(
+            ForkDetails::from_network_tx(&replay_tx.network, replay_tx.tx, cache_config.clone())
+                .await
+                .with_cache_max_size(opt.cache_max_size)
+                .with_max_memory(max_fork_memory),
+        ),
+        Command::Restore(_) => match CheckpointManifest::read(&opt.checkpoint_dir)?.fork {
+            Some(fork) => Some(
+                ForkDetails::from_network(&fork.url, Some(fork.block), cache_config.clone())
+                    .await
+                    .with_cache_max_size(opt.cache_max_size)
+                    .with_max_memory(max_fork_memory),
+            ),
+            None => None,
+        },
+    };
+    // The checkpoints are restored by forking the same network at the same block.
+    let checkpoint_fork = match &opt.command {
+        Command::Fork(ForkArgs { network, .. }) | Command::ReplayTx(ReplayArgs { network, .. }) => {
+            fork_details.as_ref().map(|fork_details| CheckpointFork {
+                url: ForkDetails::<HttpForkSource>::fork_to_url_and_client(network)
+                    .0
+                    .to_string(),
+                block: fork_details.l2_miniblock,
+            })
         }
+        _ => None,
     };
 
     // If we're replaying the transaction, we need to sync to the previous block
@@ -346,24 +821,75 @@ async fn main() -> anyhow::Result<()> {
     } else {
         vec![]
     };
+    let genesis = opt.genesis.as_ref().map(GenesisFile::open).transpose()?;
+
     let system_contracts_options = match opt.dev_system_contracts {
         DevSystemContracts::BuiltIn => system_contracts::Options::BuiltIn,
         DevSystemContracts::BuiltInNoVerify => system_contracts::Options::BuiltInWithoutSecurity,
         DevSystemContracts::Local => system_contracts::Options::Local,
     };
 
-    let node = InMemoryNode::new(
-        fork_details,
-        Some(observability),
-        InMemoryNodeConfig {
-            show_calls: opt.show_calls,
-            show_storage_logs: opt.show_storage_logs,
-            show_vm_details: opt.show_vm_details,
-            show_gas_details: opt.show_gas_details,
-            resolve_hashes: opt.resolve_hashes,
-            system_contracts_options,
+    let node_config = InMemoryNodeConfig {
+        show_calls: opt.show_calls,
+        show_storage_logs: opt.show_storage_logs,
+        show_vm_details: opt.show_vm_details,
+        show_gas_details: opt.show_gas_details,
+        show_precompiles: opt.show_precompiles,
+        show_outputs: opt.show_outputs,
+        resolve_hashes: opt.resolve_hashes,
+        system_contracts_options,
+        chain_id: opt
+            .chain_id
+            .or_else(|| genesis.as_ref().and_then(GenesisFile::chain_id)),
+        block_gas_limit: opt.block_gas_limit,
+        tx_gas_limit: opt.tx_gas_limit,
+        min_gas_price: opt.min_gas_price,
+        max_gas_price: opt.max_gas_price,
+        disable_block_gas_limit: opt.disable_block_gas_limit,
+        no_automine: opt.no_automine,
+        state_roots: opt.state_roots,
+        deterministic: opt.deterministic.is_some(),
+        start_timestamp: opt.init_timestamp.or(opt.deterministic),
+        start_block_number: opt.init_block_number,
+        fee_account: opt.fee_account,
+        import_url: opt.import_from.as_deref().map(|network| {
+            ForkDetails::<HttpForkSource>::fork_to_url_and_client(network)
+                .0
+                .to_string()
+        }),
+        execution_limits: ExecutionLimits {
+            timeout_ms: opt.vm_execution_timeout,
+            max_steps: opt.vm_max_steps,
         },
-    );
+        workers: opt.workers,
+    };
+    if let Command::SpawnPool(pool) = &opt.command {
+        spawn_pool(
+            &opt,
+            pool,
+            node_config,
+            cache_config,
+            &observability,
+            log_level_filter,
+        )
+        .await?;
+        observability.shutdown();
+        return Ok(());
+    }
+    if let Command::Bench(bench) = &opt.command {
+        let node =
+            InMemoryNode::<HttpForkSource>::new(None, Some(observability.clone()), node_config);
+        let report = node.run_bench(&BenchConfig {
+            transfers: bench.transfers,
+            deploys: bench.deploys,
+            accounts: bench.accounts,
+        })?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        observability.shutdown();
+        return Ok(());
+    }
+
+    let node = InMemoryNode::new(fork_details, Some(observability.clone()), node_config);
 
     if !transactions_to_replay.is_empty() {
         let _ = node.apply_txs(transactions_to_replay);
@@ -391,19 +917,350 @@ async fn main() -> anyhow::Result<()> {
         tracing::info!("Mnemonic: {}", &mnemonic_phrase.truecolor(128, 128, 128));
         tracing::info!("");
     }
+    node.set_address_labels(rich_account_labels())?;
 
-    let threads = build_json_http(
-        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), opt.port),
+    if !opt.import_key.is_empty() {
+        tracing::info!("Imported Accounts");
+        tracing::info!("=================");
+        for private_key in &opt.import_key {
+            let address = node.import_account(*private_key, true)?;
+            tracing::info!("{:#x} ({})", address, "1_000_000_000_000 ETH".cyan());
+        }
+        tracing::info!("");
+    }
+
+    if let Some(genesis) = genesis {
+        node.apply_genesis_file(genesis)?;
+    }
+
+    if let Some(deploy) = &opt.deploy {
+        tracing::info!("Deployed Contracts");
+        tracing::info!("==================");
+        for contract in node.predeploy(deploy)? {
+            tracing::info!("{}: {:#x}", contract.name, contract.address);
+        }
+        tracing::info!("");
+    }
+
+    if let Some(init) = &opt.init {
+        let hashes = node.run_init_script(init).await?;
+        tracing::info!("Executed {} init transaction(s)", hashes.len());
+        tracing::info!("");
+    }
+
+    let restored_checkpoint = match &opt.command {
+        Command::Restore(restore) => {
+            let index = node.restore_checkpoint(&opt.checkpoint_dir, restore.checkpoint)?;
+            tracing::info!(
+                "Restored checkpoint {} from {}",
+                index,
+                opt.checkpoint_dir.display()
+            );
+            tracing::info!("");
+            Some(index)
+        }
+        _ => None,
+    };
+    if opt.selftest {
+        let io = build_io_handler(
+            node.clone(),
+            LoggingMiddleware::new(log_level_filter, opt.log_rpc_requests),
+            RpcFilterMiddleware::new(opt.rpc_allow.clone(), opt.rpc_deny.clone())
+                .read_only(opt.read_only),
+            &opt.enable_namespaces,
+            vec![],
+        );
+        let report = run_conformance_suite(&io, &conformance_cases()?).await;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        observability.shutdown();
+        if !report.failures.is_empty() {
+            anyhow::bail!("{} conformance case(s) failed", report.failures.len());
+        }
+        return Ok(());
+    }
+
+    if let Some(interval) = opt.checkpoint_interval {
+        let checkpointer = match restored_checkpoint {
+            Some(index) => Checkpointer::resume(opt.checkpoint_dir.clone(), index, &node)?,
+            None => Checkpointer::create(opt.checkpoint_dir.clone(), checkpoint_fork)?,
+        };
+        tokio::spawn(checkpointer.run(node.clone(), interval));
+    }
+
+    if let (Some(path), Some(config)) = (opt.config.clone(), config) {
+        tokio::spawn(config_file::watch_config_file(path, node.clone(), config));
+    }
+
+    if let Command::Fork(ForkArgs {
+        fork_follow: Some(interval),
+        ..
+    }) = &opt.command
+    {
+        tokio::spawn(follow_fork(node.clone(), Duration::from_secs(*interval)));
+    }
+
+    let initialized = Arc::new(AtomicBool::new(false));
+    let public_addr = |port| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
+    let build_ws = |addr| {
+        build_json_ws(
+            addr,
+            log_level_filter,
+            opt.log_rpc_requests,
+            RpcFilterMiddleware::new(opt.rpc_allow.clone(), opt.rpc_deny.clone())
+                .read_only(opt.read_only),
+            opt.auth_token.clone(),
+            node.clone(),
+            opt.enable_namespaces.clone(),
+            vec![],
+            WsConfig {
+                max_connections: opt.ws_max_connections,
+                keepalive_interval: opt.ws_keepalive.map(Duration::from_secs),
+                idle_timeout: opt.ws_idle_timeout.map(Duration::from_secs),
+            },
+        )
+    };
+    // with the WebSocket upgrades, the HTTP server listens on the loopback behind the router of `--port`
+    let http_addr = if opt.no_ws_upgrade {
+        public_addr(opt.port)
+    } else {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)
+    };
+    let mut server = build_json_http(
+        http_addr,
         log_level_filter,
-        node,
+        opt.log_rpc_requests,
+        RpcFilterMiddleware::new(opt.rpc_allow.clone(), opt.rpc_deny.clone())
+            .read_only(opt.read_only),
+        opt.auth_token.clone(),
+        node.clone(),
+        opt.enable_namespaces.clone(),
+        vec![],
+        initialized.clone(),
+        opt.explorer,
+        opt.graphql,
     )
-    .await;
+    .await?;
+    // a single WebSocket server serves both `--ws-port` and the upgrades of `--port`, so that they share the
+    // `--ws-max-connections` budget
+    let ws_server = if opt.no_ws_upgrade && opt.ws_port.is_none() {
+        None
+    } else {
+        Some(build_ws(opt.ws_port.map_or(
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0),
+            public_addr,
+        ))?)
+    };
+    let router = match &ws_server {
+        Some(ws_server) if !opt.no_ws_upgrade => Some(
+            serve_multiplexed(
+                public_addr(opt.port),
+                MultiplexTargets {
+                    http: server.addr,
+                    ws: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), ws_server.addr.port()),
+                },
+            )
+            .await?,
+        ),
+        _ => None,
+    };
+    initialized.store(true, Ordering::SeqCst);
 
     tracing::info!("========================================");
     tracing::info!("  Node is ready at 127.0.0.1:{}", opt.port);
     tracing::info!("========================================");
+    if !opt.no_ws_upgrade {
+        tracing::info!(
+            "WebSocket connections are accepted at ws://127.0.0.1:{}",
+            opt.port
+        );
+    }
+    if let Some(ws_port) = opt.ws_port {
+        tracing::info!(
+            "WebSocket server is listening at ws://127.0.0.1:{}",
+            ws_port
+        );
+    }
+    if opt.explorer {
+        tracing::info!(
+            "Explorer is available at http://127.0.0.1:{}/explorer",
+            opt.port
+        );
+    }
 
-    future::select_all(vec![threads]).await.0.unwrap();
+    let signal = match future::select(&mut server.stopped, Box::pin(shutdown_signal())).await {
+        future::Either::Left(_) => None,
+        future::Either::Right((signal, _)) => Some(signal),
+    };
+    if let Some(signal) = signal {
+        tracing::info!("Received {}, shutting down", signal);
+        if let Some(router) = &router {
+            router.close();
+        }
+        server.shutdown().await;
+        if let Some(ws_server) = ws_server {
+            ws_server.close();
+        }
+
+        if let Some(path) = &opt.dump_state {
+            match node.dump_state_to_file(path) {
+                Ok(()) => tracing::info!("Dumped the state to {}", path.display()),
+                Err(err) => tracing::error!("Failed dumping the state: {:?}", err),
+            }
+        }
+    }
+    observability.shutdown();
 
     Ok(())
 }
+
+/// Advances the fork point of the node to the latest block of the forked network every `interval`.
+/// Returns the labels of the rich accounts in the output, numbered like when they are listed at startup.
+fn rich_account_labels() -> HashMap<H160, String> {
+    RICH_WALLETS
+        .iter()
+        .enumerate()
+        .map(|(index, wallet)| {
+            (
+                H160::from_str(wallet.0).unwrap(),
+                format!("RichAccount#{}", index),
+            )
+        })
+        .collect()
+}
+
+async fn follow_fork(node: InMemoryNode<HttpForkSource>, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        match node.advance_fork() {
+            Ok(Some(block_number)) => tracing::info!("Advanced the fork to block {}", block_number),
+            Ok(None) => {}
+            Err(err) => tracing::error!("Failed advancing the fork: {}", err),
+        }
+    }
+}
+
+/// Starts the instances of the `spawn-pool` command, prints their manifest and serves them until the node is shut
+/// down. The instances share the node and server options, but not the genesis, deployments and init transactions.
+async fn spawn_pool(
+    opt: &Cli,
+    args: &SpawnPoolArgs,
+    config: InMemoryNodeConfig,
+    cache_config: CacheConfig,
+    observability: &Observability,
+    log_level_filter: LevelFilter,
+) -> anyhow::Result<()> {
+    let max_fork_memory = opt.max_fork_memory.map(|megabytes| megabytes * 1024 * 1024);
+    let mut servers = vec![];
+    let mut instances = vec![];
+    for index in 0..args.instances {
+        let port = opt
+            .port
+            .checked_add(index)
+            .ok_or_else(|| anyhow::anyhow!("no port available for instance #{}", index))?;
+        let fork_details = match &args.fork {
+            Some(network) => Some(
+                ForkDetails::from_network(network, args.fork_at, cache_config.clone())
+                    .await
+                    .with_cache_max_size(opt.cache_max_size)
+                    .with_max_memory(max_fork_memory),
+            ),
+            None => None,
+        };
+        let node = InMemoryNode::new(fork_details, Some(observability.clone()), config.clone());
+        let addresses = LEGACY_RICH_WALLETS
+            .iter()
+            .map(|wallet| wallet.0)
+            .chain(RICH_WALLETS.iter().map(|wallet| wallet.0));
+        for address in addresses {
+            node.set_rich_account(H160::from_str(address).unwrap());
+        }
+        for private_key in &opt.import_key {
+            node.import_account(*private_key, true)?;
+        }
+        node.set_address_labels(rich_account_labels())?;
+
+        servers.push(
+            build_json_http(
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port),
+                log_level_filter,
+                opt.log_rpc_requests,
+                RpcFilterMiddleware::new(opt.rpc_allow.clone(), opt.rpc_deny.clone())
+                    .read_only(opt.read_only),
+                opt.auth_token.clone(),
+                node,
+                opt.enable_namespaces.clone(),
+                vec![],
+                Arc::new(AtomicBool::new(true)),
+                opt.explorer,
+                opt.graphql,
+            )
+            .await?,
+        );
+        instances.push(serde_json::json!({
+            "index": index,
+            "port": port,
+            "url": format!("http://127.0.0.1:{}", port),
+        }));
+    }
+
+    let manifest = serde_json::to_string_pretty(&serde_json::json!({ "instances": instances }))?;
+    if let Some(path) = &args.manifest {
+        std::fs::write(path, &manifest)?;
+    }
+    println!("{}", manifest);
+    tracing::info!("Started {} instances", servers.len());
+
+    let signal = shutdown_signal().await;
+    tracing::info!("Received {}, shutting down", signal);
+    for server in servers {
+        server.shutdown().await;
+    }
+    Ok(())
+}
+
+/// Returns the value of the `--<name>` argument (or of its environment variable), for the ones that have to be
+/// known before the rest of the arguments are parsed.
+fn early_arg(mut args: impl Iterator<Item = String>, name: &str) -> Option<String> {
+    let flag = format!("--{}", name);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if let Some(value) = arg.strip_prefix(&format!("{}=", flag)) {
+            return Some(value.to_string());
+        }
+    }
+    env::var(config_file::env_var_name(name)).ok()
+}
+
+/// Completes once SIGINT (Ctrl+C) or, on Unix, SIGTERM is received. Returns the name of the signal.
+async fn shutdown_signal() -> &'static str {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!("failed listening for SIGINT: {:?}", err);
+            future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(err) => {
+                tracing::error!("failed listening for SIGTERM: {:?}", err);
+                future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+
+    match future::select(Box::pin(ctrl_c), Box::pin(terminate)).await {
+        future::Either::Left(_) => "SIGINT",
+        future::Either::Right(_) => "SIGTERM",
+    }
+}
@@ -0,0 +1,674 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+/// Prefix of all the metric names.
+const PREFIX: &str = "era_test_node";
+/// Number of the most recent durations per RPC method the latency percentiles are computed from.
+const RECENT_DURATIONS: usize = 1000;
+/// Label of the RPC requests calling a method the node doesn't serve, so that arbitrary method names don't each
+/// create new series.
+const UNKNOWN_METHOD: &str = "unknown";
+/// Upper bounds (in seconds) of the duration histogram buckets.
+const DURATION_BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+lazy_static! {
+    /// Metrics of the running node, exposed in the Prometheus text format at the `/metrics` endpoint.
+    pub static ref METRICS: Metrics = Metrics::default();
+}
+
+/// Histogram of durations with the [DURATION_BUCKETS] buckets.
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+    /// Cumulative count of observations per bucket.
+    buckets: [u64; DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(DURATION_BUCKETS.iter()) {
+            if seconds <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+
+    fn encode(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for (bucket, bound) in self.buckets.iter().zip(DURATION_BUCKETS.iter()) {
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, bound, bucket
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}{}le=\"+Inf\"}} {}",
+            name, labels, separator, self.count
+        );
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels)
+        };
+        let _ = writeln!(out, "{}_sum{} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct RpcMethodMetrics {
+    errors: u64,
+    duration: Histogram,
+    recent_durations: VecDeque<Duration>,
+}
+
+/// Call statistics of a single RPC method, as returned by `era_getRpcStats`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcMethodStats {
+    pub calls: u64,
+    pub errors: u64,
+    /// Latency percentiles in milliseconds, over the last [RECENT_DURATIONS] calls.
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Lookup statistics of a single cache, as returned by `era_getCacheStats`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// The number of entries currently in the cache.
+    pub size: usize,
+    /// The estimated memory used by the entries of the cache, in bytes. Only reported for the caches of the fork
+    /// storage, i.e. the storage values, the factory deps and the compressed bytecodes.
+    pub memory_bytes: usize,
+    /// The number of entries evicted from memory by `--cache-max-size` or `--max-fork-memory`.
+    pub evictions: u64,
+    /// The number of entries read back from the disk cache after they were evicted from memory.
+    pub disk_reads: u64,
+}
+
+/// Counters and histograms describing the activity of the node.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Names of the RPC methods served by the node, the requests calling other methods are recorded as
+    /// [UNKNOWN_METHOD].
+    rpc_methods: Mutex<BTreeSet<String>>,
+    rpc_requests: Mutex<BTreeMap<String, RpcMethodMetrics>>,
+    vm_execution: Mutex<Histogram>,
+    transactions: AtomicU64,
+    gas_used: AtomicU64,
+    fork_requests: Mutex<BTreeMap<&'static str, u64>>,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    cache_lookups: Mutex<BTreeMap<&'static str, CacheStats>>,
+    cache_entries: Mutex<BTreeMap<&'static str, usize>>,
+    cache_memory: Mutex<BTreeMap<&'static str, usize>>,
+    fork_loaded_bytes: Mutex<BTreeMap<&'static str, u64>>,
+    fork_loaded_accounts: AtomicU64,
+    pending_transactions: AtomicU64,
+}
+
+impl Metrics {
+    /// Registers the names of the RPC methods served by the node, which the requests are recorded under.
+    pub fn register_rpc_methods<'a>(&self, methods: impl IntoIterator<Item = &'a String>) {
+        if let Ok(mut rpc_methods) = self.rpc_methods.lock() {
+            rpc_methods.extend(methods.into_iter().cloned());
+        }
+    }
+
+    /// Records a handled RPC request. The requests calling a method that wasn't registered with
+    /// [Metrics::register_rpc_methods] are recorded as [UNKNOWN_METHOD].
+    pub fn observe_rpc_request(&self, method: &str, duration: Duration, failed: bool) {
+        let known = self
+            .rpc_methods
+            .lock()
+            .map(|rpc_methods| rpc_methods.contains(method))
+            .unwrap_or_default();
+        let method = if known { method } else { UNKNOWN_METHOD };
+        if let Ok(mut rpc_requests) = self.rpc_requests.lock() {
+            let metrics = rpc_requests.entry(method.to_string()).or_default();
+            metrics.duration.observe(duration);
+            if metrics.recent_durations.len() == RECENT_DURATIONS {
+                metrics.recent_durations.pop_front();
+            }
+            metrics.recent_durations.push_back(duration);
+            if failed {
+                metrics.errors += 1;
+            }
+        }
+    }
+
+    /// Returns the call statistics of every RPC method that was called so far.
+    pub fn rpc_stats(&self) -> BTreeMap<String, RpcMethodStats> {
+        let rpc_requests = match self.rpc_requests.lock() {
+            Ok(rpc_requests) => rpc_requests,
+            Err(_) => return Default::default(),
+        };
+        rpc_requests
+            .iter()
+            .map(|(method, metrics)| {
+                let mut durations: Vec<f64> = metrics
+                    .recent_durations
+                    .iter()
+                    .map(|duration| duration.as_secs_f64() * 1000.0)
+                    .collect();
+                durations.sort_by(|a, b| a.total_cmp(b));
+                let stats = RpcMethodStats {
+                    calls: metrics.duration.count,
+                    errors: metrics.errors,
+                    p50_ms: percentile(&durations, 50),
+                    p90_ms: percentile(&durations, 90),
+                    p99_ms: percentile(&durations, 99),
+                    max_ms: durations.last().copied().unwrap_or_default(),
+                };
+                (method.clone(), stats)
+            })
+            .collect()
+    }
+
+    /// Records the time spent executing a transaction in the VM.
+    pub fn observe_vm_execution(&self, duration: Duration) {
+        if let Ok(mut vm_execution) = self.vm_execution.lock() {
+            vm_execution.observe(duration);
+        }
+    }
+
+    /// Returns the total time spent executing transactions in the VM.
+    pub fn vm_execution_time(&self) -> Duration {
+        self.vm_execution
+            .lock()
+            .map(|vm_execution| Duration::from_secs_f64(vm_execution.sum))
+            .unwrap_or_default()
+    }
+
+    /// Records a transaction committed to a block.
+    pub fn observe_transaction(&self, gas_used: u64) {
+        self.transactions.fetch_add(1, Ordering::Relaxed);
+        self.gas_used.fetch_add(gas_used, Ordering::Relaxed);
+    }
+
+    /// Records a request made to the forked network.
+    pub fn observe_fork_request(&self, method: &'static str) {
+        if let Ok(mut fork_requests) = self.fork_requests.lock() {
+            *fork_requests.entry(method).or_default() += 1;
+        }
+    }
+
+    /// Records a lookup in the fork cache named `cache`.
+    pub fn observe_cache_lookup(&self, cache: &'static str, hit: bool) {
+        let stats = self.cache_lookups.lock();
+        if hit {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+            if let Ok(mut stats) = stats {
+                stats.entry(cache).or_default().hits += 1;
+            }
+        } else {
+            self.cache_misses.fetch_add(1, Ordering::Relaxed);
+            if let Ok(mut stats) = stats {
+                stats.entry(cache).or_default().misses += 1;
+            }
+        }
+    }
+
+    /// Records the entries evicted from memory by the fork cache named `cache`.
+    pub fn observe_cache_evictions(&self, cache: &'static str, count: usize) {
+        if count == 0 {
+            return;
+        }
+        if let Ok(mut stats) = self.cache_lookups.lock() {
+            stats.entry(cache).or_default().evictions += count as u64;
+        }
+    }
+
+    /// Records an entry of the fork cache named `cache` read back from the disk after its eviction from memory.
+    pub fn observe_cache_disk_read(&self, cache: &'static str) {
+        if let Ok(mut stats) = self.cache_lookups.lock() {
+            stats.entry(cache).or_default().disk_reads += 1;
+        }
+    }
+
+    /// Records data of the kind `cache` lazily loaded from the forked network.
+    pub fn observe_fork_load(&self, cache: &'static str, bytes: usize) {
+        if let Ok(mut fork_loaded_bytes) = self.fork_loaded_bytes.lock() {
+            *fork_loaded_bytes.entry(cache).or_default() += bytes as u64;
+        }
+    }
+
+    /// Records an account whose storage started being lazily loaded from the forked network.
+    pub fn observe_fork_loaded_account(&self) {
+        self.fork_loaded_accounts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the lookup and eviction statistics of the fork cache named `cache`. The `size` and `memory_bytes`
+    /// are left empty.
+    pub fn cache_stats(&self, cache: &str) -> CacheStats {
+        self.cache_lookups
+            .lock()
+            .ok()
+            .and_then(|stats| stats.get(cache).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Sets the number of entries held in memory by the fork cache named `cache`.
+    pub fn set_cache_entries(&self, cache: &'static str, entries: usize) {
+        if let Ok(mut cache_entries) = self.cache_entries.lock() {
+            cache_entries.insert(cache, entries);
+        }
+    }
+
+    /// Sets the estimated memory used by the entries of the fork cache named `cache`, in bytes.
+    pub fn set_cache_memory(&self, cache: &'static str, bytes: usize) {
+        if let Ok(mut cache_memory) = self.cache_memory.lock() {
+            cache_memory.insert(cache, bytes);
+        }
+    }
+
+    /// Sets the number of transactions waiting to be mined.
+    pub fn set_pending_transactions(&self, count: usize) {
+        self.pending_transactions
+            .store(count as u64, Ordering::Relaxed);
+    }
+
+    /// Encodes all the metrics in the Prometheus text exposition format.
+    pub fn encode(&self) -> String {
+        let mut out = String::new();
+
+        if let Ok(rpc_requests) = self.rpc_requests.lock() {
+            header(
+                &mut out,
+                "rpc_requests_total",
+                "counter",
+                "Number of handled RPC requests",
+            );
+            for (method, metrics) in rpc_requests.iter() {
+                let _ = writeln!(
+                    out,
+                    "{}_rpc_requests_total{{method=\"{}\"}} {}",
+                    PREFIX,
+                    escape_label(method),
+                    metrics.duration.count
+                );
+            }
+            header(
+                &mut out,
+                "rpc_errors_total",
+                "counter",
+                "Number of RPC requests that resulted in an error",
+            );
+            for (method, metrics) in rpc_requests.iter() {
+                let _ = writeln!(
+                    out,
+                    "{}_rpc_errors_total{{method=\"{}\"}} {}",
+                    PREFIX,
+                    escape_label(method),
+                    metrics.errors
+                );
+            }
+            header(
+                &mut out,
+                "rpc_request_duration_seconds",
+                "histogram",
+                "Time spent handling RPC requests",
+            );
+            for (method, metrics) in rpc_requests.iter() {
+                metrics.duration.encode(
+                    &mut out,
+                    &format!("{}_rpc_request_duration_seconds", PREFIX),
+                    &format!("method=\"{}\"", escape_label(method)),
+                );
+            }
+        }
+
+        if let Ok(vm_execution) = self.vm_execution.lock() {
+            header(
+                &mut out,
+                "vm_execution_duration_seconds",
+                "histogram",
+                "Time spent executing transactions in the VM",
+            );
+            vm_execution.encode(
+                &mut out,
+                &format!("{}_vm_execution_duration_seconds", PREFIX),
+                "",
+            );
+        }
+
+        counter(
+            &mut out,
+            "transactions_total",
+            "Number of transactions committed to blocks",
+            self.transactions.load(Ordering::Relaxed),
+        );
+        counter(
+            &mut out,
+            "gas_used_total",
+            "Gas used by the transactions committed to blocks",
+            self.gas_used.load(Ordering::Relaxed),
+        );
+
+        if let Ok(fork_requests) = self.fork_requests.lock() {
+            header(
+                &mut out,
+                "fork_requests_total",
+                "counter",
+                "Number of requests made to the forked network",
+            );
+            for (method, count) in fork_requests.iter() {
+                let _ = writeln!(
+                    out,
+                    "{}_fork_requests_total{{method=\"{}\"}} {}",
+                    PREFIX,
+                    escape_label(method),
+                    count
+                );
+            }
+        }
+
+        counter(
+            &mut out,
+            "cache_hits_total",
+            "Number of fork cache lookups that found the data",
+            self.cache_hits.load(Ordering::Relaxed),
+        );
+        counter(
+            &mut out,
+            "cache_misses_total",
+            "Number of fork cache lookups that did not find the data",
+            self.cache_misses.load(Ordering::Relaxed),
+        );
+
+        if let Ok(cache_entries) = self.cache_entries.lock() {
+            header(
+                &mut out,
+                "cache_entries",
+                "gauge",
+                "Number of entries held in memory by the fork caches",
+            );
+            for (cache, entries) in cache_entries.iter() {
+                let _ = writeln!(
+                    out,
+                    "{}_cache_entries{{cache=\"{}\"}} {}",
+                    PREFIX, cache, entries
+                );
+            }
+        }
+        if let Ok(cache_memory) = self.cache_memory.lock() {
+            header(
+                &mut out,
+                "cache_memory_bytes",
+                "gauge",
+                "Estimated memory used by the entries of the fork caches",
+            );
+            for (cache, bytes) in cache_memory.iter() {
+                let _ = writeln!(
+                    out,
+                    "{}_cache_memory_bytes{{cache=\"{}\"}} {}",
+                    PREFIX, cache, bytes
+                );
+            }
+        }
+        if let Ok(cache_lookups) = self.cache_lookups.lock() {
+            header(
+                &mut out,
+                "cache_evictions_total",
+                "counter",
+                "Number of entries evicted from memory by the fork caches",
+            );
+            for (cache, stats) in cache_lookups.iter() {
+                let _ = writeln!(
+                    out,
+                    "{}_cache_evictions_total{{cache=\"{}\"}} {}",
+                    PREFIX, cache, stats.evictions
+                );
+            }
+            header(
+                &mut out,
+                "cache_disk_reads_total",
+                "counter",
+                "Number of evicted fork cache entries read back from the disk instead of the network",
+            );
+            for (cache, stats) in cache_lookups.iter() {
+                let _ = writeln!(
+                    out,
+                    "{}_cache_disk_reads_total{{cache=\"{}\"}} {}",
+                    PREFIX, cache, stats.disk_reads
+                );
+            }
+        }
+        if let Ok(fork_loaded_bytes) = self.fork_loaded_bytes.lock() {
+            header(
+                &mut out,
+                "fork_loaded_bytes_total",
+                "counter",
+                "Bytes of storage values and factory deps lazily loaded from the forked network",
+            );
+            for (cache, bytes) in fork_loaded_bytes.iter() {
+                let _ = writeln!(
+                    out,
+                    "{}_fork_loaded_bytes_total{{cache=\"{}\"}} {}",
+                    PREFIX, cache, bytes
+                );
+            }
+        }
+        counter(
+            &mut out,
+            "fork_loaded_accounts_total",
+            "Number of accounts whose storage was lazily loaded from the forked network",
+            self.fork_loaded_accounts.load(Ordering::Relaxed),
+        );
+
+        if let Some(bytes) = resident_memory_bytes() {
+            header(
+                &mut out,
+                "resident_memory_bytes",
+                "gauge",
+                "Resident memory size of the node process",
+            );
+            let _ = writeln!(out, "{}_resident_memory_bytes {}", PREFIX, bytes);
+        }
+
+        header(
+            &mut out,
+            "pending_transactions",
+            "gauge",
+            "Number of transactions waiting to be mined",
+        );
+        let _ = writeln!(
+            out,
+            "{}_pending_transactions {}",
+            PREFIX,
+            self.pending_transactions.load(Ordering::Relaxed)
+        );
+
+        out
+    }
+}
+
+/// Returns the resident memory size of the current process, if it can be read from `/proc` (Linux only).
+fn resident_memory_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kilobytes = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Returns the nearest-rank percentile of the sorted values.
+pub(crate) fn percentile(sorted: &[f64], percentile: usize) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (percentile * sorted.len() + 99) / 100;
+    sorted[rank.max(1) - 1]
+}
+
+/// Escapes a label value for the Prometheus text format, in which the backslashes, double quotes and line feeds
+/// must be escaped.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, metric_type: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {}_{} {}", PREFIX, name, help);
+    let _ = writeln!(out, "# TYPE {}_{} {}", PREFIX, name, metric_type);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    header(out, name, "counter", help);
+    let _ = writeln!(out, "{}_{} {}", PREFIX, name, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_metrics() {
+        let metrics = Metrics::default();
+        metrics.register_rpc_methods(&["eth_call".to_string()]);
+        metrics.observe_rpc_request("eth_call", Duration::from_millis(3), false);
+        metrics.observe_rpc_request("eth_call", Duration::from_millis(300), true);
+        metrics.observe_transaction(21_000);
+        metrics.observe_fork_request("get_storage_at");
+        metrics.observe_cache_lookup("transactions", true);
+        metrics.set_cache_entries("transactions", 3);
+        metrics.set_cache_memory("storage_values", 1024);
+        metrics.observe_cache_evictions("storage_values", 2);
+        metrics.observe_fork_load("factory_deps", 100);
+        metrics.set_pending_transactions(2);
+
+        let encoded = metrics.encode();
+
+        for line in [
+            "era_test_node_rpc_requests_total{method=\"eth_call\"} 2",
+            "era_test_node_rpc_errors_total{method=\"eth_call\"} 1",
+            "era_test_node_rpc_request_duration_seconds_bucket{method=\"eth_call\",le=\"0.005\"} 1",
+            "era_test_node_rpc_request_duration_seconds_bucket{method=\"eth_call\",le=\"+Inf\"} 2",
+            "era_test_node_rpc_request_duration_seconds_count{method=\"eth_call\"} 2",
+            "era_test_node_vm_execution_duration_seconds_count 0",
+            "era_test_node_gas_used_total 21000",
+            "era_test_node_fork_requests_total{method=\"get_storage_at\"} 1",
+            "era_test_node_cache_hits_total 1",
+            "era_test_node_cache_misses_total 0",
+            "era_test_node_cache_entries{cache=\"transactions\"} 3",
+            "era_test_node_cache_memory_bytes{cache=\"storage_values\"} 1024",
+            "era_test_node_cache_evictions_total{cache=\"storage_values\"} 2",
+            "era_test_node_fork_loaded_bytes_total{cache=\"factory_deps\"} 100",
+            "era_test_node_fork_loaded_accounts_total 0",
+            "era_test_node_pending_transactions 2",
+        ]
+        .iter()
+        {
+            assert!(
+                encoded.lines().any(|encoded_line| encoded_line == *line),
+                "missing `{}` in:\n{}",
+                line,
+                encoded
+            );
+        }
+    }
+
+    #[test]
+    fn test_rpc_stats() {
+        let metrics = Metrics::default();
+        metrics.register_rpc_methods(&["eth_call".to_string()]);
+        for millis in 1..=100 {
+            metrics.observe_rpc_request(
+                "eth_call",
+                Duration::from_millis(millis),
+                millis % 10 == 0,
+            );
+        }
+
+        let stats = metrics.rpc_stats();
+
+        assert_eq!(
+            Some(&RpcMethodStats {
+                calls: 100,
+                errors: 10,
+                p50_ms: 50.0,
+                p90_ms: 90.0,
+                p99_ms: 99.0,
+                max_ms: 100.0,
+            }),
+            stats.get("eth_call")
+        );
+        assert_eq!(None, stats.get("eth_chainId"));
+    }
+
+    #[test]
+    fn test_unknown_rpc_methods_are_grouped_and_labels_escaped() {
+        let metrics = Metrics::default();
+        metrics.register_rpc_methods(&["eth_call".to_string()]);
+        metrics.observe_rpc_request("eth_call", Duration::from_millis(1), false);
+        metrics.observe_rpc_request("foo_1", Duration::from_millis(1), true);
+        metrics.observe_rpc_request("foo\"}\n", Duration::from_millis(1), true);
+
+        let stats = metrics.rpc_stats();
+        assert_eq!(
+            vec!["eth_call", "unknown"],
+            stats.keys().map(String::as_str).collect::<Vec<_>>()
+        );
+        assert_eq!(2, stats["unknown"].calls);
+
+        assert_eq!(r#"a\\b\"c\nd"#, escape_label("a\\b\"c\nd"));
+    }
+
+    #[test]
+    fn test_cache_stats() {
+        let metrics = Metrics::default();
+        metrics.observe_cache_lookup("transactions", true);
+        metrics.observe_cache_lookup("transactions", false);
+        metrics.observe_cache_lookup("transactions", true);
+        metrics.observe_cache_lookup("blocks_full", false);
+
+        assert_eq!(
+            CacheStats {
+                hits: 2,
+                misses: 1,
+                ..Default::default()
+            },
+            metrics.cache_stats("transactions")
+        );
+        assert_eq!(
+            CacheStats {
+                hits: 0,
+                misses: 1,
+                ..Default::default()
+            },
+            metrics.cache_stats("blocks_full")
+        );
+        assert_eq!(CacheStats::default(), metrics.cache_stats("factory_deps"));
+    }
+}
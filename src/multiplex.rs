@@ -0,0 +1,180 @@
+//! Serves the JSON-RPC HTTP and WebSocket servers on the same port, since many clients only take a single URL. Each
+//! connection is routed by the head of its first request: the WebSocket upgrades go to the WebSocket server and the
+//! other requests to the HTTP server.
+use std::{net::SocketAddr, time::Duration};
+
+use anyhow::Context;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+/// Maximum size of the head of the first request of a connection, the rest is routed without being inspected.
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+/// Time a new connection has to send the head of its first request.
+const HEAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Addresses of the servers the connections are routed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultiplexTargets {
+    pub http: SocketAddr,
+    pub ws: SocketAddr,
+}
+
+/// Handle of the router started by [serve_multiplexed].
+#[derive(Debug)]
+pub struct RouterHandle {
+    task: JoinHandle<()>,
+    /// The address the router listens at, e.g. to find the port it was bound to if started at port `0`.
+    pub addr: SocketAddr,
+}
+
+impl RouterHandle {
+    /// Stops accepting connections.
+    pub fn close(&self) {
+        self.task.abort();
+    }
+}
+
+/// Listens at `addr` and routes the accepted connections to the `targets`.
+///
+/// # Returns
+/// A [RouterHandle] to stop listening.
+pub async fn serve_multiplexed(
+    addr: SocketAddr,
+    targets: MultiplexTargets,
+) -> anyhow::Result<RouterHandle> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed binding {}", addr))?;
+    let addr = listener.local_addr()?;
+
+    let task = tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(async move {
+                        if let Err(err) = route_connection(stream, targets).await {
+                            tracing::debug!("failed routing connection: {:?}", err);
+                        }
+                    });
+                }
+                Err(err) => tracing::warn!("failed accepting connection: {:?}", err),
+            }
+        }
+    });
+    Ok(RouterHandle { task, addr })
+}
+
+/// Forwards the connection to the server handling its first request, until either side closes it.
+async fn route_connection(mut client: TcpStream, targets: MultiplexTargets) -> anyhow::Result<()> {
+    let head = tokio::time::timeout(HEAD_TIMEOUT, read_head(&mut client))
+        .await
+        .context("timed out reading the request")??;
+    let target = if is_websocket_upgrade(&head) {
+        targets.ws
+    } else {
+        targets.http
+    };
+
+    let mut server = TcpStream::connect(target)
+        .await
+        .with_context(|| format!("failed connecting to {}", target))?;
+    server.write_all(&head).await?;
+    tokio::io::copy_bidirectional(&mut client, &mut server).await?;
+    Ok(())
+}
+
+/// Reads until the end of the head of the first request, or [MAX_HEAD_SIZE] bytes. The bytes read past the head,
+/// i.e. the start of the body, are returned along with it.
+async fn read_head(stream: &mut TcpStream) -> std::io::Result<Vec<u8>> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 4096];
+    while head.len() < MAX_HEAD_SIZE && !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    Ok(head)
+}
+
+/// Returns whether the head of an HTTP request asks to upgrade the connection to WebSocket, i.e. has an `Upgrade`
+/// header listing `websocket`.
+pub fn is_websocket_upgrade(head: &[u8]) -> bool {
+    String::from_utf8_lossy(head)
+        .split("\r\n")
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .any(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("upgrade")
+                && value
+                    .split(',')
+                    .any(|protocol| protocol.trim().eq_ignore_ascii_case("websocket"))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_websocket_upgrade() {
+        assert!(is_websocket_upgrade(
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\r\n"
+        ));
+        assert!(is_websocket_upgrade(
+            b"GET / HTTP/1.1\r\nupgrade: h2c, WebSocket\r\n\r\n"
+        ));
+        assert!(!is_websocket_upgrade(
+            b"POST / HTTP/1.1\r\nContent-Type: application/json\r\n\r\n{\"upgrade\":\"websocket\"}"
+        ));
+        assert!(!is_websocket_upgrade(
+            b"GET /upgrade: websocket HTTP/1.1\r\n\r\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_serve_multiplexed_routes_connections() {
+        async fn backend(name: &'static str) -> SocketAddr {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move {
+                loop {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    let _ = read_head(&mut stream).await;
+                    let _ = stream.write_all(name.as_bytes()).await;
+                }
+            });
+            addr
+        }
+        let targets = MultiplexTargets {
+            http: backend("http").await,
+            ws: backend("ws").await,
+        };
+        let server = serve_multiplexed("127.0.0.1:0".parse().unwrap(), targets)
+            .await
+            .expect("failed binding");
+        let addr = server.addr;
+
+        let request = |head: &'static str| async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(head.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+        assert_eq!(
+            "ws",
+            request("GET / HTTP/1.1\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\r\n").await
+        );
+        assert_eq!(
+            "http",
+            request("POST / HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}").await
+        );
+        server.close();
+    }
+}
@@ -0,0 +1,64 @@
+use jsonrpc_derive::rpc;
+use zksync_basic_types::U64;
+
+use super::RpcResult;
+use crate::node::NodeInfo;
+
+#[rpc]
+pub trait AnvilNamespaceT {
+    /// Removes the latest blocks from the chain, restoring the state of the new latest block.
+    /// Transactions included in the removed blocks are dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - (Optional) The number of blocks to roll back. Defaults to 1
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "anvil_rollback")]
+    fn rollback(&self, depth: Option<U64>) -> RpcResult<bool>;
+
+    /// Returns the details of the node: the current block, the chain environment, the mining
+    /// configuration and the fork configuration.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [NodeInfo].
+    #[rpc(name = "anvil_nodeInfo")]
+    fn node_info(&self) -> RpcResult<NodeInfo>;
+
+    /// Replaces the URL of the forked network, keeping the fork block and the local state. The new URL
+    /// must serve the forked block.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the new provider of the forked network
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "anvil_setRpcUrl")]
+    fn set_rpc_url(&self, url: String) -> RpcResult<bool>;
+
+    /// Sets the number of seconds between the timestamps of the mined blocks, instead of 1 second.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The interval, which must be greater than 0
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "anvil_setBlockTimestampInterval")]
+    fn set_block_timestamp_interval(&self, seconds: u64) -> RpcResult<bool>;
+
+    /// Removes the interval set with `anvil_setBlockTimestampInterval`, so that every block is again mined
+    /// 1 second after the previous one.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with `true` if an interval was set, otherwise `false`.
+    #[rpc(name = "anvil_removeBlockTimestampInterval")]
+    fn remove_block_timestamp_interval(&self) -> RpcResult<bool>;
+}
@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
+use crate::chaos::ChaosConfig;
 use crate::namespaces::Result;
 use crate::observability::LogLevel;
 use jsonrpc_derive::rpc;
+use zksync_basic_types::Address;
 
 #[rpc]
 pub trait ConfigurationApiNamespaceT {
@@ -68,6 +72,26 @@ pub trait ConfigurationApiNamespaceT {
     #[rpc(name = "config_setResolveHashes", returns = "bool")]
     fn config_set_resolve_hashes(&self, value: bool) -> Result<bool>;
 
+    /// Set show_precompiles for the InMemoryNodeInner
+    ///
+    /// # Parameters
+    /// - `value`: A bool to update show_precompiles to
+    ///
+    /// # Returns
+    /// The updated `show_precompiles` value for the InMemoryNodeInner.
+    #[rpc(name = "config_setShowPrecompiles", returns = "bool")]
+    fn config_set_show_precompiles(&self, value: bool) -> Result<bool>;
+
+    /// Set show_outputs for the InMemoryNodeInner
+    ///
+    /// # Parameters
+    /// - `value`: A bool to update show_outputs to
+    ///
+    /// # Returns
+    /// The updated `show_outputs` value for the InMemoryNodeInner.
+    #[rpc(name = "config_setShowOutputs", returns = "bool")]
+    fn config_set_show_outputs(&self, value: bool) -> Result<bool>;
+
     /// Set the logging for the InMemoryNodeInner
     ///
     /// # Parameters
@@ -90,4 +114,138 @@ pub trait ConfigurationApiNamespaceT {
     /// `true` if the operation succeeded, `false` otherwise.
     #[rpc(name = "config_setLogging", returns = "bool")]
     fn config_set_logging(&self, directive: String) -> Result<bool>;
+
+    /// Set the gas limit cap for a single transaction
+    ///
+    /// # Parameters
+    /// - `value`: The maximum gas limit of a transaction, or `null` to remove the cap
+    ///
+    /// # Returns
+    /// The updated `tx_gas_limit` value for the InMemoryNodeInner.
+    #[rpc(name = "config_setTxGasLimit", returns = "Option<u64>")]
+    fn config_set_tx_gas_limit(&self, value: Option<u64>) -> Result<Option<u64>>;
+
+    /// Set disable_block_gas_limit for the InMemoryNodeInner
+    ///
+    /// # Parameters
+    /// - `value`: If true, transactions exceeding the block gas limit are not rejected
+    ///
+    /// # Returns
+    /// The updated `disable_block_gas_limit` value for the InMemoryNodeInner.
+    #[rpc(name = "config_setDisableBlockGasLimit", returns = "bool")]
+    fn config_set_disable_block_gas_limit(&self, value: bool) -> Result<bool>;
+
+    /// Set skip_nonce_check for the InMemoryNodeInner
+    ///
+    /// # Parameters
+    /// - `value`: If true, the sender nonce is not validated, so transactions with any nonce are executed
+    ///
+    /// # Returns
+    /// The updated `skip_nonce_check` value for the InMemoryNodeInner.
+    #[rpc(name = "config_setSkipNonceCheck", returns = "bool")]
+    fn config_set_skip_nonce_check(&self, value: bool) -> Result<bool>;
+
+    /// Set skip_balance_check for the InMemoryNodeInner
+    ///
+    /// # Parameters
+    /// - `value`: If true, the sender balance is not validated, so transactions from unfunded accounts are executed
+    ///
+    /// # Returns
+    /// The updated `skip_balance_check` value for the InMemoryNodeInner.
+    #[rpc(name = "config_setSkipBalanceCheck", returns = "bool")]
+    fn config_set_skip_balance_check(&self, value: bool) -> Result<bool>;
+
+    /// Set the factor by which the gas limit found by `eth_estimateGas` is scaled
+    ///
+    /// # Parameters
+    /// - `value`: The scale factor, must be at least `1.0`
+    ///
+    /// # Returns
+    /// The updated/current `estimate_gas_scale_factor` value for the InMemoryNodeInner.
+    #[rpc(name = "config_setEstimateGasScaleFactor", returns = "f32")]
+    fn config_set_estimate_gas_scale_factor(&self, value: f32) -> Result<f32>;
+
+    /// Set the amount of gas `eth_estimateGas` is allowed to overestimate
+    ///
+    /// # Parameters
+    /// - `value`: The acceptable overestimation, in gas
+    ///
+    /// # Returns
+    /// The updated `estimate_gas_acceptable_overestimation` value for the InMemoryNodeInner.
+    #[rpc(
+        name = "config_setEstimateGasAcceptableOverestimation",
+        returns = "u32"
+    )]
+    fn config_set_estimate_gas_acceptable_overestimation(&self, value: u32) -> Result<u32>;
+
+    /// Set the maximum number of binary search iterations of `eth_estimateGas`
+    ///
+    /// # Parameters
+    /// - `value`: The maximum number of iterations, or `null` to remove the cap
+    ///
+    /// # Returns
+    /// The updated `estimate_gas_max_iterations` value for the InMemoryNodeInner.
+    #[rpc(name = "config_setEstimateGasMaxIterations", returns = "Option<u32>")]
+    fn config_set_estimate_gas_max_iterations(&self, value: Option<u32>) -> Result<Option<u32>>;
+
+    /// Set the number of mined blocks after which the open L1 batch is sealed
+    ///
+    /// # Parameters
+    /// - `value`: The number of blocks per batch, `0` to only seal batches with `era_sealL1Batch`,
+    ///   or `null` to seal every mined block in its own batch
+    ///
+    /// # Returns
+    /// The updated L1 batch seal interval.
+    #[rpc(name = "config_setL1BatchSealInterval", returns = "Option<u64>")]
+    fn config_set_l1_batch_seal_interval(&self, value: Option<u64>) -> Result<Option<u64>>;
+
+    /// Set the maximum wall-clock time of a single VM execution, after which calls and transactions are aborted
+    ///
+    /// # Parameters
+    /// - `value`: The timeout in milliseconds, or `null` to remove the limit
+    ///
+    /// # Returns
+    /// The updated VM execution timeout.
+    #[rpc(name = "config_setVmExecutionTimeout", returns = "Option<u64>")]
+    fn config_set_vm_execution_timeout(&self, value: Option<u64>) -> Result<Option<u64>>;
+
+    /// Set the maximum number of VM steps of a single execution, after which calls and transactions are aborted
+    ///
+    /// # Parameters
+    /// - `value`: The maximum number of steps, or `null` to remove the limit
+    ///
+    /// # Returns
+    /// The updated maximum number of VM steps.
+    #[rpc(name = "config_setVmMaxSteps", returns = "Option<u64>")]
+    fn config_set_vm_max_steps(&self, value: Option<u64>) -> Result<Option<u64>>;
+
+    /// Set the maximum depth of the call traces printed to the console, the full traces stay available with `debug_*`
+    ///
+    /// # Parameters
+    /// - `value`: The maximum depth, or `null` to print the traces in full
+    ///
+    /// # Returns
+    /// The updated maximum depth of the printed call traces.
+    #[rpc(name = "config_setShowCallsMaxDepth", returns = "Option<usize>")]
+    fn config_set_show_calls_max_depth(&self, value: Option<usize>) -> Result<Option<usize>>;
+
+    /// Set the latency and the transient errors injected into the responses of the selected RPC methods
+    ///
+    /// # Parameters
+    /// - `config`: The faults to inject, or `null` to disable the chaos mode
+    ///
+    /// # Returns
+    /// `true` if the operation succeeded, `false` otherwise.
+    #[rpc(name = "config_setChaos", returns = "bool")]
+    fn config_set_chaos(&self, config: Option<ChaosConfig>) -> Result<bool>;
+
+    /// Label addresses, so that they are displayed by their names in the call traces, events and storage logs
+    ///
+    /// # Parameters
+    /// - `labels`: The names keyed by address. An empty name removes the label of the address
+    ///
+    /// # Returns
+    /// `true` if the operation succeeded, `false` otherwise.
+    #[rpc(name = "config_setAddressLabels", returns = "bool")]
+    fn config_set_address_labels(&self, labels: HashMap<Address, String>) -> Result<bool>;
 }
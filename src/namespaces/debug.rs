@@ -3,9 +3,11 @@ use jsonrpc_derive::rpc;
 use zksync_types::{
     api::{BlockId, BlockNumber, DebugCall, ResultDebugCall, TracerConfig},
     transaction_request::CallRequest,
-    H256,
+    Address, H256,
 };
 
+use crate::node::StorageRangeResult;
+
 #[rpc]
 pub trait DebugNamespaceT {
     #[rpc(name = "debug_traceBlockByNumber")]
@@ -36,4 +38,28 @@ pub trait DebugNamespaceT {
         tx_hash: H256,
         options: Option<TracerConfig>,
     ) -> BoxFuture<Result<Option<DebugCall>>>;
+
+    /// Returns the non-empty storage slots of a contract at the given block, ordered by the keccak256 hash
+    /// of the slot, starting from `start_key`.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_hash` - The hash of the block
+    /// * `tx_index` - The index of the transaction within the block, ignored as the storage is read at the end of the block
+    /// * `address` - The address of the contract
+    /// * `start_key` - The hash of the slot to start from
+    /// * `max_result` - The maximum number of slots to return
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the slots and the hash of the slot to continue from, if any.
+    #[rpc(name = "debug_storageRangeAt")]
+    fn storage_range_at(
+        &self,
+        block_hash: H256,
+        tx_index: usize,
+        address: Address,
+        start_key: H256,
+        max_result: usize,
+    ) -> BoxFuture<Result<StorageRangeResult>>;
 }
@@ -0,0 +1,396 @@
+use bigdecimal::BigDecimal;
+use jsonrpc_derive::rpc;
+use std::collections::BTreeMap;
+
+use zksync_basic_types::{Address, H256, U256, U64};
+use zksync_types::{api::BlockNumber, transaction_request::CallRequest, Bytes};
+
+use super::RpcResult;
+use crate::{
+    fork::ForkVerification,
+    metrics::{CacheStats, RpcMethodStats},
+    node::{
+        BundleCallResult, BytecodeValidation, DecodedTransaction, EraNodeInfo, EstimateGasTrace,
+        ExecutionCoverage, FactoryDeps, FactoryDepsTarget, GasReport, HistoryFormat,
+        RejectedTransaction, SenderRecovery, StateDiff, TxFilterRule, VmStats,
+    },
+};
+
+#[rpc]
+pub trait EraNamespaceT {
+    /// Sets the ERC20 token balance of an account by writing directly to the token's balances mapping.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The address of the ERC20 token
+    /// * `account` - The account whose balance will be set
+    /// * `amount` - The new token balance
+    /// * `slot` - (Optional) The storage slot of the balances mapping. If omitted, it is detected automatically
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the storage slot of the balances mapping.
+    #[rpc(name = "era_setErc20Balance")]
+    fn set_erc20_balance(
+        &self,
+        token: Address,
+        account: Address,
+        amount: U256,
+        slot: Option<U256>,
+    ) -> RpcResult<U256>;
+
+    /// Sets the price of a token in USD reported by `zks_getTokenPrice`, so that tests depending on token prices
+    /// can control them deterministically.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The address of the token, the zero address for ETH
+    /// * `price_usd` - The price of the token in USD, e.g. `"1.0001"`
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "era_setTokenPrice")]
+    fn set_token_price(&self, token: Address, price_usd: BigDecimal) -> RpcResult<bool>;
+
+    /// Simulates a chain reorganization: removes the latest blocks and applies the given raw transactions
+    /// instead, producing blocks with new hashes at the same heights.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The number of blocks to roll back
+    /// * `transactions` - (Optional) Raw signed transactions to apply after the rollback, in order
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the hashes of the applied transactions.
+    #[rpc(name = "era_reorg")]
+    fn reorg(&self, depth: U64, transactions: Option<Vec<Bytes>>) -> RpcResult<Vec<H256>>;
+
+    /// Returns the call count, error count and latency percentiles of every RPC method called so far.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the statistics keyed by the method name.
+    #[rpc(name = "era_getRpcStats")]
+    fn get_rpc_stats(&self) -> RpcResult<BTreeMap<String, RpcMethodStats>>;
+
+    /// Runs `eth_estimateGas` for the request and returns every attempt of its binary search (the gas
+    /// limit tried, whether it succeeded and the revert reason), followed by the final check with the scaled gas limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The transaction request to estimate the gas for
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [EstimateGasTrace]. A failed estimation is reported
+    /// in its `error` field rather than as an RPC error.
+    #[rpc(name = "era_traceEstimateGas")]
+    fn trace_estimate_gas(&self, request: CallRequest) -> RpcResult<EstimateGasTrace>;
+
+    /// Decodes a signed raw transaction of any supported type (legacy, EIP-2930, EIP-1559 or EIP-712)
+    /// without submitting it.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_bytes` - The signed raw transaction
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the transaction hash and the decoded fields, including the
+    /// sender recovered from the signature. Transactions that can't be decoded result in an error describing why.
+    #[rpc(name = "era_decodeRawTransaction")]
+    fn decode_raw_transaction(&self, tx_bytes: Bytes) -> RpcResult<DecodedTransaction>;
+
+    /// Simulates the calls in order on top of the latest block, each call seeing the state changes of the
+    /// previous ones. The state of the node is not modified.
+    ///
+    /// # Arguments
+    ///
+    /// * `requests` - The calls to simulate, in order
+    /// * `block` - (Optional) The block to simulate on top of, only `latest` is supported
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the result and the call trace of every call. A failing call
+    /// does not stop the simulation of the following ones.
+    #[rpc(name = "era_simulateBundle")]
+    fn simulate_bundle(
+        &self,
+        requests: Vec<CallRequest>,
+        block: Option<BlockNumber>,
+    ) -> RpcResult<Vec<BundleCallResult>>;
+
+    /// Seals the open L1 batch, so that the next mined block starts a new batch. Batches span multiple
+    /// blocks only when `config_setL1BatchSealInterval` is set, otherwise every block is sealed in its own batch.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the number of the last sealed batch.
+    #[rpc(name = "era_sealL1Batch")]
+    fn seal_l1_batch(&self) -> RpcResult<U64>;
+
+    /// Returns the hits, misses and number of entries of every cache of the data read from the forked network.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the statistics keyed by the cache name.
+    #[rpc(name = "era_getCacheStats")]
+    fn get_cache_stats(&self) -> RpcResult<BTreeMap<String, CacheStats>>;
+
+    /// Removes the entries of a cache of the data read from the forked network, so that they are fetched again.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - (Optional) The name of the cache, as reported by `era_getCacheStats`. If omitted, all the caches are cleared
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with `true` if the cache was cleared.
+    #[rpc(name = "era_clearCache")]
+    fn clear_cache(&self, kind: Option<String>) -> RpcResult<bool>;
+
+    /// Imports a contract deployed on a live network: installs its bytecode and the values of the given storage
+    /// slots locally, leaving the rest of the state untouched. The network is the one set with `--import-from`,
+    /// or the forked network.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the contract, which is also its address locally
+    /// * `block` - (Optional) The block of the live network to import the contract at. Defaults to `latest`
+    /// * `slots` - (Optional) The storage slots to import, at most 1024
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with `true` if the contract was imported.
+    #[rpc(name = "era_importContract")]
+    fn import_contract(
+        &self,
+        address: Address,
+        block: Option<BlockNumber>,
+        slots: Option<Vec<U256>>,
+    ) -> RpcResult<bool>;
+
+    /// Compares the states of two snapshots taken with `evm_snapshot`, without reverting either of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `from_id` - The id of the first snapshot
+    /// * `to_id` - The id of the second snapshot
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the balances, nonces, code hashes and storage slots of the accounts
+    /// that differ, and the bytecodes only known in the second snapshot.
+    #[rpc(name = "era_diffSnapshots")]
+    fn diff_snapshots(&self, from_id: U64, to_id: U64) -> RpcResult<StateDiff>;
+
+    /// Begins an isolation context. The changes made until the matching `era_endContext` are undone, as with
+    /// `evm_snapshot` and `evm_revert`, but only the storage slots written in the context are copied.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the number of the open contexts.
+    #[rpc(name = "era_beginContext")]
+    fn begin_context(&self) -> RpcResult<U64>;
+
+    /// Ends the innermost isolation context, restoring the node to the state it had when the context began.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with `false` if no context was open.
+    #[rpc(name = "era_endContext")]
+    fn end_context(&self) -> RpcResult<bool>;
+
+    /// Forks the network at another block, usually an earlier one, without restarting the node. The local blocks,
+    /// transactions, state and snapshots are discarded. Only available when forking.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The number of the block of the forked network to fork at
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with `true` once the node forks the block.
+    #[rpc(name = "era_warpToBlock")]
+    fn warp_to_block(&self, number: U64) -> RpcResult<bool>;
+
+    /// Returns the record of a transaction the node rejected, e.g. because of a wrong nonce, an insufficient balance
+    /// or a failing paymaster, so that test frameworks can assert on the cause. Only the latest 1024 rejections are
+    /// kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The hash of the transaction
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [RejectedTransaction], or `None` if it was not rejected.
+    #[rpc(name = "era_getRejectedTransaction")]
+    fn get_rejected_transaction(&self, hash: H256) -> RpcResult<Option<RejectedTransaction>>;
+
+    /// Returns the statistics of the VM execution of a transaction, to track the zkEVM-specific costs beyond gas:
+    /// the cycles used, the decommitted contracts, the storage reads and writes, and the published pubdata.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The hash of the transaction
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [VmStats], or `None` if the transaction is not known.
+    #[rpc(name = "era_getVmStats")]
+    fn get_vm_stats(&self, hash: H256) -> RpcResult<Option<VmStats>>;
+
+    /// Returns the instructions of the contracts executed by a transaction, with their program counter and opcode,
+    /// so that external tools can build zkEVM-aware coverage reports. The system contracts are left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - The hash of the transaction
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [ExecutionCoverage], or `None` if the transaction is not among
+    /// the latest ones executed.
+    #[rpc(name = "era_getExecutionCoverage")]
+    fn get_execution_coverage(&self, hash: H256) -> RpcResult<Option<ExecutionCoverage>>;
+
+    /// Replaces the rules of the transaction filter, which rejects or delays the submitted transactions matching
+    /// them, to simulate an unfriendly operator. The first matching rule applies.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - The rules, each matching the transactions by `from`, `to` and calldata `selector`. An empty list
+    ///   disables the filter
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with `true` if the rules were set.
+    #[rpc(name = "era_setTxFilter")]
+    fn set_tx_filter(&self, rules: Vec<TxFilterRule>) -> RpcResult<bool>;
+
+    /// Checks a sample of the storage slots and bytecodes cached from the forked network against the network at
+    /// the fork block, to detect stale or corrupted caches. Used by the `verify-fork` command.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_size` - (Optional) The number of the most recently used storage slots and bytecodes checked. Defaults to 100
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [ForkVerification] listing the cached values that differ.
+    #[rpc(name = "era_verifyFork")]
+    fn verify_fork(&self, sample_size: Option<usize>) -> RpcResult<ForkVerification>;
+
+    /// Exports all the transactions executed in the session, in the order of their execution, so that they can be
+    /// replayed later, e.g. as the fixture of an automated test.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - (Optional) `json` for the list of the transactions, or `foundry` for a Foundry script
+    ///   broadcasting them. Defaults to `json`
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the contents of the exported file.
+    #[rpc(name = "era_exportHistory")]
+    fn export_history(&self, format: Option<HistoryFormat>) -> RpcResult<String>;
+
+    /// Recovers the signer of a raw transaction, along with the chain id it is signed for and the EIP-712 hashes it
+    /// is signed over, to diagnose the transactions rejected for an invalid signature or a wrong chain id.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_bytes` - The signed raw transaction, as sent with `eth_sendRawTransaction`
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [SenderRecovery], including why the node rejects the transaction.
+    #[rpc(name = "era_recoverSender")]
+    fn recover_sender(&self, tx_bytes: Bytes) -> RpcResult<SenderRecovery>;
+
+    /// Checks a bytecode against the length rules of the deployments, so that tooling can catch the invalid ones
+    /// before sending them.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytecode` - The EraVM bytecode
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [BytecodeValidation], including whether padding fixes the bytecode.
+    #[rpc(name = "era_validateBytecode")]
+    fn validate_bytecode(&self, bytecode: Bytes) -> RpcResult<BytecodeValidation>;
+
+    /// Starts a gas report of the transactions of the next blocks, aggregating the gas used by the calls of each
+    /// function of the contracts until `era_finishGasReport`. A report in progress is replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The label of the report, e.g. the commit being tested
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with `true` if the report was started.
+    #[rpc(name = "era_startGasReport")]
+    fn start_gas_report(&self, label: String) -> RpcResult<bool>;
+
+    /// Finishes the gas report started with `era_startGasReport`, to track gas regressions, e.g. in CI, against a
+    /// saved report.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - (Optional) A report previously returned by `era_finishGasReport`, to compare the report against
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [GasReport], including the functions whose average gas changed
+    /// since the `previous` report.
+    #[rpc(name = "era_finishGasReport")]
+    fn finish_gas_report(&self, previous: Option<GasReport>) -> RpcResult<GasReport>;
+
+    /// Returns the factory dependencies published by a transaction, or by the deployment of a contract, to debug the
+    /// deployments failing as a bytecode is not known to the node.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The hash of the transaction, or the address of the contract
+    /// * `include_bytecodes` - (Optional) Whether to return the bytecodes along with their hashes. Defaults to `false`
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [FactoryDeps], or `None` if the transaction is not known or the
+    /// address has no code.
+    #[rpc(name = "era_getFactoryDeps")]
+    fn get_factory_deps(
+        &self,
+        target: FactoryDepsTarget,
+        include_bytecodes: Option<bool>,
+    ) -> RpcResult<Option<FactoryDeps>>;
+
+    /// Returns the version and the instance of the node, so that the test orchestration can detect a mismatched or
+    /// restarted node early.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [EraNodeInfo]: the version and git commit of the build, the
+    /// instance id, the start time and uptime, the forked network and the enabled features.
+    #[rpc(name = "era_getNodeInfo")]
+    fn get_node_info(&self) -> RpcResult<EraNodeInfo>;
+
+    /// Imports a private key as a local signer, whose transactions `eth_sendTransaction` signs and whose messages
+    /// `eth_sign` signs, so that existing development keys can be used instead of the generated ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `private_key` - The private key of the account
+    /// * `fund` - (Optional) Whether to fund the account like the rich wallets. Defaults to `false`
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the address of the imported account.
+    #[rpc(name = "era_importAccount")]
+    fn import_account(&self, private_key: H256, fund: Option<bool>) -> RpcResult<Address>;
+}
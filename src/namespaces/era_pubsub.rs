@@ -0,0 +1,28 @@
+use jsonrpc_derive::rpc;
+use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+
+use crate::{namespaces::Result, subscriptions::NodeEvent};
+
+/// Subscriptions to the node-level events, only available over WebSocket.
+#[rpc]
+pub trait EraPubSubNamespaceT {
+    type Metadata;
+
+    /// Subscribes to the node-level events: snapshots taken and reverted, accounts impersonated or no
+    /// longer impersonated, and runtime options changed. Each notification holds the `type` of the event
+    /// along with its details.
+    #[pubsub(subscription = "era_subscription", subscribe, name = "era_subscribe")]
+    fn subscribe(&self, meta: Self::Metadata, subscriber: Subscriber<NodeEvent>);
+
+    /// Cancels the subscription.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the subscription existed, `false` otherwise.
+    #[pubsub(
+        subscription = "era_subscription",
+        unsubscribe,
+        name = "era_unsubscribe"
+    )]
+    fn unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool>;
+}
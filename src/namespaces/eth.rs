@@ -20,11 +20,8 @@ pub trait EthNamespaceT {
     fn call(&self, req: CallRequest, block: Option<BlockIdVariant>) -> BoxFuture<Result<Bytes>>;
 
     #[rpc(name = "eth_estimateGas")]
-    fn estimate_gas(
-        &self,
-        req: CallRequest,
-        _block: Option<BlockNumber>,
-    ) -> BoxFuture<Result<U256>>;
+    fn estimate_gas(&self, req: CallRequest, block: Option<BlockNumber>)
+        -> BoxFuture<Result<U256>>;
 
     #[rpc(name = "eth_gasPrice")]
     fn gas_price(&self) -> BoxFuture<Result<U256>>;
@@ -143,6 +140,20 @@ pub trait EthNamespaceT {
     #[rpc(name = "eth_hashrate")]
     fn hashrate(&self) -> BoxFuture<Result<U256>>;
 
+    #[rpc(name = "eth_getUncleByBlockHashAndIndex")]
+    fn get_uncle_by_block_hash_and_index(
+        &self,
+        hash: H256,
+        index: Index,
+    ) -> BoxFuture<Result<Option<Block<TransactionVariant>>>>;
+
+    #[rpc(name = "eth_getUncleByBlockNumberAndIndex")]
+    fn get_uncle_by_block_number_and_index(
+        &self,
+        number: BlockNumber,
+        index: Index,
+    ) -> BoxFuture<Result<Option<Block<TransactionVariant>>>>;
+
     #[rpc(name = "eth_getUncleCountByBlockHash")]
     fn get_uncle_count_by_block_hash(&self, hash: H256) -> BoxFuture<Result<Option<U256>>>;
 
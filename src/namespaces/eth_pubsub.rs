@@ -0,0 +1,44 @@
+use jsonrpc_derive::rpc;
+use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+
+use crate::{
+    namespaces::Result,
+    subscriptions::{LogsParams, SubscriptionKind, SubscriptionResult},
+};
+
+/// Subscriptions to the node events, only available over WebSocket.
+#[rpc]
+pub trait EthPubSubNamespaceT {
+    type Metadata;
+
+    /// Subscribes to the new block headers (`newHeads`), the logs matching the address and topics of the
+    /// `params` (`logs`), or the hashes of the new pending transactions (`newPendingTransactions`).
+    ///
+    /// The logs of the blocks that are removed again, e.g. when a snapshot is reverted, are sent once more
+    /// with `removed` set to `true`.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of the subscription
+    /// * `params` - The address and topics filter of the `logs` subscription
+    #[pubsub(subscription = "eth_subscription", subscribe, name = "eth_subscribe")]
+    fn subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: Subscriber<SubscriptionResult>,
+        kind: SubscriptionKind,
+        params: Option<LogsParams>,
+    );
+
+    /// Cancels the subscription.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the subscription existed, `false` otherwise.
+    #[pubsub(
+        subscription = "eth_subscription",
+        unsubscribe,
+        name = "eth_unsubscribe"
+    )]
+    fn unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool>;
+}
@@ -1,7 +1,7 @@
 use jsonrpc_core::{BoxFuture, Result};
 use jsonrpc_derive::rpc;
-use zksync_basic_types::H256;
-use zksync_types::transaction_request::CallRequest;
+use zksync_basic_types::{Address, H256};
+use zksync_types::{transaction_request::CallRequest, Bytes};
 
 ///
 /// ETH namespace extension for the test node.
@@ -10,4 +10,17 @@ use zksync_types::transaction_request::CallRequest;
 pub trait EthTestNodeNamespaceT {
     #[rpc(name = "eth_sendTransaction")]
     fn send_transaction(&self, tx: CallRequest) -> BoxFuture<Result<H256>>;
+
+    /// Signs a message with the key of an account imported with `--import-key` or `era_importAccount`.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the imported account
+    /// * `message` - The message to sign, prefixed as a personal message (EIP-191) before signing
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the 65 bytes signature.
+    #[rpc(name = "eth_sign")]
+    fn sign(&self, address: Address, message: Bytes) -> BoxFuture<Result<Bytes>>;
 }
@@ -17,7 +17,8 @@ pub trait EvmNamespaceT {
 
     /// Force a single block to be mined.
     ///
-    /// Will mine an empty block (containing zero transactions)
+    /// Will seal the pending transactions into the block, if there are any. Otherwise, will mine
+    /// an empty block (containing zero transactions)
     ///
     /// # Returns
     /// The string "0x0".
@@ -67,4 +68,25 @@ pub trait EvmNamespaceT {
     /// `true` if a snapshot was reverted, otherwise `false`.
     #[rpc(name = "evm_revert")]
     fn revert_snapshot(&self, snapshot_id: U64) -> RpcResult<bool>;
+
+    /// Set the block gas limit. Transactions with a higher gas limit are rejected.
+    ///
+    /// # Parameters
+    /// - `gas_limit`: The new block gas limit
+    ///
+    /// # Returns
+    /// `true` if the block gas limit was set.
+    #[rpc(name = "evm_setBlockGasLimit")]
+    fn set_block_gas_limit(&self, gas_limit: U64) -> RpcResult<bool>;
+
+    /// Enables or disables the automatic mining of a new block for each submitted transaction.
+    /// When disabled, transactions stay pending until a block is mined via `evm_mine` or `hardhat_mine`.
+    ///
+    /// # Parameters
+    /// - `enabled`: Whether automine should be enabled
+    ///
+    /// # Returns
+    /// `true` if the automine mode was set.
+    #[rpc(name = "evm_setAutomine")]
+    fn set_automine(&self, enabled: bool) -> RpcResult<bool>;
 }
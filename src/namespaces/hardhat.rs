@@ -2,6 +2,7 @@ use jsonrpc_derive::rpc;
 use zksync_basic_types::{Address, U256, U64};
 
 use super::RpcResult;
+use crate::node::HardhatMetadata;
 
 #[rpc]
 pub trait HardhatNamespaceT {
@@ -86,4 +87,34 @@ pub trait HardhatNamespaceT {
     /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
     #[rpc(name = "hardhat_setCode")]
     fn set_code(&self, address: Address, code: Vec<u8>) -> RpcResult<()>;
+
+    /// Returns whether a new block is automatically mined for each submitted transaction.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with `true` if automine is enabled, and `false` otherwise.
+    #[rpc(name = "hardhat_getAutomine")]
+    fn get_automine(&self) -> RpcResult<bool>;
+
+    /// Sets the coinbase address, i.e. the operator address that collects the transaction fees
+    /// and is reported as the `miner` of the following blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The new coinbase address
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with a `bool` representing the success of the operation.
+    #[rpc(name = "hardhat_setCoinbase")]
+    fn set_coinbase(&self, address: Address) -> RpcResult<bool>;
+
+    /// Returns the metadata of the node: the chain id, the instance id, the latest block and
+    /// the details of the forked network (if any).
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the [HardhatMetadata].
+    #[rpc(name = "hardhat_metadata")]
+    fn metadata(&self) -> RpcResult<HardhatMetadata>;
 }
@@ -1,20 +1,30 @@
+mod anvil;
 mod config;
 mod debug;
+mod era;
+mod era_pubsub;
 mod eth;
+mod eth_pubsub;
 mod eth_test;
 mod evm;
 mod hardhat;
 mod net;
+mod trace;
 mod web3;
 mod zks;
 
+pub use anvil::AnvilNamespaceT;
 pub use config::ConfigurationApiNamespaceT;
 pub use debug::DebugNamespaceT;
+pub use era::EraNamespaceT;
+pub use era_pubsub::EraPubSubNamespaceT;
 pub use eth::EthNamespaceT;
+pub use eth_pubsub::EthPubSubNamespaceT;
 pub use eth_test::EthTestNodeNamespaceT;
 pub use evm::EvmNamespaceT;
 pub use hardhat::HardhatNamespaceT;
 pub use net::NetNamespaceT;
+pub use trace::TraceNamespaceT;
 pub use web3::Web3NamespaceT;
 pub use zks::ZksNamespaceT;
 
@@ -0,0 +1,45 @@
+use jsonrpc_core::{BoxFuture, Result};
+use jsonrpc_derive::rpc;
+use zksync_types::{api::BlockNumber, H256};
+
+use crate::node::{Trace, TraceFilter};
+
+/// Flat call traces in the Parity/OpenEthereum format.
+#[rpc]
+pub trait TraceNamespaceT {
+    /// Returns the flat call traces of a transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_hash` - The hash of the transaction
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the traces, or `None` if the transaction was not executed by this node.
+    #[rpc(name = "trace_transaction")]
+    fn trace_transaction(&self, tx_hash: H256) -> BoxFuture<Result<Option<Vec<Trace>>>>;
+
+    /// Returns the flat call traces of all the transactions in a block.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The number of the block
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the traces, or `None` if the block does not exist.
+    #[rpc(name = "trace_block")]
+    fn trace_block(&self, block: BlockNumber) -> BoxFuture<Result<Option<Vec<Trace>>>>;
+
+    /// Returns the flat call traces matching the filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The block range, the sender and recipient addresses and the pagination of the traces
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `Result` with the matching traces.
+    #[rpc(name = "trace_filter")]
+    fn trace_filter(&self, filter: TraceFilter) -> BoxFuture<Result<Vec<Trace>>>;
+}
@@ -1,4 +1,6 @@
 use jsonrpc_derive::rpc;
+use zksync_basic_types::H256;
+use zksync_types::Bytes;
 
 use crate::namespaces::Result;
 
@@ -6,4 +8,8 @@ use crate::namespaces::Result;
 pub trait Web3NamespaceT {
     #[rpc(name = "web3_clientVersion", returns = "String")]
     fn web3_client_version(&self) -> Result<String>;
+
+    /// Returns the Keccak-256 hash of the given data.
+    #[rpc(name = "web3_sha3", returns = "H256")]
+    fn web3_sha3(&self, data: Bytes) -> Result<H256>;
 }
@@ -10,10 +10,12 @@ use zksync_types::{
     },
     fee::Fee,
     transaction_request::CallRequest,
-    Address, L1BatchNumber, MiniblockNumber, H256, U256, U64,
+    Address, Bytes, L1BatchNumber, MiniblockNumber, H256, U256, U64,
 };
 use zksync_web3_decl::types::Token;
 
+use crate::node::TransactionDetailedResult;
+
 #[rpc]
 pub trait ZksNamespaceT {
     #[rpc(name = "zks_estimateFee")]
@@ -108,4 +110,10 @@ pub trait ZksNamespaceT {
         keys: Vec<H256>,
         l1_batch_number: L1BatchNumber,
     ) -> BoxFuture<Result<Proof>>;
+
+    #[rpc(name = "zks_sendRawTransactionWithDetailedOutput")]
+    fn send_raw_transaction_with_detailed_output(
+        &self,
+        tx_bytes: Bytes,
+    ) -> BoxFuture<Result<TransactionDetailedResult>>;
 }
@@ -0,0 +1,61 @@
+use zksync_basic_types::U64;
+use zksync_web3_decl::error::Web3Error;
+
+use crate::{
+    fork::ForkSource,
+    namespaces::{AnvilNamespaceT, RpcResult},
+    node::{InMemoryNode, NodeInfo},
+    utils::{into_jsrpc_error, IntoBoxedFuture},
+};
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> AnvilNamespaceT
+    for InMemoryNode<S>
+{
+    fn rollback(&self, depth: Option<U64>) -> RpcResult<bool> {
+        self.rollback_blocks(depth.unwrap_or_else(U64::one))
+            .map(|_| true)
+            .map_err(|err| {
+                tracing::error!("failed rolling back blocks: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn node_info(&self) -> RpcResult<NodeInfo> {
+        self.node_info()
+            .map_err(|err| {
+                tracing::error!("failed getting node info: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn set_rpc_url(&self, url: String) -> RpcResult<bool> {
+        self.set_rpc_url(url)
+            .map(|_| true)
+            .map_err(|err| {
+                tracing::error!("failed setting the fork url: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn set_block_timestamp_interval(&self, seconds: u64) -> RpcResult<bool> {
+        self.set_block_timestamp_interval(seconds)
+            .map(|_| true)
+            .map_err(|err| {
+                tracing::error!("failed setting the block timestamp interval: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn remove_block_timestamp_interval(&self) -> RpcResult<bool> {
+        self.remove_block_timestamp_interval()
+            .map_err(|err| {
+                tracing::error!("failed removing the block timestamp interval: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+}
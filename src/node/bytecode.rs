@@ -0,0 +1,122 @@
+//! Validation of the EraVM bytecodes against the length rules of the deployments, reported by `era_validateBytecode`
+//! and when rejecting the transactions publishing invalid bytecodes.
+use serde::Serialize;
+use zksync_basic_types::H256;
+use zksync_utils::bytecode::{compress_bytecode, hash_bytecode};
+
+/// Maximum length of a bytecode in 32-byte words, as its hash stores the length in 2 bytes.
+const MAX_BYTECODE_LENGTH_IN_WORDS: usize = (1 << 16) - 1;
+/// Maximum length of a bytecode in bytes.
+pub const MAX_BYTECODE_LENGTH: usize = MAX_BYTECODE_LENGTH_IN_WORDS * 32;
+
+/// Diagnostic of a bytecode, as returned by `era_validateBytecode`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BytecodeValidation {
+    pub valid: bool,
+    /// The length of the bytecode in bytes.
+    pub length: usize,
+    pub max_length: usize,
+    /// The broken rules, empty if the bytecode is valid.
+    pub errors: Vec<String>,
+    /// The length the bytecode can be padded to with zero bytes to become valid, if it is too short to be.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub padded_length: Option<usize>,
+    /// The versioned hash of a valid bytecode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<H256>,
+    /// The length of a valid bytecode once compressed, as published to L1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressed_length: Option<usize>,
+}
+
+impl BytecodeValidation {
+    /// Returns the message of the error of the transactions publishing the bytecode, `None` if it is valid.
+    pub fn error_message(&self) -> Option<String> {
+        if self.valid {
+            return None;
+        }
+        let mut message = format!(
+            "invalid bytecode of {} bytes (max {}): {}",
+            self.length,
+            self.max_length,
+            self.errors.join(", ")
+        );
+        if let Some(padded_length) = self.padded_length {
+            message.push_str(&format!(
+                "; padding it to {} bytes would fix it",
+                padded_length
+            ));
+        }
+        Some(message)
+    }
+}
+
+/// Checks the bytecode against the rules of the deployments: its length must be a multiple of 32 bytes and an odd
+/// number of words, of at most [MAX_BYTECODE_LENGTH] bytes.
+pub fn validate_bytecode(bytecode: &[u8]) -> BytecodeValidation {
+    let length = bytecode.len();
+    let mut errors = vec![];
+    if length % 32 != 0 {
+        errors.push("the length is not a multiple of 32 bytes".to_string());
+    } else if (length / 32) % 2 == 0 {
+        errors.push("the length in 32-byte words is even".to_string());
+    }
+    if length > MAX_BYTECODE_LENGTH {
+        errors.push(format!(
+            "the length exceeds the maximum of {} bytes",
+            MAX_BYTECODE_LENGTH
+        ));
+    }
+
+    let valid = errors.is_empty();
+    let mut words = (length + 31) / 32;
+    if words % 2 == 0 {
+        words += 1;
+    }
+    BytecodeValidation {
+        valid,
+        length,
+        max_length: MAX_BYTECODE_LENGTH,
+        errors,
+        padded_length: Some(words * 32)
+            .filter(|padded_length| !valid && *padded_length <= MAX_BYTECODE_LENGTH),
+        hash: Some(bytecode).filter(|_| valid).map(hash_bytecode),
+        compressed_length: Some(bytecode)
+            .filter(|_| valid)
+            .and_then(|bytecode| compress_bytecode(bytecode).ok())
+            .map(|compressed| compressed.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_bytecode_reports_padding_fixes() {
+        let validation = validate_bytecode(&[0u8; 96]);
+        assert!(validation.valid);
+        assert_eq!(None, validation.error_message());
+        assert!(validation.hash.is_some());
+        assert!(validation.compressed_length.is_some());
+
+        let validation = validate_bytecode(&[0u8; 40]);
+        assert!(!validation.valid);
+        assert_eq!(Some(96), validation.padded_length);
+        assert_eq!(None, validation.hash);
+        assert_eq!(
+            Some(format!(
+                "invalid bytecode of 40 bytes (max {}): the length is not a multiple of 32 bytes; \
+                 padding it to 96 bytes would fix it",
+                MAX_BYTECODE_LENGTH
+            )),
+            validation.error_message()
+        );
+        assert_eq!(Some(96), validate_bytecode(&[0u8; 64]).padded_length);
+
+        let validation = validate_bytecode(&vec![0u8; MAX_BYTECODE_LENGTH + 64]);
+        assert!(!validation.valid);
+        assert_eq!(None, validation.padded_length);
+    }
+}
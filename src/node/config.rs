@@ -1,10 +1,15 @@
+use std::collections::HashMap;
+
+use zksync_basic_types::Address;
 use zksync_web3_decl::error::Web3Error;
 
 use crate::{
+    chaos::ChaosConfig,
     fork::ForkSource,
     namespaces::{ConfigurationApiNamespaceT, Result},
-    node::InMemoryNode,
+    node::{InMemoryNode, L1BatchSealer},
     observability::LogLevel,
+    subscriptions::NodeEvent,
     utils::into_jsrpc_error,
 };
 
@@ -47,7 +52,11 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> Configurat
             })
             .map(|mut writer| {
                 writer.show_calls = show_calls;
-                writer.show_calls.to_string()
+                let show_calls = writer.show_calls.to_string();
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed("show-calls", &show_calls));
+                show_calls
             })
     }
 
@@ -74,7 +83,14 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> Configurat
             })
             .map(|mut writer| {
                 writer.show_storage_logs = show_storage_logs;
-                writer.show_storage_logs.to_string()
+                let show_storage_logs = writer.show_storage_logs.to_string();
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "show-storage-logs",
+                        &show_storage_logs,
+                    ));
+                show_storage_logs
             })
     }
 
@@ -101,7 +117,14 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> Configurat
             })
             .map(|mut writer| {
                 writer.show_vm_details = show_vm_details;
-                writer.show_vm_details.to_string()
+                let show_vm_details = writer.show_vm_details.to_string();
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "show-vm-details",
+                        &show_vm_details,
+                    ));
+                show_vm_details
             })
     }
 
@@ -128,7 +151,14 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> Configurat
             })
             .map(|mut writer| {
                 writer.show_gas_details = show_gas_details;
-                writer.show_gas_details.to_string()
+                let show_gas_details = writer.show_gas_details.to_string();
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "show-gas-details",
+                        &show_gas_details,
+                    ));
+                show_gas_details
             })
     }
 
@@ -141,22 +171,64 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> Configurat
             })
             .map(|mut writer| {
                 writer.resolve_hashes = value;
-                writer.resolve_hashes
+                let resolve_hashes = writer.resolve_hashes;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "resolve-hashes",
+                        &resolve_hashes,
+                    ));
+                resolve_hashes
             })
     }
 
-    fn config_set_log_level(&self, level: LogLevel) -> Result<bool> {
-        if let Some(observability) = &self
-            .get_inner()
-            .read()
+    fn config_set_show_precompiles(&self, value: bool) -> Result<bool> {
+        self.get_inner()
+            .write()
             .map_err(|err| {
                 tracing::error!("failed acquiring lock: {:?}", err);
                 into_jsrpc_error(Web3Error::InternalError)
-            })?
-            .observability
-        {
+            })
+            .map(|mut writer| {
+                writer.show_precompiles = value;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed("show-precompiles", &value));
+                value
+            })
+    }
+
+    fn config_set_show_outputs(&self, value: bool) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer.show_outputs = value;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed("show-outputs", &value));
+                value
+            })
+    }
+
+    fn config_set_log_level(&self, level: LogLevel) -> Result<bool> {
+        let inner = self.get_inner();
+        let mut writer = inner.write().map_err(|err| {
+            tracing::error!("failed acquiring lock: {:?}", err);
+            into_jsrpc_error(Web3Error::InternalError)
+        })?;
+        let writer = &mut *writer;
+        if let Some(observability) = &writer.observability {
             match observability.set_log_level(level.clone()) {
-                Ok(_) => tracing::info!("set log level to '{}'", level),
+                Ok(_) => {
+                    tracing::info!("set log level to '{}'", level);
+                    writer
+                        .subscriptions
+                        .notify_node_event(NodeEvent::config_changed("log", level.to_string()));
+                }
                 Err(err) => {
                     tracing::error!("failed setting log level {:?}", err);
                     return Ok(false);
@@ -167,17 +239,20 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> Configurat
     }
 
     fn config_set_logging(&self, directive: String) -> Result<bool> {
-        if let Some(observability) = &self
-            .get_inner()
-            .read()
-            .map_err(|err| {
-                tracing::error!("failed acquiring lock: {:?}", err);
-                into_jsrpc_error(Web3Error::InternalError)
-            })?
-            .observability
-        {
+        let inner = self.get_inner();
+        let mut writer = inner.write().map_err(|err| {
+            tracing::error!("failed acquiring lock: {:?}", err);
+            into_jsrpc_error(Web3Error::InternalError)
+        })?;
+        let writer = &mut *writer;
+        if let Some(observability) = &writer.observability {
             match observability.set_logging(&directive) {
-                Ok(_) => tracing::info!("set logging to '{}'", directive),
+                Ok(_) => {
+                    tracing::info!("set logging to '{}'", directive);
+                    writer
+                        .subscriptions
+                        .notify_node_event(NodeEvent::config_changed("logging", &directive));
+                }
                 Err(err) => {
                     tracing::error!("failed setting logging to '{}': {:?}", directive, err);
                     return Ok(false);
@@ -186,4 +261,270 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> Configurat
         }
         Ok(true)
     }
+
+    fn config_set_tx_gas_limit(&self, value: Option<u64>) -> Result<Option<u64>> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer.tx_gas_limit = value;
+                let tx_gas_limit = writer.tx_gas_limit;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed("tx-gas-limit", &tx_gas_limit));
+                tx_gas_limit
+            })
+    }
+
+    fn config_set_disable_block_gas_limit(&self, value: bool) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer.disable_block_gas_limit = value;
+                let disable_block_gas_limit = writer.disable_block_gas_limit;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "disable-block-gas-limit",
+                        &disable_block_gas_limit,
+                    ));
+                disable_block_gas_limit
+            })
+    }
+
+    fn config_set_skip_nonce_check(&self, value: bool) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer.skip_nonce_check = value;
+                let skip_nonce_check = writer.skip_nonce_check;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "skip-nonce-check",
+                        &skip_nonce_check,
+                    ));
+                skip_nonce_check
+            })
+    }
+
+    fn config_set_skip_balance_check(&self, value: bool) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer.skip_balance_check = value;
+                let skip_balance_check = writer.skip_balance_check;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "skip-balance-check",
+                        &skip_balance_check,
+                    ));
+                skip_balance_check
+            })
+    }
+
+    fn config_set_estimate_gas_scale_factor(&self, value: f32) -> Result<f32> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                if value.is_finite() && value >= 1.0 {
+                    writer.estimate_gas_scale_factor = value;
+                    writer
+                        .subscriptions
+                        .notify_node_event(NodeEvent::config_changed(
+                            "estimate-gas-scale-factor",
+                            value,
+                        ));
+                }
+                writer.estimate_gas_scale_factor
+            })
+    }
+
+    fn config_set_estimate_gas_acceptable_overestimation(&self, value: u32) -> Result<u32> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer.estimate_gas_acceptable_overestimation = value;
+                let estimate_gas_acceptable_overestimation =
+                    writer.estimate_gas_acceptable_overestimation;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "estimate-gas-acceptable-overestimation",
+                        &estimate_gas_acceptable_overestimation,
+                    ));
+                estimate_gas_acceptable_overestimation
+            })
+    }
+
+    fn config_set_estimate_gas_max_iterations(&self, value: Option<u32>) -> Result<Option<u32>> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer.estimate_gas_max_iterations = value;
+                let estimate_gas_max_iterations = writer.estimate_gas_max_iterations;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "estimate-gas-max-iterations",
+                        &estimate_gas_max_iterations,
+                    ));
+                estimate_gas_max_iterations
+            })
+    }
+
+    fn config_set_l1_batch_seal_interval(&self, value: Option<u64>) -> Result<Option<u64>> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                let inner = &mut *writer;
+                match value {
+                    Some(interval) => {
+                        if let Some(sealer) = inner.l1_batch_sealer.as_mut() {
+                            sealer.interval = interval;
+                        } else {
+                            let open_batch = inner.latest_l1_batch().saturating_add(1);
+                            inner.l1_batch_sealer = Some(L1BatchSealer::new(interval, open_batch));
+                        }
+                    }
+                    None => inner.l1_batch_sealer = None,
+                }
+                let interval = inner.l1_batch_sealer.as_ref().map(|sealer| sealer.interval);
+                inner
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "l1-batch-seal-interval",
+                        interval,
+                    ));
+                interval
+            })
+    }
+
+    fn config_set_vm_execution_timeout(&self, value: Option<u64>) -> Result<Option<u64>> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer.execution_limits.timeout_ms = value;
+                let timeout_ms = writer.execution_limits.timeout_ms;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "vm-execution-timeout",
+                        &timeout_ms,
+                    ));
+                timeout_ms
+            })
+    }
+
+    fn config_set_vm_max_steps(&self, value: Option<u64>) -> Result<Option<u64>> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer.execution_limits.max_steps = value;
+                let max_steps = writer.execution_limits.max_steps;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed("vm-max-steps", &max_steps));
+                max_steps
+            })
+    }
+
+    fn config_set_show_calls_max_depth(&self, value: Option<usize>) -> Result<Option<usize>> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer.show_calls_max_depth = value;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed("show-calls-max-depth", &value));
+                value
+            })
+    }
+
+    fn config_set_chaos(&self, config: Option<ChaosConfig>) -> Result<bool> {
+        if let Err(err) = self.chaos.set_config(config.clone()) {
+            tracing::error!("failed setting chaos mode: {:?}", err);
+            return Ok(false);
+        }
+        match &config {
+            Some(config) => tracing::info!(
+                "chaos mode: {}ms latency and {} error rate",
+                config.latency_ms,
+                config.error_rate
+            ),
+            None => tracing::info!("chaos mode disabled"),
+        }
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed("chaos", &config));
+                true
+            })
+    }
+
+    fn config_set_address_labels(&self, labels: HashMap<Address, String>) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .map(|mut writer| {
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed("address-labels", &labels));
+                writer.address_labels.extend(labels);
+                true
+            })
+    }
 }
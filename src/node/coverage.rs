@@ -0,0 +1,182 @@
+//! Coverage of the instructions executed by the transactions, returned by `era_getExecutionCoverage`, so that external
+//! tools can map them to the sources of the contracts and build zkEVM-aware coverage reports.
+use std::{collections::BTreeMap, sync::Arc};
+
+use multivm::{
+    interface::{dyn_tracers::vm_1_4_1::DynTracer, tracer::VmExecutionStopReason},
+    vm_latest::{BootloaderState, HistoryMode, SimpleMemory, VmTracer, ZkSyncVmState},
+    zk_evm_1_4_1::tracing::{BeforeExecutionData, VmLocalStateData},
+};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use zksync_basic_types::{Address, H256};
+use zksync_state::{ReadStorage, StoragePtr, WriteStorage};
+use zksync_types::get_code_key;
+
+use crate::{
+    fork::ForkSource,
+    node::{InMemoryNodeInner, MAX_EXECUTION_COVERAGES},
+    utils::is_system_contract,
+};
+
+/// Instructions executed by a transaction, as returned by `era_getExecutionCoverage`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionCoverage {
+    /// The contracts whose code was executed, by address.
+    pub contracts: Vec<ContractCoverage>,
+}
+
+/// Instructions executed in the code of a contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractCoverage {
+    /// The address of the executed code, i.e. of the implementation for the delegate calls.
+    pub address: Address,
+    /// The versioned hash of the bytecode of the contract after the transaction, to find its compiled artifact.
+    pub bytecode_hash: H256,
+    /// The executed instructions, by program counter.
+    pub instructions: Vec<InstructionCoverage>,
+}
+
+/// Instruction executed in the code of a contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionCoverage {
+    /// The program counter of the instruction, in 8-byte instruction words.
+    pub pc: u16,
+    pub opcode: String,
+    /// The number of times the instruction was executed.
+    pub hits: u64,
+}
+
+/// The role of this tracer is to count the executions of the instructions of the contracts, leaving out the system
+/// contracts.
+pub struct CoverageTracer {
+    result: Arc<OnceCell<ExecutionCoverage>>,
+    contracts: BTreeMap<Address, BTreeMap<u16, InstructionCoverage>>,
+}
+
+impl CoverageTracer {
+    pub fn new(result: Arc<OnceCell<ExecutionCoverage>>) -> Self {
+        Self {
+            result,
+            contracts: BTreeMap::new(),
+        }
+    }
+
+    fn record(&mut self, address: Address, pc: u16, opcode: impl FnOnce() -> String) {
+        self.contracts
+            .entry(address)
+            .or_default()
+            .entry(pc)
+            .or_insert_with(|| InstructionCoverage {
+                pc,
+                opcode: opcode(),
+                hits: 0,
+            })
+            .hits += 1;
+    }
+
+    fn coverage(&mut self) -> ExecutionCoverage {
+        ExecutionCoverage {
+            contracts: std::mem::take(&mut self.contracts)
+                .into_iter()
+                .map(|(address, instructions)| ContractCoverage {
+                    address,
+                    bytecode_hash: H256::zero(),
+                    instructions: instructions.into_values().collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<S, H: HistoryMode> DynTracer<S, SimpleMemory<H>> for CoverageTracer {
+    fn before_execution(
+        &mut self,
+        state: VmLocalStateData<'_>,
+        data: BeforeExecutionData,
+        _memory: &SimpleMemory<H>,
+        _storage: StoragePtr<S>,
+    ) {
+        let frame = &state.vm_local_state.callstack.current;
+        if is_system_contract(&frame.code_address) {
+            return;
+        }
+        self.record(frame.code_address, frame.pc, || {
+            format!("{:?}", data.opcode.variant.opcode)
+        });
+    }
+}
+
+impl<S: WriteStorage, H: HistoryMode> VmTracer<S, H> for CoverageTracer {
+    fn after_vm_execution(
+        &mut self,
+        _state: &mut ZkSyncVmState<S, H>,
+        _bootloader_state: &BootloaderState,
+        _stop_reason: VmExecutionStopReason,
+    ) {
+        let _ = self.result.set(self.coverage());
+    }
+}
+
+impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
+    /// Records the coverage of an executed transaction, evicting the oldest record above [MAX_EXECUTION_COVERAGES].
+    /// The bytecode hashes of the contracts are filled in, and the accounts without code, which run the default
+    /// account, are left out.
+    pub(crate) fn record_execution_coverage(
+        &mut self,
+        tx_hash: H256,
+        mut coverage: ExecutionCoverage,
+    ) {
+        coverage.contracts.retain_mut(|contract| {
+            contract.bytecode_hash = self
+                .fork_storage
+                .read_value(&get_code_key(&contract.address));
+            !contract.bytecode_hash.is_zero()
+        });
+        if self.execution_coverage.len() >= MAX_EXECUTION_COVERAGES {
+            self.execution_coverage.shift_remove_index(0);
+        }
+        self.execution_coverage.insert(tx_hash, coverage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_tracer_counts_instruction_hits() {
+        let mut tracer = CoverageTracer::new(Arc::new(OnceCell::new()));
+        let contract = Address::repeat_byte(0x1);
+        tracer.record(contract, 0, || "Add(Add)".to_string());
+        tracer.record(contract, 1, || "Jump".to_string());
+        tracer.record(contract, 0, || unreachable!("opcode formatted twice"));
+        tracer.record(Address::repeat_byte(0x2), 5, || "Ret(Ok)".to_string());
+
+        let coverage = tracer.coverage();
+        assert_eq!(2, coverage.contracts.len());
+        assert_eq!(
+            ContractCoverage {
+                address: contract,
+                bytecode_hash: H256::zero(),
+                instructions: vec![
+                    InstructionCoverage {
+                        pc: 0,
+                        opcode: "Add(Add)".to_string(),
+                        hits: 2,
+                    },
+                    InstructionCoverage {
+                        pc: 1,
+                        opcode: "Jump".to_string(),
+                        hits: 1,
+                    },
+                ],
+            },
+            coverage.contracts[0]
+        );
+        assert!(tracer.contracts.is_empty());
+    }
+}
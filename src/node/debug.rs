@@ -1,13 +1,14 @@
 use itertools::Itertools;
 use once_cell::sync::OnceCell;
-use std::sync::Arc;
+use serde::Serialize;
+use std::{collections::BTreeMap, sync::Arc};
 
 use multivm::interface::VmInterface;
 use multivm::tracers::CallTracer;
 use multivm::vm_latest::HistoryDisabled;
 use multivm::vm_latest::{constants::ETH_CALL_GAS_LIMIT, ToTracerPointer, Vm};
 
-use zksync_basic_types::H256;
+use zksync_basic_types::{web3::signing::keccak256, Address, H256};
 use zksync_types::{
     api::{BlockId, BlockNumber, DebugCall, ResultDebugCall, TracerConfig, TransactionVariant},
     l2::L2Tx,
@@ -18,12 +19,29 @@ use zksync_web3_decl::error::Web3Error;
 
 use crate::deps::storage_view::StorageView;
 use crate::{
+    execution_limit::ExecutionLimitTracer,
     fork::ForkSource,
     namespaces::{DebugNamespaceT, Result, RpcResult},
     node::{InMemoryNode, MAX_TX_SIZE},
     utils::{create_debug_output, into_jsrpc_error, to_real_block_number},
 };
 
+/// Storage slots of a contract returned by `debug_storageRangeAt`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageRangeResult {
+    /// The slots, keyed by the keccak256 hash of the slot.
+    pub storage: BTreeMap<H256, StorageEntry>,
+    /// The hash of the first slot that was not returned, `None` if all the slots were returned.
+    pub next_key: Option<H256>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StorageEntry {
+    pub key: H256,
+    pub value: H256,
+}
+
 impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> DebugNamespaceT
     for InMemoryNode<S>
 {
@@ -140,15 +158,15 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> DebugNames
         options: Option<TracerConfig>,
     ) -> RpcResult<DebugCall> {
         let only_top = options.is_some_and(|o| o.tracer_config.only_top_call);
-        let inner = self.get_inner().clone();
-        Box::pin(async move {
+        self.run_on_worker(move |node| {
             if block.is_some() && !matches!(block, Some(BlockId::Number(BlockNumber::Latest))) {
                 return Err(jsonrpc_core::Error::invalid_params(
                     "tracing only supported at `latest` block",
                 ));
             }
 
-            let inner = inner
+            let inner = node
+                .get_inner()
                 .read()
                 .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
 
@@ -188,8 +206,11 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> DebugNames
             vm.push_transaction(tx);
 
             let call_tracer_result = Arc::new(OnceCell::default());
-            let tracer = CallTracer::new(call_tracer_result.clone()).into_tracer_pointer();
-            let tx_result = vm.inspect(tracer.into(), multivm::interface::VmExecutionMode::OneTx);
+            let tracers = vec![
+                CallTracer::new(call_tracer_result.clone()).into_tracer_pointer(),
+                ExecutionLimitTracer::new(inner.execution_limits).into_tracer_pointer(),
+            ];
+            let tx_result = vm.inspect(tracers.into(), multivm::interface::VmExecutionMode::OneTx);
 
             let call_traces = if only_top {
                 vec![]
@@ -225,6 +246,72 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> DebugNames
                 .map(|tx| tx.debug_info(only_top)))
         })
     }
+
+    fn storage_range_at(
+        &self,
+        block_hash: H256,
+        _tx_index: usize,
+        address: Address,
+        start_key: H256,
+        max_result: usize,
+    ) -> RpcResult<StorageRangeResult> {
+        let inner = self.get_inner().clone();
+        Box::pin(async move {
+            let inner = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+            // only the locally modified slots are known, the slots of a forked network can't be enumerated
+            let mut slots = if block_hash == inner.current_miniblock_hash {
+                let storage = inner
+                    .fork_storage
+                    .inner
+                    .read()
+                    .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+                collect_slots(storage.raw_storage.state.iter(), address, start_key)
+            } else {
+                let state = inner
+                    .previous_states
+                    .get(&block_hash)
+                    .ok_or_else(|| into_jsrpc_error(Web3Error::NoBlock))?;
+                collect_slots(state.iter(), address, start_key)
+            };
+
+            let next_key = if slots.len() > max_result {
+                slots.split_off(max_result).first().map(|(hash, _)| *hash)
+            } else {
+                None
+            };
+
+            Ok(StorageRangeResult {
+                storage: slots.into_iter().collect(),
+                next_key,
+            })
+        })
+    }
+}
+
+/// Returns the non-zero slots of `address` whose hash is at least `start_key`, ordered by the hash.
+fn collect_slots<'a>(
+    state: impl Iterator<Item = (&'a zksync_types::StorageKey, &'a H256)>,
+    address: Address,
+    start_key: H256,
+) -> Vec<(H256, StorageEntry)> {
+    state
+        .filter(|(key, value)| *key.address() == address && !value.is_zero())
+        .map(|(key, value)| {
+            let hash = H256(keccak256(key.key().as_bytes()));
+            (
+                hash,
+                StorageEntry {
+                    key: *key.key(),
+                    value: *value,
+                },
+            )
+        })
+        .filter(|(hash, _)| *hash >= start_key)
+        .sorted_by_key(|(hash, _)| *hash)
+        .collect()
 }
 
 #[cfg(test)]
@@ -560,4 +647,49 @@ mod tests {
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].result.calls.len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_storage_range_at_paginates_by_slot_hash() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let address = Address::repeat_byte(0x1);
+        let block_hash = {
+            let mut writer = node.get_inner().write().unwrap();
+            for slot in 1..=3u8 {
+                writer.fork_storage.set_value(
+                    zksync_types::StorageKey::new(
+                        zksync_types::AccountTreeId::new(address),
+                        H256::from_low_u64_be(slot as u64),
+                    ),
+                    H256::repeat_byte(slot),
+                );
+            }
+            writer.current_miniblock_hash
+        };
+
+        let first_page = node
+            .storage_range_at(block_hash, 0, address, H256::zero(), 2)
+            .await
+            .unwrap();
+        let next_key = first_page.next_key.expect("missing next key");
+        let last_page = node
+            .storage_range_at(block_hash, 0, address, next_key, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(2, first_page.storage.len());
+        assert_eq!(1, last_page.storage.len());
+        assert_eq!(None, last_page.next_key);
+        assert!(first_page.storage.keys().all(|hash| *hash < next_key));
+        let entry = &last_page.storage[&next_key];
+        assert_eq!(H256(keccak256(entry.key.as_bytes())), next_key);
+        assert_eq!(
+            H256::repeat_byte(entry.key.to_low_u64_be() as u8),
+            entry.value
+        );
+
+        assert!(node
+            .storage_range_at(H256::repeat_byte(0xab), 0, address, H256::zero(), 2)
+            .await
+            .is_err());
+    }
 }
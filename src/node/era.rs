@@ -0,0 +1,665 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bigdecimal::BigDecimal;
+use multivm::{
+    interface::{
+        ExecutionResult, TxExecutionMode, VmExecutionMode, VmExecutionResultAndLogs, VmInterface,
+    },
+    tracers::CallTracer,
+    vm_latest::{constants::ETH_CALL_GAS_LIMIT, HistoryDisabled, ToTracerPointer, Vm},
+};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use zksync_basic_types::{Address, H256, U256, U64};
+use zksync_types::{
+    api::{BlockNumber, DebugCall},
+    l2::L2Tx,
+    transaction_request::{CallRequest, TransactionRequest},
+    Bytes, PackedEthSignature, StorageLogQueryType,
+};
+use zksync_web3_decl::error::Web3Error;
+
+use crate::{
+    deps::storage_view::StorageView,
+    execution_limit::ExecutionLimitTracer,
+    fork::{ForkSource, ForkVerification},
+    metrics::{CacheStats, RpcMethodStats, METRICS},
+    namespaces::{EraNamespaceT, EthNamespaceT, RpcResult},
+    node::{
+        validate_bytecode, BytecodeValidation, EraNodeInfo, EstimateGasTrace, ExecutionCoverage,
+        FactoryDeps, FactoryDepsTarget, GasReport, HistoryFormat, InMemoryNode,
+        RejectedTransaction, SenderRecovery, StateDiff, TxFilterRule, MAX_TX_SIZE,
+    },
+    utils::{create_debug_output, into_jsrpc_error, IntoBoxedFuture},
+};
+
+/// Number of the storage slots and bytecodes checked by `era_verifyFork` by default.
+const DEFAULT_FORK_VERIFICATION_SAMPLE: usize = 100;
+
+/// Signed raw transaction decoded by `era_decodeRawTransaction`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedTransaction {
+    pub hash: H256,
+    /// The decoded fields. `from` is the sender recovered from the signature.
+    pub transaction: TransactionRequest,
+}
+
+/// Statistics of the VM execution of a transaction, as returned by `era_getVmStats`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VmStats {
+    pub cycles_used: u32,
+    pub gas_used: u32,
+    /// The gas used by the execution, without the gas spent on pubdata.
+    pub computational_gas_used: u32,
+    /// Number of the contracts decommitted, i.e. whose bytecode was loaded.
+    pub contracts_used: usize,
+    pub storage_reads: usize,
+    pub storage_writes: usize,
+    /// Number of the bytes of pubdata published to L1.
+    pub pubdata_published: u32,
+}
+
+impl VmStats {
+    fn new(result: &VmExecutionResultAndLogs) -> Self {
+        let storage_reads = result
+            .logs
+            .storage_logs
+            .iter()
+            .filter(|log| log.log_type == StorageLogQueryType::Read)
+            .count();
+        Self {
+            cycles_used: result.statistics.cycles_used,
+            gas_used: result.statistics.gas_used,
+            computational_gas_used: result.statistics.computational_gas_used,
+            contracts_used: result.statistics.contracts_used,
+            storage_reads,
+            storage_writes: result.logs.storage_logs.len() - storage_reads,
+            pubdata_published: result.statistics.pubdata_published,
+        }
+    }
+}
+
+/// Result of a single call simulated by `era_simulateBundle`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleCallResult {
+    pub success: bool,
+    /// The return data of a successful call.
+    pub output: Bytes,
+    /// The revert reason or the halt reason of a failed call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    pub gas_used: U256,
+    /// The call trace, missing if the call halted.
+    pub trace: Option<DebugCall>,
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EraNamespaceT
+    for InMemoryNode<S>
+{
+    fn set_erc20_balance(
+        &self,
+        token: Address,
+        account: Address,
+        amount: U256,
+        slot: Option<U256>,
+    ) -> RpcResult<U256> {
+        self.set_erc20_balance(token, account, amount, slot)
+            .map_err(|err| {
+                tracing::error!("failed setting erc20 balance: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn set_token_price(&self, token: Address, price_usd: BigDecimal) -> RpcResult<bool> {
+        self.set_token_price(token, price_usd)
+            .map(|_| true)
+            .map_err(|err| {
+                tracing::error!("failed setting token price: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn reorg(&self, depth: U64, transactions: Option<Vec<Bytes>>) -> RpcResult<Vec<H256>> {
+        let node = self.clone();
+        Box::pin(async move {
+            node.rollback_blocks(depth).map_err(|err| {
+                tracing::error!("failed rolling back blocks: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })?;
+
+            let mut hashes = vec![];
+            for tx in transactions.unwrap_or_default() {
+                hashes.push(node.send_raw_transaction(tx).await?);
+            }
+            Ok(hashes)
+        })
+    }
+
+    fn get_rpc_stats(&self) -> RpcResult<BTreeMap<String, RpcMethodStats>> {
+        Ok(METRICS.rpc_stats()).into_boxed_future()
+    }
+
+    fn trace_estimate_gas(&self, request: CallRequest) -> RpcResult<EstimateGasTrace> {
+        self.get_inner()
+            .read()
+            .map(|reader| reader.trace_estimate_gas_impl(request))
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn decode_raw_transaction(&self, tx_bytes: Bytes) -> RpcResult<DecodedTransaction> {
+        let chain_id = match self.get_inner().read() {
+            Ok(reader) => reader.fork_storage.chain_id,
+            Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future(),
+        };
+
+        TransactionRequest::from_bytes(&tx_bytes.0, chain_id)
+            .map(|(transaction, hash)| DecodedTransaction { hash, transaction })
+            .map_err(|err| into_jsrpc_error(Web3Error::SerializationError(err)))
+            .into_boxed_future()
+    }
+
+    fn simulate_bundle(
+        &self,
+        requests: Vec<CallRequest>,
+        block: Option<BlockNumber>,
+    ) -> RpcResult<Vec<BundleCallResult>> {
+        let inner = self.get_inner().clone();
+        Box::pin(async move {
+            if !matches!(block, None | Some(BlockNumber::Latest)) {
+                return Err(jsonrpc_core::Error::invalid_params(
+                    "bundle simulation only supported at `latest` block",
+                ));
+            }
+
+            let mut transactions = Vec::with_capacity(requests.len());
+            for request in requests {
+                let mut l2_tx = L2Tx::from_request(request.into(), MAX_TX_SIZE)
+                    .map_err(|err| into_jsrpc_error(Web3Error::SerializationError(err)))?;
+                // Protection against infinite loops, same as `eth_call`.
+                l2_tx.common_data.fee.gas_limit = ETH_CALL_GAS_LIMIT.into();
+                // We must inject *some* signature (otherwise bootloader code fails to generate hash).
+                if l2_tx.common_data.signature.is_empty() {
+                    l2_tx.common_data.signature =
+                        PackedEthSignature::default().serialize_packed().into();
+                }
+                transactions.push(l2_tx);
+            }
+
+            let inner = inner
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+            // all the calls are executed in the same VM, so they share the state changes, which are
+            // only kept in the storage view and never written to the node
+            let storage = StorageView::new(&inner.fork_storage).into_rc_ptr();
+            let bootloader_code = inner.system_contracts.contracts_for_l2_call();
+            let (batch_env, _) = inner.create_l1_batch_env(storage.clone());
+            let system_env =
+                inner.create_system_env(bootloader_code.clone(), TxExecutionMode::EthCall);
+            let mut vm: Vm<_, HistoryDisabled> = Vm::new(batch_env, system_env, storage);
+
+            let results = transactions
+                .into_iter()
+                .map(|l2_tx| {
+                    vm.push_transaction(l2_tx.clone().into());
+                    let call_tracer_result = Arc::new(OnceCell::default());
+                    let tracers = vec![
+                        CallTracer::new(call_tracer_result.clone()).into_tracer_pointer(),
+                        ExecutionLimitTracer::new(inner.execution_limits).into_tracer_pointer(),
+                    ];
+                    let result = vm.inspect(tracers.into(), VmExecutionMode::OneTx);
+                    let call_traces = Arc::try_unwrap(call_tracer_result)
+                        .unwrap()
+                        .take()
+                        .unwrap_or_default();
+
+                    let (output, revert_reason) = match &result.result {
+                        ExecutionResult::Success { output } => (output.clone().into(), None),
+                        ExecutionResult::Revert { output } => {
+                            (Default::default(), Some(output.to_user_friendly_string()))
+                        }
+                        ExecutionResult::Halt { reason } => {
+                            (Default::default(), Some(reason.to_string()))
+                        }
+                    };
+                    BundleCallResult {
+                        success: revert_reason.is_none(),
+                        output,
+                        revert_reason,
+                        gas_used: result.statistics.gas_used.into(),
+                        trace: create_debug_output(&l2_tx, &result, call_traces).ok(),
+                    }
+                })
+                .collect();
+
+            Ok(results)
+        })
+    }
+
+    fn seal_l1_batch(&self) -> RpcResult<U64> {
+        self.get_inner()
+            .write()
+            .map(|mut writer| {
+                let latest_batch = writer.latest_l1_batch();
+                let sealed_batch = writer
+                    .l1_batch_sealer
+                    .as_mut()
+                    .map_or(latest_batch, |sealer| sealer.seal());
+                tracing::info!("👷 Sealed L1 batch #{}", sealed_batch);
+                U64::from(sealed_batch)
+            })
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn get_cache_stats(&self) -> RpcResult<BTreeMap<String, CacheStats>> {
+        self.get_inner()
+            .read()
+            .map(|reader| reader.fork_storage.cache_stats())
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn clear_cache(&self, kind: Option<String>) -> RpcResult<bool> {
+        let fork_storage = match self.get_inner().read() {
+            Ok(reader) => reader.fork_storage.clone(),
+            Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future(),
+        };
+
+        if !fork_storage.clear_cache(kind.as_deref()) {
+            return Err(jsonrpc_core::Error::invalid_params(format!(
+                "unknown cache `{}`",
+                kind.unwrap_or_default()
+            )))
+            .into_boxed_future();
+        }
+        tracing::info!("🧹 Cleared cache {}", kind.as_deref().unwrap_or("(all)"));
+        Ok(true).into_boxed_future()
+    }
+
+    fn import_contract(
+        &self,
+        address: Address,
+        block: Option<BlockNumber>,
+        slots: Option<Vec<U256>>,
+    ) -> RpcResult<bool> {
+        self.import_contract(address, block, slots.unwrap_or_default())
+            .map(|_| true)
+            .map_err(|err| {
+                tracing::error!("failed importing contract: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+    fn diff_snapshots(&self, from_id: U64, to_id: U64) -> RpcResult<StateDiff> {
+        self.diff_snapshots(from_id, to_id)
+            .map_err(|err| {
+                tracing::error!("failed diffing snapshots: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn begin_context(&self) -> RpcResult<U64> {
+        self.begin_context()
+            .map_err(|err| {
+                tracing::error!("failed beginning context: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn end_context(&self) -> RpcResult<bool> {
+        self.end_context()
+            .map_err(|err| {
+                tracing::error!("failed ending context: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn warp_to_block(&self, number: U64) -> RpcResult<bool> {
+        self.warp_to_block(number)
+            .map(|_| true)
+            .map_err(|err| {
+                tracing::error!("failed warping to block {}: {:?}", number, err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn get_rejected_transaction(&self, hash: H256) -> RpcResult<Option<RejectedTransaction>> {
+        self.get_inner()
+            .read()
+            .map(|reader| reader.rejected_transactions.get(&hash).cloned())
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn set_tx_filter(&self, rules: Vec<TxFilterRule>) -> RpcResult<bool> {
+        self.set_tx_filter(rules)
+            .map(|_| true)
+            .map_err(|err| {
+                tracing::error!("failed setting transaction filter: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn get_vm_stats(&self, hash: H256) -> RpcResult<Option<VmStats>> {
+        self.get_inner()
+            .read()
+            .map(|reader| {
+                reader
+                    .tx_results
+                    .get(&hash)
+                    .map(|tx_result| VmStats::new(&tx_result.info.result))
+            })
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn get_execution_coverage(&self, hash: H256) -> RpcResult<Option<ExecutionCoverage>> {
+        self.get_inner()
+            .read()
+            .map(|reader| reader.execution_coverage.get(&hash).cloned())
+            .map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn verify_fork(&self, sample_size: Option<usize>) -> RpcResult<ForkVerification> {
+        let fork_storage = match self.get_inner().read() {
+            Ok(reader) => reader.fork_storage.clone(),
+            Err(_) => return Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future(),
+        };
+
+        fork_storage
+            .verify_cache(sample_size.unwrap_or(DEFAULT_FORK_VERIFICATION_SAMPLE))
+            .map(|verification| {
+                tracing::info!(
+                    "Verified {} storage slots and {} bytecodes cached from the fork: {} divergences",
+                    verification.checked_slots,
+                    verification.checked_bytecodes,
+                    verification.divergences.len()
+                );
+                verification
+            })
+            .map_err(|err| {
+                tracing::error!("failed verifying fork cache: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn validate_bytecode(&self, bytecode: Bytes) -> RpcResult<BytecodeValidation> {
+        Ok(validate_bytecode(&bytecode.0)).into_boxed_future()
+    }
+
+    fn recover_sender(&self, tx_bytes: Bytes) -> RpcResult<SenderRecovery> {
+        self.recover_sender(&tx_bytes.0)
+            .map_err(|err| jsonrpc_core::Error::invalid_params(err.to_string()))
+            .into_boxed_future()
+    }
+
+    fn export_history(&self, format: Option<HistoryFormat>) -> RpcResult<String> {
+        self.export_history(format.unwrap_or_default())
+            .map_err(|err| {
+                tracing::error!("failed exporting history: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn start_gas_report(&self, label: String) -> RpcResult<bool> {
+        self.start_gas_report(label)
+            .map(|_| true)
+            .map_err(|err| {
+                tracing::error!("failed starting gas report: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn finish_gas_report(&self, previous: Option<GasReport>) -> RpcResult<GasReport> {
+        self.finish_gas_report(previous)
+            .map_err(|err| jsonrpc_core::Error::invalid_params(err.to_string()))
+            .into_boxed_future()
+    }
+
+    fn get_factory_deps(
+        &self,
+        target: FactoryDepsTarget,
+        include_bytecodes: Option<bool>,
+    ) -> RpcResult<Option<FactoryDeps>> {
+        self.get_factory_deps(target, include_bytecodes.unwrap_or_default())
+            .map_err(|err| {
+                tracing::error!("failed getting factory deps: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn get_node_info(&self) -> RpcResult<EraNodeInfo> {
+        self.era_node_info()
+            .map_err(|err| {
+                tracing::error!("failed getting node info: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn import_account(&self, private_key: H256, fund: Option<bool>) -> RpcResult<Address> {
+        self.import_account(private_key, fund.unwrap_or_default())
+            .map_err(|err| {
+                tracing::error!("failed importing account: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+
+    #[tokio::test]
+    async fn test_decode_raw_transaction_fails_for_invalid_bytes() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let result = node
+            .decode_raw_transaction(Bytes::from(vec![0x02, 0xde, 0xad]))
+            .await;
+
+        let err = result.expect_err("decoding succeeded");
+        assert_eq!(jsonrpc_core::ErrorCode::ServerError(3), err.code);
+    }
+
+    #[tokio::test]
+    async fn test_seal_l1_batch_groups_mined_blocks() {
+        use crate::{
+            namespaces::{ConfigurationApiNamespaceT, ZksNamespaceT},
+            testing,
+        };
+        use zksync_basic_types::L1BatchNumber;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        node.config_set_l1_batch_seal_interval(Some(0))
+            .expect("failed setting interval");
+
+        testing::apply_tx(&node, H256::repeat_byte(0x1));
+        testing::apply_tx(&node, H256::repeat_byte(0x2));
+        let sealed_batch = node.seal_l1_batch().await.expect("failed sealing batch");
+        testing::apply_tx(&node, H256::repeat_byte(0x3));
+
+        assert_eq!(U64::from(1), sealed_batch);
+        assert_eq!(
+            Some((U64::from(1), U64::from(4))),
+            node.get_miniblock_range(L1BatchNumber(1)).await.unwrap()
+        );
+        assert_eq!(
+            Some((U64::from(5), U64::from(6))),
+            node.get_miniblock_range(L1BatchNumber(2)).await.unwrap()
+        );
+        assert_eq!(U64::from(2), node.get_l1_batch_number().await.unwrap());
+        let inner = node.get_inner().read().unwrap();
+        let receipt = &inner.tx_results[&H256::repeat_byte(0x2)].receipt;
+        assert_eq!(Some(U64::from(1)), receipt.l1_batch_number);
+    }
+
+    #[tokio::test]
+    async fn test_get_rejected_transaction_returns_rejection_reason() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let rejected_tx = crate::testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x1))
+            .build();
+        let accepted_tx = crate::testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x2))
+            .build();
+        node.set_rich_account(accepted_tx.common_data.initiator_address);
+
+        let err = node
+            .submit_l2_tx(rejected_tx.clone())
+            .expect_err("transaction without funds was accepted");
+        node.submit_l2_tx(accepted_tx.clone())
+            .expect("failed submitting tx");
+
+        let rejected = node
+            .get_rejected_transaction(rejected_tx.hash())
+            .await
+            .unwrap()
+            .expect("rejection not recorded");
+        assert_eq!(err, rejected.reason);
+        assert_eq!(rejected_tx.initiator_account(), rejected.from);
+        assert_eq!(
+            None,
+            node.get_rejected_transaction(accepted_tx.hash())
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_vm_stats_of_executed_transaction() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        crate::testing::apply_tx(&node, H256::repeat_byte(0x1));
+
+        let stats = node
+            .get_vm_stats(H256::repeat_byte(0x1))
+            .await
+            .unwrap()
+            .expect("missing stats");
+        assert!(stats.cycles_used > 0);
+        assert!(stats.computational_gas_used <= stats.gas_used);
+        assert!(stats.contracts_used > 0);
+        assert!(stats.storage_reads > 0);
+        assert!(stats.storage_writes > 0);
+        assert_eq!(
+            None,
+            node.get_vm_stats(H256::repeat_byte(0x2)).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clear_cache_empties_fork_storage_caches() {
+        use zksync_types::{AccountTreeId, StorageKey};
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        {
+            let inner = node.get_inner().read().unwrap();
+            let mut fork_storage = inner.fork_storage.inner.write().unwrap();
+            fork_storage.value_read_cache.insert(
+                StorageKey::new(AccountTreeId::default(), H256::zero()),
+                H256::repeat_byte(0x1),
+            );
+            fork_storage
+                .factory_dep_cache
+                .insert(H256::repeat_byte(0x2), None);
+        }
+
+        let stats = node.get_cache_stats().await.expect("failed getting stats");
+        assert_eq!(1, stats["storage_values"].size);
+        assert_eq!(1, stats["factory_deps"].size);
+        assert!(stats.contains_key("compressed_bytecodes"));
+        assert_eq!(0, stats["blocks_full"].size);
+
+        node.clear_cache(Some(String::from("storage_values")))
+            .await
+            .expect("failed clearing cache");
+        let stats = node.get_cache_stats().await.expect("failed getting stats");
+        assert_eq!(0, stats["storage_values"].size);
+        assert_eq!(1, stats["factory_deps"].size);
+
+        node.clear_cache(None)
+            .await
+            .expect("failed clearing caches");
+        let stats = node.get_cache_stats().await.expect("failed getting stats");
+        assert_eq!(0, stats["factory_deps"].size);
+
+        let err = node
+            .clear_cache(Some(String::from("unknown")))
+            .await
+            .expect_err("cleared unknown cache");
+        assert_eq!(jsonrpc_core::ErrorCode::InvalidParams, err.code);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_bundle_shares_state_without_modifying_node() {
+        use ethers::abi::{short_signature, ParamType};
+        use zksync_types::{transaction_request::CallRequestBuilder, L2_ETH_TOKEN_ADDRESS};
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let from = Address::repeat_byte(0x1);
+        let to = Address::repeat_byte(0x2);
+        node.set_rich_account(from);
+
+        let mut balance_of = short_signature("balanceOf", &[ParamType::Uint(256)]).to_vec();
+        balance_of.extend_from_slice(H256::from(to).as_bytes());
+        let requests = vec![
+            CallRequestBuilder::default()
+                .from(from)
+                .to(to)
+                .value(U256::from(100))
+                .build(),
+            CallRequestBuilder::default()
+                .to(L2_ETH_TOKEN_ADDRESS)
+                .data(balance_of.into())
+                .build(),
+        ];
+
+        let results = node
+            .simulate_bundle(requests, None)
+            .await
+            .expect("failed simulating bundle");
+
+        assert_eq!(2, results.len());
+        assert!(results.iter().all(|result| result.success));
+        assert!(results[0].trace.is_some());
+        assert_eq!(U256::from(100), U256::from_big_endian(&results[1].output.0));
+        assert_eq!(U256::zero(), node.get_balance(to, None).await.unwrap());
+    }
+}
@@ -0,0 +1,68 @@
+use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+use zksync_web3_decl::error::Web3Error;
+
+use crate::{
+    fork::ForkSource,
+    logging_middleware::Meta,
+    namespaces::{EraPubSubNamespaceT, Result},
+    node::InMemoryNode,
+    subscriptions::NodeEvent,
+    utils::into_jsrpc_error,
+};
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EraPubSubNamespaceT
+    for InMemoryNode<S>
+{
+    type Metadata = Meta;
+
+    fn subscribe(&self, _meta: Self::Metadata, subscriber: Subscriber<NodeEvent>) {
+        let inner = self.get_inner();
+        let mut writer = match inner.write() {
+            Ok(writer) => writer,
+            Err(_) => {
+                let _ = subscriber.reject(into_jsrpc_error(Web3Error::InternalError));
+                return;
+            }
+        };
+
+        let id = writer.subscriptions.next_id();
+        match subscriber.assign_id(id.clone()) {
+            Ok(sink) => writer.subscriptions.add_node_events(id, sink),
+            Err(_) => tracing::debug!("client disconnected before subscribing"),
+        }
+    }
+
+    fn unsubscribe(&self, _meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map(|mut writer| writer.subscriptions.remove_node_events(&id))
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+    use zksync_basic_types::H160;
+
+    #[tokio::test]
+    async fn test_node_events_are_notified() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let (subscriber, _id, mut receiver) = Subscriber::new_test("era_subscription");
+        EraPubSubNamespaceT::subscribe(&node, Meta::default(), subscriber);
+
+        let snapshot_id = node.snapshot().expect("failed taking snapshot");
+        node.impersonate_account(H160::repeat_byte(0x1))
+            .expect("failed impersonating account");
+        node.revert_snapshot(snapshot_id)
+            .expect("failed reverting snapshot");
+
+        let notifications =
+            std::iter::from_fn(|| receiver.try_next().ok().flatten()).collect::<Vec<_>>();
+        assert_eq!(3, notifications.len());
+        assert!(notifications[0].contains(r#""type":"snapshotTaken""#));
+        assert!(notifications[1].contains(r#""type":"impersonationToggled""#));
+        assert!(notifications[2].contains(r#""type":"snapshotReverted""#));
+    }
+}
@@ -9,12 +9,11 @@ use zksync_basic_types::{web3, AccountTreeId, Address, Bytes, H160, H256, U256,
 use zksync_state::ReadStorage;
 use zksync_types::{
     api::{Block, BlockIdVariant, BlockNumber, TransactionVariant},
-    fee::Fee,
     get_code_key, get_nonce_key,
     l2::L2Tx,
-    transaction_request::TransactionRequest,
+    transaction_request::{Eip712Meta, TransactionRequest},
     utils::storage_key_for_standard_token_balance,
-    PackedEthSignature, StorageKey, L2_ETH_TOKEN_ADDRESS,
+    Eip712Domain, PackedEthSignature, StorageKey, L2_ETH_TOKEN_ADDRESS,
 };
 use zksync_utils::{h256_to_u256, u256_to_h256};
 use zksync_web3_decl::{
@@ -26,8 +25,13 @@ use crate::{
     filters::{FilterType, LogFilter},
     fork::ForkSource,
     namespaces::{EthNamespaceT, EthTestNodeNamespaceT, RpcResult},
-    node::{InMemoryNode, TransactionResult, L2_GAS_PRICE, MAX_TX_SIZE, PROTOCOL_VERSION},
-    utils::{self, h256_to_u64, into_jsrpc_error, not_implemented, IntoBoxedFuture},
+    node::{
+        decode_raw_transaction, InMemoryNode, TransactionResult, L2_GAS_PRICE, MAX_TX_SIZE,
+        PROTOCOL_VERSION,
+    },
+    utils::{
+        self, h256_to_u64, into_jsrpc_error, not_implemented, ExecutionErrorData, IntoBoxedFuture,
+    },
 };
 
 impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespaceT
@@ -46,7 +50,8 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
     /// # Arguments
     ///
     /// * `req` - The call request containing the function name and arguments.
-    /// * `_block` - The block ID variant (unused).
+    /// * `block` - The block ID variant. Blocks preceding the fork are called on the forked network, the
+    ///   other ones use the latest state.
     ///
     /// # Returns
     ///
@@ -54,32 +59,58 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
     fn call(
         &self,
         req: zksync_types::transaction_request::CallRequest,
-        _block: Option<BlockIdVariant>,
+        block: Option<BlockIdVariant>,
     ) -> RpcResult<zksync_basic_types::Bytes> {
+        match self.historical_fork_source(block) {
+            Ok(Some(fork_source)) => {
+                return fork_source
+                    .call(req, block)
+                    .map_err(|err| {
+                        tracing::error!("failed calling the forked network: {:?}", err);
+                        into_jsrpc_error(Web3Error::SubmitTransactionError(
+                            err.root_cause().to_string(),
+                            vec![],
+                        ))
+                    })
+                    .into_boxed_future()
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::error!("{}", err);
+                return Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future();
+            }
+        }
+
+        let contract = req.to;
         match L2Tx::from_request(req.into(), MAX_TX_SIZE) {
             Ok(mut tx) => {
                 tx.common_data.fee.gas_limit = ETH_CALL_GAS_LIMIT.into();
-                let result = self.run_l2_call(tx);
-
-                match result {
+                self.run_on_worker(move |node| match node.run_l2_call(tx) {
                     Ok(execution_result) => match execution_result {
-                        ExecutionResult::Success { output } => {
-                            Ok(output.into()).into_boxed_future()
-                        }
+                        ExecutionResult::Success { output } => Ok(output.into()),
                         ExecutionResult::Revert { output } => {
                             let message = output.to_user_friendly_string();
+                            let location = node
+                                .get_inner()
+                                .read()
+                                .ok()
+                                .and_then(|reader| {
+                                    reader
+                                        .verified_contracts
+                                        .revert_location(contract, &output.encoded_data())
+                                })
+                                .map(|location| format!(" ({})", location))
+                                .unwrap_or_default();
                             let pretty_message = format!(
-                                "execution reverted{}{}",
+                                "execution reverted{}{}{}",
                                 if message.is_empty() { "" } else { ": " },
-                                message
+                                message,
+                                location
                             );
 
                             tracing::info!("{}", pretty_message.on_red());
-                            Err(into_jsrpc_error(Web3Error::SubmitTransactionError(
-                                pretty_message,
-                                output.encoded_data(),
-                            )))
-                            .into_boxed_future()
+                            Err(ExecutionErrorData::revert(pretty_message, &output)
+                                .into_jsrpc_error())
                         }
                         ExecutionResult::Halt { reason } => {
                             let message = reason.to_string();
@@ -90,20 +121,17 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
                             );
 
                             tracing::info!("{}", pretty_message.on_red());
-                            Err(into_jsrpc_error(Web3Error::SubmitTransactionError(
-                                pretty_message,
-                                vec![],
-                            )))
-                            .into_boxed_future()
+                            Err(ExecutionErrorData::halt(pretty_message, &reason)
+                                .into_jsrpc_error())
                         }
                     },
                     Err(e) => {
                         let error = Web3Error::InvalidTransactionData(
                             zksync_types::ethabi::Error::InvalidName(e),
                         );
-                        Err(into_jsrpc_error(error)).into_boxed_future()
+                        Err(into_jsrpc_error(error))
                     }
-                }
+                })
             }
             Err(e) => {
                 let error = Web3Error::SerializationError(e);
@@ -131,9 +159,9 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
                 &address,
             );
 
-            match inner.write() {
-                Ok(mut inner_guard) => {
-                    let balance = inner_guard.fork_storage.read_value(&balance_key);
+            match inner.read() {
+                Ok(inner_guard) => {
+                    let balance = (&inner_guard.fork_storage).read_value(&balance_key);
                     Ok(h256_to_u256(balance))
                 }
                 Err(_) => {
@@ -248,12 +276,11 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
         Box::pin(async move {
             let code_key = get_code_key(&address);
 
-            match inner.write() {
-                Ok(mut guard) => {
-                    let code_hash = guard.fork_storage.read_value(&code_key);
+            match inner.read() {
+                Ok(guard) => {
+                    let code_hash = (&guard.fork_storage).read_value(&code_key);
 
-                    let code = guard
-                        .fork_storage
+                    let code = (&guard.fork_storage)
                         .load_factory_dep(code_hash)
                         .unwrap_or_default();
 
@@ -277,16 +304,19 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
     fn get_transaction_count(
         &self,
         address: zksync_basic_types::Address,
-        _block: Option<BlockIdVariant>,
+        block: Option<BlockIdVariant>,
     ) -> RpcResult<U256> {
         let inner = self.get_inner().clone();
 
         Box::pin(async move {
             let nonce_key = get_nonce_key(&address);
 
-            match inner.write() {
-                Ok(mut guard) => {
-                    let result = guard.fork_storage.read_value(&nonce_key);
+            match inner.read() {
+                Ok(guard) => {
+                    if let Some(BlockIdVariant::BlockNumber(BlockNumber::Pending)) = block {
+                        return Ok(U256::from(guard.pending_nonce(address).0));
+                    }
+                    let result = (&guard.fork_storage).read_value(&nonce_key);
                     Ok(h256_to_u64(result).into())
                 }
                 Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)),
@@ -343,15 +373,16 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
             }
         };
 
-        let (tx_req, hash) = match TransactionRequest::from_bytes(&tx_bytes.0, chain_id) {
-            Ok(result) => result,
+        let envelope = match decode_raw_transaction(&tx_bytes.0, chain_id) {
+            Ok(envelope) => envelope,
             Err(e) => {
                 return futures::future::err(into_jsrpc_error(Web3Error::SerializationError(e)))
                     .boxed()
             }
         };
+        let hash = envelope.hash;
 
-        let mut l2_tx: L2Tx = match L2Tx::from_request(tx_req, MAX_TX_SIZE) {
+        let mut l2_tx: L2Tx = match L2Tx::from_request(envelope.request, MAX_TX_SIZE) {
             Ok(tx) => tx,
             Err(e) => {
                 return futures::future::err(into_jsrpc_error(Web3Error::SerializationError(e)))
@@ -366,10 +397,27 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
             )))
             .boxed();
         };
+        if envelope.access_list_ignored {
+            tracing::info!(
+                "Ignoring the access list of {:?}, its signature is verified by the node",
+                hash
+            );
+            // inserted before the submission, as an automined transaction is executed within it; the hash is
+            // removed once the transaction is sealed or rejected
+            match self.get_inner().write() {
+                Ok(mut writer) => writer.node_verified_txs.insert(hash),
+                Err(_) => {
+                    return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+                }
+            };
+        }
 
-        match self.run_l2_tx(l2_tx.clone(), TxExecutionMode::VerifyExecute) {
+        match self.submit_filtered_l2_tx(l2_tx.clone()) {
             Ok(_) => Ok(hash).into_boxed_future(),
             Err(e) => {
+                if let Ok(mut writer) = self.get_inner().write() {
+                    writer.node_verified_txs.remove(&hash);
+                }
                 let error_message = format!("Execution error: {}", e);
                 futures::future::err(into_jsrpc_error(Web3Error::SubmitTransactionError(
                     error_message,
@@ -477,7 +525,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
 
             let maybe_result = {
                 // try retrieving transaction from memory, and if unavailable subsequently from the fork
-                reader.tx_results.get(&hash).and_then(|TransactionResult { info, .. }| {
+                reader.tx_results.get(&hash).and_then(|TransactionResult { info, receipt, .. }| {
                     let input_data = info.tx.common_data.input.clone().or(None)?;
                     let chain_id = info.tx.common_data.extract_chain_id().or(None)?;
                     Some(zksync_types::api::Transaction {
@@ -514,7 +562,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
                         ),
                         chain_id: U256::from(chain_id),
                         l1_batch_number: Some(U64::from(info.batch_number as u64)),
-                        l1_batch_tx_index: None,
+                        l1_batch_tx_index: receipt.l1_batch_tx_index,
                     })
                 }).or_else(|| {
                     reader
@@ -554,7 +602,8 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
     /// # Arguments
     ///
     /// * `req` - A `CallRequest` struct representing the call request to estimate gas for.
-    /// * `_block` - An optional `BlockNumber` struct representing the block number to estimate gas for.
+    /// * `block` - An optional `BlockNumber` struct representing the block number to estimate gas for.
+    ///   Blocks preceding the fork are estimated on the forked network, the other ones use the latest state.
     ///
     /// # Returns
     ///
@@ -562,21 +611,36 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
     fn estimate_gas(
         &self,
         req: zksync_types::transaction_request::CallRequest,
-        _block: Option<BlockNumber>,
+        block: Option<BlockNumber>,
     ) -> RpcResult<U256> {
-        let inner = self.get_inner().clone();
-        let reader = match inner.read() {
-            Ok(r) => r,
-            Err(_) => {
-                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+        match self.historical_fork_source(block.map(BlockIdVariant::BlockNumber)) {
+            Ok(Some(fork_source)) => {
+                return fork_source
+                    .estimate_gas(req, block)
+                    .map_err(|err| {
+                        tracing::error!("failed estimating gas on the forked network: {:?}", err);
+                        into_jsrpc_error(Web3Error::SubmitTransactionError(
+                            err.root_cause().to_string(),
+                            vec![],
+                        ))
+                    })
+                    .into_boxed_future()
+            }
+            Ok(None) => {}
+            Err(err) => {
+                tracing::error!("{}", err);
+                return Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future();
             }
-        };
-
-        let result: jsonrpc_core::Result<Fee> = reader.estimate_gas_impl(req);
-        match result {
-            Ok(fee) => Ok(fee.gas_limit).into_boxed_future(),
-            Err(err) => return futures::future::err(err).boxed(),
         }
+
+        self.run_on_worker(move |node| {
+            let reader = node
+                .get_inner()
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
+
+            reader.estimate_gas_impl(req).map(|fee| fee.gas_limit)
+        })
     }
 
     /// Returns the current gas price in U256 format.
@@ -870,10 +934,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
                     })
             };
 
-            match maybe_result {
-                Some(value) => Ok(Some(value)),
-                None => Err(into_jsrpc_error(Web3Error::NoBlock)),
-            }
+            Ok(maybe_result)
         })
     }
 
@@ -909,10 +970,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
                         })
                 });
 
-            match maybe_result {
-                Some(value) => Ok(Some(value)),
-                None => Err(into_jsrpc_error(Web3Error::NoBlock)),
-            }
+            Ok(maybe_result)
         })
     }
 
@@ -936,7 +994,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
         let inner = self.get_inner().clone();
 
         Box::pin(async move {
-            let mut writer = match inner.write() {
+            let reader = match inner.read() {
                 Ok(r) => r,
                 Err(_) => {
                     return Err(into_jsrpc_error(Web3Error::InternalError));
@@ -949,13 +1007,13 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
                 .map(|block| match block {
                     BlockIdVariant::BlockNumber(block_number) => Ok(utils::to_real_block_number(
                         block_number,
-                        U64::from(writer.current_miniblock),
+                        U64::from(reader.current_miniblock),
                     )),
                     BlockIdVariant::BlockNumberObject(o) => Ok(utils::to_real_block_number(
                         o.block_number,
-                        U64::from(writer.current_miniblock),
+                        U64::from(reader.current_miniblock),
                     )),
-                    BlockIdVariant::BlockHashObject(o) => writer
+                    BlockIdVariant::BlockHashObject(o) => reader
                         .blocks
                         .get(&o.block_hash)
                         .map(|block| block.number)
@@ -967,26 +1025,26 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
                             into_jsrpc_error(Web3Error::InternalError)
                         }),
                 })
-                .unwrap_or_else(|| Ok(U64::from(writer.current_miniblock)))?;
+                .unwrap_or_else(|| Ok(U64::from(reader.current_miniblock)))?;
 
-            if block_number.as_u64() == writer.current_miniblock {
-                Ok(H256(writer.fork_storage.read_value(&storage_key).0))
-            } else if writer.block_hashes.contains_key(&block_number.as_u64()) {
-                let value = writer
+            if block_number.as_u64() == reader.current_miniblock {
+                Ok(H256((&reader.fork_storage).read_value(&storage_key).0))
+            } else if reader.block_hashes.contains_key(&block_number.as_u64()) {
+                let value = reader
                     .block_hashes
                     .get(&block_number.as_u64())
-                    .and_then(|block_hash| writer.previous_states.get(block_hash))
+                    .and_then(|block_hash| reader.previous_states.get(block_hash))
                     .and_then(|state| state.get(&storage_key))
                     .cloned()
                     .unwrap_or_default();
 
                 if value.is_zero() {
-                    Ok(H256(writer.fork_storage.read_value(&storage_key).0))
+                    Ok(H256((&reader.fork_storage).read_value(&storage_key).0))
                 } else {
                     Ok(value)
                 }
             } else {
-                writer
+                reader
                     .fork_storage
                     .inner
                     .read()
@@ -1154,7 +1212,8 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
     }
     /// Returns a list of available accounts.
     ///
-    /// This function fetches the accounts from the inner state, and returns them as a list of addresses (`H160`).
+    /// This function fetches the rich accounts and the imported signers from the inner state, and returns them as a
+    /// list of addresses (`H160`).
     ///
     /// # Errors
     ///
@@ -1172,12 +1231,21 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
             }
         };
 
-        let accounts: Vec<H160> = reader.rich_accounts.clone().into_iter().collect();
+        let accounts: Vec<H160> = reader
+            .rich_accounts
+            .iter()
+            .chain(reader.signers.keys())
+            .copied()
+            .unique()
+            .collect();
         futures::future::ok(accounts).boxed()
     }
 
     fn coinbase(&self) -> RpcResult<zksync_basic_types::Address> {
-        not_implemented("eth_coinbase")
+        match self.get_inner().read() {
+            Ok(inner) => Ok(inner.fee_account).into_boxed_future(),
+            Err(_) => Err(into_jsrpc_error(Web3Error::InternalError)).into_boxed_future(),
+        }
     }
 
     fn compilers(&self) -> RpcResult<Vec<String>> {
@@ -1188,15 +1256,61 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
         not_implemented("eth_hashrate")
     }
 
-    fn get_uncle_count_by_block_hash(
+    /// Returns an uncle of a block by block hash and uncle index position. The blocks have no uncles.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that always resolves to `None`.
+    fn get_uncle_by_block_hash_and_index(
         &self,
         _hash: zksync_basic_types::H256,
+        _index: web3::types::Index,
+    ) -> RpcResult<Option<Block<TransactionVariant>>> {
+        Ok(None).into_boxed_future()
+    }
+
+    /// Returns an uncle of a block by block number and uncle index position. The blocks have no uncles.
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that always resolves to `None`.
+    fn get_uncle_by_block_number_and_index(
+        &self,
+        _number: BlockNumber,
+        _index: web3::types::Index,
+    ) -> RpcResult<Option<Block<TransactionVariant>>> {
+        Ok(None).into_boxed_future()
+    }
+
+    /// Returns the number of uncles of a block by block hash, which is always zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash`: Hash of the block
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to zero, or `None` if the block is not found.
+    fn get_uncle_count_by_block_hash(
+        &self,
+        hash: zksync_basic_types::H256,
     ) -> RpcResult<Option<U256>> {
-        not_implemented("eth_getUncleCountByBlockHash")
+        let block = self.get_block_by_hash(hash, false);
+        Box::pin(async move { Ok(block.await?.map(|_| U256::zero())) })
     }
 
-    fn get_uncle_count_by_block_number(&self, _number: BlockNumber) -> RpcResult<Option<U256>> {
-        not_implemented("eth_getUncleCountByBlockNumber")
+    /// Returns the number of uncles of a block by block number, which is always zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `number`: A block number, or the string "earliest", "latest" or "pending".
+    ///
+    /// # Returns
+    ///
+    /// A `BoxFuture` containing a `jsonrpc_core::Result` that resolves to zero, or `None` if the block is not found.
+    fn get_uncle_count_by_block_number(&self, number: BlockNumber) -> RpcResult<Option<U256>> {
+        let block = self.get_block_by_number(number, false);
+        Box::pin(async move { Ok(block.await?.map(|_| U256::zero())) })
     }
 
     fn mining(&self) -> RpcResult<bool> {
@@ -1263,7 +1377,9 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthNamespa
 impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthTestNodeNamespaceT
     for InMemoryNode<S>
 {
-    /// Sends a transaction to the L2 network. Can be used for the impersonated account.
+    /// Sends a transaction to the L2 network. Can be used for the impersonated accounts, and for the accounts
+    /// imported with `--import-key` or `era_importAccount`, whose transactions are signed by the node as EIP-712
+    /// transactions, with their missing fees estimated.
     ///
     /// # Arguments
     ///
@@ -1274,10 +1390,36 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthTestNod
     /// A future that resolves to the hash of the transaction if successful, or an error if the transaction is invalid or execution fails.
     fn send_transaction(
         &self,
-        tx: zksync_types::transaction_request::CallRequest,
+        mut tx: zksync_types::transaction_request::CallRequest,
     ) -> jsonrpc_core::BoxFuture<jsonrpc_core::Result<zksync_basic_types::H256>> {
-        let chain_id = match self.get_inner().read() {
-            Ok(reader) => reader.fork_storage.chain_id,
+        let (chain_id, signer) = match self.get_inner().read() {
+            Ok(reader) => {
+                // a missing nonce follows the pending and queued transactions of the sender
+                if tx.nonce.is_none() {
+                    tx.nonce = tx.from.map(|from| U256::from(reader.pending_nonce(from).0));
+                }
+                let signer = tx.from.and_then(|from| reader.signers.get(&from).copied());
+                let missing_fee = tx.gas.is_none()
+                    || (tx.gas_price.is_none() && tx.max_fee_per_gas.is_none())
+                    || tx.eip712_meta.is_none();
+                if signer.is_some() && missing_fee {
+                    let fee = match reader.estimate_gas_impl(tx.clone()) {
+                        Ok(fee) => fee,
+                        Err(err) => return futures::future::err(err).boxed(),
+                    };
+                    tx.gas.get_or_insert(fee.gas_limit);
+                    if tx.gas_price.is_none() {
+                        tx.max_fee_per_gas.get_or_insert(fee.max_fee_per_gas);
+                    }
+                    tx.eip712_meta.get_or_insert_with(|| Eip712Meta {
+                        gas_per_pubdata: fee.gas_per_pubdata_limit,
+                        factory_deps: None,
+                        custom_signature: None,
+                        paymaster_params: None,
+                    });
+                }
+                (reader.fork_storage.chain_id, signer)
+            }
             Err(_) => {
                 return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
             }
@@ -1299,6 +1441,25 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthTestNod
                 tx_req.transaction_type = Some(zksync_types::EIP_1559_TX_TYPE.into());
             }
         }
+        // the transactions of the local signers are signed and sent like the raw transactions
+        if let Some(private_key) = signer {
+            tx_req.transaction_type = Some(zksync_types::EIP_712_TX_TYPE.into());
+            tx_req.chain_id = Some(chain_id.as_u64());
+            let digest = PackedEthSignature::typed_data_to_signed_bytes(
+                &Eip712Domain::new(chain_id),
+                &tx_req,
+            );
+            return match PackedEthSignature::sign_raw(&private_key, &digest) {
+                Ok(signature) => {
+                    self.send_raw_transaction(Bytes(tx_req.get_signed_bytes(&signature, chain_id)))
+                }
+                Err(err) => {
+                    tracing::error!("failed signing transaction: {:?}", err);
+                    futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed()
+                }
+            };
+        }
+
         // Needed to calculate hash
         tx_req.r = Some(U256::default());
         tx_req.s = Some(U256::default());
@@ -1347,7 +1508,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthTestNod
             }
         }
 
-        match self.run_l2_tx(l2_tx.clone(), TxExecutionMode::VerifyExecute) {
+        match self.submit_filtered_l2_tx(l2_tx.clone()) {
             Ok(_) => Ok(l2_tx.hash()).into_boxed_future(),
             Err(e) => {
                 let error_message = format!("Execution error: {}", e);
@@ -1359,6 +1520,15 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthTestNod
             }
         }
     }
+
+    fn sign(&self, address: Address, message: Bytes) -> RpcResult<Bytes> {
+        self.sign_message(address, &message.0)
+            .map_err(|err| {
+                tracing::error!("failed signing message: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
 }
 
 #[cfg(test)]
@@ -1384,6 +1554,82 @@ mod tests {
 
     use super::*;
 
+    #[tokio::test]
+    async fn test_send_transaction_assigns_pending_nonce() {
+        use zksync_types::transaction_request::CallRequestBuilder;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let from = H160::repeat_byte(0x1);
+        node.set_rich_account(from);
+        node.impersonate_account(from)
+            .expect("failed impersonating account");
+        node.set_automine(false).expect("failed disabling automine");
+
+        for _ in 0..2 {
+            node.send_transaction(
+                CallRequestBuilder::default()
+                    .from(from)
+                    .to(H160::repeat_byte(0x2))
+                    .gas(U256::from(1_000_000))
+                    .gas_price(U256::from(250_000_000))
+                    .build(),
+            )
+            .await
+            .expect("failed sending transaction");
+        }
+
+        let nonces: Vec<Nonce> = node
+            .get_inner()
+            .read()
+            .unwrap()
+            .pending_transactions
+            .iter()
+            .map(|tx| tx.nonce())
+            .collect();
+        assert_eq!(vec![Nonce(0), Nonce(1)], nonces);
+        let pending = Some(BlockIdVariant::BlockNumber(BlockNumber::Pending));
+        assert_eq!(
+            U256::from(2),
+            node.get_transaction_count(from, pending).await.unwrap()
+        );
+        assert_eq!(
+            U256::zero(),
+            node.get_transaction_count(from, None).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_transaction_signs_for_imported_account() {
+        use zksync_types::transaction_request::CallRequestBuilder;
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let from = node
+            .import_account(H256::repeat_byte(0xef), true)
+            .expect("failed importing account");
+        let to = H160::repeat_byte(0x2);
+
+        let hash = node
+            .send_transaction(
+                CallRequestBuilder::default()
+                    .from(from)
+                    .to(to)
+                    .value(U256::from(100))
+                    .build(),
+            )
+            .await
+            .expect("failed sending transaction");
+
+        let receipt = node
+            .get_transaction_receipt(hash)
+            .await
+            .expect("failed getting receipt")
+            .expect("missing receipt");
+        assert_eq!(from, receipt.from);
+        assert_eq!(U64::from(1), receipt.status);
+        assert_eq!(U256::from(100), node.get_balance(to, None).await.unwrap());
+        assert!(node.accounts().await.unwrap().contains(&from));
+    }
+
     #[tokio::test]
     async fn test_eth_syncing() {
         let node = InMemoryNode::<HttpForkSource>::default();
@@ -1867,6 +2113,23 @@ mod tests {
         assert_eq!(U256::from(1), actual_transaction_count);
     }
 
+    #[tokio::test]
+    async fn test_get_block_transaction_count_returns_none_for_unknown_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let by_hash = node
+            .get_block_transaction_count_by_hash(H256::repeat_byte(0x01))
+            .await
+            .expect("failed fetching transaction count");
+        let by_number = node
+            .get_block_transaction_count_by_number(BlockNumber::Number(U64::from(42)))
+            .await
+            .expect("failed fetching transaction count");
+
+        assert!(by_hash.is_none());
+        assert!(by_number.is_none());
+    }
+
     #[tokio::test]
     async fn test_get_block_transaction_count_by_number_uses_fork_source() {
         let mock_server = MockServer::run_with_config(ForkBlockConfig {
@@ -2263,6 +2526,86 @@ mod tests {
         assert_eq!(input_storage_value, actual_value);
     }
 
+    #[tokio::test]
+    async fn test_call_uses_fork_for_historical_block() {
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            transaction_count: 0,
+            hash: H256::repeat_byte(0xab),
+        });
+        let request = zksync_types::transaction_request::CallRequestBuilder::default()
+            .to(H160::repeat_byte(0x1))
+            .data(vec![0x12, 0x34].into())
+            .build();
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_call",
+                "params": [request, { "blockNumber": "0x2" }],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": "0xabcd",
+            }),
+        );
+
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            None,
+            Default::default(),
+        );
+
+        let result = node
+            .call(
+                request,
+                Some(BlockIdVariant::BlockNumberObject(BlockNumberObject {
+                    block_number: BlockNumber::Number(U64::from(2)),
+                })),
+            )
+            .await
+            .expect("failed calling");
+        assert_eq!(Bytes::from(vec![0xab, 0xcd]), result);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_gas_uses_fork_for_historical_block() {
+        let mock_server = MockServer::run_with_config(ForkBlockConfig {
+            number: 10,
+            transaction_count: 0,
+            hash: H256::repeat_byte(0xab),
+        });
+        let request = zksync_types::transaction_request::CallRequestBuilder::default()
+            .to(H160::repeat_byte(0x1))
+            .build();
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_estimateGas",
+                "params": [request, "earliest"],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": "0x5208",
+            }),
+        );
+
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            None,
+            Default::default(),
+        );
+
+        let gas = node
+            .estimate_gas(request, Some(BlockNumber::Earliest))
+            .await
+            .expect("failed estimating gas");
+        assert_eq!(U256::from(21000), gas);
+    }
+
     #[tokio::test]
     async fn test_get_storage_uses_archived_storage_to_get_value_for_missing_key() {
         let input_address = H160::repeat_byte(0x1);
@@ -2666,6 +3009,17 @@ mod tests {
         inner.current_miniblock = 1;
         inner.current_miniblock_hash = H256::repeat_byte(0x1);
         inner.current_timestamp = 1;
+        inner.pending_transactions.push(
+            testing::TransactionBuilder::new()
+                .set_hash(H256::repeat_byte(0x1))
+                .build(),
+        );
+        inner.queued_transactions.insert(
+            (H160::repeat_byte(0x1), Nonce(1)),
+            testing::TransactionBuilder::new()
+                .set_hash(H256::repeat_byte(0x1))
+                .build(),
+        );
         inner
             .filters
             .add_block_filter()
@@ -2684,9 +3038,13 @@ mod tests {
         let expected_snapshot = Snapshot {
             current_timestamp: inner.current_timestamp,
             current_batch: inner.current_batch,
+            l1_batch_sealer: inner.l1_batch_sealer.clone(),
             current_miniblock: inner.current_miniblock,
             current_miniblock_hash: inner.current_miniblock_hash,
             fee_input_provider: inner.fee_input_provider.clone(),
+            pending_transactions: inner.pending_transactions.clone(),
+            queued_transactions: inner.queued_transactions.clone(),
+            node_verified_txs: inner.node_verified_txs.clone(),
             tx_results: inner.tx_results.clone(),
             blocks: inner.blocks.clone(),
             block_hashes: inner.block_hashes.clone(),
@@ -2720,6 +3078,22 @@ mod tests {
             expected_snapshot.fee_input_provider,
             actual_snapshot.fee_input_provider
         );
+        assert_eq!(
+            expected_snapshot
+                .pending_transactions
+                .iter()
+                .map(|tx| tx.hash())
+                .collect_vec(),
+            actual_snapshot
+                .pending_transactions
+                .iter()
+                .map(|tx| tx.hash())
+                .collect_vec()
+        );
+        assert_eq!(
+            expected_snapshot.queued_transactions.keys().collect_vec(),
+            actual_snapshot.queued_transactions.keys().collect_vec()
+        );
         assert_eq!(
             expected_snapshot.tx_results.keys().collect_vec(),
             actual_snapshot.tx_results.keys().collect_vec()
@@ -2772,6 +3146,17 @@ mod tests {
         inner.current_miniblock = 1;
         inner.current_miniblock_hash = H256::repeat_byte(0x1);
         inner.current_timestamp = 1;
+        inner.pending_transactions.push(
+            testing::TransactionBuilder::new()
+                .set_hash(H256::repeat_byte(0x1))
+                .build(),
+        );
+        inner.queued_transactions.insert(
+            (H160::repeat_byte(0x1), Nonce(1)),
+            testing::TransactionBuilder::new()
+                .set_hash(H256::repeat_byte(0x1))
+                .build(),
+        );
         inner
             .filters
             .add_block_filter()
@@ -2791,9 +3176,13 @@ mod tests {
             Snapshot {
                 current_timestamp: inner.current_timestamp,
                 current_batch: inner.current_batch,
+                l1_batch_sealer: inner.l1_batch_sealer.clone(),
                 current_miniblock: inner.current_miniblock,
                 current_miniblock_hash: inner.current_miniblock_hash,
                 fee_input_provider: inner.fee_input_provider.clone(),
+                pending_transactions: inner.pending_transactions.clone(),
+                queued_transactions: inner.queued_transactions.clone(),
+                node_verified_txs: inner.node_verified_txs.clone(),
                 tx_results: inner.tx_results.clone(),
                 blocks: inner.blocks.clone(),
                 block_hashes: inner.block_hashes.clone(),
@@ -2825,6 +3214,17 @@ mod tests {
         inner.current_miniblock = 2;
         inner.current_miniblock_hash = H256::repeat_byte(0x2);
         inner.current_timestamp = 2;
+        inner.pending_transactions.push(
+            testing::TransactionBuilder::new()
+                .set_hash(H256::repeat_byte(0x2))
+                .build(),
+        );
+        inner.queued_transactions.insert(
+            (H160::repeat_byte(0x2), Nonce(2)),
+            testing::TransactionBuilder::new()
+                .set_hash(H256::repeat_byte(0x2))
+                .build(),
+        );
         inner
             .filters
             .add_pending_transaction_filter()
@@ -2856,6 +3256,22 @@ mod tests {
             expected_snapshot.fee_input_provider,
             inner.fee_input_provider
         );
+        assert_eq!(
+            expected_snapshot
+                .pending_transactions
+                .iter()
+                .map(|tx| tx.hash())
+                .collect_vec(),
+            inner
+                .pending_transactions
+                .iter()
+                .map(|tx| tx.hash())
+                .collect_vec()
+        );
+        assert_eq!(
+            expected_snapshot.queued_transactions.keys().collect_vec(),
+            inner.queued_transactions.keys().collect_vec()
+        );
         assert_eq!(
             expected_snapshot.tx_results.keys().collect_vec(),
             inner.tx_results.keys().collect_vec()
@@ -2877,6 +3293,34 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_get_uncles_of_produced_block() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let (block_hash, _) = testing::apply_tx(&node, H256::repeat_byte(0x01));
+        let count_by_hash = node
+            .get_uncle_count_by_block_hash(block_hash)
+            .await
+            .expect("failed fetching uncle count");
+        let count_by_number = node
+            .get_uncle_count_by_block_number(BlockNumber::Number(U64::from(1)))
+            .await
+            .expect("failed fetching uncle count");
+        let unknown_count = node
+            .get_uncle_count_by_block_hash(H256::repeat_byte(0xab))
+            .await
+            .expect("failed fetching uncle count");
+        let uncle = node
+            .get_uncle_by_block_hash_and_index(block_hash, web3::types::Index::from(0))
+            .await
+            .expect("failed fetching uncle");
+
+        assert_eq!(Some(U256::zero()), count_by_hash);
+        assert_eq!(Some(U256::zero()), count_by_number);
+        assert_eq!(None, unknown_count);
+        assert!(uncle.is_none());
+    }
+
     #[tokio::test]
     async fn test_get_transaction_by_block_hash_and_index_returns_none_for_invalid_block_hash() {
         let node = InMemoryNode::<HttpForkSource>::default();
@@ -0,0 +1,91 @@
+use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+use zksync_web3_decl::error::Web3Error;
+
+use crate::{
+    fork::ForkSource,
+    logging_middleware::Meta,
+    namespaces::{EthPubSubNamespaceT, Result},
+    node::InMemoryNode,
+    subscriptions::{LogsParams, SubscriptionKind, SubscriptionResult},
+    utils::into_jsrpc_error,
+};
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EthPubSubNamespaceT
+    for InMemoryNode<S>
+{
+    type Metadata = Meta;
+
+    fn subscribe(
+        &self,
+        _meta: Self::Metadata,
+        subscriber: Subscriber<SubscriptionResult>,
+        kind: SubscriptionKind,
+        params: Option<LogsParams>,
+    ) {
+        let inner = self.get_inner();
+        let mut writer = match inner.write() {
+            Ok(writer) => writer,
+            Err(_) => {
+                let _ = subscriber.reject(into_jsrpc_error(Web3Error::InternalError));
+                return;
+            }
+        };
+
+        let id = writer.subscriptions.next_id();
+        match subscriber.assign_id(id.clone()) {
+            Ok(sink) => writer
+                .subscriptions
+                .add(id, kind, params.unwrap_or_default(), sink),
+            Err(_) => tracing::debug!("client disconnected before subscribing"),
+        }
+    }
+
+    fn unsubscribe(&self, _meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map(|mut writer| writer.subscriptions.remove(&id))
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_fork_source::HttpForkSource, testing};
+    use zksync_basic_types::H256;
+
+    #[tokio::test]
+    async fn test_logs_subscription_is_notified_of_removed_logs_on_revert() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let (subscriber, _id, mut receiver) = Subscriber::new_test("eth_subscription");
+        EthPubSubNamespaceT::subscribe(
+            &node,
+            Meta::default(),
+            subscriber,
+            SubscriptionKind::Logs,
+            None,
+        );
+
+        let snapshot = node
+            .get_inner()
+            .read()
+            .unwrap()
+            .snapshot()
+            .expect("failed taking snapshot");
+        testing::apply_tx(&node, H256::repeat_byte(0x1));
+        node.get_inner()
+            .write()
+            .unwrap()
+            .restore_snapshot(snapshot)
+            .expect("failed restoring snapshot");
+
+        let notifications =
+            std::iter::from_fn(|| receiver.try_next().ok().flatten()).collect::<Vec<_>>();
+        let removed = notifications
+            .iter()
+            .filter(|notification| notification.contains(r#""removed":true"#))
+            .count();
+        assert!(removed > 0);
+        assert_eq!(notifications.len(), 2 * removed);
+    }
+}
@@ -64,4 +64,22 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> EvmNamespa
             })
             .into_boxed_future()
     }
+
+    fn set_block_gas_limit(&self, gas_limit: U64) -> RpcResult<bool> {
+        self.set_block_gas_limit(gas_limit)
+            .map_err(|err| {
+                tracing::error!("failed setting block gas limit: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn set_automine(&self, enabled: bool) -> RpcResult<bool> {
+        self.set_automine(enabled)
+            .map_err(|err| {
+                tracing::error!("failed setting automine: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
 }
@@ -0,0 +1,167 @@
+//! Factory dependencies of the deployments, returned by `era_getFactoryDeps`, to debug the "factory deps not found"
+//! failures of the deployments made by the contracts.
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use zksync_basic_types::{Address, H256};
+use zksync_state::ReadStorage;
+use zksync_types::{get_code_key, Bytes};
+use zksync_utils::bytecode::hash_bytecode;
+
+use crate::{fork::ForkSource, node::InMemoryNode};
+
+/// What `era_getFactoryDeps` returns the factory dependencies of: a transaction, or the deployment of a contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FactoryDepsTarget {
+    Transaction(H256),
+    Contract(Address),
+}
+
+/// Factory dependencies published by a transaction, as returned by `era_getFactoryDeps`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FactoryDeps {
+    /// The transaction that published the factory dependencies, missing if the contract was not deployed locally.
+    pub transaction_hash: Option<H256>,
+    /// The bytecode hash of the contract, if the factory dependencies of a contract were requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytecode_hash: Option<H256>,
+    pub factory_deps: Vec<FactoryDep>,
+}
+
+/// Factory dependency published by a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FactoryDep {
+    /// The versioned bytecode hash, which the deployments refer to.
+    pub hash: H256,
+    /// The length of the bytecode in bytes.
+    pub length: usize,
+    /// Whether the bytecode is known to the node, i.e. it can be deployed.
+    pub known: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytecode: Option<Bytes>,
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Returns the factory dependencies published by the transaction, or by the transaction that deployed the
+    /// contract, i.e. the one that published its bytecode, along with the bytecodes if `include_bytecodes` is set.
+    ///
+    /// # Returns
+    /// `None` if the transaction is not known or the address has no code.
+    pub fn get_factory_deps(
+        &self,
+        target: FactoryDepsTarget,
+        include_bytecodes: bool,
+    ) -> anyhow::Result<Option<FactoryDeps>> {
+        let inner = self.get_inner();
+        let reader = inner
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+
+        let (tx_result, bytecode_hash) = match target {
+            FactoryDepsTarget::Transaction(hash) => match reader.tx_results.get(&hash) {
+                Some(tx_result) => (Some(tx_result), None),
+                None => return Ok(None),
+            },
+            FactoryDepsTarget::Contract(address) => {
+                let bytecode_hash = (&reader.fork_storage).read_value(&get_code_key(&address));
+                if bytecode_hash.is_zero() {
+                    return Ok(None);
+                }
+                let tx_result = reader.tx_results.values().find(|tx_result| {
+                    tx_result
+                        .info
+                        .tx
+                        .execute
+                        .factory_deps
+                        .iter()
+                        .flatten()
+                        .any(|bytecode| hash_bytecode(bytecode) == bytecode_hash)
+                });
+                (tx_result, Some(bytecode_hash))
+            }
+        };
+
+        let factory_deps = tx_result
+            .map(|tx_result| tx_result.info.tx.execute.factory_deps.clone())
+            .unwrap_or_default()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|bytecode| {
+                let hash = hash_bytecode(&bytecode);
+                FactoryDep {
+                    hash,
+                    length: bytecode.len(),
+                    known: (&reader.fork_storage).load_factory_dep(hash).is_some(),
+                    bytecode: Some(Bytes(bytecode)).filter(|_| include_bytecodes),
+                }
+            })
+            .collect();
+        Ok(Some(FactoryDeps {
+            transaction_hash: tx_result.map(|tx_result| tx_result.receipt.transaction_hash),
+            bytecode_hash,
+            factory_deps,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_fork_source::HttpForkSource, testing};
+    use zksync_basic_types::{Nonce, U256};
+    use zksync_types::{utils::deployed_address_create, PackedEthSignature};
+
+    #[test]
+    fn test_get_factory_deps_of_deployment() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let private_key = H256::repeat_byte(0xef);
+        let from = PackedEthSignature::address_from_private_key(&private_key)
+            .expect("failed generating address");
+        node.set_rich_account(from);
+        let bytecode = hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap();
+        let tx_hash = H256::repeat_byte(0x1);
+        testing::deploy_contract(
+            &node,
+            tx_hash,
+            private_key,
+            bytecode.clone(),
+            None,
+            Nonce(0),
+        );
+        let address = deployed_address_create(from, U256::zero());
+
+        let deps = node
+            .get_factory_deps(FactoryDepsTarget::Contract(address), false)
+            .expect("failed getting factory deps")
+            .expect("missing factory deps");
+        assert_eq!(Some(tx_hash), deps.transaction_hash);
+        assert_eq!(Some(hash_bytecode(&bytecode)), deps.bytecode_hash);
+        assert_eq!(
+            vec![FactoryDep {
+                hash: hash_bytecode(&bytecode),
+                length: bytecode.len(),
+                known: true,
+                bytecode: None,
+            }],
+            deps.factory_deps
+        );
+
+        let deps = node
+            .get_factory_deps(FactoryDepsTarget::Transaction(tx_hash), true)
+            .expect("failed getting factory deps")
+            .expect("missing factory deps");
+        assert_eq!(None, deps.bytecode_hash);
+        assert_eq!(Some(Bytes(bytecode)), deps.factory_deps[0].bytecode);
+
+        assert_eq!(
+            None,
+            node.get_factory_deps(
+                FactoryDepsTarget::Contract(Address::repeat_byte(0x1)),
+                false
+            )
+            .expect("failed getting factory deps")
+        );
+    }
+}
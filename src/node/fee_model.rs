@@ -21,6 +21,10 @@ impl TestNodeFeeInputProvider {
     pub fn new(l1_gas_price: u64) -> Self {
         Self { l1_gas_price }
     }
+
+    pub fn l1_gas_price(&self) -> u64 {
+        self.l1_gas_price
+    }
 }
 
 impl BatchFeeModelInputProvider for TestNodeFeeInputProvider {
@@ -0,0 +1,340 @@
+//! Gas reports of the calls made during a window opened with `era_startGasReport` and closed with
+//! `era_finishGasReport`, so that the gas used by each function of the contracts can be tracked against a saved
+//! report, e.g. to catch gas regressions in CI.
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use zksync_basic_types::Address;
+use zksync_types::api::{DebugCall, DebugCallType};
+
+use crate::{
+    fork::ForkSource,
+    node::{InMemoryNode, TransactionResult},
+    utils::is_system_contract,
+};
+
+/// Window of a gas report started with `era_startGasReport`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasReportWindow {
+    pub label: String,
+    /// The latest block when the report was started, the report covers the transactions of the later blocks.
+    pub from_block: u64,
+}
+
+/// Gas report returned by `era_finishGasReport`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasReport {
+    pub label: String,
+    /// The first block of the report.
+    pub from_block: u64,
+    /// The last block of the report.
+    pub to_block: u64,
+    /// The number of the transactions of the report.
+    pub transactions: usize,
+    /// The total gas used by the transactions, including their validation and fees.
+    pub gas_used: u64,
+    /// The gas used by the calls of each function of the contracts, by contract and then by function.
+    pub functions: Vec<FunctionGas>,
+    /// The functions whose average gas differs from the previous report the report was compared against.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<GasComparison>,
+}
+
+/// Gas used by the calls of a function of a contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionGas {
+    pub contract: Address,
+    /// The selector of the function, e.g. `0xa9059cbb`, or `constructor` for the deployments and `fallback` for the
+    /// calls without calldata.
+    pub function: String,
+    pub calls: u64,
+    /// The gas used by the calls, including the calls they made.
+    pub total_gas: u64,
+    pub min_gas: u64,
+    pub max_gas: u64,
+    pub avg_gas: u64,
+}
+
+/// Comparison of a [GasReport] against a previous one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasComparison {
+    pub previous_label: String,
+    /// The difference of the total gas used by the transactions.
+    pub gas_used_difference: i64,
+    pub changes: Vec<GasChange>,
+}
+
+/// Change of the average gas of a function since the previous report. The functions only called in one of the
+/// reports are missing the average gas of the other.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasChange {
+    pub contract: Address,
+    pub function: String,
+    pub previous_avg_gas: Option<u64>,
+    pub avg_gas: Option<u64>,
+    pub difference: i64,
+    /// The difference in percent of the previous average gas, if the function was called in both reports.
+    pub percent: Option<f64>,
+}
+
+impl GasReport {
+    /// Aggregates the gas used by the calls of the transactions, in the order of their execution. The calls to the
+    /// system contracts and to the sender of each transaction, i.e. its validation and execution by the account, are
+    /// left out.
+    fn new(window: &GasReportWindow, to_block: u64, tx_results: &[&TransactionResult]) -> Self {
+        let mut functions: BTreeMap<(Address, String), FunctionGas> = BTreeMap::new();
+        for tx_result in tx_results {
+            let sender = tx_result.info.tx.initiator_account();
+            aggregate_calls(&tx_result.debug.calls, sender, &mut functions);
+        }
+        Self {
+            label: window.label.clone(),
+            from_block: window.from_block + 1,
+            to_block,
+            transactions: tx_results.len(),
+            gas_used: tx_results
+                .iter()
+                .map(|tx_result| tx_result.debug.gas_used.low_u64())
+                .sum(),
+            functions: functions.into_values().collect(),
+            comparison: None,
+        }
+    }
+
+    /// Compares the report against a previous one, listing the functions whose average gas changed.
+    pub fn compare(&self, previous: &GasReport) -> GasComparison {
+        let averages = |report: &GasReport| {
+            report
+                .functions
+                .iter()
+                .map(|function| {
+                    (
+                        (function.contract, function.function.clone()),
+                        function.avg_gas,
+                    )
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        let (previous_averages, averages) = (averages(previous), averages(self));
+
+        let mut keys: Vec<_> = previous_averages.keys().chain(averages.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        let changes = keys
+            .into_iter()
+            .filter_map(|key| {
+                let previous_avg_gas = previous_averages.get(key).copied();
+                let avg_gas = averages.get(key).copied();
+                if previous_avg_gas == avg_gas {
+                    return None;
+                }
+                let difference = avg_gas.unwrap_or_default() as i64
+                    - previous_avg_gas.unwrap_or_default() as i64;
+                Some(GasChange {
+                    contract: key.0,
+                    function: key.1.clone(),
+                    previous_avg_gas,
+                    avg_gas,
+                    difference,
+                    percent: previous_avg_gas
+                        .zip(avg_gas)
+                        .filter(|(previous, _)| *previous > 0)
+                        .map(|(previous, _)| difference as f64 * 100.0 / previous as f64),
+                })
+            })
+            .collect();
+        GasComparison {
+            previous_label: previous.label.clone(),
+            gas_used_difference: self.gas_used as i64 - previous.gas_used as i64,
+            changes,
+        }
+    }
+}
+
+fn aggregate_calls(
+    calls: &[DebugCall],
+    sender: Address,
+    functions: &mut BTreeMap<(Address, String), FunctionGas>,
+) {
+    for call in calls {
+        if !is_system_contract(&call.to) && call.to != sender {
+            let function = match (&call.r#type, call.input.0.get(..4)) {
+                (DebugCallType::Create, _) => "constructor".to_string(),
+                (_, Some(selector)) => format!("0x{}", hex::encode(selector)),
+                (_, None) => "fallback".to_string(),
+            };
+            let gas = call.gas_used.low_u64();
+            functions
+                .entry((call.to, function.clone()))
+                .and_modify(|entry| {
+                    entry.calls += 1;
+                    entry.total_gas += gas;
+                    entry.min_gas = entry.min_gas.min(gas);
+                    entry.max_gas = entry.max_gas.max(gas);
+                    entry.avg_gas = entry.total_gas / entry.calls;
+                })
+                .or_insert(FunctionGas {
+                    contract: call.to,
+                    function,
+                    calls: 1,
+                    total_gas: gas,
+                    min_gas: gas,
+                    max_gas: gas,
+                    avg_gas: gas,
+                });
+        }
+        aggregate_calls(&call.calls, sender, functions);
+    }
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Starts a gas report of the transactions of the next blocks, replacing the one in progress if any.
+    pub fn start_gas_report(&self, label: String) -> anyhow::Result<()> {
+        let inner = self.get_inner();
+        let mut writer = inner
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        writer.gas_report = Some(GasReportWindow {
+            label,
+            from_block: writer.current_miniblock,
+        });
+        Ok(())
+    }
+
+    /// Finishes the gas report in progress and returns it, compared against the `previous` report if given.
+    pub fn finish_gas_report(&self, previous: Option<GasReport>) -> anyhow::Result<GasReport> {
+        let inner = self.get_inner();
+        let mut writer = inner
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        let window = writer
+            .gas_report
+            .take()
+            .ok_or_else(|| anyhow!("no gas report was started"))?;
+
+        let mut tx_results: Vec<&TransactionResult> = writer
+            .tx_results
+            .values()
+            .filter(|tx_result| tx_result.info.miniblock_number > window.from_block)
+            .collect();
+        tx_results.sort_by_key(|tx_result| {
+            (
+                tx_result.info.miniblock_number,
+                tx_result.receipt.transaction_index,
+            )
+        });
+        let mut report = GasReport::new(&window, writer.current_miniblock, &tx_results);
+        report.comparison = previous.map(|previous| report.compare(&previous));
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zksync_basic_types::U256;
+    use zksync_types::Bytes;
+
+    fn call(to: Address, input: &[u8], gas_used: u64, calls: Vec<DebugCall>) -> DebugCall {
+        DebugCall {
+            r#type: DebugCallType::Call,
+            from: Address::zero(),
+            to,
+            gas: U256::zero(),
+            gas_used: U256::from(gas_used),
+            value: U256::zero(),
+            output: Bytes::default(),
+            input: Bytes(input.to_vec()),
+            error: None,
+            revert_reason: None,
+            calls,
+        }
+    }
+
+    #[test]
+    fn test_gas_report_aggregates_calls_by_function() {
+        let sender = Address::repeat_byte(0x1);
+        let token = Address::repeat_byte(0x2);
+        let vault = Address::repeat_byte(0x3);
+        let calls = vec![call(
+            sender,
+            &[],
+            10_000,
+            vec![
+                call(
+                    vault,
+                    &[0xb6, 0xb5, 0x5f, 0x25],
+                    5_000,
+                    vec![call(token, &[0xa9, 0x05, 0x9c, 0xbb, 0x1], 1_000, vec![])],
+                ),
+                call(token, &[0xa9, 0x05, 0x9c, 0xbb], 2_000, vec![]),
+                call(Address::from_low_u64_be(0x8002), &[0x1], 100, vec![]),
+            ],
+        )];
+
+        let mut functions = BTreeMap::new();
+        aggregate_calls(&calls, sender, &mut functions);
+        let functions: Vec<_> = functions.into_values().collect();
+        assert_eq!(2, functions.len());
+        assert_eq!(
+            FunctionGas {
+                contract: token,
+                function: "0xa9059cbb".to_string(),
+                calls: 2,
+                total_gas: 3_000,
+                min_gas: 1_000,
+                max_gas: 2_000,
+                avg_gas: 1_500,
+            },
+            functions[0]
+        );
+        assert_eq!(vault, functions[1].contract);
+        assert_eq!(5_000, functions[1].avg_gas);
+
+        let report = GasReport {
+            label: "new".to_string(),
+            gas_used: 12_000,
+            functions: functions.clone(),
+            ..Default::default()
+        };
+        let mut previous_functions = functions;
+        previous_functions[0].avg_gas = 2_000;
+        previous_functions.remove(1);
+        let previous = GasReport {
+            label: "old".to_string(),
+            gas_used: 10_000,
+            functions: previous_functions,
+            ..Default::default()
+        };
+
+        let comparison = report.compare(&previous);
+        assert_eq!("old", comparison.previous_label);
+        assert_eq!(2_000, comparison.gas_used_difference);
+        assert_eq!(
+            vec![
+                GasChange {
+                    contract: token,
+                    function: "0xa9059cbb".to_string(),
+                    previous_avg_gas: Some(2_000),
+                    avg_gas: Some(1_500),
+                    difference: -500,
+                    percent: Some(-25.0),
+                },
+                GasChange {
+                    contract: vault,
+                    function: "0xb6b55f25".to_string(),
+                    previous_avg_gas: None,
+                    avg_gas: Some(5_000),
+                    difference: 5_000,
+                    percent: None,
+                },
+            ],
+            comparison.changes
+        );
+    }
+}
@@ -4,7 +4,7 @@ use zksync_web3_decl::error::Web3Error;
 use crate::{
     fork::ForkSource,
     namespaces::{HardhatNamespaceT, RpcResult},
-    node::InMemoryNode,
+    node::{HardhatMetadata, InMemoryNode},
     utils::{into_jsrpc_error, IntoBoxedFuture},
 };
 
@@ -64,4 +64,31 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> HardhatNam
             })
             .into_boxed_future()
     }
+
+    fn get_automine(&self) -> RpcResult<bool> {
+        self.get_automine()
+            .map_err(|err| {
+                tracing::error!("failed getting automine: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn set_coinbase(&self, address: Address) -> RpcResult<bool> {
+        self.set_coinbase(address)
+            .map_err(|err| {
+                tracing::error!("failed setting coinbase: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
+
+    fn metadata(&self) -> RpcResult<HardhatMetadata> {
+        self.hardhat_metadata()
+            .map_err(|err| {
+                tracing::error!("failed getting metadata: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })
+            .into_boxed_future()
+    }
 }
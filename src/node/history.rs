@@ -0,0 +1,188 @@
+//! Export of the transactions executed in the session with `era_exportHistory`, so that a manual exploration of the
+//! node can be replayed later, e.g. as the fixture of an automated test.
+use std::fmt::Write;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use zksync_basic_types::{Address, H256, U256};
+use zksync_types::{Bytes, CONTRACT_DEPLOYER_ADDRESS};
+
+use crate::{
+    fork::ForkSource,
+    node::{InMemoryNode, TransactionResult},
+};
+
+/// Format of the history exported with `era_exportHistory`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryFormat {
+    /// JSON array of [HistoryEntry], which can be sent back with `eth_sendTransaction` from impersonated accounts.
+    #[default]
+    Json,
+    /// Solidity script broadcasting the transactions, run with `forge script`.
+    Foundry,
+}
+
+/// Transaction executed in the session, with the fields of an `eth_sendTransaction` request.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub hash: H256,
+    pub block_number: u64,
+    pub from: Address,
+    pub to: Address,
+    pub value: U256,
+    pub data: Bytes,
+    pub gas: U256,
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+    pub nonce: U256,
+    /// The bytecodes published with the transaction, e.g. of the deployed contracts.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub factory_deps: Vec<Bytes>,
+    /// Whether the transaction succeeded.
+    pub success: bool,
+}
+
+impl HistoryEntry {
+    fn new(tx_result: &TransactionResult) -> Self {
+        let tx = &tx_result.info.tx;
+        Self {
+            hash: tx.hash(),
+            block_number: tx_result.info.miniblock_number,
+            from: tx.initiator_account(),
+            to: tx.recipient_account(),
+            value: tx.execute.value,
+            data: Bytes(tx.execute.calldata().to_vec()),
+            gas: tx.common_data.fee.gas_limit,
+            max_fee_per_gas: tx.common_data.fee.max_fee_per_gas,
+            max_priority_fee_per_gas: tx.common_data.fee.max_priority_fee_per_gas,
+            nonce: U256::from(tx.nonce().0),
+            factory_deps: tx
+                .execute
+                .factory_deps
+                .iter()
+                .flatten()
+                .cloned()
+                .map(Bytes)
+                .collect(),
+            success: !tx_result.info.result.result.is_failed(),
+        }
+    }
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Returns the transactions executed in the session, in the order of their execution.
+    pub fn history(&self) -> anyhow::Result<Vec<HistoryEntry>> {
+        let inner = self.get_inner();
+        let reader = inner
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        let mut tx_results: Vec<&TransactionResult> = reader.tx_results.values().collect();
+        tx_results.sort_by_key(|tx_result| {
+            (
+                tx_result.info.miniblock_number,
+                tx_result.receipt.transaction_index,
+            )
+        });
+        Ok(tx_results.into_iter().map(HistoryEntry::new).collect())
+    }
+
+    /// Exports the transactions executed in the session in the given format.
+    pub fn export_history(&self, format: HistoryFormat) -> anyhow::Result<String> {
+        let history = self.history()?;
+        match format {
+            HistoryFormat::Json => Ok(serde_json::to_string_pretty(&history)?),
+            HistoryFormat::Foundry => Ok(foundry_script(&history)),
+        }
+    }
+}
+
+/// Returns a Foundry script broadcasting the transactions from their original senders. The deployments are left out
+/// as comments, as their bytecodes are published with the transaction rather than in its calldata.
+fn foundry_script(history: &[HistoryEntry]) -> String {
+    let mut script = String::from(
+        "// SPDX-License-Identifier: UNLICENSED\n\
+         pragma solidity ^0.8.0;\n\
+         \n\
+         import {Script} from \"forge-std/Script.sol\";\n\
+         \n\
+         /// Transactions exported from era_test_node with era_exportHistory.\n\
+         contract ReplayHistory is Script {\n    \
+             function run() external {\n",
+    );
+    for (index, entry) in history.iter().enumerate() {
+        let _ = writeln!(
+            script,
+            "        // {:#x} (block {})",
+            entry.hash, entry.block_number
+        );
+        if entry.to == CONTRACT_DEPLOYER_ADDRESS && !entry.factory_deps.is_empty() {
+            let _ = writeln!(
+                script,
+                "        // skipped: deployment with {} factory deps",
+                entry.factory_deps.len()
+            );
+            continue;
+        }
+        let _ = writeln!(
+            script,
+            "        vm.broadcast(address(bytes20(hex\"{}\")));",
+            hex::encode(entry.from)
+        );
+        let _ = writeln!(
+            script,
+            "        (bool success{}, ) = address(bytes20(hex\"{}\")).call{{value: {}, gas: {}}}(hex\"{}\");",
+            index,
+            hex::encode(entry.to),
+            entry.value,
+            entry.gas,
+            hex::encode(&entry.data.0)
+        );
+        if entry.success {
+            let _ = writeln!(
+                script,
+                "        require(success{}, \"transaction {:#x} failed\");",
+                index, entry.hash
+            );
+        }
+    }
+    script.push_str("    }\n}\n");
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_fork_source::HttpForkSource, testing};
+
+    #[test]
+    fn test_export_history_in_execution_order() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let hashes = [H256::repeat_byte(0x2), H256::repeat_byte(0x1)];
+        for hash in hashes {
+            let tx = testing::TransactionBuilder::new().set_hash(hash).build();
+            node.set_rich_account(tx.initiator_account());
+            node.submit_l2_tx(tx).expect("failed submitting tx");
+        }
+
+        let history = node.history().expect("failed getting history");
+        assert_eq!(
+            hashes.to_vec(),
+            history.iter().map(|entry| entry.hash).collect::<Vec<_>>()
+        );
+        assert!(history[0].block_number < history[1].block_number);
+
+        let json = node
+            .export_history(HistoryFormat::Json)
+            .expect("failed exporting history");
+        let entries: serde_json::Value = serde_json::from_str(&json).expect("invalid JSON");
+        assert_eq!(serde_json::to_value(hashes[0]).unwrap(), entries[0]["hash"]);
+
+        let script = node
+            .export_history(HistoryFormat::Foundry)
+            .expect("failed exporting history");
+        assert!(script.contains("contract ReplayHistory is Script"));
+        assert_eq!(2, script.matches("vm.broadcast(").count());
+    }
+}
@@ -0,0 +1,24 @@
+use multivm::interface::VmExecutionResultAndLogs;
+use zksync_types::{
+    api::{Block, TransactionVariant},
+    l2::L2Tx,
+};
+
+/// Hook into the lifecycle of the transactions, e.g. to build custom analyzers, indexers or invariant
+/// checkers on top of the node. Registered via [super::InMemoryNode::add_hook].
+///
+/// Except for [NodeHook::before_validation], the hooks are called while the node state is locked,
+/// so they must not call back into the node.
+pub trait NodeHook: Send + Sync {
+    /// Called before a transaction is validated. Returning an error rejects the transaction.
+    fn before_validation(&self, _tx: &L2Tx) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called after a transaction was executed and committed to a block, with its VM result.
+    fn after_execution(&self, _tx: &L2Tx, _result: &VmExecutionResultAndLogs) {}
+
+    /// Called after a block was sealed and added to the chain, including the empty blocks mined by `evm_mine`
+    /// and `hardhat_mine`.
+    fn on_block_sealed(&self, _block: &Block<TransactionVariant>) {}
+}
@@ -1,35 +1,51 @@
 //! In-memory node, that supports forking other networks.
 use crate::{
     bootloader_debug::{BootloaderDebug, BootloaderDebugTracer},
+    cache::LruCache,
+    chaos::ChaosMiddleware,
     console_log::ConsoleLogHandler,
     deps::{storage_view::StorageView, InMemoryStorage},
+    execution_limit::{ExecutionLimitTracer, ExecutionLimits},
     filters::EthFilters,
-    fork::{block_on, ForkDetails, ForkSource, ForkStorage},
-    formatter,
+    fork::{block_on, supported_protocol_versions, ForkDetails, ForkSource, ForkStorage},
+    formatter::{self, AddressLabels},
+    health::ForkStatusCache,
+    metrics::METRICS,
     node::{
+        bytecode::validate_bytecode,
         fee_model::{TestNodeFeeInputProvider, CONFIG},
         storage_logs::print_storage_logs_details,
+        CoverageTracer, ExecutionCoverage, GasReportWindow, NodeHook, TxFilterRule, VmWorkers,
     },
     observability::Observability,
+    observability::{SPAN_TARGET, STRUCTURED_LOG_TARGET},
+    subscriptions::EthSubscriptions,
     system_contracts::{self, SystemContracts},
-    utils::{bytecode_to_factory_dep, create_debug_output, into_jsrpc_error, to_human_size},
+    utils::{
+        bytecode_to_factory_dep, create_debug_output, into_jsrpc_error, to_human_size,
+        ExecutionErrorData,
+    },
+    verification::{SourceLocation, VerifiedContracts},
 };
+use bigdecimal::BigDecimal;
 use clap::Parser;
 use colored::Colorize;
 use core::fmt::Display;
 use indexmap::IndexMap;
 use once_cell::sync::OnceCell;
+use serde::Serialize;
 use std::{
     cmp::{self},
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     str::FromStr,
     sync::{Arc, RwLock},
+    time::{Instant, SystemTime},
 };
 
 use multivm::{
     interface::{
         ExecutionResult, L1BatchEnv, L2BlockEnv, SystemEnv, TxExecutionMode, VmExecutionMode,
-        VmExecutionResultAndLogs, VmInterface,
+        VmExecutionResultAndLogs, VmInterface, VmInterfaceHistoryEnabled,
     },
     vm_latest::L2Block,
     VmVersion,
@@ -40,22 +56,25 @@ use multivm::{
         adjust_pubdata_price_for_tx, derive_base_fee_and_gas_per_pubdata, derive_overhead,
         get_max_gas_per_pubdata_byte,
     },
-    vm_latest::HistoryDisabled,
     vm_latest::{
         constants::{BLOCK_GAS_LIMIT, MAX_PUBDATA_PER_BLOCK},
         utils::l2_blocks::load_last_l2_block,
         ToTracerPointer, TracerPointer, Vm,
     },
+    vm_latest::{HistoryDisabled, HistoryEnabled},
 };
 use zksync_basic_types::{
-    web3::signing::keccak256, AccountTreeId, Address, Bytes, L1BatchNumber, MiniblockNumber, H160,
-    H256, U256, U64,
+    web3::signing::keccak256, AccountTreeId, Address, Bytes, L1BatchNumber, MiniblockNumber, Nonce,
+    H160, H256, U256, U64,
 };
 use zksync_contracts::BaseSystemContracts;
 use zksync_core::fee_model::BatchFeeModelInputProvider;
 use zksync_state::{ReadStorage, StoragePtr, WriteStorage};
 use zksync_types::{
-    api::{Block, DebugCall, Log, TransactionReceipt, TransactionVariant},
+    api::{
+        Block, BlockIdVariant, BlockNumber, BlockNumberObject, DebugCall, Log, TransactionReceipt,
+        TransactionVariant,
+    },
     block::{unpack_block_info, MiniblockHasher},
     fee::Fee,
     get_nonce_key,
@@ -67,10 +86,7 @@ use zksync_types::{
     ACCOUNT_CODE_STORAGE_ADDRESS, MAX_L2_TX_GAS_LIMIT, SYSTEM_CONTEXT_ADDRESS,
     SYSTEM_CONTEXT_BLOCK_INFO_POSITION,
 };
-use zksync_utils::{
-    bytecode::{compress_bytecode, hash_bytecode},
-    h256_to_account_address, h256_to_u256, u256_to_h256,
-};
+use zksync_utils::{bytecode::hash_bytecode, h256_to_account_address, h256_to_u256, u256_to_h256};
 use zksync_web3_decl::error::Web3Error;
 
 /// Max possible size of an ABI encoded tx (in bytes).
@@ -94,6 +110,12 @@ pub const ESTIMATE_GAS_ACCEPTABLE_OVERESTIMATION: u32 = 1_000;
 pub const ESTIMATE_GAS_SCALE_FACTOR: f32 = 1.3;
 /// The maximum number of previous blocks to store the state for.
 pub const MAX_PREVIOUS_STATES: u16 = 128;
+/// The maximum number of rejected transactions kept for `era_getRejectedTransaction`.
+pub const MAX_REJECTED_TRANSACTIONS: usize = 1024;
+/// The maximum number of transactions whose coverage is kept for `era_getExecutionCoverage`.
+pub const MAX_EXECUTION_COVERAGES: usize = 1024;
+/// The maximum number of transactions waiting for a nonce gap to be filled.
+pub const MAX_QUEUED_TRANSACTIONS: usize = 1024;
 /// The zks protocol version.
 pub const PROTOCOL_VERSION: &str = "zks/1";
 
@@ -127,6 +149,46 @@ pub fn create_empty_block<TX>(
     }
 }
 
+/// Single attempt of the `eth_estimateGas` binary search.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimateGasIteration {
+    /// The gas limit the transaction was executed with (excluding the overhead).
+    pub gas_limit: u32,
+    pub gas_used: u32,
+    pub success: bool,
+    /// The revert or halt reason, if the attempt failed.
+    pub revert_reason: Option<String>,
+}
+
+impl EstimateGasIteration {
+    fn new(gas_limit: u32, result: &VmExecutionResultAndLogs) -> Self {
+        let revert_reason = match &result.result {
+            ExecutionResult::Success { .. } => None,
+            ExecutionResult::Revert { output } => Some(output.to_string()),
+            ExecutionResult::Halt { reason } => Some(reason.to_string()),
+        };
+        Self {
+            gas_limit,
+            gas_used: result.statistics.gas_used,
+            success: revert_reason.is_none(),
+            revert_reason,
+        }
+    }
+}
+
+/// All the attempts of an `eth_estimateGas` run, as returned by `era_traceEstimateGas`.
+/// The last iteration is the final check with the scaled gas limit.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EstimateGasTrace {
+    pub iterations: Vec<EstimateGasIteration>,
+    /// The estimated gas limit (including the overhead), if the estimation succeeded.
+    pub estimated_gas: Option<U256>,
+    /// The error `eth_estimateGas` returns, if the estimation failed.
+    pub error: Option<String>,
+}
+
 /// Information about the executed transaction.
 #[derive(Debug, Clone)]
 pub struct TxExecutionInfo {
@@ -259,6 +321,35 @@ impl Display for ShowGasDetails {
     }
 }
 
+/// A transaction rejected by the node, e.g. because its validation failed, as returned by
+/// `era_getRejectedTransaction`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedTransaction {
+    pub hash: H256,
+    pub from: Address,
+    pub to: Address,
+    pub nonce: U256,
+    /// The reason of the rejection, e.g. the halt reason of the VM.
+    pub reason: String,
+    /// The latest block when the transaction was rejected.
+    pub block_number: U64,
+}
+
+impl RejectedTransaction {
+    /// Returns the record of the transaction, with the reason and the block number yet to be filled in.
+    pub(crate) fn new(tx: &L2Tx) -> Self {
+        Self {
+            hash: tx.hash(),
+            from: tx.initiator_account(),
+            to: tx.recipient_account(),
+            nonce: U256::from(tx.nonce().0),
+            reason: String::new(),
+            block_number: U64::zero(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TransactionResult {
     pub info: TxExecutionInfo,
@@ -280,6 +371,84 @@ impl TransactionResult {
             ..self.debug.clone()
         }
     }
+
+    /// Returns the storage writes and the events of the transaction.
+    pub fn detailed_output(&self) -> TransactionDetailedResult {
+        TransactionDetailedResult {
+            transaction_hash: self.receipt.transaction_hash,
+            storage_logs: self
+                .info
+                .result
+                .logs
+                .storage_logs
+                .iter()
+                .filter(|log| log.log_type != StorageLogQueryType::Read)
+                .map(|log| ApiStorageLog {
+                    address: log.log_query.address,
+                    key: log.log_query.key,
+                    written_value: log.log_query.written_value,
+                })
+                .collect(),
+            events: self.receipt.logs.clone(),
+        }
+    }
+}
+
+/// Storage write made by a transaction, as returned by `zks_sendRawTransactionWithDetailedOutput`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiStorageLog {
+    pub address: Address,
+    pub key: U256,
+    pub written_value: U256,
+}
+
+/// Result of `zks_sendRawTransactionWithDetailedOutput`, matching the format of the main node.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionDetailedResult {
+    pub transaction_hash: H256,
+    pub storage_logs: Vec<ApiStorageLog>,
+    pub events: Vec<Log>,
+}
+
+/// Groups the mined blocks into L1 batches that span multiple blocks.
+///
+/// Every mined block is executed in its own batch by the VM; the sealer only changes the batch number
+/// the blocks, transactions and logs are reported with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct L1BatchSealer {
+    /// The number of mined blocks after which the open batch is sealed, `0` to only seal it with `era_sealL1Batch`.
+    pub interval: u64,
+    /// The number of the open batch, which the next mined block is assigned to.
+    pub open_batch: u32,
+    /// The number of blocks mined in the open batch.
+    pub blocks: u64,
+    /// The batch numbers used by the VM for the blocks assigned by the sealer, needed when rolling back.
+    vm_batches: BTreeMap<u64, u32>,
+}
+
+impl L1BatchSealer {
+    pub fn new(interval: u64, open_batch: u32) -> Self {
+        Self {
+            interval,
+            open_batch,
+            blocks: 0,
+            vm_batches: Default::default(),
+        }
+    }
+
+    /// Seals the open batch, unless it's empty.
+    ///
+    /// # Returns
+    /// The number of the last sealed batch.
+    pub fn seal(&mut self) -> u32 {
+        if self.blocks > 0 {
+            self.open_batch = self.open_batch.saturating_add(1);
+            self.blocks = 0;
+        }
+        self.open_batch.saturating_sub(1)
+    }
 }
 
 /// Helper struct for InMemoryNode.
@@ -307,6 +476,8 @@ pub struct InMemoryNodeInner<S> {
     pub block_hashes: HashMap<u64, H256>,
     // Map from filter_id to the eth filter
     pub filters: EthFilters,
+    // Subscriptions of the WebSocket clients, kept across snapshots.
+    pub subscriptions: EthSubscriptions,
     // Underlying storage
     pub fork_storage: ForkStorage<S>,
     // Debug level information.
@@ -317,16 +488,98 @@ pub struct InMemoryNodeInner<S> {
     pub show_vm_details: ShowVMDetails,
     // Gas details information.
     pub show_gas_details: ShowGasDetails,
+    /// If true - the calls to the precompiles are printed along with the user and system calls.
+    pub show_precompiles: bool,
+    /// The maximum depth of the printed call traces, the deeper calls are only counted.
+    pub show_calls_max_depth: Option<usize>,
+    /// If true - the return data of the calls is printed along with the call traces.
+    pub show_outputs: bool,
     // If true - will contact openchain to resolve the ABI to function names.
     pub resolve_hashes: bool,
+    /// The maximum amount of gas a block may use. Transactions with a higher gas limit are rejected.
+    pub block_gas_limit: u64,
+    /// An optional cap on the gas limit of a single transaction.
+    pub tx_gas_limit: Option<u64>,
+    /// The minimum max fee per gas of the submitted transactions, the ones offering less are rejected.
+    pub min_gas_price: Option<u64>,
+    /// The maximum max fee per gas of the submitted transactions, the ones offering more are rejected.
+    pub max_gas_price: Option<u64>,
+    /// If true - transactions exceeding the [InMemoryNodeInner::block_gas_limit] are not rejected.
+    pub disable_block_gas_limit: bool,
+    /// If true - every submitted transaction is executed in its own block right away. Otherwise, transactions
+    /// are kept in [InMemoryNodeInner::pending_transactions] until the next block is mined.
+    pub automine: bool,
+    /// If true - the state root of each block is computed from the storage, otherwise it is left zero.
+    pub state_roots: bool,
+    /// The number of seconds between the timestamps of the mined blocks, set with `anvil_setBlockTimestampInterval`.
+    /// If `None`, every block is 1 second after the previous one.
+    pub block_timestamp_interval: Option<u64>,
+    /// Transactions waiting to be sealed into the next block when automine is disabled.
+    pub pending_transactions: Vec<L2Tx>,
+    /// Transactions submitted with a nonce ahead of the next nonce of their sender, keyed by sender and nonce.
+    /// They are submitted once the transactions filling the gap are. Limited to [MAX_QUEUED_TRANSACTIONS].
+    pub queued_transactions: BTreeMap<(Address, Nonce), L2Tx>,
+    /// If true - the received timestamps of transactions are derived from the block timestamp
+    /// instead of the wall-clock time.
+    pub deterministic: bool,
+    /// The operator address that collects the transaction fees, reported as the `miner` of the blocks.
+    pub fee_account: Address,
+    /// If true - the sender nonce is aligned with the transaction nonce before execution.
+    pub skip_nonce_check: bool,
+    /// If true - the sender is credited with the funds it misses to pay for the transaction before execution.
+    pub skip_balance_check: bool,
+    /// The factor by which the gas limit found by `eth_estimateGas` is scaled.
+    pub estimate_gas_scale_factor: f32,
+    /// The amount of gas `eth_estimateGas` is allowed to overestimate before the binary search stops.
+    pub estimate_gas_acceptable_overestimation: u32,
+    /// An optional cap on the number of binary search iterations of `eth_estimateGas`.
+    pub estimate_gas_max_iterations: Option<u32>,
+    /// Limits of a single VM execution, aborting runaway calls and transactions.
+    pub execution_limits: ExecutionLimits,
+    /// Groups the mined blocks into L1 batches. If `None`, every mined block is sealed in its own batch.
+    pub l1_batch_sealer: Option<L1BatchSealer>,
     pub console_log_handler: ConsoleLogHandler,
     pub system_contracts: SystemContracts,
     pub impersonated_accounts: HashSet<Address>,
+    /// Hashes of the transactions whose signature was verified by the node rather than by their account, e.g. as it
+    /// covers an ignored access list. They are executed like the transactions of the impersonated accounts, and
+    /// removed once sealed or rejected.
+    pub node_verified_txs: HashSet<H256>,
     pub rich_accounts: HashSet<H160>,
+    /// Private keys of the accounts imported with `--import-key` or `era_importAccount`, which the node signs the
+    /// transactions and messages of.
+    pub signers: HashMap<Address, H256>,
     /// Keeps track of historical states indexed via block hash. Limited to [MAX_PREVIOUS_STATES].
     pub previous_states: IndexMap<H256, HashMap<StorageKey, StorageValue>>,
+    /// The number of times the chain was rolled back, mixed into the hashes of the blocks mined afterwards so that a
+    /// re-mined block never gets the hash of the block it replaces.
+    pub rollbacks: u64,
     /// An optional handle to the observability stack
     pub observability: Option<Observability>,
+    /// Hooks called on the transaction lifecycle events.
+    pub hooks: Vec<Arc<dyn NodeHook>>,
+    /// Random identifier of this node instance, so that tools can detect a restarted node.
+    pub instance_id: H256,
+    /// Time the node was started, reported by `era_getNodeInfo`.
+    pub started_at: SystemTime,
+    /// URL of the network `era_importContract` imports the contracts from, if not the forked one.
+    pub import_url: Option<String>,
+    /// Contracts submitted to the verification API, kept across snapshots.
+    pub verified_contracts: VerifiedContracts,
+    /// Labels of the addresses displayed in the output, set with `config_setAddressLabels`.
+    pub address_labels: AddressLabels,
+    /// Prices of the tokens in USD set with `era_setTokenPrice`, taking precedence over the built-in ones.
+    pub token_prices: HashMap<Address, BigDecimal>,
+    /// The latest transactions rejected by the node, keyed by hash. Limited to [MAX_REJECTED_TRANSACTIONS].
+    pub rejected_transactions: IndexMap<H256, RejectedTransaction>,
+    /// The instructions executed by the latest transactions, keyed by hash. Limited to [MAX_EXECUTION_COVERAGES].
+    pub execution_coverage: IndexMap<H256, ExecutionCoverage>,
+    /// The open isolation contexts, the innermost last.
+    pub contexts: Vec<IsolationContext>,
+    /// Rules of the transaction filter set with `era_setTxFilter`, the first matching one applies.
+    pub tx_filter: Vec<TxFilterRule>,
+    /// The window of the gas report started with `era_startGasReport`, if any.
+    pub gas_report: Option<GasReportWindow>,
 }
 
 type L2TxResult = (
@@ -339,6 +592,14 @@ type L2TxResult = (
 );
 
 impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
+    /// Returns the base fee of the L1 batches produced by the node, which is the effective gas price
+    /// of their transactions.
+    pub fn batch_base_fee(&self) -> u64 {
+        let fee_input_provider = self.fee_input_provider.clone();
+        let fee_input = block_on(async move { fee_input_provider.get_batch_fee_input().await });
+        derive_base_fee_and_gas_per_pubdata(fee_input, VmVersion::latest()).0
+    }
+
     /// Create [L1BatchEnv] to be used in the VM.
     ///
     /// We compute l1/l2 block details from storage to support fork testing, where the storage
@@ -361,12 +622,15 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
             self.current_timestamp,
         );
 
-        let block_ctx = BlockContext::from_current(
+        let mut block_ctx = BlockContext::from_current(
             last_l1_block_num,
             last_l2_block.number as u64,
             latest_timestamp,
         )
         .new_batch();
+        if let Some(interval) = self.block_timestamp_interval {
+            block_ctx.timestamp = latest_timestamp.saturating_add(interval);
+        }
 
         let fee_input_provider = self.fee_input_provider.clone();
         let batch_env = L1BatchEnv {
@@ -375,7 +639,7 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
             number: L1BatchNumber::from(block_ctx.batch),
             timestamp: block_ctx.timestamp,
             fee_input: block_on(async move { fee_input_provider.get_batch_fee_input().await }),
-            fee_account: H160::zero(),
+            fee_account: self.fee_account,
             enforced_base_fee: None,
             first_l2_block: L2BlockEnv {
                 // the 'current_miniblock' contains the block that was already produced.
@@ -414,6 +678,143 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         }
     }
 
+    /// Returns `true` if no blocks were mined and no transactions are pending since the fork at `fork_miniblock`.
+    fn is_at_fork_point(&self, fork_miniblock: u64) -> bool {
+        self.current_miniblock == fork_miniblock && self.pending_transactions.is_empty()
+    }
+
+    /// Returns the L1 batch number of the latest block.
+    pub fn latest_l1_batch(&self) -> u32 {
+        self.blocks
+            .get(&self.current_miniblock_hash)
+            .and_then(|block| block.l1_batch_number)
+            .map_or(self.current_batch, |number| number.as_u32())
+    }
+
+    /// Returns the blocks of the L1 batch, by number.
+    pub fn l1_batch_blocks(&self, batch: L1BatchNumber) -> Vec<&Block<TransactionVariant>> {
+        let batch = U64::from(batch.0);
+        let mut blocks: Vec<_> = self
+            .blocks
+            .values()
+            .filter(|block| block.l1_batch_number == Some(batch))
+            .collect();
+        blocks.sort_by_key(|block| block.number);
+        blocks
+    }
+
+    /// Assigns the block to the open batch of the [L1BatchSealer], if any, updating the batch number of
+    /// the block, its transactions and their logs. `first_in_vm_batch` is `true` for the first block
+    /// executed in a new VM batch, which counts as a mined block.
+    pub(crate) fn assign_l1_batch(
+        &mut self,
+        block: &mut Block<TransactionVariant>,
+        first_in_vm_batch: bool,
+    ) {
+        let batch_number = match self.l1_batch_sealer.as_mut() {
+            Some(sealer) => {
+                if first_in_vm_batch {
+                    if sealer.interval > 0 && sealer.blocks >= sealer.interval {
+                        sealer.seal();
+                    }
+                    sealer.blocks += 1;
+                }
+                sealer
+                    .vm_batches
+                    .insert(block.number.as_u64(), self.current_batch);
+                // blocks older than the archived states can't be rolled back to
+                if sealer.vm_batches.len() > MAX_PREVIOUS_STATES as usize {
+                    sealer.vm_batches.pop_first();
+                }
+                U64::from(sealer.open_batch)
+            }
+            None => return,
+        };
+
+        block.l1_batch_number = Some(batch_number);
+        for tx in block.transactions.iter() {
+            let tx_hash = match tx {
+                TransactionVariant::Full(tx) => tx.hash,
+                TransactionVariant::Hash(hash) => *hash,
+            };
+            if let Some(tx_result) = self.tx_results.get_mut(&tx_hash) {
+                tx_result.info.batch_number = batch_number.as_u32();
+                tx_result.receipt.l1_batch_number = Some(batch_number);
+                for log in tx_result.receipt.logs.iter_mut() {
+                    log.l1_batch_number = Some(batch_number);
+                }
+                for log in tx_result.receipt.l2_to_l1_logs.iter_mut() {
+                    log.l1_batch_number = Some(batch_number);
+                }
+            }
+        }
+    }
+
+    /// Adds the block to the chain. The block must directly follow the current miniblock, and `index` is its
+    /// position within the batch. The state of the current miniblock must have been archived with
+    /// [InMemoryNodeInner::archive_head_state] before the state of the new blocks was committed.
+    fn apply_block(&mut self, mut block: Block<TransactionVariant>, index: usize) {
+        // the blocks following the first one of the batch don't change the state, so the state of the previous
+        // block is the committed one
+        if index > 0 {
+            self.archive_head_state();
+        }
+
+        // only the first block of the batch is mined with the interval, the others follow it by 1 second
+        let timestamp_delta = match self.block_timestamp_interval {
+            Some(interval) if index == 0 => interval,
+            _ => 1,
+        };
+        self.current_miniblock = self.current_miniblock.saturating_add(1);
+        self.current_timestamp = self.current_timestamp.saturating_add(timestamp_delta);
+
+        let actual_l1_batch_number = block
+            .l1_batch_number
+            .expect("block must have a l1_batch_number");
+        if actual_l1_batch_number.as_u32() != self.current_batch {
+            panic!(
+                "expected next block to have batch_number {}, got {}",
+                self.current_batch,
+                actual_l1_batch_number.as_u32()
+            );
+        }
+
+        if block.number.as_u64() != self.current_miniblock {
+            panic!(
+                "expected next block to have miniblock {}, got {} | {index}",
+                self.current_miniblock,
+                block.number.as_u64()
+            );
+        }
+
+        if block.timestamp.as_u64() != self.current_timestamp {
+            panic!(
+                "expected next block to have timestamp {}, got {} | {index}",
+                self.current_timestamp,
+                block.timestamp.as_u64()
+            );
+        }
+
+        self.publish_block(block, index == 0);
+    }
+
+    /// Makes the block the head of the chain, once the current miniblock, timestamp and batch were advanced to
+    /// it and its state committed and sealed with [InMemoryNodeInner::seal_state_root], and notifies the hooks,
+    /// subscriptions and filters of it. `new_batch` tells whether the block is the first one of its VM batch.
+    pub(crate) fn publish_block(&mut self, mut block: Block<TransactionVariant>, new_batch: bool) {
+        self.assign_l1_batch(&mut block, new_batch);
+        block.author = self.fee_account;
+        let block_hash = block.hash;
+        self.current_miniblock_hash = block_hash;
+        self.block_hashes.insert(block.number.as_u64(), block.hash);
+        for hook in self.hooks.iter() {
+            hook.on_block_sealed(&block);
+        }
+        self.subscriptions.notify_new_block(&block);
+        self.blocks.insert(block.hash, block);
+        self.filters.notify_new_block(block_hash);
+    }
+
     /// Estimates the gas required for a given call request.
     ///
     /// # Arguments
@@ -426,6 +827,41 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
     pub fn estimate_gas_impl(
         &self,
         req: zksync_types::transaction_request::CallRequest,
+    ) -> jsonrpc_core::Result<Fee> {
+        self.estimate_gas_with_iterations(req, &mut vec![])
+    }
+
+    /// Estimates the gas required for a given call request, recording every attempt of the estimation.
+    ///
+    /// # Arguments
+    ///
+    /// * `req` - A `CallRequest` struct representing the call request to estimate gas for.
+    ///
+    /// # Returns
+    ///
+    /// An [EstimateGasTrace] with the attempts and the outcome of the estimation.
+    pub fn trace_estimate_gas_impl(
+        &self,
+        req: zksync_types::transaction_request::CallRequest,
+    ) -> EstimateGasTrace {
+        let mut iterations = vec![];
+        let result = self.estimate_gas_with_iterations(req, &mut iterations);
+        let (estimated_gas, error) = match result {
+            Ok(fee) => (Some(fee.gas_limit), None),
+            Err(err) => (None, Some(err.message)),
+        };
+
+        EstimateGasTrace {
+            iterations,
+            estimated_gas,
+            error,
+        }
+    }
+
+    fn estimate_gas_with_iterations(
+        &self,
+        req: zksync_types::transaction_request::CallRequest,
+        iterations: &mut Vec<EstimateGasIteration>,
     ) -> jsonrpc_core::Result<Fee> {
         let mut request_with_gas_per_pubdata_overridden = req;
 
@@ -501,15 +937,12 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
             .unwrap_or_default()
             .iter()
             .map(|bytecode| {
-                if storage_view.is_bytecode_known(&hash_bytecode(bytecode)) {
+                let hash = hash_bytecode(bytecode);
+                if storage_view.is_bytecode_known(&hash) {
                     return 0;
                 }
 
-                let length = if let Ok(compressed) = compress_bytecode(bytecode) {
-                    compressed.len()
-                } else {
-                    bytecode.len()
-                };
+                let length = self.fork_storage.published_bytecode_len(hash, bytecode);
                 length as u32 + ESTIMATE_GAS_PUBLISH_BYTE_OVERHEAD
             })
             .sum::<u32>();
@@ -545,7 +978,11 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         let mut attempt_count = 1;
 
         tracing::trace!("Starting gas estimation loop");
-        while lower_bound + ESTIMATE_GAS_ACCEPTABLE_OVERESTIMATION < upper_bound {
+        while lower_bound + self.estimate_gas_acceptable_overestimation < upper_bound
+            && self
+                .estimate_gas_max_iterations
+                .map_or(true, |max_iterations| attempt_count <= max_iterations)
+        {
             let mid = (lower_bound + upper_bound) / 2;
             tracing::trace!(
                 "Attempt {} (lower_bound: {}, upper_bound: {}, mid: {})",
@@ -556,14 +993,20 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
             );
             let try_gas_limit = gas_for_bytecodes_pubdata + mid;
 
-            let estimate_gas_result = InMemoryNodeInner::estimate_gas_step(
+            let (estimate_gas_result, _) = InMemoryNodeInner::estimate_gas_step(
                 l2_tx.clone(),
                 gas_per_pubdata_byte,
                 try_gas_limit,
                 batch_env.clone(),
                 system_env.clone(),
                 &self.fork_storage,
+                self.execution_limits,
+                false,
             );
+            iterations.push(EstimateGasIteration::new(
+                try_gas_limit,
+                &estimate_gas_result,
+            ));
 
             if estimate_gas_result.result.is_failed() {
                 tracing::trace!("Attempt {} FAILED", attempt_count);
@@ -577,22 +1020,28 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
 
         tracing::trace!("Gas Estimation Values:");
         tracing::trace!("  Final upper_bound: {}", upper_bound);
-        tracing::trace!("  ESTIMATE_GAS_SCALE_FACTOR: {}", ESTIMATE_GAS_SCALE_FACTOR);
+        tracing::trace!("  Scale factor: {}", self.estimate_gas_scale_factor);
         tracing::trace!("  MAX_L2_TX_GAS_LIMIT: {}", MAX_L2_TX_GAS_LIMIT);
         let tx_body_gas_limit = cmp::min(
             MAX_L2_TX_GAS_LIMIT as u32,
-            (upper_bound as f32 * ESTIMATE_GAS_SCALE_FACTOR) as u32,
+            (upper_bound as f32 * self.estimate_gas_scale_factor) as u32,
         );
         let suggested_gas_limit = tx_body_gas_limit + gas_for_bytecodes_pubdata;
 
-        let estimate_gas_result = InMemoryNodeInner::estimate_gas_step(
+        let (estimate_gas_result, call_traces) = InMemoryNodeInner::estimate_gas_step(
             l2_tx.clone(),
             gas_per_pubdata_byte,
             suggested_gas_limit,
             batch_env,
             system_env,
             &self.fork_storage,
+            self.execution_limits,
+            true,
         );
+        iterations.push(EstimateGasIteration::new(
+            suggested_gas_limit,
+            &estimate_gas_result,
+        ));
 
         let overhead: u32 = derive_overhead(
             suggested_gas_limit,
@@ -602,51 +1051,75 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
             VmVersion::latest(),
         );
 
+        if estimate_gas_result.result.is_failed() {
+            tracing::info!("{}", format!("Unable to estimate gas for the request with our suggested gas limit of {}. The transaction is most likely unexecutable. Breakdown of estimation:", suggested_gas_limit + overhead).red());
+            tracing::info!(
+                "{}",
+                format!(
+                    "\tEstimated transaction body gas cost: {}",
+                    tx_body_gas_limit
+                )
+                .red()
+            );
+            tracing::info!(
+                "{}",
+                format!("\tGas for pubdata: {}", gas_for_bytecodes_pubdata).red()
+            );
+            tracing::info!("{}", format!("\tOverhead: {}", overhead).red());
+            tracing::info!("=== Call traces of the failed attempt:");
+            let show_calls = if self.show_calls == ShowCalls::None {
+                ShowCalls::User
+            } else {
+                self.show_calls.clone()
+            };
+            for call in &call_traces {
+                formatter::print_call(
+                    call,
+                    0,
+                    &show_calls,
+                    self.show_precompiles,
+                    self.show_calls_max_depth,
+                    self.show_outputs,
+                    &self.verified_contracts,
+                    &self.address_labels,
+                    self.resolve_hashes,
+                );
+            }
+        }
+
+        // the trace of the failed attempt is returned along with the error
+        let trace = estimate_gas_result
+            .result
+            .is_failed()
+            .then(|| create_debug_output(&l2_tx, &estimate_gas_result, call_traces.clone()).ok())
+            .flatten();
         match estimate_gas_result.result {
             ExecutionResult::Revert { output } => {
-                tracing::info!("{}", format!("Unable to estimate gas for the request with our suggested gas limit of {}. The transaction is most likely unexecutable. Breakdown of estimation:", suggested_gas_limit + overhead).red());
-                tracing::info!(
-                    "{}",
-                    format!(
-                        "\tEstimated transaction body gas cost: {}",
-                        tx_body_gas_limit
-                    )
-                    .red()
-                );
-                tracing::info!(
-                    "{}",
-                    format!("\tGas for pubdata: {}", gas_for_bytecodes_pubdata).red()
-                );
-                tracing::info!("{}", format!("\tOverhead: {}", overhead).red());
-                let message = output.to_string();
+                let message = match output.to_string() {
+                    message if message.is_empty() => {
+                        innermost_revert_reason(&call_traces).unwrap_or_default()
+                    }
+                    message => message,
+                };
+                let location = innermost_revert_location(&self.verified_contracts, &call_traces)
+                    .map(|location| format!(" ({})", location))
+                    .unwrap_or_default();
                 let pretty_message = format!(
-                    "execution reverted{}{}",
+                    "execution reverted{}{}{}",
                     if message.is_empty() { "" } else { ": " },
-                    message
+                    message,
+                    location
                 );
-                let data = output.encoded_data();
                 tracing::info!("{}", pretty_message.on_red());
-                Err(into_jsrpc_error(Web3Error::SubmitTransactionError(
-                    pretty_message,
-                    data,
-                )))
+                let mut data = ExecutionErrorData::revert(pretty_message, &output);
+                data.trace = trace;
+                Err(data.into_jsrpc_error())
             }
             ExecutionResult::Halt { reason } => {
-                tracing::info!("{}", format!("Unable to estimate gas for the request with our suggested gas limit of {}. The transaction is most likely unexecutable. Breakdown of estimation:", suggested_gas_limit + overhead).red());
-                tracing::info!(
-                    "{}",
-                    format!(
-                        "\tEstimated transaction body gas cost: {}",
-                        tx_body_gas_limit
-                    )
-                    .red()
-                );
-                tracing::info!(
-                    "{}",
-                    format!("\tGas for pubdata: {}", gas_for_bytecodes_pubdata).red()
-                );
-                tracing::info!("{}", format!("\tOverhead: {}", overhead).red());
-                let message = reason.to_string();
+                // The halt reason is usually generic (e.g. a failed bootloader call), so the revert
+                // reason of the call that actually failed is preferred.
+                let revert_reason = innermost_revert_reason(&call_traces);
+                let message = revert_reason.clone().unwrap_or_else(|| reason.to_string());
                 let pretty_message = format!(
                     "execution reverted{}{}",
                     if message.is_empty() { "" } else { ": " },
@@ -654,10 +1127,10 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
                 );
 
                 tracing::info!("{}", pretty_message.on_red());
-                Err(into_jsrpc_error(Web3Error::SubmitTransactionError(
-                    pretty_message,
-                    vec![],
-                )))
+                let mut data = ExecutionErrorData::halt(pretty_message, &reason);
+                data.reason = revert_reason;
+                data.trace = trace;
+                Err(data.into_jsrpc_error())
             }
             ExecutionResult::Success { .. } => {
                 let full_gas_limit = match tx_body_gas_limit
@@ -703,6 +1176,7 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
     }
 
     /// Runs fee estimation against a sandbox vm with the given gas_limit.
+    /// The call traces are only collected if `trace_calls` is set.
     #[allow(clippy::too_many_arguments)]
     fn estimate_gas_step(
         mut l2_tx: L2Tx,
@@ -711,7 +1185,9 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         batch_env: L1BatchEnv,
         system_env: SystemEnv,
         fork_storage: &ForkStorage<S>,
-    ) -> VmExecutionResultAndLogs {
+        execution_limits: ExecutionLimits,
+        trace_calls: bool,
+    ) -> (VmExecutionResultAndLogs, Vec<Call>) {
         let tx: Transaction = l2_tx.clone().into();
 
         // Set gas_limit for transaction
@@ -752,7 +1228,26 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         let tx: Transaction = l2_tx.into();
         vm.push_transaction(tx);
 
-        vm.execute(VmExecutionMode::OneTx)
+        let limit_tracer = ExecutionLimitTracer::new(execution_limits).into_tracer_pointer();
+        if !trace_calls {
+            return (
+                vm.inspect(limit_tracer.into(), VmExecutionMode::OneTx),
+                vec![],
+            );
+        }
+
+        let call_tracer_result = Arc::new(OnceCell::default());
+        let tracers = vec![
+            CallTracer::new(call_tracer_result.clone()).into_tracer_pointer(),
+            limit_tracer,
+        ];
+        let result = vm.inspect(tracers.into(), VmExecutionMode::OneTx);
+        let call_traces = Arc::try_unwrap(call_tracer_result)
+            .unwrap()
+            .take()
+            .unwrap_or_default();
+
+        (result, call_traces)
     }
 
     /// Sets the `impersonated_account` field of the node.
@@ -766,6 +1261,60 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         self.impersonated_accounts.remove(&address)
     }
 
+    /// Returns whether the transaction is executed without the validation of its account, as it is sent from an
+    /// impersonated account or its signature was verified by the node.
+    fn skips_account_validation(&self, tx: &L2Tx) -> bool {
+        self.impersonated_accounts
+            .contains(&tx.common_data.initiator_address)
+            || self.node_verified_txs.contains(&tx.hash())
+    }
+
+    /// Returns the nonce the next transaction of the account must have: the nonce following its pending
+    /// transactions, or else its account nonce.
+    pub fn next_nonce(&self, address: Address) -> Nonce {
+        let full_nonce = (&self.fork_storage).read_value(&get_nonce_key(&address));
+        let account_nonce = Nonce(decompose_full_nonce(h256_to_u256(full_nonce)).0.low_u32());
+        self.pending_transactions
+            .iter()
+            .filter(|tx| tx.initiator_account() == address)
+            .map(|tx| Nonce(tx.nonce().0.saturating_add(1)))
+            .fold(account_nonce, cmp::max)
+    }
+
+    /// Returns the nonce of the next transaction of the account in the pending state: the [Self::next_nonce],
+    /// skipping over the nonces of its queued transactions that directly follow it.
+    pub fn pending_nonce(&self, address: Address) -> Nonce {
+        let mut nonce = self.next_nonce(address);
+        while self.queued_transactions.contains_key(&(address, nonce)) {
+            nonce = Nonce(nonce.0.saturating_add(1));
+        }
+        nonce
+    }
+
+    /// Archives the current state under the current miniblock, logging a failure. Must be called before the state
+    /// of a new block is committed, so that the archived state is the one the current miniblock ended with.
+    pub(crate) fn archive_head_state(&mut self) {
+        if let Err(err) = self.archive_state() {
+            tracing::error!(
+                "failed archiving state for block {}: {}",
+                self.current_miniblock,
+                err
+            );
+        }
+    }
+
+    /// Computes the hash of a new block, which differs from the hash of any block removed by a rollback.
+    /// The computed block hash here will be different than that in production.
+    pub(crate) fn new_block_hash(&self, block_number: u64, tx_hash: H256) -> H256 {
+        let hash = compute_hash(block_number, tx_hash);
+        if self.rollbacks == 0 {
+            return hash;
+        }
+        H256(keccak256(
+            &[hash.as_bytes(), &self.rollbacks.to_be_bytes()[..]].concat(),
+        ))
+    }
+
     /// Archives the current state for later queries.
     pub fn archive_state(&mut self) -> Result<(), String> {
         if self.previous_states.len() > MAX_PREVIOUS_STATES as usize {
@@ -792,6 +1341,136 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         Ok(())
     }
 
+    /// Records the result of a transaction executed in the block being sealed: builds its logs and receipt, notifies
+    /// the filters and the subscriptions of the logs, and runs the hooks. The transactions that halted must have been
+    /// rejected instead.
+    fn seal_transaction(
+        &mut self,
+        sealed: &mut SealedTransactions,
+        l2_tx: L2Tx,
+        result: VmExecutionResultAndLogs,
+        call_traces: Vec<Call>,
+        started_at: Instant,
+    ) {
+        let tx_hash = l2_tx.hash();
+        let tx_index = sealed.transactions.len();
+        self.node_verified_txs.remove(&tx_hash);
+        let gas_used = l2_tx.common_data.fee.gas_limit - result.refunds.gas_refunded;
+        sealed.cumulative_gas_used += gas_used;
+
+        let logs: Vec<Log> = result
+            .logs
+            .events
+            .iter()
+            .enumerate()
+            .map(|(log_idx, log)| Log {
+                address: log.address,
+                topics: log.indexed_topics.clone(),
+                data: Bytes(log.value.clone()),
+                block_hash: Some(sealed.block_hash),
+                block_number: Some(sealed.block_number),
+                l1_batch_number: sealed.l1_batch_number,
+                transaction_hash: Some(tx_hash),
+                transaction_index: Some(U64::from(tx_index)),
+                log_index: Some(U256::from(sealed.log_index + log_idx)),
+                transaction_log_index: Some(U256::from(log_idx)),
+                log_type: None,
+                removed: Some(false),
+            })
+            .collect();
+        sealed.log_index += logs.len();
+        for log in logs.iter() {
+            self.filters.notify_new_log(log, sealed.block_number);
+            self.subscriptions.notify_new_log(log, sealed.block_number);
+        }
+
+        let l2_to_l1_logs = api_l2_to_l1_logs(
+            &result,
+            tx_hash,
+            tx_index,
+            sealed.block_hash,
+            sealed.block_number,
+            sealed.l1_batch_number,
+            sealed.l2_to_l1_log_index,
+        );
+        sealed.l2_to_l1_log_index += l2_to_l1_logs.len();
+
+        let tx_receipt = TransactionReceipt {
+            transaction_hash: tx_hash,
+            transaction_index: U64::from(tx_index),
+            block_hash: sealed.block_hash,
+            block_number: sealed.block_number,
+            // all the transactions of the block are sealed in the same batch
+            l1_batch_tx_index: Some(U64::from(tx_index)),
+            l1_batch_number: sealed.l1_batch_number,
+            from: l2_tx.initiator_account(),
+            to: Some(l2_tx.recipient_account()),
+            root: H256::zero(),
+            cumulative_gas_used: sealed.cumulative_gas_used,
+            gas_used: Some(gas_used),
+            contract_address: contract_address_from_tx_result(&result),
+            logs,
+            l2_to_l1_logs,
+            status: if result.result.is_failed() {
+                U64::from(0)
+            } else {
+                U64::from(1)
+            },
+            effective_gas_price: Some(sealed.effective_gas_price),
+            ..Default::default()
+        };
+
+        let mut transaction = zksync_types::api::Transaction::from(l2_tx.clone());
+        transaction.block_hash = Some(sealed.block_hash);
+        transaction.block_number = Some(sealed.block_number);
+        transaction.transaction_index = Some(U64::from(tx_index));
+        sealed
+            .transactions
+            .push(TransactionVariant::Full(transaction));
+
+        // OK to unwrap here as the halted transactions are rejected before
+        let debug = create_debug_output(&l2_tx, &result, call_traces).expect("create debug output");
+        METRICS.observe_transaction(gas_used.low_u64());
+        tracing::info!(
+            target: STRUCTURED_LOG_TARGET,
+            tx_hash = %format!("{:#x}", tx_hash),
+            block = sealed.block_number.as_u64(),
+            gas_used = gas_used.low_u64(),
+            success = !result.result.is_failed(),
+            duration_ms = started_at.elapsed().as_millis() as u64,
+            "transaction executed"
+        );
+        for hook in self.hooks.iter() {
+            hook.after_execution(&l2_tx, &result);
+        }
+        self.tx_results.insert(
+            tx_hash,
+            TransactionResult {
+                info: TxExecutionInfo {
+                    tx: l2_tx,
+                    batch_number: sealed.l1_batch_number.unwrap_or_default().as_u32(),
+                    miniblock_number: sealed.block_number.as_u64(),
+                    result,
+                },
+                receipt: tx_receipt,
+                debug,
+            },
+        );
+    }
+
+    /// Records the rejection of a transaction, evicting the oldest record above [MAX_REJECTED_TRANSACTIONS].
+    fn record_rejected_transaction(&mut self, mut rejected: RejectedTransaction, reason: &str) {
+        if self.rejected_transactions.len() >= MAX_REJECTED_TRANSACTIONS {
+            self.rejected_transactions.shift_remove_index(0);
+        }
+        rejected.reason = reason.to_string();
+        rejected.block_number = U64::from(self.current_miniblock);
+        self.node_verified_txs.remove(&rejected.hash);
+        // a resubmitted transaction is recorded as the latest one
+        self.rejected_transactions.shift_remove(&rejected.hash);
+        self.rejected_transactions.insert(rejected.hash, rejected);
+    }
+
     /// Creates a [Snapshot] of the current state of the node.
     pub fn snapshot(&self) -> Result<Snapshot, String> {
         let storage = self
@@ -803,9 +1482,13 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         Ok(Snapshot {
             current_timestamp: self.current_timestamp,
             current_batch: self.current_batch,
+            l1_batch_sealer: self.l1_batch_sealer.clone(),
             current_miniblock: self.current_miniblock,
             current_miniblock_hash: self.current_miniblock_hash,
             fee_input_provider: self.fee_input_provider.clone(),
+            pending_transactions: self.pending_transactions.clone(),
+            queued_transactions: self.queued_transactions.clone(),
+            node_verified_txs: self.node_verified_txs.clone(),
             tx_results: self.tx_results.clone(),
             blocks: self.blocks.clone(),
             block_hashes: self.block_hashes.clone(),
@@ -829,9 +1512,19 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
 
         self.current_timestamp = snapshot.current_timestamp;
         self.current_batch = snapshot.current_batch;
+        self.l1_batch_sealer = snapshot.l1_batch_sealer;
         self.current_miniblock = snapshot.current_miniblock;
         self.current_miniblock_hash = snapshot.current_miniblock_hash;
         self.fee_input_provider = snapshot.fee_input_provider;
+        self.pending_transactions = snapshot.pending_transactions;
+        self.queued_transactions = snapshot.queued_transactions;
+        self.node_verified_txs = snapshot.node_verified_txs;
+        self.subscriptions.notify_removed_logs(
+            self.tx_results
+                .iter()
+                .filter(|(hash, _)| !snapshot.tx_results.contains_key(hash))
+                .flat_map(|(_, result)| result.receipt.logs.iter()),
+        );
         self.tx_results = snapshot.tx_results;
         self.blocks = snapshot.blocks;
         self.block_hashes = snapshot.block_hashes;
@@ -839,57 +1532,405 @@ impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
         self.impersonated_accounts = snapshot.impersonated_accounts;
         self.rich_accounts = snapshot.rich_accounts;
         self.previous_states = snapshot.previous_states;
-        storage.raw_storage = snapshot.raw_storage;
+        storage.replace_state(snapshot.raw_storage.state);
+        storage.replace_factory_deps(snapshot.raw_storage.factory_deps);
         storage.value_read_cache = snapshot.value_read_cache;
         storage.factory_dep_cache = snapshot.factory_dep_cache;
 
         Ok(())
     }
-}
 
-/// Creates a restorable snapshot for the [InMemoryNodeInner]. The snapshot contains all the necessary
-/// data required to restore the [InMemoryNodeInner] state to a previous point in time.
-#[derive(Debug, Clone)]
-pub struct Snapshot {
-    pub(crate) current_timestamp: u64,
-    pub(crate) current_batch: u32,
-    pub(crate) current_miniblock: u64,
-    pub(crate) current_miniblock_hash: H256,
-    // Currently, the fee is static and the fee input provider is immutable during the test node life cycle,
-    // but in the future, it may contain some mutable state.
-    pub(crate) fee_input_provider: TestNodeFeeInputProvider,
-    pub(crate) tx_results: HashMap<H256, TransactionResult>,
-    pub(crate) blocks: HashMap<H256, Block<TransactionVariant>>,
-    pub(crate) block_hashes: HashMap<u64, H256>,
-    pub(crate) filters: EthFilters,
-    pub(crate) impersonated_accounts: HashSet<Address>,
-    pub(crate) rich_accounts: HashSet<H160>,
-    pub(crate) previous_states: IndexMap<H256, HashMap<StorageKey, StorageValue>>,
-    pub(crate) raw_storage: InMemoryStorage,
-    pub(crate) value_read_cache: HashMap<StorageKey, H256>,
-    pub(crate) factory_dep_cache: HashMap<H256, Option<Vec<u8>>>,
-}
+    /// Begins an isolation context, returning the number of the open contexts. Unlike a [Snapshot], only the
+    /// head of the chain is copied; the storage writes are journaled until the context ends.
+    pub fn begin_context(&mut self) -> Result<usize, String> {
+        self.fork_storage
+            .inner
+            .write()
+            .map_err(|err| format!("failed acquiring write lock on storage: {:?}", err))?
+            .journals
+            .push(Default::default());
+        self.contexts.push(IsolationContext {
+            current_timestamp: self.current_timestamp,
+            current_batch: self.current_batch,
+            l1_batch_sealer: self.l1_batch_sealer.clone(),
+            current_miniblock: self.current_miniblock,
+            current_miniblock_hash: self.current_miniblock_hash,
+            fee_input_provider: self.fee_input_provider.clone(),
+            pending_transactions: self.pending_transactions.clone(),
+            queued_transactions: self.queued_transactions.clone(),
+            node_verified_txs: self.node_verified_txs.clone(),
+            filters: self.filters.clone(),
+            impersonated_accounts: self.impersonated_accounts.clone(),
+            rich_accounts: self.rich_accounts.clone(),
+        });
+        Ok(self.contexts.len())
+    }
 
-/// Defines the configuration parameters for the [InMemoryNode].
-#[derive(Default, Debug, Clone)]
-pub struct InMemoryNodeConfig {
-    pub show_calls: ShowCalls,
-    pub show_storage_logs: ShowStorageLogs,
-    pub show_vm_details: ShowVMDetails,
-    pub show_gas_details: ShowGasDetails,
-    pub resolve_hashes: bool,
-    pub system_contracts_options: system_contracts::Options,
-}
+    /// Ends the innermost isolation context, restoring the node to the state it had when the context began: the
+    /// blocks mined since are removed along with their transactions and the storage writes are undone.
+    /// Returns `false` if no context is open.
+    pub fn end_context(&mut self) -> Result<bool, String> {
+        let context = match self.contexts.pop() {
+            Some(context) => context,
+            None => return Ok(false),
+        };
+
+        let mut numbers: Vec<u64> = self
+            .block_hashes
+            .keys()
+            .copied()
+            .filter(|number| *number > context.current_miniblock)
+            .collect();
+        numbers.sort_unstable();
+        let mut removed_tx_results = vec![];
+        for number in numbers.into_iter().rev() {
+            if let Some(hash) = self.block_hashes.remove(&number) {
+                if let Some(block) = self.blocks.remove(&hash) {
+                    for tx in block.transactions.iter() {
+                        let tx_hash = match tx {
+                            TransactionVariant::Full(tx) => tx.hash,
+                            TransactionVariant::Hash(hash) => *hash,
+                        };
+                        removed_tx_results.extend(self.tx_results.remove(&tx_hash));
+                    }
+                }
+                self.previous_states.shift_remove(&hash);
+            }
+        }
+
+        self.fork_storage
+            .inner
+            .write()
+            .map_err(|err| format!("failed acquiring write lock on storage: {:?}", err))?
+            .revert_journal();
+        self.current_timestamp = context.current_timestamp;
+        self.current_batch = context.current_batch;
+        self.l1_batch_sealer = context.l1_batch_sealer;
+        self.current_miniblock = context.current_miniblock;
+        self.current_miniblock_hash = context.current_miniblock_hash;
+        self.fee_input_provider = context.fee_input_provider;
+        self.pending_transactions = context.pending_transactions;
+        self.queued_transactions = context.queued_transactions;
+        self.node_verified_txs = context.node_verified_txs;
+        self.filters = context.filters;
+        self.impersonated_accounts = context.impersonated_accounts;
+        self.rich_accounts = context.rich_accounts;
+        self.subscriptions.notify_removed_logs(
+            removed_tx_results
+                .iter()
+                .flat_map(|result| result.receipt.logs.iter()),
+        );
+
+        Ok(true)
+    }
+
+    /// Forks the network at another block (see `era_warpToBlock`), discarding the local blocks, transactions
+    /// and state. The impersonated accounts, the signers, the hooks and the options set with the `config_*` and
+    /// `era_*` methods are kept, and the rich accounts are funded again, as on a restart with `--fork-at`.
+    pub fn reset_fork(&mut self, fork: ForkDetails<S>) {
+        self.subscriptions.notify_removed_logs(
+            self.tx_results
+                .values()
+                .flat_map(|result| result.receipt.logs.iter()),
+        );
+
+        self.current_timestamp = fork.block_timestamp;
+        self.current_batch = fork.l1_block.0;
+        self.current_miniblock = fork.l2_miniblock;
+        self.current_miniblock_hash = fork.l2_miniblock_hash;
+        self.fee_input_provider = TestNodeFeeInputProvider::new(fork.l1_gas_price);
+        if let Some(sealer) = self.l1_batch_sealer.as_mut() {
+            *sealer = L1BatchSealer::new(sealer.interval, fork.l1_block.0 + 1);
+        }
+        self.tx_results.clear();
+        self.blocks.clear();
+        self.blocks
+            .insert(fork.l2_miniblock_hash, fork.l2_block.clone());
+        self.block_hashes.clear();
+        self.block_hashes
+            .insert(fork.l2_miniblock, fork.l2_miniblock_hash);
+        self.pending_transactions.clear();
+        self.queued_transactions.clear();
+        self.node_verified_txs.clear();
+        self.contexts.clear();
+        self.previous_states.clear();
+        self.fork_storage.reset_fork(fork);
+        for address in self.rich_accounts.clone() {
+            self.fund_rich_account(address);
+        }
+    }
+
+    /// Adds a lot of tokens to a given account, which is then known as rich.
+    pub(crate) fn fund_rich_account(&mut self, address: H160) {
+        let key = storage_key_for_eth_balance(&address);
+        let keys = {
+            let mut storage_view = StorageView::new(&self.fork_storage);
+            storage_view.set_value(key, u256_to_h256(U256::from(10u128.pow(30))));
+            storage_view.modified_storage_keys().clone()
+        };
+
+        for (key, value) in keys.iter() {
+            self.fork_storage.set_value(*key, *value);
+        }
+        self.rich_accounts.insert(address);
+    }
+
+    /// Removes the latest `num_blocks` blocks along with their transactions, and restores the state
+    /// that was archived for the new latest block. Limited to the last [MAX_PREVIOUS_STATES] blocks.
+    /// The queued transactions are dropped.
+    ///
+    /// # Returns
+    /// The hashes of the removed blocks, starting with the latest one.
+    pub fn rollback_blocks(&mut self, num_blocks: u64) -> Result<Vec<H256>, String> {
+        if num_blocks == 0 {
+            return Err("number of blocks must be greater than 0".to_string());
+        }
+        let target_number = self
+            .current_miniblock
+            .checked_sub(num_blocks)
+            .ok_or_else(|| format!("cannot roll back {} blocks", num_blocks))?;
+        let target_hash = *self
+            .block_hashes
+            .get(&target_number)
+            .ok_or_else(|| format!("block #{} is not available", target_number))?;
+        let target_block = self
+            .blocks
+            .get(&target_hash)
+            .ok_or_else(|| format!("block #{} is not available", target_number))?;
+        let target_timestamp = target_block.timestamp.as_u64();
+        let target_batch = target_block.l1_batch_number.unwrap_or_default().as_u32();
+        let target_state = self
+            .previous_states
+            .get(&target_hash)
+            .cloned()
+            .ok_or_else(|| format!("state for block #{} is not available", target_number))?;
+
+        let mut removed = vec![];
+        let mut removed_tx_results = vec![];
+        for number in (target_number + 1..=self.current_miniblock).rev() {
+            if let Some(hash) = self.block_hashes.remove(&number) {
+                if let Some(block) = self.blocks.remove(&hash) {
+                    for tx in block.transactions.iter() {
+                        let tx_hash = match tx {
+                            TransactionVariant::Full(tx) => tx.hash,
+                            TransactionVariant::Hash(hash) => *hash,
+                        };
+                        removed_tx_results.extend(self.tx_results.remove(&tx_hash));
+                    }
+                }
+                self.previous_states.shift_remove(&hash);
+                removed.push(hash);
+            }
+        }
+
+        self.fork_storage
+            .inner
+            .write()
+            .map_err(|err| format!("failed acquiring write lock on storage: {:?}", err))?
+            .replace_state(target_state);
+        self.current_miniblock = target_number;
+        self.current_miniblock_hash = target_hash;
+        self.current_timestamp = target_timestamp;
+        self.current_batch = target_batch;
+        self.rollbacks = self.rollbacks.saturating_add(1);
+        // the queued transactions wait for nonces of the removed blocks, so they could be released out of order
+        self.queued_transactions.clear();
+        if let Some(sealer) = self.l1_batch_sealer.as_mut() {
+            if let Some(vm_batch) = sealer.vm_batches.get(&target_number) {
+                self.current_batch = *vm_batch;
+            }
+            sealer.vm_batches.split_off(&(target_number + 1));
+            // the blocks mined after the rollback start a new batch
+            sealer.open_batch = target_batch.saturating_add(1);
+            sealer.blocks = 0;
+        }
+        self.subscriptions.notify_removed_logs(
+            removed_tx_results
+                .iter()
+                .flat_map(|result| result.receipt.logs.iter()),
+        );
+
+        Ok(removed)
+    }
+}
+
+/// Head of the chain and the small parts of the [InMemoryNodeInner] at the beginning of an isolation context,
+/// see [InMemoryNodeInner::begin_context]. The blocks are not copied, as the ones mined in the context are simply
+/// removed when it ends.
+#[derive(Debug, Clone)]
+pub struct IsolationContext {
+    current_timestamp: u64,
+    current_batch: u32,
+    l1_batch_sealer: Option<L1BatchSealer>,
+    current_miniblock: u64,
+    current_miniblock_hash: H256,
+    fee_input_provider: TestNodeFeeInputProvider,
+    pending_transactions: Vec<L2Tx>,
+    queued_transactions: BTreeMap<(Address, Nonce), L2Tx>,
+    node_verified_txs: HashSet<H256>,
+    filters: EthFilters,
+    impersonated_accounts: HashSet<Address>,
+    rich_accounts: HashSet<H160>,
+}
+
+/// Creates a restorable snapshot for the [InMemoryNodeInner]. The snapshot contains all the necessary
+/// data required to restore the [InMemoryNodeInner] state to a previous point in time.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub(crate) current_timestamp: u64,
+    pub(crate) current_batch: u32,
+    pub(crate) l1_batch_sealer: Option<L1BatchSealer>,
+    pub(crate) current_miniblock: u64,
+    pub(crate) current_miniblock_hash: H256,
+    // Currently, the fee is static and the fee input provider is immutable during the test node life cycle,
+    // but in the future, it may contain some mutable state.
+    pub(crate) fee_input_provider: TestNodeFeeInputProvider,
+    // The transactions submitted after the snapshot must not be sealed into the reverted chain.
+    pub(crate) pending_transactions: Vec<L2Tx>,
+    pub(crate) queued_transactions: BTreeMap<(Address, Nonce), L2Tx>,
+    pub(crate) node_verified_txs: HashSet<H256>,
+    pub(crate) tx_results: HashMap<H256, TransactionResult>,
+    pub(crate) blocks: HashMap<H256, Block<TransactionVariant>>,
+    pub(crate) block_hashes: HashMap<u64, H256>,
+    pub(crate) filters: EthFilters,
+    pub(crate) impersonated_accounts: HashSet<Address>,
+    pub(crate) rich_accounts: HashSet<H160>,
+    pub(crate) previous_states: IndexMap<H256, HashMap<StorageKey, StorageValue>>,
+    pub(crate) raw_storage: InMemoryStorage,
+    pub(crate) value_read_cache: LruCache<StorageKey, H256>,
+    pub(crate) factory_dep_cache: LruCache<H256, Option<Vec<u8>>>,
+}
+
+/// Defines the configuration parameters for the [InMemoryNode].
+#[derive(Default, Debug, Clone)]
+pub struct InMemoryNodeConfig {
+    pub show_calls: ShowCalls,
+    pub show_storage_logs: ShowStorageLogs,
+    pub show_vm_details: ShowVMDetails,
+    pub show_gas_details: ShowGasDetails,
+    pub show_precompiles: bool,
+    pub show_outputs: bool,
+    pub resolve_hashes: bool,
+    pub system_contracts_options: system_contracts::Options,
+    /// Chain id to use when not forking. Defaults to [TEST_NODE_NETWORK_ID].
+    pub chain_id: Option<u32>,
+    /// The maximum amount of gas a block may use. Defaults to [BLOCK_GAS_LIMIT].
+    pub block_gas_limit: Option<u64>,
+    /// An optional cap on the gas limit of a single transaction.
+    pub tx_gas_limit: Option<u64>,
+    /// The bounds of the max fee per gas of the submitted transactions. Unbounded by default.
+    pub min_gas_price: Option<u64>,
+    pub max_gas_price: Option<u64>,
+    /// If true - transactions exceeding the block gas limit are not rejected.
+    pub disable_block_gas_limit: bool,
+    /// If true - pending transactions are only sealed into a block when mined explicitly.
+    pub no_automine: bool,
+    /// If true - the state roots of the blocks are computed, see [crate::node::compute_state_root]. Zero by default.
+    pub state_roots: bool,
+    /// If true - wall-clock time is never used, so that repeated runs produce the same output.
+    pub deterministic: bool,
+    /// Timestamp of the first block (if not running in fork mode).
+    /// Defaults to [NON_FORK_FIRST_BLOCK_TIMESTAMP].
+    pub start_timestamp: Option<u64>,
+    /// Number of the first block (if not running in fork mode). Defaults to 0.
+    pub start_block_number: Option<u64>,
+    /// The operator address that collects the transaction fees. Defaults to the zero address.
+    pub fee_account: Option<Address>,
+    /// URL of the network `era_importContract` imports the contracts from. Defaults to the forked network, if any.
+    pub import_url: Option<String>,
+    /// Limits of a single VM execution. Unlimited by default.
+    pub execution_limits: ExecutionLimits,
+    /// Number of the threads running the VM for the read-only RPC methods (e.g. `eth_call`). Defaults to 1.
+    pub workers: Option<usize>,
+}
 
 /// In-memory node, that can be used for local & unit testing.
 /// It also supports the option of forking testnet/mainnet.
 /// All contents are removed when object is destroyed.
 #[derive(Clone)]
 pub struct InMemoryNode<S: Clone> {
-    /// A thread safe reference to the [InMemoryNodeInner].
+    /// A thread safe reference to the [InMemoryNodeInner]. Read-only requests (e.g. `eth_call` or
+    /// `eth_getBalance`) only take the read lock, also while running the VM, so that they do not block each
+    /// other; the write lock is reserved for the requests changing the node state.
     inner: Arc<RwLock<InMemoryNodeInner<S>>>,
+    /// Threads running the VM for the read-only requests, off the event loop of the RPC server.
+    pub(crate) workers: VmWorkers,
+    /// Faults injected into the RPC responses, set with `config_setChaos`.
+    pub(crate) chaos: ChaosMiddleware,
     /// List of snapshots of the [InMemoryNodeInner]. This is bounded at runtime by [MAX_SNAPSHOTS].
     pub(crate) snapshots: Arc<RwLock<Vec<Snapshot>>>,
+    /// Connectivity of the forked network last reported by the health checks.
+    pub(crate) fork_status: ForkStatusCache,
+}
+
+/// Generates a random instance id, or a constant one in deterministic mode.
+fn new_instance_id(deterministic: bool) -> H256 {
+    if deterministic {
+        H256::zero()
+    } else {
+        H256::random()
+    }
+}
+
+/// Returns the revert reason (or error) of the innermost call on the failing call path, which usually
+/// explains a failure better than the generic reason reported for the whole transaction.
+fn innermost_revert_reason(calls: &[Call]) -> Option<String> {
+    calls
+        .iter()
+        .rev()
+        .find(|call| call.revert_reason.is_some() || call.error.is_some())
+        .and_then(|call| {
+            innermost_revert_reason(&call.calls)
+                .or_else(|| call.revert_reason.clone())
+                .or_else(|| call.error.clone())
+        })
+}
+
+/// Returns the location in the verified sources of the revert of the innermost failing call, if any.
+fn innermost_revert_location(
+    verified_contracts: &VerifiedContracts,
+    calls: &[Call],
+) -> Option<SourceLocation> {
+    let call = calls
+        .iter()
+        .rev()
+        .find(|call| call.revert_reason.is_some() || call.error.is_some())?;
+    innermost_revert_location(verified_contracts, &call.calls)
+        .or_else(|| verified_contracts.revert_location(Some(call.to), &call.output))
+}
+
+/// Prepares the sender account so that the transaction passes the nonce and/or balance validation:
+/// the account nonce is set to the transaction nonce, and the balance is topped up to cover the
+/// maximum fee and the transferred value.
+fn relax_validation<ST: WriteStorage>(
+    storage: &mut ST,
+    tx: &L2Tx,
+    skip_nonce_check: bool,
+    skip_balance_check: bool,
+) {
+    let sender = tx.common_data.initiator_address;
+
+    if skip_nonce_check {
+        let nonce_key = get_nonce_key(&sender);
+        let (_, deployment_nonce) =
+            decompose_full_nonce(h256_to_u256(storage.read_value(&nonce_key)));
+        let full_nonce = nonces_to_full_nonce(U256::from(tx.common_data.nonce.0), deployment_nonce);
+        storage.set_value(nonce_key, u256_to_h256(full_nonce));
+    }
+
+    if skip_balance_check {
+        let balance_key = storage_key_for_eth_balance(&sender);
+        let balance = h256_to_u256(storage.read_value(&balance_key));
+        let required = tx
+            .common_data
+            .fee
+            .gas_limit
+            .saturating_mul(tx.common_data.fee.max_fee_per_gas)
+            .saturating_add(tx.execute.value);
+        if balance < required {
+            storage.set_value(balance_key, u256_to_h256(required));
+        }
+    }
 }
 
 fn contract_address_from_tx_result(execution_result: &VmExecutionResultAndLogs) -> Option<H160> {
@@ -903,6 +1944,40 @@ fn contract_address_from_tx_result(execution_result: &VmExecutionResultAndLogs)
     None
 }
 
+/// Converts the user L2->L1 logs emitted by a transaction into their API representation.
+/// `first_log_index` is the index of the first log within the block.
+fn api_l2_to_l1_logs(
+    execution_result: &VmExecutionResultAndLogs,
+    tx_hash: H256,
+    tx_index: usize,
+    block_hash: H256,
+    block_number: U64,
+    l1_batch_number: Option<U64>,
+    first_log_index: usize,
+) -> Vec<zksync_types::api::L2ToL1Log> {
+    execution_result
+        .logs
+        .user_l2_to_l1_logs
+        .iter()
+        .enumerate()
+        .map(|(log_idx, log)| zksync_types::api::L2ToL1Log {
+            block_hash: Some(block_hash),
+            block_number,
+            l1_batch_number,
+            log_index: U256::from(first_log_index + log_idx),
+            transaction_index: U64::from(tx_index),
+            transaction_hash: tx_hash,
+            transaction_log_index: U256::from(log_idx),
+            tx_index_in_l1_batch: Some(U64::from(tx_index)),
+            shard_id: U64::from(log.0.shard_id),
+            is_service: log.0.is_service,
+            sender: log.0.sender,
+            key: log.0.key,
+            value: log.0.value,
+        })
+        .collect()
+}
+
 impl<S: ForkSource + std::fmt::Debug + Clone> Default for InMemoryNode<S> {
     fn default() -> Self {
         InMemoryNode::new(None, None, InMemoryNodeConfig::default())
@@ -915,7 +1990,8 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
         observability: Option<Observability>,
         config: InMemoryNodeConfig,
     ) -> Self {
-        let inner = if let Some(f) = &fork {
+        let forked = fork.is_some();
+        let mut inner = if let Some(f) = &fork {
             let mut block_hashes = HashMap::<u64, H256>::new();
             block_hashes.insert(f.l2_block.number.as_u64(), f.l2_block.hash);
             let mut blocks = HashMap::<H256, Block<TransactionVariant>>::new();
@@ -931,56 +2007,164 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
                 blocks,
                 block_hashes,
                 filters: Default::default(),
-                fork_storage: ForkStorage::new(fork, &config.system_contracts_options),
+                subscriptions: Default::default(),
+                fork_storage: ForkStorage::new(
+                    fork,
+                    &config.system_contracts_options,
+                    config.chain_id,
+                ),
                 show_calls: config.show_calls,
                 show_storage_logs: config.show_storage_logs,
                 show_vm_details: config.show_vm_details,
                 show_gas_details: config.show_gas_details,
+                show_precompiles: config.show_precompiles,
+                show_calls_max_depth: None,
+                show_outputs: config.show_outputs,
                 resolve_hashes: config.resolve_hashes,
+                block_gas_limit: config.block_gas_limit.unwrap_or(BLOCK_GAS_LIMIT as u64),
+                tx_gas_limit: config.tx_gas_limit,
+                min_gas_price: config.min_gas_price,
+                max_gas_price: config.max_gas_price,
+                disable_block_gas_limit: config.disable_block_gas_limit,
+                automine: !config.no_automine,
+                state_roots: config.state_roots,
+                block_timestamp_interval: None,
+                pending_transactions: Default::default(),
+                queued_transactions: Default::default(),
+                deterministic: config.deterministic,
+                fee_account: config.fee_account.unwrap_or_default(),
+                skip_nonce_check: false,
+                skip_balance_check: false,
+                estimate_gas_scale_factor: ESTIMATE_GAS_SCALE_FACTOR,
+                estimate_gas_acceptable_overestimation: ESTIMATE_GAS_ACCEPTABLE_OVERESTIMATION,
+                estimate_gas_max_iterations: None,
+                execution_limits: config.execution_limits,
+                l1_batch_sealer: None,
                 console_log_handler: ConsoleLogHandler::default(),
                 system_contracts: SystemContracts::from_options(&config.system_contracts_options),
                 impersonated_accounts: Default::default(),
+                node_verified_txs: Default::default(),
                 rich_accounts: HashSet::new(),
+                signers: HashMap::new(),
+                hooks: vec![],
+                rollbacks: 0,
+                instance_id: new_instance_id(config.deterministic),
+                started_at: SystemTime::now(),
+                import_url: config.import_url.clone(),
+                verified_contracts: Default::default(),
+                address_labels: Default::default(),
+                token_prices: Default::default(),
+                rejected_transactions: Default::default(),
+                execution_coverage: Default::default(),
+                contexts: vec![],
+                tx_filter: vec![],
+                gas_report: None,
                 previous_states: Default::default(),
                 observability,
             }
         } else {
+            let start_block_number = config.start_block_number.unwrap_or_default();
             let mut block_hashes = HashMap::<u64, H256>::new();
-            let block_hash = compute_hash(0, H256::zero());
-            block_hashes.insert(0, block_hash);
+            let block_hash = compute_hash(start_block_number, H256::zero());
+            block_hashes.insert(start_block_number, block_hash);
             let mut blocks = HashMap::<H256, Block<TransactionVariant>>::new();
-            blocks.insert(
-                block_hash,
-                create_empty_block(0, NON_FORK_FIRST_BLOCK_TIMESTAMP, 0, None),
-            );
+            let start_timestamp = config
+                .start_timestamp
+                .unwrap_or(NON_FORK_FIRST_BLOCK_TIMESTAMP);
+            let mut genesis_block =
+                create_empty_block(start_block_number, start_timestamp, 0, None);
+            if let Some(block_gas_limit) = config.block_gas_limit {
+                genesis_block.gas_limit = U256::from(block_gas_limit);
+            }
+            genesis_block.author = config.fee_account.unwrap_or_default();
+            blocks.insert(block_hash, genesis_block);
 
             InMemoryNodeInner {
-                current_timestamp: NON_FORK_FIRST_BLOCK_TIMESTAMP,
+                current_timestamp: start_timestamp,
                 current_batch: 0,
-                current_miniblock: 0,
+                current_miniblock: start_block_number,
                 current_miniblock_hash: block_hash,
                 fee_input_provider: TestNodeFeeInputProvider::new(L1_GAS_PRICE),
                 tx_results: Default::default(),
                 blocks,
                 block_hashes,
                 filters: Default::default(),
-                fork_storage: ForkStorage::new(fork, &config.system_contracts_options),
+                subscriptions: Default::default(),
+                fork_storage: ForkStorage::new(
+                    fork,
+                    &config.system_contracts_options,
+                    config.chain_id,
+                ),
                 show_calls: config.show_calls,
                 show_storage_logs: config.show_storage_logs,
                 show_vm_details: config.show_vm_details,
                 show_gas_details: config.show_gas_details,
+                show_precompiles: config.show_precompiles,
+                show_calls_max_depth: None,
+                show_outputs: config.show_outputs,
                 resolve_hashes: config.resolve_hashes,
+                block_gas_limit: config.block_gas_limit.unwrap_or(BLOCK_GAS_LIMIT as u64),
+                tx_gas_limit: config.tx_gas_limit,
+                min_gas_price: config.min_gas_price,
+                max_gas_price: config.max_gas_price,
+                disable_block_gas_limit: config.disable_block_gas_limit,
+                automine: !config.no_automine,
+                state_roots: config.state_roots,
+                block_timestamp_interval: None,
+                pending_transactions: Default::default(),
+                queued_transactions: Default::default(),
+                deterministic: config.deterministic,
+                fee_account: config.fee_account.unwrap_or_default(),
+                skip_nonce_check: false,
+                skip_balance_check: false,
+                estimate_gas_scale_factor: ESTIMATE_GAS_SCALE_FACTOR,
+                estimate_gas_acceptable_overestimation: ESTIMATE_GAS_ACCEPTABLE_OVERESTIMATION,
+                estimate_gas_max_iterations: None,
+                execution_limits: config.execution_limits,
+                l1_batch_sealer: None,
                 console_log_handler: ConsoleLogHandler::default(),
                 system_contracts: SystemContracts::from_options(&config.system_contracts_options),
                 impersonated_accounts: Default::default(),
+                node_verified_txs: Default::default(),
                 rich_accounts: HashSet::new(),
+                signers: HashMap::new(),
+                hooks: vec![],
+                rollbacks: 0,
+                instance_id: new_instance_id(config.deterministic),
+                started_at: SystemTime::now(),
+                import_url: config.import_url.clone(),
+                verified_contracts: Default::default(),
+                address_labels: Default::default(),
+                token_prices: Default::default(),
+                rejected_transactions: Default::default(),
+                execution_coverage: Default::default(),
+                contexts: vec![],
+                tx_filter: vec![],
+                gas_report: None,
                 previous_states: Default::default(),
                 observability,
             }
         };
 
+        // the genesis block is created before the storage, so its state root, and the hash including it, are
+        // only known now
+        if !forked && inner.state_roots {
+            let genesis_hash = inner.current_miniblock_hash;
+            if let Some(mut genesis_block) = inner.blocks.remove(&genesis_hash) {
+                inner.seal_state_root(&mut genesis_block);
+                inner.current_miniblock_hash = genesis_block.hash;
+                inner
+                    .block_hashes
+                    .insert(genesis_block.number.as_u64(), genesis_block.hash);
+                inner.blocks.insert(genesis_block.hash, genesis_block);
+            }
+        }
+
         InMemoryNode {
             inner: Arc::new(RwLock::new(inner)),
+            workers: VmWorkers::new(config.workers.unwrap_or(1)),
+            chaos: Default::default(),
+            fork_status: Default::default(),
             snapshots: Default::default(),
         }
     }
@@ -989,6 +2173,143 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
         self.inner.clone()
     }
 
+    /// Returns the source of the forked network if the `block` precedes the fork, so that the requests
+    /// at that block are answered by the forked network rather than by the local state.
+    pub fn historical_fork_source(
+        &self,
+        block: Option<BlockIdVariant>,
+    ) -> Result<Option<S>, String> {
+        let block = match block {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        let inner = self
+            .inner
+            .read()
+            .map_err(|err| format!("failed acquiring lock: {:?}", err))?;
+        let storage = inner
+            .fork_storage
+            .inner
+            .read()
+            .map_err(|err| format!("failed acquiring read lock on storage: {:?}", err))?;
+        let fork = match &storage.fork {
+            Some(fork) => fork,
+            None => return Ok(None),
+        };
+
+        let precedes_fork = match block {
+            BlockIdVariant::BlockNumber(block_number)
+            | BlockIdVariant::BlockNumberObject(BlockNumberObject { block_number }) => {
+                match block_number {
+                    BlockNumber::Number(number) => number.as_u64() < fork.l2_miniblock,
+                    BlockNumber::Earliest => fork.l2_miniblock > 0,
+                    _ => false,
+                }
+            }
+            // blocks unknown to the node were produced by the forked network
+            BlockIdVariant::BlockHashObject(object) => {
+                !inner.blocks.contains_key(&object.block_hash)
+            }
+        };
+
+        Ok(precedes_fork.then(|| fork.fork_source.clone()))
+    }
+
+    /// Advances the fork point to the latest block of the forked network, unless blocks were mined or
+    /// transactions were submitted locally since the fork, whose state would conflict with the new fork point.
+    /// Local storage overrides (e.g. balances set via `hardhat_setBalance`) are kept.
+    ///
+    /// # Returns
+    /// The new fork block number, or `None` if the fork point was not advanced.
+    pub fn advance_fork(&self) -> Result<Option<u64>, String> {
+        let (fork_source, fork_miniblock) = {
+            let inner = self
+                .inner
+                .read()
+                .map_err(|err| format!("failed acquiring lock: {:?}", err))?;
+            let storage = inner
+                .fork_storage
+                .inner
+                .read()
+                .map_err(|err| format!("failed acquiring read lock on storage: {:?}", err))?;
+            match &storage.fork {
+                Some(fork) if inner.is_at_fork_point(fork.l2_miniblock) => {
+                    (fork.fork_source.clone(), fork.l2_miniblock)
+                }
+                _ => return Ok(None),
+            }
+        };
+
+        let latest = fork_source
+            .get_block_by_number(BlockNumber::Latest, false)
+            .map_err(|err| format!("failed fetching the latest block of the fork: {:?}", err))?
+            .ok_or_else(|| "the fork returned no latest block".to_string())?
+            .number
+            .as_u64();
+        if latest <= fork_miniblock {
+            return Ok(None);
+        }
+
+        let details = fork_source
+            .get_block_details(MiniblockNumber(latest as u32))
+            .map_err(|err| format!("failed fetching details of block {}: {:?}", latest, err))?
+            .ok_or_else(|| format!("the fork returned no details of block {}", latest))?;
+        if !details
+            .protocol_version
+            .map(supported_protocol_versions)
+            .unwrap_or(false)
+        {
+            return Err(format!(
+                "block {} is using the unsupported protocol version {:?}",
+                latest, details.protocol_version
+            ));
+        }
+        let block = fork_source
+            .get_block_by_number(BlockNumber::Number(U64::from(latest)), true)
+            .map_err(|err| format!("failed fetching block {}: {:?}", latest, err))?
+            .ok_or_else(|| format!("the fork returned no block {}", latest))?;
+
+        let mut writer = self
+            .inner
+            .write()
+            .map_err(|err| format!("failed acquiring lock: {:?}", err))?;
+        // the node may have changed while the block was fetched
+        if !writer.is_at_fork_point(fork_miniblock) {
+            return Ok(None);
+        }
+        let inner = &mut *writer;
+        {
+            let mut storage = inner
+                .fork_storage
+                .inner
+                .write()
+                .map_err(|err| format!("failed acquiring write lock on storage: {:?}", err))?;
+            // the cached values were read at the previous fork point
+            storage.value_read_cache.clear();
+            if let Some(fork) = storage.fork.as_mut() {
+                fork.l1_block = details.l1_batch_number;
+                fork.l2_block = block.clone();
+                fork.l2_miniblock = latest;
+                fork.l2_miniblock_hash = block.hash;
+                fork.block_timestamp = details.base.timestamp;
+                fork.l1_gas_price = details.base.l1_gas_price;
+            }
+        }
+
+        inner.current_timestamp = inner.current_timestamp.max(details.base.timestamp);
+        inner.current_batch = details.l1_batch_number.0;
+        inner.current_miniblock = latest;
+        inner.current_miniblock_hash = block.hash;
+        if let Some(sealer) = inner.l1_batch_sealer.as_mut() {
+            sealer.open_batch = details.l1_batch_number.0.saturating_add(1);
+        }
+        inner.block_hashes.insert(latest, block.hash);
+        inner.blocks.insert(block.hash, block.clone());
+        inner.subscriptions.notify_new_block(&block);
+
+        Ok(Some(latest))
+    }
+
     /// Applies multiple transactions - but still one per L1 batch.
     pub fn apply_txs(&self, txs: Vec<L2Tx>) -> Result<(), String> {
         tracing::info!("Running {:?} transactions (one per batch)", txs.len());
@@ -1000,10 +2321,16 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
         Ok(())
     }
 
+    /// Registers a hook called on the transaction lifecycle events.
+    pub fn add_hook(&self, hook: Arc<dyn NodeHook>) {
+        match self.inner.write() {
+            Ok(mut inner) => inner.hooks.push(hook),
+            Err(e) => tracing::info!("Failed to acquire write lock: {}", e),
+        }
+    }
+
     /// Adds a lot of tokens to a given account.
     pub fn set_rich_account(&self, address: H160) {
-        let key = storage_key_for_eth_balance(&address);
-
         let mut inner = match self.inner.write() {
             Ok(guard) => guard,
             Err(e) => {
@@ -1011,27 +2338,26 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
                 return;
             }
         };
+        inner.fund_rich_account(address);
+    }
 
-        let keys = {
-            let mut storage_view = StorageView::new(&inner.fork_storage);
-            storage_view.set_value(key, u256_to_h256(U256::from(10u128.pow(30))));
-            storage_view.modified_storage_keys().clone()
-        };
-
-        for (key, value) in keys.iter() {
-            inner.fork_storage.set_value(*key, *value);
-        }
-        inner.rich_accounts.insert(address);
+    /// Labels the addresses in the output, see [AddressLabels::extend].
+    pub fn set_address_labels(&self, labels: HashMap<Address, String>) -> anyhow::Result<()> {
+        self.inner
+            .write()
+            .map_err(|err| anyhow::anyhow!("failed acquiring lock: {:?}", err))
+            .map(|mut writer| writer.address_labels.extend(labels))
     }
 
     /// Runs L2 'eth call' method - that doesn't commit to a block.
     pub fn run_l2_call(&self, mut l2_tx: L2Tx) -> Result<ExecutionResult, String> {
         let execution_mode = TxExecutionMode::EthCall;
 
+        // The call only reads the node state, so it runs concurrently with the other readers.
         let inner = self
             .inner
-            .write()
-            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+            .read()
+            .map_err(|e| format!("Failed to acquire read lock: {}", e))?;
 
         let storage = StorageView::new(&inner.fork_storage).into_rc_ptr();
 
@@ -1054,9 +2380,13 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
 
         let call_tracer_result = Arc::new(OnceCell::default());
 
-        let custom_tracer = CallTracer::new(call_tracer_result.clone()).into_tracer_pointer();
+        let tracers = vec![
+            CallTracer::new(call_tracer_result.clone()).into_tracer_pointer(),
+            ExecutionLimitTracer::new(inner.execution_limits).into_tracer_pointer(),
+        ];
 
-        let tx_result = vm.inspect(custom_tracer.into(), VmExecutionMode::OneTx);
+        let tx_result = tracing::info_span!(target: SPAN_TARGET, "vm_execution", mode = "call")
+            .in_scope(|| vm.inspect(tracers.into(), VmExecutionMode::OneTx));
 
         let call_traces = Arc::try_unwrap(call_tracer_result)
             .unwrap()
@@ -1084,7 +2414,20 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
 
         tracing::info!("=== Call traces:");
         for call in &call_traces {
-            formatter::print_call(call, 0, &inner.show_calls, inner.resolve_hashes);
+            formatter::print_call(
+                call,
+                0,
+                &inner.show_calls,
+                inner.show_precompiles,
+                inner.show_calls_max_depth,
+                inner.show_outputs,
+                &inner.verified_contracts,
+                &inner.address_labels,
+                inner.resolve_hashes,
+            );
+        }
+        if let Some(location) = innermost_revert_location(&inner.verified_contracts, &call_traces) {
+            tracing::info!("Reverted at {}", location.to_string().red());
         }
 
         Ok(tx_result.result)
@@ -1250,6 +2593,15 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
 
     // Validates L2 transaction
     fn validate_tx(&self, tx: &L2Tx) -> Result<(), String> {
+        let hooks = self
+            .inner
+            .read()
+            .map(|reader| reader.hooks.clone())
+            .map_err(|e| format!("Failed to acquire read lock: {}", e))?;
+        for hook in hooks.iter() {
+            hook.before_validation(tx)?;
+        }
+
         let max_gas = U256::from(u32::MAX);
         if tx.common_data.fee.gas_limit > max_gas
             || tx.common_data.fee.gas_per_pubdata_limit > max_gas
@@ -1257,6 +2609,72 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
             return Err("exceeds block gas limit".into());
         }
 
+        let (block_gas_limit, tx_gas_limit, disable_block_gas_limit, min_gas_price, max_gas_price) =
+            self.inner
+                .read()
+                .map(|reader| {
+                    (
+                        reader.block_gas_limit,
+                        reader.tx_gas_limit,
+                        reader.disable_block_gas_limit,
+                        reader.min_gas_price,
+                        reader.max_gas_price,
+                    )
+                })
+                .map_err(|e| format!("Failed to acquire read lock: {}", e))?;
+
+        if !disable_block_gas_limit && tx.common_data.fee.gas_limit > U256::from(block_gas_limit) {
+            tracing::info!(
+                "Submitted Tx is Unexecutable {:?} because its gas limit {} exceeds the block gas limit {}",
+                tx.hash(),
+                tx.common_data.fee.gas_limit,
+                block_gas_limit
+            );
+            return Err("exceeds block gas limit".into());
+        }
+
+        if let Some(tx_gas_limit) = tx_gas_limit {
+            if tx.common_data.fee.gas_limit > U256::from(tx_gas_limit) {
+                tracing::info!(
+                    "Submitted Tx is Unexecutable {:?} because its gas limit {} exceeds the transaction gas limit {}",
+                    tx.hash(),
+                    tx.common_data.fee.gas_limit,
+                    tx_gas_limit
+                );
+                return Err("exceeds transaction gas limit".into());
+            }
+        }
+
+        if let Some(min_gas_price) = min_gas_price {
+            if tx.common_data.fee.max_fee_per_gas < U256::from(min_gas_price) {
+                tracing::info!(
+                    "Submitted Tx is Unexecutable {:?} because its max fee per gas {} is below the minimum gas price {}",
+                    tx.hash(),
+                    tx.common_data.fee.max_fee_per_gas,
+                    min_gas_price
+                );
+                return Err(format!(
+                    "max fee per gas {} lower than the minimum gas price {}",
+                    tx.common_data.fee.max_fee_per_gas, min_gas_price
+                ));
+            }
+        }
+
+        if let Some(max_gas_price) = max_gas_price {
+            if tx.common_data.fee.max_fee_per_gas > U256::from(max_gas_price) {
+                tracing::info!(
+                    "Submitted Tx is Unexecutable {:?} because its max fee per gas {} is above the maximum gas price {}",
+                    tx.hash(),
+                    tx.common_data.fee.max_fee_per_gas,
+                    max_gas_price
+                );
+                return Err(format!(
+                    "max fee per gas {} higher than the maximum gas price {}",
+                    tx.common_data.fee.max_fee_per_gas, max_gas_price
+                ));
+            }
+        }
+
         if tx.common_data.fee.max_fee_per_gas < L2_GAS_PRICE.into() {
             tracing::info!(
                 "Submitted Tx is Unexecutable {:?} because of MaxFeePerGasTooLow {}",
@@ -1274,6 +2692,17 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
             );
             return Err("max priority fee per gas higher than max fee per gas".into());
         }
+
+        for bytecode in tx.execute.factory_deps.iter().flatten() {
+            if let Some(message) = validate_bytecode(bytecode).error_message() {
+                tracing::info!(
+                    "Submitted Tx is Unexecutable {:?} because of an {}",
+                    tx.hash(),
+                    message
+                );
+                return Err(message);
+            }
+        }
         Ok(())
     }
 
@@ -1314,6 +2743,12 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
             .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
 
         let storage = StorageView::new(inner.fork_storage.clone()).into_rc_ptr();
+        relax_validation(
+            &mut *storage.borrow_mut(),
+            &l2_tx,
+            inner.skip_nonce_check,
+            inner.skip_balance_check,
+        );
 
         let (batch_env, block_ctx) = inner.create_l1_batch_env(storage.clone());
 
@@ -1327,6 +2762,12 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
                     l2_tx.common_data.initiator_address
                 );
                 inner.system_contracts.contracts(execution_mode, true)
+            } else if inner.node_verified_txs.contains(&l2_tx.hash()) {
+                tracing::info!(
+                    "🔏 Executing tx {:?} with a signature verified by the node",
+                    l2_tx.hash()
+                );
+                inner.system_contracts.contracts(execution_mode, true)
             } else {
                 inner.system_contracts.contracts(execution_mode, false)
             }
@@ -1350,8 +2791,16 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
             }
             .into_tracer_pointer(),
         );
+        tracers.push(ExecutionLimitTracer::new(inner.execution_limits).into_tracer_pointer());
 
-        let tx_result = vm.inspect(tracers.into(), VmExecutionMode::OneTx);
+        let started_at = Instant::now();
+        let tx_result = tracing::info_span!(
+            target: SPAN_TARGET,
+            "vm_execution",
+            tx_hash = %format!("{:#x}", l2_tx.hash())
+        )
+        .in_scope(|| vm.inspect(tracers.into(), VmExecutionMode::OneTx));
+        METRICS.observe_vm_execution(started_at.elapsed());
 
         let call_traces = call_tracer_result.get().unwrap();
 
@@ -1371,10 +2820,16 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
         tracing::info!("Initiator: {:?}", tx.initiator_account());
         tracing::info!("Payer: {:?}", tx.payer());
         tracing::info!(
-            "Gas - Limit: {} | Used: {} | Refunded: {}",
+            "Gas - Limit: {} | Used: {} | Refunded: {} | Price: {} wei | Pubdata: {} bytes",
             to_human_size(tx.gas_limit()),
             to_human_size(tx.gas_limit() - tx_result.refunds.gas_refunded),
-            to_human_size(tx_result.refunds.gas_refunded.into())
+            to_human_size(tx_result.refunds.gas_refunded.into()),
+            to_human_size(
+                derive_base_fee_and_gas_per_pubdata(batch_env.fee_input, VmVersion::latest())
+                    .0
+                    .into()
+            ),
+            to_human_size(tx_result.statistics.pubdata_published.into())
         );
 
         match inner.show_gas_details {
@@ -1395,7 +2850,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
         }
 
         if inner.show_storage_logs != ShowStorageLogs::None {
-            print_storage_logs_details(&inner.show_storage_logs, &tx_result);
+            print_storage_logs_details(&inner.show_storage_logs, &inner.address_labels, &tx_result);
         }
 
         if inner.show_vm_details != ShowVMDetails::None {
@@ -1421,20 +2876,32 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
 
         if inner.show_calls != ShowCalls::None {
             for call in call_traces {
-                formatter::print_call(call, 0, &inner.show_calls, inner.resolve_hashes);
+                formatter::print_call(
+                    call,
+                    0,
+                    &inner.show_calls,
+                    inner.show_precompiles,
+                    inner.show_calls_max_depth,
+                    inner.show_outputs,
+                    &inner.verified_contracts,
+                    &inner.address_labels,
+                    inner.resolve_hashes,
+                );
             }
         }
+        if let Some(location) = innermost_revert_location(&inner.verified_contracts, call_traces) {
+            tracing::info!("Reverted at {}", location.to_string().red());
+        }
         tracing::info!("");
         tracing::info!(
             "==== {}",
             format!("{} events", tx_result.logs.events.len()).bold()
         );
         for event in &tx_result.logs.events {
-            formatter::print_event(event, inner.resolve_hashes);
+            formatter::print_event(event, &inner.address_labels, inner.resolve_hashes);
         }
 
-        // The computed block hash here will be different than that in production.
-        let hash = compute_hash(block_ctx.miniblock, l2_tx.hash());
+        let hash = inner.new_block_hash(block_ctx.miniblock, l2_tx.hash());
 
         let mut transaction = zksync_types::api::Transaction::from(l2_tx);
         transaction.block_hash = Some(inner.current_miniblock_hash);
@@ -1454,7 +2921,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
             l1_batch_number: Some(U64::from(batch_env.number.0)),
             transactions: vec![TransactionVariant::Full(transaction)],
             gas_used: U256::from(tx_result.statistics.gas_used),
-            gas_limit: U256::from(BLOCK_GAS_LIMIT),
+            gas_limit: U256::from(inner.block_gas_limit),
             ..Default::default()
         };
 
@@ -1482,6 +2949,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
     /// Runs L2 transaction and commits it to a new block.
     pub fn run_l2_tx(&self, l2_tx: L2Tx, execution_mode: TxExecutionMode) -> Result<(), String> {
         let tx_hash = l2_tx.hash();
+        let started_at = Instant::now();
 
         tracing::info!("");
         tracing::info!("Validating {}", format!("{:?}", tx_hash).bold());
@@ -1501,10 +2969,16 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
                 .write()
                 .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
             inner.filters.notify_new_pending_transaction(tx_hash);
+            inner.subscriptions.notify_new_pending_transaction(tx_hash);
         }
 
-        let (keys, result, call_traces, block, bytecodes, block_ctx) =
-            self.run_l2_tx_raw(l2_tx.clone(), execution_mode, vec![], true)?;
+        let coverage_result = Arc::new(OnceCell::default());
+        let (keys, result, call_traces, mut block, bytecodes, block_ctx) = self.run_l2_tx_raw(
+            l2_tx.clone(),
+            execution_mode,
+            vec![CoverageTracer::new(coverage_result.clone()).into_tracer_pointer()],
+            true,
+        )?;
 
         if let ExecutionResult::Halt { reason } = result.result {
             // Halt means that something went really bad with the transaction execution (in most cases invalid signature,
@@ -1517,6 +2991,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
             .inner
             .write()
             .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        inner.archive_head_state();
         for (key, value) in keys.iter() {
             inner.fork_storage.set_value(*key, *value);
         }
@@ -1534,94 +3009,33 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
                     .collect(),
             )
         }
+        inner.seal_state_root(&mut block);
 
-        for (log_idx, event) in result.logs.events.iter().enumerate() {
-            inner.filters.notify_new_log(
-                &Log {
-                    address: event.address,
-                    topics: event.indexed_topics.clone(),
-                    data: Bytes(event.value.clone()),
-                    block_hash: Some(block.hash),
-                    block_number: Some(block.number),
-                    l1_batch_number: block.l1_batch_number,
-                    transaction_hash: Some(tx_hash),
-                    transaction_index: Some(U64::zero()),
-                    log_index: Some(U256::from(log_idx)),
-                    transaction_log_index: Some(U256::from(log_idx)),
-                    log_type: None,
-                    removed: Some(false),
-                },
-                block.number,
-            );
-        }
-        let tx_receipt = TransactionReceipt {
-            transaction_hash: tx_hash,
-            transaction_index: U64::from(0),
-            block_hash: block.hash,
-            block_number: block.number,
-            l1_batch_tx_index: None,
-            l1_batch_number: block.l1_batch_number,
-            from: l2_tx.initiator_account(),
-            to: Some(l2_tx.recipient_account()),
-            root: H256::zero(),
-            cumulative_gas_used: Default::default(),
-            gas_used: Some(l2_tx.common_data.fee.gas_limit - result.refunds.gas_refunded),
-            contract_address: contract_address_from_tx_result(&result),
-            logs: result
-                .logs
-                .events
-                .iter()
-                .enumerate()
-                .map(|(log_idx, log)| Log {
-                    address: log.address,
-                    topics: log.indexed_topics.clone(),
-                    data: Bytes(log.value.clone()),
-                    block_hash: Some(block.hash),
-                    block_number: Some(block.number),
-                    l1_batch_number: block.l1_batch_number,
-                    transaction_hash: Some(tx_hash),
-                    transaction_index: Some(U64::zero()),
-                    log_index: Some(U256::from(log_idx)),
-                    transaction_log_index: Some(U256::from(log_idx)),
-                    log_type: None,
-                    removed: Some(false),
-                })
-                .collect(),
-            l2_to_l1_logs: vec![],
-            status: if result.result.is_failed() {
-                U64::from(0)
-            } else {
-                U64::from(1)
-            },
-            effective_gas_price: Some(L2_GAS_PRICE.into()),
-            ..Default::default()
-        };
-        let debug = create_debug_output(&l2_tx, &result, call_traces).expect("create debug output"); // OK to unwrap here as Halt is handled above
-        inner.tx_results.insert(
-            tx_hash,
-            TransactionResult {
-                info: TxExecutionInfo {
-                    tx: l2_tx,
-                    batch_number: block.l1_batch_number.unwrap_or_default().as_u32(),
-                    miniblock_number: block.number.as_u64(),
-                    result,
-                },
-                receipt: tx_receipt,
-                debug,
-            },
+        let mut sealed = SealedTransactions::new(
+            block.hash,
+            block.number,
+            block.l1_batch_number,
+            U256::from(inner.batch_base_fee()),
         );
+        inner.seal_transaction(&mut sealed, l2_tx, result, call_traces, started_at);
+        if let Some(coverage) = coverage_result.get() {
+            inner.record_execution_coverage(tx_hash, coverage.clone());
+        }
+        block.transactions = sealed.transactions;
 
         // With the introduction of 'l2 blocks' (and virtual blocks),
         // we are adding one l2 block at the end of each batch (to handle things like remaining events etc).
         //  You can look at insert_fictive_l2_block function in VM to see how this fake block is inserted.
         let block_ctx = block_ctx.new_block();
-        let parent_block_hash = block.hash;
-        let empty_block_at_end_of_batch = create_empty_block(
+        let mut empty_block_at_end_of_batch = create_empty_block(
             block_ctx.miniblock,
             block_ctx.timestamp,
             block_ctx.batch,
-            Some(parent_block_hash),
+            Some(block.hash),
         );
+        empty_block_at_end_of_batch.hash = inner.new_block_hash(block_ctx.miniblock, H256::zero());
+        empty_block_at_end_of_batch.gas_limit = U256::from(inner.block_gas_limit);
+        inner.seal_state_root(&mut empty_block_at_end_of_batch);
 
         inner.current_batch = inner.current_batch.saturating_add(1);
 
@@ -1629,54 +3043,448 @@ impl<S: ForkSource + std::fmt::Debug + Clone> InMemoryNode<S> {
             .into_iter()
             .enumerate()
         {
-            // archive current state before we produce new batch/blocks
-            if let Err(err) = inner.archive_state() {
-                tracing::error!(
-                    "failed archiving state for block {}: {}",
-                    inner.current_miniblock,
-                    err
-                );
-            }
+            inner.apply_block(block, i);
+        }
 
-            inner.current_miniblock = inner.current_miniblock.saturating_add(1);
-            inner.current_timestamp = inner.current_timestamp.saturating_add(1);
-
-            let actual_l1_batch_number = block
-                .l1_batch_number
-                .expect("block must have a l1_batch_number");
-            if actual_l1_batch_number.as_u32() != inner.current_batch {
-                panic!(
-                    "expected next block to have batch_number {}, got {}",
-                    inner.current_batch,
-                    actual_l1_batch_number.as_u32()
-                );
-            }
+        Ok(())
+    }
 
-            if block.number.as_u64() != inner.current_miniblock {
-                panic!(
-                    "expected next block to have miniblock {}, got {} | {i}",
-                    inner.current_miniblock,
-                    block.number.as_u64()
+    /// Submits the transaction to the node. If automine is enabled, the transaction is executed
+    /// and committed to a new block right away. Otherwise, it is validated and added to the pending
+    /// transactions, which are sealed into a single block the next time a block is mined.
+    ///
+    /// A transaction with a nonce ahead of the next nonce of its sender is queued instead, and submitted once
+    /// the transactions filling the gap are, so that concurrently broadcast transactions may arrive out of order.
+    pub fn submit_l2_tx(&self, l2_tx: L2Tx) -> Result<(), String> {
+        let rejected = RejectedTransaction::new(&l2_tx);
+        self.queue_or_submit_l2_tx(l2_tx).map_err(|reason| {
+            self.reject_transaction(rejected, &reason);
+            reason
+        })
+    }
+
+    /// Records the rejection of a transaction for `era_getRejectedTransaction`.
+    pub(crate) fn reject_transaction(&self, rejected: RejectedTransaction, reason: &str) {
+        match self.inner.write() {
+            Ok(mut inner) => inner.record_rejected_transaction(rejected, reason),
+            Err(err) => tracing::error!("failed recording rejected transaction: {}", err),
+        }
+    }
+
+    fn queue_or_submit_l2_tx(&self, l2_tx: L2Tx) -> Result<(), String> {
+        let sender = l2_tx.initiator_account();
+        {
+            let mut inner = self
+                .inner
+                .write()
+                .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+            let next_nonce = inner.next_nonce(sender);
+            if !inner.skip_nonce_check && l2_tx.nonce() > next_nonce {
+                if inner.queued_transactions.len() >= MAX_QUEUED_TRANSACTIONS {
+                    return Err(format!(
+                        "nonce too high: expected {}, got {}, and the queue of {} transactions is full",
+                        next_nonce.0,
+                        l2_tx.nonce().0,
+                        MAX_QUEUED_TRANSACTIONS
+                    ));
+                }
+                tracing::info!(
+                    "Queued {} until the transaction with nonce {} of {:?} is submitted",
+                    format!("{:?}", l2_tx.hash()).bold(),
+                    next_nonce.0,
+                    sender
                 );
+                inner
+                    .queued_transactions
+                    .insert((sender, l2_tx.nonce()), l2_tx);
+                return Ok(());
             }
+        }
 
-            if block.timestamp.as_u64() != inner.current_timestamp {
-                panic!(
-                    "expected next block to have timestamp {}, got {} | {i}",
-                    inner.current_timestamp,
-                    block.timestamp.as_u64()
+        self.submit_l2_tx_in_order(l2_tx)?;
+
+        // the transaction may have filled a nonce gap
+        loop {
+            let queued = {
+                let mut inner = self
+                    .inner
+                    .write()
+                    .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+                let next_nonce = inner.next_nonce(sender);
+                inner.queued_transactions.remove(&(sender, next_nonce))
+            };
+            let queued = match queued {
+                Some(queued) => queued,
+                None => break,
+            };
+            let rejected = RejectedTransaction::new(&queued);
+            if let Err(err) = self.submit_l2_tx_in_order(queued) {
+                tracing::error!(
+                    "Failed submitting queued transaction {:?}: {}",
+                    rejected.hash,
+                    err
                 );
+                self.reject_transaction(rejected, &err);
+                break;
             }
+        }
+        Ok(())
+    }
 
-            let block_hash = block.hash;
-            inner.current_miniblock_hash = block_hash;
-            inner.block_hashes.insert(block.number.as_u64(), block.hash);
-            inner.blocks.insert(block.hash, block);
-            inner.filters.notify_new_block(block_hash);
+    fn submit_l2_tx_in_order(&self, mut l2_tx: L2Tx) -> Result<(), String> {
+        let (automine, deterministic, current_timestamp) = self
+            .inner
+            .read()
+            .map(|reader| {
+                (
+                    reader.automine,
+                    reader.deterministic,
+                    reader.current_timestamp,
+                )
+            })
+            .map_err(|e| format!("Failed to acquire read lock: {}", e))?;
+        if deterministic {
+            l2_tx.received_timestamp_ms = current_timestamp.saturating_add(1).saturating_mul(1_000);
+        }
+        if automine {
+            return self.run_l2_tx(l2_tx, TxExecutionMode::VerifyExecute);
         }
 
+        self.validate_tx(&l2_tx)?;
+        let mut inner = self
+            .inner
+            .write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        tracing::info!(
+            "Added {} to pending transactions",
+            format!("{:?}", l2_tx.hash()).bold()
+        );
+        inner.filters.notify_new_pending_transaction(l2_tx.hash());
+        inner
+            .subscriptions
+            .notify_new_pending_transaction(l2_tx.hash());
+        inner.pending_transactions.push(l2_tx);
+        METRICS.set_pending_transactions(inner.pending_transactions.len());
+
         Ok(())
     }
+
+    /// Seals the pending transactions into a single new block. Transactions that do not fit into
+    /// the block gas limit, or that are validated unlike the first one, remain pending (see
+    /// [InMemoryNode::seal_block]). If there are no pending transactions, an empty block is mined.
+    ///
+    /// # Returns
+    /// The hashes of the transactions included in the block.
+    pub fn seal_pending_block(&self) -> Result<Vec<H256>, String> {
+        let pending = std::mem::take(
+            &mut self
+                .inner
+                .write()
+                .map_err(|e| format!("Failed to acquire write lock: {}", e))?
+                .pending_transactions,
+        );
+
+        let (included, remaining) = self.seal_block(pending)?;
+
+        let mut inner = self
+            .inner
+            .write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+        let submitted_meanwhile = std::mem::replace(&mut inner.pending_transactions, remaining);
+        inner.pending_transactions.extend(submitted_meanwhile);
+        METRICS.set_pending_transactions(inner.pending_transactions.len());
+
+        Ok(included)
+    }
+
+    /// Executes the given transactions within a single L1 batch and commits them to a single L2 block,
+    /// followed by the empty block at the end of the batch. The execution and the commit happen under the same
+    /// lock, so that no state committed in between is overwritten.
+    ///
+    /// Transactions that halt are rejected, as with [InMemoryNode::run_l2_tx], and can be looked up with
+    /// `era_getRejectedTransaction`.
+    ///
+    /// # Returns
+    /// The hashes of the sealed transactions and the transactions left for the next block, see
+    /// [InMemoryNode::execute_batch].
+    pub fn seal_block(&self, txs: Vec<L2Tx>) -> Result<(Vec<H256>, Vec<L2Tx>), String> {
+        let started_at = Instant::now();
+        let mut inner = self
+            .inner
+            .write()
+            .map_err(|e| format!("Failed to acquire write lock: {}", e))?;
+
+        let execution = Self::execute_batch(&inner, &txs);
+        for (l2_tx, reason) in execution.halted.iter() {
+            tracing::info!(
+                "Rejected {} as it halted",
+                format!("{:?}", l2_tx.hash()).bold()
+            );
+            inner.record_rejected_transaction(
+                RejectedTransaction::new(l2_tx),
+                &format!("Transaction HALT: {}", reason),
+            );
+        }
+
+        // Write all the mutated keys (storage slots).
+        inner.archive_head_state();
+        for (key, value) in execution.keys.iter() {
+            inner.fork_storage.set_value(*key, *value);
+        }
+
+        // Write all the factory deps.
+        for (hash, code) in execution.bytecodes.iter() {
+            inner.fork_storage.store_factory_dep(
+                u256_to_h256(*hash),
+                code.iter()
+                    .flat_map(|entry| {
+                        let mut bytes = vec![0u8; 32];
+                        entry.to_big_endian(&mut bytes);
+                        bytes.to_vec()
+                    })
+                    .collect(),
+            )
+        }
+
+        let block_ctx = execution.block_ctx;
+        let block_hash = inner.new_block_hash(
+            block_ctx.miniblock,
+            execution
+                .results
+                .first()
+                .map(|(tx, _, _, _)| tx.hash())
+                .unwrap_or_default(),
+        );
+        let parent_block_hash = inner
+            .block_hashes
+            .get(&(block_ctx.miniblock - 1))
+            .cloned()
+            .unwrap_or_default();
+        let mut block = Block {
+            hash: block_hash,
+            parent_hash: parent_block_hash,
+            number: U64::from(block_ctx.miniblock),
+            timestamp: U256::from(block_ctx.timestamp),
+            l1_batch_number: Some(U64::from(block_ctx.batch)),
+            gas_limit: U256::from(inner.block_gas_limit),
+            ..Default::default()
+        };
+        inner.seal_state_root(&mut block);
+
+        let mut sealed = SealedTransactions::new(
+            block.hash,
+            block.number,
+            block.l1_batch_number,
+            U256::from(inner.batch_base_fee()),
+        );
+        for (l2_tx, result, call_traces, coverage) in execution.results {
+            let tx_hash = l2_tx.hash();
+            inner.seal_transaction(&mut sealed, l2_tx, result, call_traces, started_at);
+            inner.record_execution_coverage(tx_hash, coverage);
+        }
+        let included: Vec<H256> = sealed
+            .transactions
+            .iter()
+            .filter_map(|tx| match tx {
+                TransactionVariant::Full(tx) => Some(tx.hash),
+                TransactionVariant::Hash(hash) => Some(*hash),
+            })
+            .collect();
+
+        block.transactions = sealed.transactions;
+        block.gas_used = sealed.cumulative_gas_used;
+        let block_number = block.number;
+
+        // With the introduction of 'l2 blocks' (and virtual blocks),
+        // we are adding one l2 block at the end of each batch (to handle things like remaining events etc).
+        let block_ctx = block_ctx.new_block();
+        let mut empty_block_at_end_of_batch = create_empty_block(
+            block_ctx.miniblock,
+            block_ctx.timestamp,
+            block_ctx.batch,
+            Some(block.hash),
+        );
+        empty_block_at_end_of_batch.hash = inner.new_block_hash(block_ctx.miniblock, H256::zero());
+        empty_block_at_end_of_batch.gas_limit = U256::from(inner.block_gas_limit);
+        inner.seal_state_root(&mut empty_block_at_end_of_batch);
+
+        inner.current_batch = inner.current_batch.saturating_add(1);
+        for (i, block) in vec![block, empty_block_at_end_of_batch]
+            .into_iter()
+            .enumerate()
+        {
+            inner.apply_block(block, i);
+        }
+        tracing::info!(
+            "👷 Sealed block #{} with {} transaction(s)",
+            block_number,
+            included.len()
+        );
+
+        Ok((included, execution.remaining))
+    }
+
+    /// Executes the transactions one after another in a single VM batch. Nothing is committed to the node state.
+    ///
+    /// The batch stops once the block gas limit is reached, or at the first transaction that is validated unlike
+    /// the first one (see [InMemoryNodeInner::skips_account_validation]), since the bootloader validates all the
+    /// transactions of a batch alike. The transactions that halt are rolled back and left out of the batch.
+    fn execute_batch(inner: &InMemoryNodeInner<S>, txs: &[L2Tx]) -> BatchExecution {
+        let storage = StorageView::new(inner.fork_storage.clone()).into_rc_ptr();
+        let (batch_env, block_ctx) = inner.create_l1_batch_env(storage.clone());
+
+        let impersonating = txs
+            .first()
+            .map_or(false, |tx| inner.skips_account_validation(tx));
+        let bootloader_code = inner
+            .system_contracts
+            .contracts(TxExecutionMode::VerifyExecute, impersonating);
+        let system_env =
+            inner.create_system_env(bootloader_code.clone(), TxExecutionMode::VerifyExecute);
+        let mut vm: Vm<_, HistoryEnabled> = Vm::new(batch_env, system_env, storage.clone());
+
+        let mut results = vec![];
+        let mut halted = vec![];
+        let mut bytecodes = HashMap::new();
+        let mut remaining = vec![];
+        let mut gas_used = U256::zero();
+        for (index, l2_tx) in txs.iter().enumerate() {
+            if inner.skips_account_validation(l2_tx) != impersonating {
+                remaining = txs[index..].to_vec();
+                break;
+            }
+            if !results.is_empty()
+                && !inner.disable_block_gas_limit
+                && gas_used + l2_tx.common_data.fee.gas_limit > U256::from(inner.block_gas_limit)
+            {
+                remaining = txs[index..].to_vec();
+                break;
+            }
+
+            tracing::info!("Executing {}", format!("{:?}", l2_tx.hash()).bold());
+            vm.make_snapshot();
+            vm.push_transaction(l2_tx.clone().into());
+            let call_tracer_result = Arc::new(OnceCell::default());
+            let coverage_result = Arc::new(OnceCell::default());
+            let tracers: Vec<TracerPointer<StorageView<ForkStorage<S>>, HistoryEnabled>> = vec![
+                CallTracer::new(call_tracer_result.clone()).into_tracer_pointer(),
+                CoverageTracer::new(coverage_result.clone()).into_tracer_pointer(),
+                ExecutionLimitTracer::new(inner.execution_limits).into_tracer_pointer(),
+            ];
+            let started_at = Instant::now();
+            let tx_result = tracing::info_span!(
+                target: SPAN_TARGET,
+                "vm_execution",
+                tx_hash = %format!("{:#x}", l2_tx.hash())
+            )
+            .in_scope(|| vm.inspect(tracers.into(), VmExecutionMode::OneTx));
+            METRICS.observe_vm_execution(started_at.elapsed());
+
+            match &tx_result.result {
+                ExecutionResult::Success { .. } => {
+                    tracing::info!("Transaction: {}", "SUCCESS".green())
+                }
+                ExecutionResult::Revert { .. } => tracing::info!("Transaction: {}", "FAILED".red()),
+                ExecutionResult::Halt { reason } => {
+                    tracing::info!("Transaction: {}", "HALTED".red());
+                    // the halted transaction changed nothing, so the following ones are executed as if it had
+                    // never been pushed
+                    vm.rollback_to_the_latest_snapshot();
+                    halted.push((l2_tx.clone(), reason.to_string()));
+                    continue;
+                }
+            }
+            vm.pop_snapshot_no_rollback();
+
+            let call_traces: Vec<Call> = call_tracer_result.get().cloned().unwrap_or_default();
+            for call in call_traces.iter() {
+                inner.console_log_handler.handle_call_recursive(call);
+            }
+            if inner.show_calls != ShowCalls::None {
+                for call in call_traces.iter() {
+                    formatter::print_call(
+                        call,
+                        0,
+                        &inner.show_calls,
+                        inner.show_precompiles,
+                        inner.show_calls_max_depth,
+                        inner.show_outputs,
+                        &inner.verified_contracts,
+                        &inner.address_labels,
+                        inner.resolve_hashes,
+                    );
+                }
+            }
+            for event in tx_result.logs.events.iter() {
+                formatter::print_event(event, &inner.address_labels, inner.resolve_hashes);
+            }
+
+            gas_used += l2_tx.common_data.fee.gas_limit - tx_result.refunds.gas_refunded;
+            bytecodes.extend(
+                vm.get_last_tx_compressed_bytecodes()
+                    .iter()
+                    .map(|b| bytecode_to_factory_dep(b.original.clone())),
+            );
+            let coverage = coverage_result.get().cloned().unwrap_or_default();
+            results.push((l2_tx.clone(), tx_result, call_traces, coverage));
+        }
+
+        vm.execute(VmExecutionMode::Bootloader);
+        let keys = storage.borrow().modified_storage_keys().clone();
+
+        BatchExecution {
+            keys,
+            results,
+            halted,
+            bytecodes,
+            block_ctx,
+            remaining,
+        }
+    }
+}
+
+/// Result of executing multiple transactions in a single batch via [InMemoryNode::seal_block].
+struct BatchExecution {
+    keys: HashMap<StorageKey, H256>,
+    results: Vec<(L2Tx, VmExecutionResultAndLogs, Vec<Call>, ExecutionCoverage)>,
+    /// The transactions that halted, with the halt reason.
+    halted: Vec<(L2Tx, String)>,
+    bytecodes: HashMap<U256, Vec<U256>>,
+    block_ctx: BlockContext,
+    remaining: Vec<L2Tx>,
+}
+
+/// Transactions sealed into a block by [InMemoryNodeInner::seal_transaction], along with the running totals of
+/// the block that the following transactions continue from.
+struct SealedTransactions {
+    block_hash: H256,
+    block_number: U64,
+    l1_batch_number: Option<U64>,
+    effective_gas_price: U256,
+    transactions: Vec<TransactionVariant>,
+    cumulative_gas_used: U256,
+    log_index: usize,
+    l2_to_l1_log_index: usize,
+}
+
+impl SealedTransactions {
+    fn new(
+        block_hash: H256,
+        block_number: U64,
+        l1_batch_number: Option<U64>,
+        effective_gas_price: U256,
+    ) -> Self {
+        Self {
+            block_hash,
+            block_number,
+            l1_batch_number,
+            effective_gas_price,
+            transactions: vec![],
+            cumulative_gas_used: U256::zero(),
+            log_index: 0,
+            l2_to_l1_log_index: 0,
+        }
+    }
 }
 
 /// Keeps track of a block's batch number, miniblock number and timestamp.
@@ -1741,6 +3549,599 @@ mod tests {
         assert_eq!(result.err(), Some("exceeds block gas limit".into()));
     }
 
+    #[tokio::test]
+    async fn test_run_l2_tx_validates_configured_block_gas_limit() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                block_gas_limit: Some(1_000_000),
+                ..Default::default()
+            },
+        );
+        let tx = testing::TransactionBuilder::new()
+            .set_gas_limit(U256::from(1_000_001))
+            .build();
+        node.set_rich_account(tx.common_data.initiator_address);
+
+        let result = node.run_l2_tx(tx, TxExecutionMode::VerifyExecute);
+
+        assert_eq!(result.err(), Some("exceeds block gas limit".into()));
+    }
+
+    #[tokio::test]
+    async fn test_run_l2_tx_skips_block_gas_limit_when_disabled() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                block_gas_limit: Some(1_000),
+                disable_block_gas_limit: true,
+                ..Default::default()
+            },
+        );
+        let tx = testing::TransactionBuilder::new().build();
+        node.set_rich_account(tx.common_data.initiator_address);
+
+        let result = node.run_l2_tx(tx, TxExecutionMode::VerifyExecute);
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_trace_estimate_gas_records_iterations() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let from = Address::repeat_byte(0x1);
+        node.set_rich_account(from);
+        let request = zksync_types::transaction_request::CallRequestBuilder::default()
+            .from(from)
+            .to(Address::repeat_byte(0x2))
+            .value(U256::from(1))
+            .build();
+
+        let inner = node.get_inner().read().unwrap();
+        let trace = inner.trace_estimate_gas_impl(request.clone());
+        let fee = inner.estimate_gas_impl(request).unwrap();
+
+        assert_eq!(Some(fee.gas_limit), trace.estimated_gas);
+        assert_eq!(None, trace.error);
+        assert!(trace.iterations.len() > 1);
+        assert!(trace.iterations.iter().any(|iteration| !iteration.success));
+        let last = trace.iterations.last().unwrap();
+        assert!(last.success);
+        assert_eq!(None, last.revert_reason);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_gas_returns_the_trace_of_the_failed_attempt() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let request = zksync_types::transaction_request::CallRequestBuilder::default()
+            .from(Address::repeat_byte(0x1))
+            .to(Address::repeat_byte(0x2))
+            .value(U256::from(1))
+            .build();
+
+        let err = node
+            .get_inner()
+            .read()
+            .unwrap()
+            .estimate_gas_impl(request)
+            .expect_err("expected estimation to fail");
+
+        let data = err.data.expect("missing error data");
+        assert_eq!(
+            serde_json::json!(format!("{:?}", Address::repeat_byte(0x1))),
+            data["trace"]["from"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detailed_output_contains_storage_writes() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx_hash = H256::repeat_byte(0x1);
+        testing::apply_tx(&node, tx_hash);
+
+        let output = node.get_inner().read().unwrap().tx_results[&tx_hash].detailed_output();
+
+        assert_eq!(tx_hash, output.transaction_hash);
+        assert!(!output.storage_logs.is_empty());
+        assert!(!output.events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_receipt_contains_batch_details() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx_hash = H256::repeat_byte(0x1);
+        testing::apply_tx(&node, tx_hash);
+
+        let inner = node.get_inner().read().unwrap();
+        let receipt = &inner.tx_results[&tx_hash].receipt;
+
+        assert_eq!(Some(U64::zero()), receipt.l1_batch_tx_index);
+        assert_eq!(Some(U64::from(1)), receipt.l1_batch_number);
+        assert_eq!(
+            Some(U256::from(inner.batch_base_fee())),
+            receipt.effective_gas_price
+        );
+        assert!(receipt.l2_to_l1_logs.is_empty());
+    }
+
+    #[test]
+    fn test_innermost_revert_reason() {
+        let failed_call = |revert_reason: &str, calls: Vec<Call>| Call {
+            revert_reason: Some(revert_reason.to_string()),
+            calls,
+            ..Default::default()
+        };
+        let calls = vec![
+            failed_call("caught", vec![]),
+            failed_call(
+                "outer",
+                vec![Call::default(), failed_call("inner", vec![Call::default()])],
+            ),
+        ];
+
+        assert_eq!(Some("inner".to_string()), innermost_revert_reason(&calls));
+        assert_eq!(None, innermost_revert_reason(&[Call::default()]));
+    }
+
+    #[tokio::test]
+    async fn test_run_l2_tx_skips_balance_check_when_enabled() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx = testing::TransactionBuilder::new().build();
+
+        let result = node.run_l2_tx(tx.clone(), TxExecutionMode::VerifyExecute);
+        assert!(result.is_err());
+
+        node.get_inner().write().unwrap().skip_balance_check = true;
+        let result = node.run_l2_tx(tx, TxExecutionMode::VerifyExecute);
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_l2_tx_skips_nonce_check_when_enabled() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx = testing::TransactionBuilder::new().build();
+        node.set_rich_account(tx.common_data.initiator_address);
+        node.set_nonce(tx.common_data.initiator_address, U256::from(5))
+            .unwrap();
+
+        let result = node.run_l2_tx(tx.clone(), TxExecutionMode::VerifyExecute);
+        assert!(result.is_err());
+
+        node.get_inner().write().unwrap().skip_nonce_check = true;
+        let result = node.run_l2_tx(tx, TxExecutionMode::VerifyExecute);
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_l2_tx_validates_tx_gas_limit_cap() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                tx_gas_limit: Some(1_000_000),
+                ..Default::default()
+            },
+        );
+        let tx = testing::TransactionBuilder::new()
+            .set_gas_limit(U256::from(1_000_001))
+            .build();
+        node.set_rich_account(tx.common_data.initiator_address);
+
+        let result = node.run_l2_tx(tx, TxExecutionMode::VerifyExecute);
+
+        assert_eq!(result.err(), Some("exceeds transaction gas limit".into()));
+    }
+
+    #[tokio::test]
+    async fn test_run_l2_tx_validates_gas_price_bounds() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                min_gas_price: Some(L2_GAS_PRICE * 2),
+                max_gas_price: Some(L2_GAS_PRICE * 4),
+                ..Default::default()
+            },
+        );
+        let tx = |max_fee_per_gas: u64| {
+            let tx = testing::TransactionBuilder::new()
+                .set_max_fee_per_gas(U256::from(max_fee_per_gas))
+                .build();
+            node.set_rich_account(tx.common_data.initiator_address);
+            tx
+        };
+
+        assert_eq!(
+            Some(format!(
+                "max fee per gas {} lower than the minimum gas price {}",
+                L2_GAS_PRICE,
+                L2_GAS_PRICE * 2
+            )),
+            node.run_l2_tx(tx(L2_GAS_PRICE), TxExecutionMode::VerifyExecute)
+                .err()
+        );
+        assert_eq!(
+            Some(format!(
+                "max fee per gas {} higher than the maximum gas price {}",
+                L2_GAS_PRICE * 5,
+                L2_GAS_PRICE * 4
+            )),
+            node.run_l2_tx(tx(L2_GAS_PRICE * 5), TxExecutionMode::VerifyExecute)
+                .err()
+        );
+        assert!(node
+            .run_l2_tx(tx(L2_GAS_PRICE * 3), TxExecutionMode::VerifyExecute)
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_run_l2_tx_rejects_invalid_bytecodes() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let mut tx = testing::TransactionBuilder::new().build();
+        node.set_rich_account(tx.common_data.initiator_address);
+        tx.execute.factory_deps = Some(vec![vec![0u8; 64]]);
+
+        let err = node
+            .run_l2_tx(tx, TxExecutionMode::VerifyExecute)
+            .expect_err("transaction succeeded");
+        assert!(err.starts_with("invalid bytecode of 64 bytes"));
+        assert!(err.ends_with("padding it to 96 bytes would fix it"));
+    }
+
+    #[tokio::test]
+    async fn test_run_l2_call_does_not_block_other_readers() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx = testing::TransactionBuilder::new().build();
+
+        let _reader = node.get_inner().read().expect("failed acquiring lock");
+        let result = node.run_l2_call(tx);
+
+        assert!(result.is_ok(), "{:?}", result.err());
+    }
+
+    #[tokio::test]
+    async fn test_run_l2_call_aborts_after_vm_max_steps() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                execution_limits: ExecutionLimits {
+                    timeout_ms: None,
+                    max_steps: Some(100),
+                },
+                ..Default::default()
+            },
+        );
+        let tx = testing::TransactionBuilder::new().build();
+
+        let result = node.run_l2_call(tx).expect("failed running call");
+
+        match result {
+            ExecutionResult::Halt { reason } => assert!(reason
+                .to_string()
+                .contains("execution exceeded the limit of 100 VM steps")),
+            result => panic!("expected the call to be aborted, got {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_mode_does_not_use_wall_clock() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                deterministic: true,
+                start_timestamp: Some(5_000),
+                ..Default::default()
+            },
+        );
+        let tx = testing::TransactionBuilder::new().build();
+        node.set_rich_account(tx.common_data.initiator_address);
+
+        node.submit_l2_tx(tx.clone()).expect("failed submitting tx");
+
+        let inner = node.get_inner();
+        let reader = inner.read().unwrap();
+        let genesis_block = reader.blocks.get(&reader.block_hashes[&0]).unwrap();
+        assert_eq!(U256::from(5_000), genesis_block.timestamp);
+        let tx_result = reader.tx_results.get(&tx.hash()).unwrap();
+        assert_eq!(5_001_000, tx_result.info.tx.received_timestamp_ms);
+        let tx_block = reader.blocks.get(&tx_result.receipt.block_hash).unwrap();
+        assert_eq!(U256::from(5_001), tx_block.timestamp);
+    }
+
+    #[tokio::test]
+    async fn test_node_starts_at_configured_block_number_and_timestamp() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                start_block_number: Some(1_000),
+                start_timestamp: Some(1_700_000_000),
+                ..Default::default()
+            },
+        );
+        let tx = testing::TransactionBuilder::new().build();
+        node.set_rich_account(tx.common_data.initiator_address);
+
+        node.submit_l2_tx(tx.clone()).expect("failed submitting tx");
+
+        let inner = node.get_inner();
+        let reader = inner.read().unwrap();
+        let genesis_block = reader.blocks.get(&reader.block_hashes[&1_000]).unwrap();
+        assert_eq!(U256::from(1_700_000_000), genesis_block.timestamp);
+        assert!(!reader.block_hashes.contains_key(&0));
+        let tx_result = reader.tx_results.get(&tx.hash()).unwrap();
+        assert_eq!(Some(U64::from(1_001)), tx_result.receipt.block_number);
+        let tx_block = reader.blocks.get(&tx_result.receipt.block_hash).unwrap();
+        assert!(tx_block.timestamp > genesis_block.timestamp);
+    }
+
+    #[tokio::test]
+    async fn test_seal_pending_block_packs_transactions_into_single_block() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                no_automine: true,
+                ..Default::default()
+            },
+        );
+        let tx1 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x1))
+            .build();
+        let tx2 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x2))
+            .build();
+        node.set_rich_account(tx1.common_data.initiator_address);
+        node.set_rich_account(tx2.common_data.initiator_address);
+
+        node.submit_l2_tx(tx1.clone())
+            .expect("failed submitting tx1");
+        node.submit_l2_tx(tx2.clone())
+            .expect("failed submitting tx2");
+        {
+            let inner = node.get_inner();
+            let reader = inner.read().unwrap();
+            assert_eq!(2, reader.pending_transactions.len());
+            assert!(reader.tx_results.is_empty());
+        }
+
+        let included = node.seal_pending_block().expect("failed sealing block");
+
+        assert_eq!(vec![tx1.hash(), tx2.hash()], included);
+        let inner = node.get_inner();
+        let reader = inner.read().unwrap();
+        assert!(reader.pending_transactions.is_empty());
+        let receipt1 = &reader.tx_results.get(&tx1.hash()).unwrap().receipt;
+        let receipt2 = &reader.tx_results.get(&tx2.hash()).unwrap().receipt;
+        assert_eq!(receipt1.block_number, receipt2.block_number);
+        assert_eq!(U64::from(0), receipt1.transaction_index);
+        assert_eq!(U64::from(1), receipt2.transaction_index);
+        assert_eq!(
+            receipt1.gas_used.unwrap() + receipt2.gas_used.unwrap(),
+            receipt2.cumulative_gas_used
+        );
+        let block = reader.blocks.get(&receipt1.block_hash).unwrap();
+        assert_eq!(2, block.transactions.len());
+    }
+
+    #[tokio::test]
+    async fn test_seal_pending_block_rejects_halted_transactions() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                no_automine: true,
+                ..Default::default()
+            },
+        );
+        let tx1 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x1))
+            .build();
+        let tx2 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x2))
+            .build();
+        let tx3 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x3))
+            .build();
+        for tx in [&tx1, &tx2, &tx3] {
+            node.set_rich_account(tx.common_data.initiator_address);
+            node.submit_l2_tx(tx.clone()).expect("failed submitting tx");
+        }
+        // the fee of tx2 can no longer be charged, so it halts
+        node.get_inner().write().unwrap().fork_storage.set_value(
+            storage_key_for_eth_balance(&tx2.common_data.initiator_address),
+            H256::zero(),
+        );
+
+        let included = node.seal_pending_block().expect("failed sealing block");
+
+        assert_eq!(vec![tx1.hash(), tx3.hash()], included);
+        let inner = node.get_inner();
+        let reader = inner.read().unwrap();
+        assert!(reader.pending_transactions.is_empty());
+        assert!(!reader.tx_results.contains_key(&tx2.hash()));
+        assert!(reader.rejected_transactions[&tx2.hash()]
+            .reason
+            .starts_with("Transaction HALT"));
+        let receipt1 = &reader.tx_results[&tx1.hash()].receipt;
+        let receipt3 = &reader.tx_results[&tx3.hash()].receipt;
+        assert_eq!(U64::from(1), receipt3.status);
+        assert_eq!(U64::from(1), receipt3.transaction_index);
+        assert_eq!(receipt1.block_hash, receipt3.block_hash);
+    }
+
+    #[tokio::test]
+    async fn test_seal_pending_block_splits_impersonated_and_validated_transactions() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                no_automine: true,
+                ..Default::default()
+            },
+        );
+        let tx1 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x1))
+            .build();
+        let tx2 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x2))
+            .build();
+        node.set_rich_account(tx1.common_data.initiator_address);
+        node.set_rich_account(tx2.common_data.initiator_address);
+        node.impersonate_account(tx2.common_data.initiator_address)
+            .expect("failed impersonating account");
+        node.submit_l2_tx(tx1.clone())
+            .expect("failed submitting tx1");
+        node.submit_l2_tx(tx2.clone())
+            .expect("failed submitting tx2");
+
+        // tx1 must be validated, unlike the impersonated tx2, so they are sealed into separate blocks
+        assert_eq!(
+            vec![tx1.hash()],
+            node.seal_pending_block().expect("failed sealing block")
+        );
+        assert_eq!(
+            vec![tx2.hash()],
+            node.seal_pending_block().expect("failed sealing block")
+        );
+        let inner = node.get_inner();
+        let reader = inner.read().unwrap();
+        assert_ne!(
+            reader.tx_results[&tx1.hash()].receipt.block_number,
+            reader.tx_results[&tx2.hash()].receipt.block_number
+        );
+    }
+
+    #[tokio::test]
+    async fn test_node_verified_txs_are_removed_once_sealed_or_rejected() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx1 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x1))
+            .build();
+        let tx2 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x2))
+            .build();
+        node.set_rich_account(tx1.common_data.initiator_address);
+        {
+            let inner = node.get_inner();
+            let mut writer = inner.write().unwrap();
+            writer.node_verified_txs.insert(tx1.hash());
+            writer.node_verified_txs.insert(tx2.hash());
+        }
+
+        node.submit_l2_tx(tx1.clone())
+            .expect("failed submitting tx1");
+        // the sender of tx2 has no balance
+        node.submit_l2_tx(tx2.clone())
+            .expect_err("tx2 was not rejected");
+
+        let inner = node.get_inner();
+        let reader = inner.read().unwrap();
+        assert!(reader.tx_results.contains_key(&tx1.hash()));
+        assert!(reader.rejected_transactions.contains_key(&tx2.hash()));
+        assert!(reader.node_verified_txs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_seal_pending_block_keeps_transactions_exceeding_block_gas_limit() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                no_automine: true,
+                block_gas_limit: Some(1_500_000),
+                ..Default::default()
+            },
+        );
+        let tx1 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x1))
+            .build();
+        let tx2 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x2))
+            .build();
+        node.set_rich_account(tx1.common_data.initiator_address);
+        node.set_rich_account(tx2.common_data.initiator_address);
+        node.submit_l2_tx(tx1.clone())
+            .expect("failed submitting tx1");
+        node.submit_l2_tx(tx2.clone())
+            .expect("failed submitting tx2");
+
+        let included = node.seal_pending_block().expect("failed sealing block");
+
+        assert_eq!(vec![tx1.hash()], included);
+        let pending = node
+            .get_inner()
+            .read()
+            .unwrap()
+            .pending_transactions
+            .iter()
+            .map(|tx| tx.hash())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![tx2.hash()], pending);
+    }
+
+    #[derive(Default)]
+    struct RecordingHook {
+        rejected: Option<H256>,
+        executed: RwLock<Vec<H256>>,
+        sealed: RwLock<Vec<u64>>,
+    }
+
+    impl NodeHook for RecordingHook {
+        fn before_validation(&self, tx: &L2Tx) -> Result<(), String> {
+            if Some(tx.hash()) == self.rejected {
+                return Err("rejected by hook".into());
+            }
+            Ok(())
+        }
+
+        fn after_execution(&self, tx: &L2Tx, result: &VmExecutionResultAndLogs) {
+            assert!(!result.result.is_failed());
+            self.executed.write().unwrap().push(tx.hash());
+        }
+
+        fn on_block_sealed(&self, block: &Block<TransactionVariant>) {
+            self.sealed.write().unwrap().push(block.number.as_u64());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_are_called_on_transaction_lifecycle() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let hook = Arc::new(RecordingHook {
+            rejected: Some(H256::repeat_byte(0x2)),
+            ..Default::default()
+        });
+        node.add_hook(hook.clone());
+        let tx1 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x1))
+            .build();
+        let tx2 = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x2))
+            .build();
+        node.set_rich_account(tx1.common_data.initiator_address);
+        node.set_rich_account(tx2.common_data.initiator_address);
+
+        node.run_l2_tx(tx1.clone(), TxExecutionMode::VerifyExecute)
+            .expect("failed running tx1");
+        let result = node.run_l2_tx(tx2, TxExecutionMode::VerifyExecute);
+
+        assert_eq!(result.err(), Some("rejected by hook".into()));
+        assert_eq!(vec![tx1.hash()], *hook.executed.read().unwrap());
+        // the transaction block and the fictive block at the end of the batch
+        assert_eq!(vec![1, 2], *hook.sealed.read().unwrap());
+
+        node.mine_blocks(Some(U64::from(2)), None)
+            .expect("failed mining blocks");
+        assert_eq!(vec![1, 2, 3, 4], *hook.sealed.read().unwrap());
+    }
+
     #[tokio::test]
     async fn test_run_l2_tx_validates_tx_max_fee_per_gas_too_low() {
         let node = InMemoryNode::<HttpForkSource>::default();
@@ -1827,6 +4228,8 @@ mod tests {
                 block_timestamp: 1002,
                 overwrite_chain_id: None,
                 l1_gas_price: 1000,
+                cache_max_size: None,
+                max_memory: None,
             }),
             None,
             Default::default(),
@@ -1841,6 +4244,45 @@ mod tests {
         .expect("transaction must pass with external storage");
     }
 
+    #[tokio::test]
+    async fn test_submit_l2_tx_queues_transactions_until_nonce_gap_is_filled() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let mut builder = testing::TransactionBuilder::new();
+        let tx2 = builder
+            .set_hash(H256::repeat_byte(0x3))
+            .set_nonce(Nonce(2))
+            .build();
+        let tx1 = builder
+            .set_hash(H256::repeat_byte(0x2))
+            .set_nonce(Nonce(1))
+            .build();
+        let tx0 = builder
+            .set_hash(H256::repeat_byte(0x1))
+            .set_nonce(Nonce(0))
+            .build();
+        node.set_rich_account(tx0.initiator_account());
+
+        node.submit_l2_tx(tx2.clone())
+            .expect("failed submitting tx2");
+        node.submit_l2_tx(tx1.clone())
+            .expect("failed submitting tx1");
+        {
+            let reader = node.inner.read().unwrap();
+            assert_eq!(2, reader.queued_transactions.len());
+            assert!(reader.tx_results.is_empty());
+        }
+
+        node.submit_l2_tx(tx0.clone())
+            .expect("failed submitting tx0");
+
+        let reader = node.inner.read().unwrap();
+        assert!(reader.queued_transactions.is_empty());
+        let block_number = |hash: H256| reader.tx_results[&hash].receipt.block_number;
+        assert!(block_number(tx0.hash()) < block_number(tx1.hash()));
+        assert!(block_number(tx1.hash()) < block_number(tx2.hash()));
+        assert_eq!(Nonce(3), reader.next_nonce(tx0.initiator_account()));
+    }
+
     #[tokio::test]
     async fn test_transact_returns_data_in_built_in_without_security_mode() {
         let node = InMemoryNode::<HttpForkSource>::new(
@@ -1899,6 +4341,108 @@ mod tests {
             _ => panic!("invalid result {:?}", result.result),
         }
     }
+
+    #[tokio::test]
+    async fn test_advance_fork_follows_latest_block_unless_transactions_are_pending() {
+        let mock_server = testing::MockServer::run_with_config(testing::ForkBlockConfig {
+            number: 10,
+            transaction_count: 0,
+            hash: H256::repeat_byte(0xab),
+        });
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(
+                ForkDetails::from_network(
+                    &mock_server.url(),
+                    None,
+                    crate::cache::CacheConfig::None,
+                )
+                .await,
+            ),
+            None,
+            Default::default(),
+        );
+
+        let new_block_hash = H256::repeat_byte(0xcd);
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getBlockByNumber",
+                "params": ["latest", false],
+            }),
+            testing::BlockResponseBuilder::new()
+                .set_hash(new_block_hash)
+                .set_number(12)
+                .build(),
+        );
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "zks_getBlockDetails",
+                "params": [12],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": {
+                    "number": 12,
+                    "l1BatchNumber": 2,
+                    "timestamp": 1676461090u64,
+                    "l1TxCount": 0,
+                    "l2TxCount": 0,
+                    "rootHash": format!("{:#x}", new_block_hash),
+                    "status": "sealed",
+                    "commitTxHash": null,
+                    "committedAt": null,
+                    "proveTxHash": null,
+                    "provenAt": null,
+                    "executeTxHash": null,
+                    "executedAt": null,
+                    "l1GasPrice": 29860969933u64,
+                    "l2FairGasPrice": 500000000u64,
+                    "baseSystemContractsHashes": {
+                      "bootloader": "0x0100038581be3d0e201b3cc45d151ef5cc59eb3a0f146ad44f0f72abf00b594c",
+                      "default_aa": "0x0100038dc66b69be75ec31653c64cb931678299b9b659472772b2550b703f41c"
+                    },
+                    "operatorAddress": "0xfeee860e7aae671124e9a4e61139f3a5085dfeee",
+                    "protocolVersion": zksync_types::ProtocolVersionId::Version15,
+                },
+            }),
+        );
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getBlockByNumber",
+                "params": ["0xc", true],
+            }),
+            testing::BlockResponseBuilder::new()
+                .set_hash(new_block_hash)
+                .set_number(12)
+                .build(),
+        );
+
+        assert_eq!(
+            Some(12),
+            node.advance_fork().expect("failed advancing fork")
+        );
+        {
+            let inner = node.get_inner();
+            let reader = inner.read().expect("failed reading");
+            assert_eq!(12, reader.current_miniblock);
+            assert_eq!(new_block_hash, reader.current_miniblock_hash);
+            assert_eq!(2, reader.current_batch);
+            assert!(reader.blocks.contains_key(&new_block_hash));
+        }
+
+        node.get_inner()
+            .write()
+            .expect("failed writing")
+            .pending_transactions
+            .push(testing::TransactionBuilder::new().build());
+        assert_eq!(None, node.advance_fork().expect("failed advancing fork"));
+    }
 }
 
 pub fn load_last_l1_batch<S: ReadStorage>(storage: StoragePtr<S>) -> Option<(u64, u64)> {
@@ -1,15 +1,31 @@
 use anyhow::anyhow;
-use zksync_basic_types::{Address, U256, U64};
-use zksync_state::ReadStorage;
+use bigdecimal::BigDecimal;
+use multivm::{
+    interface::{ExecutionResult, TxExecutionMode, VmExecutionMode, VmInterface},
+    vm_latest::{constants::ETH_CALL_GAS_LIMIT, HistoryDisabled, ToTracerPointer, Vm},
+};
+use zksync_basic_types::{
+    web3::signing::keccak256, AccountTreeId, Address, MiniblockNumber, H256, U256, U64,
+};
+use zksync_state::{ReadStorage, WriteStorage};
 use zksync_types::{
+    api::{BlockIdVariant, BlockNumber},
     get_code_key, get_nonce_key,
+    l2::L2Tx,
+    transaction_request::CallRequestBuilder,
     utils::{decompose_full_nonce, nonces_to_full_nonce, storage_key_for_eth_balance},
+    PackedEthSignature, StorageKey,
 };
 use zksync_utils::{h256_to_u256, u256_to_h256};
 
 use crate::{
+    cache::CacheConfig,
+    deps::storage_view::StorageView,
+    execution_limit::ExecutionLimitTracer,
     fork::ForkSource,
-    node::InMemoryNode,
+    http_fork_source::HttpForkSource,
+    node::{InMemoryNode, InMemoryNodeInner, MAX_TX_SIZE},
+    subscriptions::NodeEvent,
     utils::{self, bytecode_to_factory_dep},
 };
 
@@ -19,6 +35,15 @@ type Result<T> = anyhow::Result<T>;
 /// and can be used to revert the node to an earlier point in time.
 const MAX_SNAPSHOTS: u8 = 100;
 
+/// The number of storage slots probed when looking up the balances mapping of an ERC20 token.
+const MAX_ERC20_BALANCE_SLOT: u64 = 100;
+
+/// Selector of the ERC20 `balanceOf(address)` function.
+const ERC20_BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+
+/// The maximum number of storage slots imported with a contract by `era_importContract`.
+const MAX_IMPORTED_STORAGE_SLOTS: usize = 1024;
+
 impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
     /// Increase the current timestamp for the node
     ///
@@ -87,13 +112,66 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNo
             })
     }
 
+    /// Sets the number of seconds between the timestamps of the mined blocks, instead of 1 second.
+    ///
+    /// # Parameters
+    /// - `seconds`: The interval, which must be greater than 0
+    pub fn set_block_timestamp_interval(&self, seconds: u64) -> Result<()> {
+        if seconds == 0 {
+            return Err(anyhow!("block timestamp interval must be greater than 0"));
+        }
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .map(|mut writer| {
+                writer.block_timestamp_interval = Some(seconds);
+                tracing::info!("👷 Block timestamp interval set to {}s", seconds);
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::config_changed(
+                        "block-timestamp-interval",
+                        Some(seconds),
+                    ));
+            })
+    }
+
+    /// Removes the interval set with [InMemoryNode::set_block_timestamp_interval], so that every block is again
+    /// mined 1 second after the previous one.
+    ///
+    /// # Returns
+    /// `true` if an interval was set, otherwise `false`.
+    pub fn remove_block_timestamp_interval(&self) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .map(|mut writer| {
+                let removed = writer.block_timestamp_interval.take().is_some();
+                if removed {
+                    tracing::info!("👷 Block timestamp interval removed");
+                    writer
+                        .subscriptions
+                        .notify_node_event(NodeEvent::config_changed(
+                            "block-timestamp-interval",
+                            None::<u64>,
+                        ));
+                }
+                removed
+            })
+    }
+
     /// Force a single block to be mined.
     ///
-    /// Will mine an empty block (containing zero transactions)
+    /// Will seal the pending transactions into the block, if there are any. Otherwise, will mine
+    /// an empty block (containing zero transactions)
     ///
     /// # Returns
     /// The string "0x0".
     pub fn mine_block(&self) -> Result<String> {
+        if self.has_pending_transactions()? {
+            self.seal_pending_block().map_err(|err| anyhow!(err))?;
+            return Ok("0x0".to_string());
+        }
+
         self.get_inner()
             .write()
             .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
@@ -116,7 +194,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNo
         self.get_inner()
             .write()
             .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
-            .and_then(|writer| {
+            .and_then(|mut writer| {
                 // validate max snapshots
                 snapshots
                     .read()
@@ -134,7 +212,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNo
 
                 // snapshot the node
                 let snapshot = writer.snapshot().map_err(|err| anyhow!("{}", err))?;
-                snapshots
+                let id = snapshots
                     .write()
                     .map(|mut snapshots| {
                         snapshots.push(snapshot);
@@ -142,7 +220,11 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNo
                         snapshots.len()
                     })
                     .map_err(|err| anyhow!("failed storing snapshot: {:?}", err))
-                    .map(U64::from)
+                    .map(U64::from)?;
+                writer
+                    .subscriptions
+                    .notify_node_event(NodeEvent::SnapshotTaken { id });
+                Ok(id)
             })
     }
 
@@ -180,12 +262,72 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNo
                     .restore_snapshot(selected_snapshot)
                     .map(|_| {
                         tracing::info!("Reverting node to snapshot '{snapshot_id:?}'");
+                        writer
+                            .subscriptions
+                            .notify_node_event(NodeEvent::SnapshotReverted { id: snapshot_id });
                         true
                     })
                     .map_err(|err| anyhow!("{}", err))
             })
     }
 
+    /// Begins an isolation context: the changes made until the matching [InMemoryNode::end_context] (blocks,
+    /// transactions, storage, time, ...) are then undone. Cheaper than a snapshot, as only the storage slots written
+    /// in the context are copied. Contexts can be nested.
+    ///
+    /// # Returns
+    /// The number of the open contexts.
+    pub fn begin_context(&self) -> Result<U64> {
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .and_then(|mut writer| {
+                let depth = writer.begin_context().map_err(|err| anyhow!("{}", err))?;
+                tracing::info!("Began context #{}", depth);
+                Ok(U64::from(depth))
+            })
+    }
+
+    /// Ends the innermost isolation context, restoring the node to the state it had when the context began.
+    ///
+    /// # Returns
+    /// `true` if a context was ended, `false` if none was open.
+    pub fn end_context(&self) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .and_then(|mut writer| {
+                let depth = writer.contexts.len();
+                let ended = writer.end_context().map_err(|err| anyhow!("{}", err))?;
+                if ended {
+                    tracing::info!("Ended context #{}", depth);
+                }
+                Ok(ended)
+            })
+    }
+
+    /// Removes the latest `num_blocks` blocks from the chain and restores the state of the new latest block.
+    /// Transactions included in the removed blocks are dropped.
+    ///
+    /// # Returns
+    /// The hashes of the removed blocks, starting with the latest one.
+    pub fn rollback_blocks(&self, num_blocks: U64) -> Result<Vec<H256>> {
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .and_then(|mut writer| {
+                let removed = writer
+                    .rollback_blocks(num_blocks.as_u64())
+                    .map_err(|err| anyhow!("{}", err))?;
+                tracing::info!(
+                    "⏪ Rolled back {} blocks, latest block is #{}",
+                    removed.len(),
+                    writer.current_miniblock
+                );
+                Ok(removed)
+            })
+    }
+
     pub fn set_balance(&self, address: Address, balance: U256) -> Result<bool> {
         self.get_inner()
             .write()
@@ -243,23 +385,32 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNo
     }
 
     pub fn mine_blocks(&self, num_blocks: Option<U64>, interval: Option<U64>) -> Result<bool> {
+        let mut num_blocks = num_blocks.unwrap_or_else(|| U64::from(1));
+        if num_blocks.is_zero() {
+            return Err(anyhow!(
+                "Number of blocks must be greater than 0".to_string()
+            ));
+        }
+        // The pending transactions (if any) are sealed into the first of the mined blocks.
+        if self.has_pending_transactions()? {
+            self.seal_pending_block().map_err(|err| anyhow!(err))?;
+            num_blocks -= U64::one();
+            if num_blocks.is_zero() {
+                return Ok(true);
+            }
+        }
+
         self.get_inner()
             .write()
             .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
-            .and_then(|mut writer| {
-                let num_blocks = num_blocks.unwrap_or_else(|| U64::from(1));
+            .map(|mut writer| {
                 let interval_ms = interval
                     .unwrap_or_else(|| U64::from(1))
                     .saturating_mul(1_000.into());
-                if num_blocks.is_zero() {
-                    return Err(anyhow!(
-                        "Number of blocks must be greater than 0".to_string(),
-                    ));
-                }
                 utils::mine_empty_blocks(&mut writer, num_blocks.as_u64(), interval_ms.as_u64());
                 tracing::info!("👷 Mined {} blocks", num_blocks);
 
-                Ok(true)
+                true
             })
     }
 
@@ -270,6 +421,12 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNo
             .map(|mut writer| {
                 if writer.set_impersonated_account(address) {
                     tracing::info!("🕵️ Account {:?} has been impersonated", address);
+                    writer
+                        .subscriptions
+                        .notify_node_event(NodeEvent::ImpersonationToggled {
+                            address,
+                            impersonated: true,
+                        });
                     true
                 } else {
                     tracing::info!("🕵️ Account {:?} was already impersonated", address);
@@ -285,6 +442,12 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNo
             .map(|mut writer| {
                 if writer.stop_impersonating_account(address) {
                     tracing::info!("🕵️ Stopped impersonating account {:?}", address);
+                    writer
+                        .subscriptions
+                        .notify_node_event(NodeEvent::ImpersonationToggled {
+                            address,
+                            impersonated: false,
+                        });
                     true
                 } else {
                     tracing::info!(
@@ -296,6 +459,69 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNo
             })
     }
 
+    /// Set the block gas limit. Transactions with a higher gas limit are rejected.
+    ///
+    /// # Parameters
+    /// - `gas_limit`: The new block gas limit
+    ///
+    /// # Returns
+    /// `true` if the block gas limit was set.
+    pub fn set_block_gas_limit(&self, gas_limit: U64) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .and_then(|mut writer| {
+                if gas_limit.is_zero() {
+                    return Err(anyhow!("Block gas limit must be greater than 0"));
+                }
+                writer.block_gas_limit = gas_limit.as_u64();
+                tracing::info!("👷 Block gas limit has been set to {}", gas_limit);
+                Ok(true)
+            })
+    }
+
+    /// Enables or disables automatic mining of a new block for each submitted transaction.
+    /// When disabled, transactions are kept pending until the next block is mined.
+    pub fn set_automine(&self, enabled: bool) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .map(|mut writer| {
+                writer.automine = enabled;
+                tracing::info!(
+                    "👷 Automine has been {}",
+                    if enabled { "enabled" } else { "disabled" }
+                );
+                true
+            })
+    }
+
+    /// Sets the operator address that collects the transaction fees of the following blocks.
+    pub fn set_coinbase(&self, address: Address) -> Result<bool> {
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .map(|mut writer| {
+                writer.fee_account = address;
+                tracing::info!("👷 Coinbase has been set to {:?}", address);
+                true
+            })
+    }
+
+    pub fn get_automine(&self) -> Result<bool> {
+        self.get_inner()
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .map(|reader| reader.automine)
+    }
+
+    fn has_pending_transactions(&self) -> Result<bool> {
+        self.get_inner()
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .map(|reader| !reader.pending_transactions.is_empty())
+    }
+
     pub fn set_code(&self, address: Address, code: Vec<u8>) -> Result<()> {
         self.get_inner()
             .write()
@@ -318,16 +544,344 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNo
                 writer.fork_storage.set_value(code_key, hash);
             })
     }
+
+    /// Sets the ERC20 token balance of the given account by writing directly to the token's balances mapping.
+    ///
+    /// # Parameters
+    /// - `token`: The address of the ERC20 token
+    /// - `account`: The account whose balance will be set
+    /// - `amount`: The new balance
+    /// - `slot`: The storage slot of the balances mapping. If not provided, the first [MAX_ERC20_BALANCE_SLOT]
+    ///   slots are probed. The Solidity and the Vyper mapping layouts are both probed until `balanceOf` reflects the
+    ///   change, the storage is left untouched if neither does.
+    ///
+    /// # Returns
+    /// The storage slot of the balances mapping.
+    pub fn set_erc20_balance(
+        &self,
+        token: Address,
+        account: Address,
+        amount: U256,
+        slot: Option<U256>,
+    ) -> Result<U256> {
+        // the keys are probed on a storage view of the node, only the balance is written to its storage
+        let inner = self.get_inner();
+        let mut writer = inner
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        let code_hash = writer.fork_storage.read_value(&get_code_key(&token));
+        if code_hash.is_zero() {
+            return Err(anyhow!("token {:?} has no deployed code", token));
+        }
+
+        let slots: Vec<U256> = match slot {
+            Some(slot) => vec![slot],
+            None => (0..MAX_ERC20_BALANCE_SLOT).map(U256::from).collect(),
+        };
+        let probe = U256::from_big_endian(&keccak256(b"era_setErc20Balance"));
+        for slot in slots {
+            for key in erc20_balance_keys(token, account, slot) {
+                if probe_erc20_balance(&writer, token, account, key, probe)? == probe {
+                    writer.fork_storage.set_value(key, u256_to_h256(amount));
+                    tracing::info!(
+                        "👷 ERC20 balance of {:?} for token {:?} has been manually set to {} (slot {})",
+                        account,
+                        token,
+                        amount,
+                        slot
+                    );
+                    return Ok(slot);
+                }
+            }
+        }
+
+        Err(match slot {
+            Some(slot) => anyhow!(
+                "the balances mapping of token {:?} is not at slot {} in the Solidity or the Vyper layout",
+                token,
+                slot
+            ),
+            None => anyhow!(
+                "failed locating the balances mapping of token {:?} within the first {} slots",
+                token,
+                MAX_ERC20_BALANCE_SLOT
+            ),
+        })
+    }
+
+    /// Replaces the URL of the forked network, keeping the fork block and the local state.
+    ///
+    /// # Parameters
+    /// - `url`: The new URL, which must serve the forked block
+    pub fn set_rpc_url(&self, url: String) -> Result<()> {
+        let (mut fork_source, fork_miniblock, fork_hash) = {
+            let inner = self
+                .get_inner()
+                .read()
+                .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+            let storage = inner
+                .fork_storage
+                .inner
+                .read()
+                .map_err(|err| anyhow!("failed acquiring read lock on storage: {:?}", err))?;
+            let fork = storage
+                .fork
+                .as_ref()
+                .ok_or_else(|| anyhow!("the node is not forking a network"))?;
+            (
+                fork.fork_source.clone(),
+                fork.l2_miniblock,
+                fork.l2_miniblock_hash,
+            )
+        };
+
+        // make sure the new URL serves the same chain before switching to it
+        fork_source
+            .set_fork_url(url.clone())
+            .map_err(|err| anyhow!("failed switching to {}: {:?}", url, err))?;
+        let root_hash = fork_source
+            .get_block_details(MiniblockNumber(fork_miniblock as u32))
+            .map_err(|err| anyhow!("failed fetching the fork block from {}: {:?}", url, err))?
+            .and_then(|details| details.base.root_hash);
+        if root_hash != Some(fork_hash) {
+            return Err(anyhow!(
+                "{} does not serve the forked block {} ({:#x})",
+                url,
+                fork_miniblock,
+                fork_hash
+            ));
+        }
+
+        let mut writer = self
+            .get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        if let Some(fork) = writer
+            .fork_storage
+            .inner
+            .write()
+            .map_err(|err| anyhow!("failed acquiring write lock on storage: {:?}", err))?
+            .fork
+            .as_mut()
+        {
+            fork.fork_source = fork_source;
+        }
+        tracing::info!("🍴 Forked network is now served by {}", url);
+        writer
+            .subscriptions
+            .notify_node_event(NodeEvent::config_changed("fork-url", url));
+        Ok(())
+    }
+
+    /// Forks the network at another block, usually an earlier one, without restarting the node. The local
+    /// blocks, transactions, state and snapshots are discarded.
+    ///
+    /// # Parameters
+    /// - `number`: The number of the block of the forked network to fork at
+    pub fn warp_to_block(&self, number: U64) -> Result<()> {
+        let fork = {
+            let inner = self
+                .get_inner()
+                .read()
+                .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+            let storage = inner
+                .fork_storage
+                .inner
+                .read()
+                .map_err(|err| anyhow!("failed acquiring read lock on storage: {:?}", err))?;
+            storage
+                .fork
+                .clone()
+                .ok_or_else(|| anyhow!("the node is not forking a network"))?
+        };
+        // fetch the block before discarding anything, the node is left untouched if it does not exist
+        let fork = fork
+            .at_miniblock(number.as_u64())
+            .map_err(|err| anyhow!("failed fetching block {}: {:?}", number, err))?;
+
+        let mut writer = self
+            .get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        self.snapshots
+            .write()
+            .map_err(|err| anyhow!("failed acquiring write lock for snapshots: {:?}", err))?
+            .clear();
+        writer.reset_fork(fork);
+        tracing::info!("🍴 Warped to block {} of the forked network", number);
+        writer
+            .subscriptions
+            .notify_node_event(NodeEvent::config_changed("fork-at", number));
+        Ok(())
+    }
+
+    /// Sets the price of the token in USD reported by `zks_getTokenPrice`, overriding the built-in one if any.
+    ///
+    /// # Parameters
+    /// - `token`: The address of the token, the zero address for ETH
+    /// - `price`: The price of the token in USD, which must not be negative
+    pub fn set_token_price(&self, token: Address, price: BigDecimal) -> Result<()> {
+        if price < BigDecimal::from(0) {
+            return Err(anyhow!("token price must not be negative, got {}", price));
+        }
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .map(|mut writer| {
+                tracing::info!("💲 Price of token {:?} set to {} USD", token, price);
+                writer.token_prices.insert(token, price);
+            })
+    }
+
+    /// Imports a contract deployed on a live network, installing its bytecode and the values of the given
+    /// storage slots locally. The network is the one set with `--import-from`, or else the forked network.
+    ///
+    /// # Parameters
+    /// - `address`: The address of the contract
+    /// - `block`: The block of the live network to import the contract at, `latest` if not provided
+    /// - `slots`: The storage slots to import, at most [MAX_IMPORTED_STORAGE_SLOTS]
+    pub fn import_contract(
+        &self,
+        address: Address,
+        block: Option<BlockNumber>,
+        slots: Vec<U256>,
+    ) -> Result<()> {
+        if slots.len() > MAX_IMPORTED_STORAGE_SLOTS {
+            return Err(anyhow!(
+                "at most {} storage slots can be imported, got {}",
+                MAX_IMPORTED_STORAGE_SLOTS,
+                slots.len()
+            ));
+        }
+        let url = {
+            let inner = self
+                .get_inner()
+                .read()
+                .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+            let fork_url = inner
+                .fork_storage
+                .inner
+                .read()
+                .map_err(|err| anyhow!("failed acquiring read lock on storage: {:?}", err))?
+                .fork
+                .as_ref()
+                .and_then(|fork| fork.fork_source.get_fork_url().ok());
+            inner.import_url.clone().or(fork_url).ok_or_else(|| {
+                anyhow!("no network to import from, use --import-from or fork a network")
+            })?
+        };
+
+        let source = HttpForkSource::new(url.clone(), CacheConfig::None);
+        let block = Some(BlockIdVariant::BlockNumber(
+            block.unwrap_or(BlockNumber::Latest),
+        ));
+        let code = source
+            .get_code(address, block)
+            .map_err(|err| anyhow!("failed fetching the code from {}: {:?}", url, err))?;
+        if code.0.is_empty() {
+            return Err(anyhow!("{:?} has no deployed code on {}", address, url));
+        }
+        let values = slots
+            .into_iter()
+            .map(|slot| {
+                source
+                    .get_storage_at(address, slot, block)
+                    .map(|value| (slot, value))
+            })
+            .collect::<eyre::Result<Vec<_>>>()
+            .map_err(|err| anyhow!("failed fetching the storage from {}: {:?}", url, err))?;
+
+        self.set_code(address, code.0)?;
+        for (slot, value) in &values {
+            let key = StorageKey::new(AccountTreeId::new(address), u256_to_h256(*slot));
+            self.write_storage(key, *value)?;
+        }
+        tracing::info!(
+            "📥 Imported contract {:?} with {} storage slot(s) from {}",
+            address,
+            values.len(),
+            url
+        );
+        Ok(())
+    }
+
+    /// Writes the value to the given storage key.
+    fn write_storage(&self, key: StorageKey, value: H256) -> Result<()> {
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .map(|mut writer| writer.fork_storage.set_value(key, value))
+    }
+}
+
+/// Calls `balanceOf(account)` on the given ERC20 token with the `probe` value written at the storage key. The call
+/// runs on a storage view, so the value is never written to the node. Reverts are treated as a zero balance.
+fn probe_erc20_balance<S: ForkSource + std::fmt::Debug>(
+    inner: &InMemoryNodeInner<S>,
+    token: Address,
+    account: Address,
+    key: StorageKey,
+    probe: U256,
+) -> Result<U256> {
+    let mut data = ERC20_BALANCE_OF_SELECTOR.to_vec();
+    data.extend_from_slice(H256::from(account).as_bytes());
+    let request = CallRequestBuilder::default()
+        .to(token)
+        .data(data.into())
+        .build();
+    let mut tx = L2Tx::from_request(request.into(), MAX_TX_SIZE)
+        .map_err(|err| anyhow!("failed creating balanceOf call: {:?}", err))?;
+    tx.common_data.fee.gas_limit = ETH_CALL_GAS_LIMIT.into();
+    // We must inject *some* signature (otherwise bootloader code fails to generate hash).
+    tx.common_data.signature = PackedEthSignature::default().serialize_packed().into();
+
+    let mut storage_view = StorageView::new(&inner.fork_storage);
+    storage_view.set_value(key, u256_to_h256(probe));
+    let storage = storage_view.into_rc_ptr();
+    let bootloader_code = inner.system_contracts.contracts_for_l2_call();
+    let (batch_env, _) = inner.create_l1_batch_env(storage.clone());
+    let system_env = inner.create_system_env(bootloader_code.clone(), TxExecutionMode::EthCall);
+    let mut vm: Vm<_, HistoryDisabled> = Vm::new(batch_env, system_env, storage);
+    vm.push_transaction(tx.into());
+    let tracers = vec![ExecutionLimitTracer::new(inner.execution_limits).into_tracer_pointer()];
+
+    match vm.inspect(tracers.into(), VmExecutionMode::OneTx).result {
+        ExecutionResult::Success { output } if output.len() >= 32 => {
+            Ok(U256::from_big_endian(&output[..32]))
+        }
+        _ => Ok(U256::zero()),
+    }
+}
+
+/// Returns the candidate storage keys of `account` in a balances mapping stored at `slot`,
+/// using the Solidity (`keccak(account . slot)`) and the Vyper (`keccak(slot . account)`) layouts.
+fn erc20_balance_keys(token: Address, account: Address, slot: U256) -> [StorageKey; 2] {
+    let account = H256::from(account);
+    let slot = u256_to_h256(slot);
+    let solidity = keccak256(&[account.as_bytes(), slot.as_bytes()].concat());
+    let vyper = keccak256(&[slot.as_bytes(), account.as_bytes()].concat());
+
+    [
+        StorageKey::new(AccountTreeId::new(token), H256(solidity)),
+        StorageKey::new(AccountTreeId::new(token), H256(vyper)),
+    ]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::namespaces::EthNamespaceT;
-    use crate::{http_fork_source::HttpForkSource, node::InMemoryNode};
+    use crate::{
+        cache::CacheConfig, fork::ForkDetails, http_fork_source::HttpForkSource,
+        node::InMemoryNode, testing,
+    };
     use std::str::FromStr;
-    use zksync_basic_types::{Nonce, H256};
-    use zksync_types::{api::BlockNumber, fee::Fee, l2::L2Tx, PackedEthSignature};
+    use zksync_basic_types::Nonce;
+    use zksync_types::{
+        api::{BlockIdVariant, BlockNumber},
+        fee::Fee,
+        PackedEthSignature, L2_ETH_TOKEN_ADDRESS,
+    };
 
     #[tokio::test]
     async fn test_set_balance() {
@@ -431,6 +985,141 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_set_automine() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        assert!(node.get_automine().unwrap());
+
+        let result = node.set_automine(false).expect("set_automine");
+
+        assert!(result);
+        assert!(!node.get_automine().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_set_coinbase() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let coinbase = Address::repeat_byte(0x5);
+        assert_eq!(Address::zero(), node.coinbase().await.unwrap());
+
+        let result = node.set_coinbase(coinbase).expect("set_coinbase");
+        node.mine_block().expect("mine_block");
+
+        assert!(result);
+        assert_eq!(coinbase, node.coinbase().await.unwrap());
+        let block = node
+            .get_block_by_number(zksync_types::api::BlockNumber::Latest, false)
+            .await
+            .unwrap()
+            .expect("block exists");
+        assert_eq!(coinbase, block.author);
+    }
+
+    #[tokio::test]
+    async fn test_mine_block_seals_pending_transactions() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        node.set_automine(false).unwrap();
+        let tx = crate::testing::TransactionBuilder::new().build();
+        node.set_rich_account(tx.common_data.initiator_address);
+        node.submit_l2_tx(tx.clone()).unwrap();
+        assert!(node
+            .get_transaction_receipt(tx.hash())
+            .await
+            .unwrap()
+            .is_none());
+
+        node.mine_block().expect("mine_block");
+
+        let receipt = node
+            .get_transaction_receipt(tx.hash())
+            .await
+            .unwrap()
+            .expect("receipt exists");
+        assert_eq!(U64::from(1), receipt.status);
+    }
+
+    #[tokio::test]
+    async fn test_rollback_blocks_removes_blocks_and_transactions() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let start_block = node
+            .get_block_by_number(BlockNumber::Latest, false)
+            .await
+            .unwrap()
+            .expect("block exists");
+        let tx = crate::testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x1))
+            .build();
+        let sender = tx.common_data.initiator_address;
+        node.set_rich_account(sender);
+        let balance = node.get_balance(sender, None).await.unwrap();
+        let nonce = node.get_transaction_count(sender, None).await.unwrap();
+        node.apply_txs(vec![tx]).expect("failed applying tx");
+        let block_hash = node
+            .get_transaction_receipt(H256::repeat_byte(0x1))
+            .await
+            .unwrap()
+            .expect("receipt exists")
+            .block_hash;
+        assert_eq!(
+            nonce + 1,
+            node.get_transaction_count(sender, None).await.unwrap()
+        );
+        assert_ne!(balance, node.get_balance(sender, None).await.unwrap());
+        let queued = crate::testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x2))
+            .set_nonce(Nonce(2))
+            .build();
+        node.get_inner()
+            .write()
+            .unwrap()
+            .queued_transactions
+            .insert((sender, Nonce(2)), queued);
+
+        // the transaction block and the empty block at the end of the batch
+        let removed = node.rollback_blocks(U64::from(2)).expect("rollback_blocks");
+
+        assert_eq!(2, removed.len());
+        assert_eq!(block_hash, removed[1]);
+        assert_eq!(
+            nonce,
+            node.get_transaction_count(sender, None).await.unwrap()
+        );
+        assert_eq!(balance, node.get_balance(sender, None).await.unwrap());
+        assert!(node
+            .get_inner()
+            .read()
+            .unwrap()
+            .queued_transactions
+            .is_empty());
+        let current_block = node
+            .get_block_by_number(BlockNumber::Latest, false)
+            .await
+            .unwrap()
+            .expect("block exists");
+        assert_eq!(start_block.number, current_block.number);
+        assert_eq!(start_block.hash, current_block.hash);
+        assert!(node
+            .get_transaction_receipt(H256::repeat_byte(0x1))
+            .await
+            .unwrap()
+            .is_none());
+
+        // the block re-mined with the same transaction hash gets a new hash
+        let (new_block_hash, new_block_number) =
+            crate::testing::apply_tx(&node, H256::repeat_byte(0x1));
+        assert_eq!(start_block.number + 1, new_block_number);
+        assert_ne!(block_hash, new_block_hash);
+    }
+
+    #[tokio::test]
+    async fn test_rollback_blocks_fails_beyond_available_states() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let result = node.rollback_blocks(U64::from(10));
+
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_impersonate_account() {
         let node = InMemoryNode::<HttpForkSource>::default();
@@ -835,6 +1524,42 @@ mod tests {
         assert_eq!(start_block.timestamp + 2, current_block.timestamp);
     }
 
+    #[tokio::test]
+    async fn test_block_timestamp_interval() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let timestamp = |node: &InMemoryNode<HttpForkSource>| {
+            node.get_inner().read().unwrap().current_timestamp
+        };
+        assert!(node.set_block_timestamp_interval(0).is_err());
+
+        node.set_block_timestamp_interval(60)
+            .expect("failed setting interval");
+        let before = timestamp(&node);
+        node.mine_block().expect("failed mining block");
+        assert_eq!(before + 60, timestamp(&node));
+
+        let tx = testing::TransactionBuilder::new().build();
+        node.set_rich_account(tx.common_data.initiator_address);
+        node.submit_l2_tx(tx.clone()).expect("failed submitting tx");
+        let inner = node.get_inner();
+        let tx_block_timestamp = {
+            let reader = inner.read().unwrap();
+            let receipt = &reader.tx_results[&tx.hash()].receipt;
+            reader.blocks[&receipt.block_hash].timestamp.as_u64()
+        };
+        assert_eq!(before + 120, tx_block_timestamp);
+
+        assert!(node
+            .remove_block_timestamp_interval()
+            .expect("failed removing interval"));
+        let before = timestamp(&node);
+        node.mine_block().expect("failed mining block");
+        assert_eq!(before + 1, timestamp(&node));
+        assert!(!node
+            .remove_block_timestamp_interval()
+            .expect("failed removing interval"));
+    }
+
     #[tokio::test]
     async fn test_evm_snapshot_creates_incrementing_ids() {
         let node = InMemoryNode::<HttpForkSource>::default();
@@ -891,6 +1616,45 @@ mod tests {
         assert_eq!(1, node.snapshots.read().unwrap().len());
     }
 
+    #[tokio::test]
+    async fn test_end_context_restores_state_from_begin_context() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let address = Address::repeat_byte(0x1);
+        node.set_balance(address, U256::from(100))
+            .expect("failed setting balance");
+        let initial_block = node.get_block_number().await.unwrap();
+
+        assert_eq!(U64::from(1), node.begin_context().unwrap());
+        node.set_balance(address, U256::from(200))
+            .expect("failed setting balance");
+        node.mine_block().expect("mine_block");
+        assert_eq!(U64::from(2), node.begin_context().unwrap());
+        node.set_balance(address, U256::from(300))
+            .expect("failed setting balance");
+        node.mine_block().expect("mine_block");
+
+        assert!(node.end_context().unwrap());
+        assert_eq!(
+            U256::from(200),
+            node.get_balance(address, None).await.unwrap()
+        );
+        assert_eq!(initial_block + 1, node.get_block_number().await.unwrap());
+
+        assert!(node.end_context().unwrap());
+        assert_eq!(
+            U256::from(100),
+            node.get_balance(address, None).await.unwrap()
+        );
+        assert_eq!(initial_block, node.get_block_number().await.unwrap());
+        let reader = node.get_inner().read().unwrap();
+        assert!(!reader
+            .block_hashes
+            .contains_key(&(initial_block.as_u64() + 1)));
+        drop(reader);
+
+        assert!(!node.end_context().unwrap());
+    }
+
     #[tokio::test]
     async fn test_evm_revert_snapshot_fails_for_invalid_snapshot_id() {
         let node = InMemoryNode::<HttpForkSource>::default();
@@ -898,4 +1662,271 @@ mod tests {
         let result = node.revert_snapshot(U64::from(100));
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_set_block_gas_limit() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let result = node
+            .set_block_gas_limit(U64::from(30_000_000))
+            .expect("failed setting block gas limit");
+
+        assert!(result);
+        assert_eq!(30_000_000, node.get_inner().read().unwrap().block_gas_limit);
+    }
+
+    #[tokio::test]
+    async fn test_set_block_gas_limit_fails_for_zero() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let result = node.set_block_gas_limit(U64::zero());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_erc20_balance_keys_match_solidity_mapping_layout() {
+        let account = Address::from_str("0x36615Cf349d7F6344891B1e7CA7C72883F5dc049").unwrap();
+
+        // the base token stores balances in a solidity mapping at slot 0
+        let keys = erc20_balance_keys(L2_ETH_TOKEN_ADDRESS, account, U256::zero());
+
+        assert_eq!(storage_key_for_eth_balance(&account), keys[0]);
+        assert_ne!(keys[0], keys[1]);
+    }
+
+    #[tokio::test]
+    async fn test_set_erc20_balance_with_manual_slot_leaves_storage_untouched_on_failure() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let token = Address::repeat_byte(0x1);
+        let account = Address::repeat_byte(0x2);
+        // not a token, so `balanceOf` never reflects the probe
+        node.set_code(
+            token,
+            hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+        )
+        .expect("failed setting code");
+
+        let result = node.set_erc20_balance(token, account, U256::from(1337), Some(U256::from(3)));
+
+        assert!(result.is_err());
+        for key in erc20_balance_keys(token, account, U256::from(3)) {
+            let value = node
+                .get_storage(
+                    token,
+                    h256_to_u256(*key.key()),
+                    Some(BlockIdVariant::BlockNumber(BlockNumber::Latest)),
+                )
+                .await
+                .expect("failed reading storage");
+            assert_eq!(H256::zero(), value);
+        }
+        // the probes are never written to the node, so they show in neither the dumps nor the state diffs
+        let inner = node.get_inner();
+        let reader = inner.read().unwrap();
+        let storage = reader.fork_storage.inner.read().unwrap();
+        for key in erc20_balance_keys(token, account, U256::from(3)) {
+            assert!(!storage.raw_storage.state.contains_key(&key));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_set_erc20_balance_fails_for_account_without_code() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let token = Address::repeat_byte(0x1);
+        let account = Address::repeat_byte(0x2);
+
+        let result = node.set_erc20_balance(token, account, U256::from(1337), None);
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_contract_installs_code_and_storage() {
+        let address = Address::repeat_byte(0x1);
+        let code = vec![0xab; 32];
+        let mock_server = testing::MockServer::run();
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getCode",
+                "params": [format!("{:#x}", address), "latest"],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("0x{}", hex::encode(&code)),
+            }),
+        );
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getStorageAt",
+                "params": [format!("{:#x}", address), "0x1", "latest"],
+            }),
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": format!("{:#x}", H256::repeat_byte(0x2a)),
+            }),
+        );
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            crate::node::InMemoryNodeConfig {
+                import_url: Some(mock_server.url()),
+                ..Default::default()
+            },
+        );
+
+        node.import_contract(address, None, vec![U256::one()])
+            .expect("failed importing contract");
+
+        let imported_code = node
+            .get_code(address, None)
+            .await
+            .expect("failed getting code");
+        assert_eq!(code, imported_code.0);
+        let value = node
+            .get_storage(address, U256::one(), None)
+            .await
+            .expect("failed getting storage");
+        assert_eq!(H256::repeat_byte(0x2a), value);
+    }
+
+    /// Returns the `zks_getBlockDetails` response of the mock server for the given block.
+    fn block_details_response(number: u64, root_hash: H256) -> serde_json::Value {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "result": {
+                "number": number,
+                "l1BatchNumber": 1,
+                "timestamp": 1676461082u64,
+                "l1TxCount": 0,
+                "l2TxCount": 0,
+                "rootHash": format!("{:#x}", root_hash),
+                "status": "verified",
+                "l1GasPrice": 29860969933u64,
+                "l2FairGasPrice": 500000000u64,
+                "baseSystemContractsHashes": {
+                  "bootloader": "0x0100038581be3d0e201b3cc45d151ef5cc59eb3a0f146ad44f0f72abf00b594c",
+                  "default_aa": "0x0100038dc66b69be75ec31653c64cb931678299b9b659472772b2550b703f41c"
+                },
+                "operatorAddress": "0xfeee860e7aae671124e9a4e61139f3a5085dfeee",
+                "protocolVersion": zksync_types::ProtocolVersionId::Version15,
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_set_rpc_url_switches_to_provider_serving_the_fork_block() {
+        let fork_hash = H256::repeat_byte(0xab);
+        let mock_server = testing::MockServer::run_with_config(testing::ForkBlockConfig {
+            number: 10,
+            transaction_count: 0,
+            hash: fork_hash,
+        });
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            None,
+            Default::default(),
+        );
+        let details_request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "zks_getBlockDetails",
+            "params": [10],
+        });
+
+        let other_chain = testing::MockServer::run();
+        other_chain.expect(
+            details_request.clone(),
+            block_details_response(10, H256::repeat_byte(0xcd)),
+        );
+        assert!(node.set_rpc_url(other_chain.url()).is_err());
+
+        let same_chain = testing::MockServer::run();
+        same_chain.expect(details_request, block_details_response(10, fork_hash));
+        node.set_rpc_url(same_chain.url())
+            .expect("failed setting the fork url");
+
+        let node_info = node.node_info().expect("failed getting node info");
+        assert_eq!(Some(same_chain.url()), node_info.fork_config.fork_url);
+    }
+
+    #[tokio::test]
+    async fn test_warp_to_block_forks_at_earlier_block() {
+        let mock_server = testing::MockServer::run_with_config(testing::ForkBlockConfig {
+            number: 10,
+            transaction_count: 0,
+            hash: H256::repeat_byte(0xab),
+        });
+        let node = InMemoryNode::<HttpForkSource>::new(
+            Some(ForkDetails::from_network(&mock_server.url(), None, CacheConfig::None).await),
+            None,
+            Default::default(),
+        );
+        let address = Address::repeat_byte(0x1);
+        node.set_balance(address, U256::from(100))
+            .expect("failed setting balance");
+        let rich_address = Address::repeat_byte(0x2);
+        node.set_rich_account(rich_address);
+        node.snapshot().expect("failed creating snapshot");
+
+        let warp_hash = H256::repeat_byte(0x5);
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "zks_getBlockDetails",
+                "params": [5],
+            }),
+            block_details_response(5, warp_hash),
+        );
+        mock_server.expect(
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "method": "eth_getBlockByHash",
+                "params": [format!("{:#x}", warp_hash), true],
+            }),
+            testing::BlockResponseBuilder::new()
+                .set_hash(warp_hash)
+                .set_number(5)
+                .build(),
+        );
+        node.warp_to_block(U64::from(5))
+            .expect("failed warping to block");
+
+        let inner = node.get_inner();
+        let reader = inner.read().unwrap();
+        assert_eq!(5, reader.current_miniblock);
+        assert_eq!(warp_hash, reader.current_miniblock_hash);
+        assert_eq!(Some(&warp_hash), reader.block_hashes.get(&5));
+        assert!(!reader.block_hashes.contains_key(&10));
+        assert!(!reader
+            .fork_storage
+            .inner
+            .read()
+            .unwrap()
+            .raw_storage
+            .state
+            .contains_key(&storage_key_for_eth_balance(&address)));
+        assert!(reader.rich_accounts.contains(&rich_address));
+        assert_eq!(
+            Some(&u256_to_h256(U256::from(10u128.pow(30)))),
+            reader
+                .fork_storage
+                .inner
+                .read()
+                .unwrap()
+                .raw_storage
+                .state
+                .get(&storage_key_for_eth_balance(&rich_address))
+        );
+        assert!(node.snapshots.read().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,305 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use serde::Serialize;
+use zksync_basic_types::{H256, U256};
+
+use crate::{
+    fork::ForkSource,
+    node::{InMemoryNode, L2_GAS_PRICE},
+};
+
+/// Version reported by the node, same as `web3_clientVersion`.
+const CLIENT_VERSION: &str = "zkSync/v2.0";
+/// Commit the node was built from, set by the build script if built from a git checkout.
+const GIT_COMMIT: Option<&str> = option_env!("GIT_COMMIT_HASH");
+
+/// Response of `hardhat_metadata`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HardhatMetadata {
+    pub client_version: String,
+    pub chain_id: u64,
+    pub instance_id: H256,
+    pub latest_block_number: u64,
+    pub latest_block_hash: H256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forked_network: Option<HardhatForkedNetwork>,
+}
+
+/// Details of the forked network in [HardhatMetadata].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HardhatForkedNetwork {
+    pub chain_id: u64,
+    pub fork_block_number: u64,
+    pub fork_block_hash: H256,
+}
+
+/// Response of `anvil_nodeInfo`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+    pub current_block_number: u64,
+    pub current_block_timestamp: u64,
+    pub current_block_hash: H256,
+    pub hard_fork: String,
+    pub transaction_order: String,
+    pub instance_id: H256,
+    pub environment: NodeEnvironment,
+    pub mining: NodeMining,
+    pub fork_config: NodeForkConfig,
+}
+
+/// Chain environment in [NodeInfo].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeEnvironment {
+    pub base_fee: U256,
+    pub chain_id: u64,
+    pub gas_limit: U256,
+    pub gas_price: U256,
+}
+
+/// Mining configuration in [NodeInfo].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeMining {
+    pub automine: bool,
+    pub pending_transactions: usize,
+    /// The number of seconds between the timestamps of the mined blocks, if set.
+    pub block_timestamp_interval: Option<u64>,
+}
+
+/// Fork configuration in [NodeInfo]. All the fields are `None` if the node is not forked.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeForkConfig {
+    pub fork_url: Option<String>,
+    pub fork_block_number: Option<u64>,
+    pub fork_block_hash: Option<H256>,
+}
+
+/// Response of `era_getNodeInfo`, so that the test orchestration can detect a mismatched or restarted node.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EraNodeInfo {
+    /// The version of the `era_test_node` build.
+    pub version: String,
+    /// The commit the node was built from, if known.
+    pub git_commit: Option<String>,
+    pub client_version: String,
+    /// Identifier of this node instance, formatted as a UUID. It changes on every start, except in deterministic mode.
+    pub instance_id: String,
+    /// The unix timestamp of the start of the node, in seconds.
+    pub started_at: u64,
+    /// The number of seconds since the start of the node.
+    pub uptime: u64,
+    pub chain_id: u64,
+    /// The forked network, if the node is forked.
+    pub fork: Option<EraForkInfo>,
+    /// The features enabled in the build and the configuration of the node, e.g. `automine` or `fork`.
+    pub features: Vec<String>,
+}
+
+/// Forked network in [EraNodeInfo].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EraForkInfo {
+    pub url: Option<String>,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub block_timestamp: u64,
+    pub l1_batch_number: u32,
+}
+
+/// Formats the first 16 bytes of the instance id as a version 4 UUID.
+fn instance_uuid(instance_id: H256) -> String {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&instance_id.as_bytes()[..16]);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Returns the version, the instance and the configuration of the node, in the format of `era_getNodeInfo`.
+    pub fn era_node_info(&self) -> anyhow::Result<EraNodeInfo> {
+        let inner = self
+            .get_inner()
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        let fork = inner
+            .fork_storage
+            .inner
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?
+            .fork
+            .as_ref()
+            .map(|fork| EraForkInfo {
+                url: fork.fork_source.get_fork_url().ok(),
+                block_number: fork.l2_miniblock,
+                block_hash: fork.l2_miniblock_hash,
+                block_timestamp: fork.block_timestamp,
+                l1_batch_number: fork.l1_block.0,
+            });
+
+        let features = [
+            ("otlp", cfg!(feature = "otlp")),
+            ("fork", fork.is_some()),
+            ("automine", inner.automine),
+            ("deterministic", inner.deterministic),
+            ("l1BatchSealing", inner.l1_batch_sealer.is_some()),
+            ("skipNonceCheck", inner.skip_nonce_check),
+            ("skipBalanceCheck", inner.skip_balance_check),
+            ("chaos", self.chaos.is_enabled()),
+        ]
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(feature, _)| feature.to_string())
+        .collect();
+
+        Ok(EraNodeInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: GIT_COMMIT.map(str::to_string),
+            client_version: CLIENT_VERSION.to_string(),
+            instance_id: instance_uuid(inner.instance_id),
+            started_at: inner
+                .started_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            uptime: SystemTime::now()
+                .duration_since(inner.started_at)
+                .unwrap_or(Duration::ZERO)
+                .as_secs(),
+            chain_id: inner.fork_storage.chain_id.as_u64(),
+            fork,
+            features,
+        })
+    }
+
+    /// Returns the metadata of the node, in the format of `hardhat_metadata`.
+    pub fn hardhat_metadata(&self) -> anyhow::Result<HardhatMetadata> {
+        let info = self.node_info()?;
+        let forked_network = match (
+            info.fork_config.fork_block_number,
+            info.fork_config.fork_block_hash,
+        ) {
+            (Some(fork_block_number), Some(fork_block_hash)) => Some(HardhatForkedNetwork {
+                chain_id: info.environment.chain_id,
+                fork_block_number,
+                fork_block_hash,
+            }),
+            _ => None,
+        };
+
+        Ok(HardhatMetadata {
+            client_version: CLIENT_VERSION.to_string(),
+            chain_id: info.environment.chain_id,
+            instance_id: info.instance_id,
+            latest_block_number: info.current_block_number,
+            latest_block_hash: info.current_block_hash,
+            forked_network,
+        })
+    }
+
+    /// Returns the details of the node, in the format of `anvil_nodeInfo`.
+    pub fn node_info(&self) -> anyhow::Result<NodeInfo> {
+        let inner = self
+            .get_inner()
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        let fork_config = inner
+            .fork_storage
+            .inner
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?
+            .fork
+            .as_ref()
+            .map(|fork| NodeForkConfig {
+                fork_url: fork.fork_source.get_fork_url().ok(),
+                fork_block_number: Some(fork.l2_miniblock),
+                fork_block_hash: Some(fork.l2_miniblock_hash),
+            })
+            .unwrap_or_default();
+
+        Ok(NodeInfo {
+            current_block_number: inner.current_miniblock,
+            current_block_timestamp: inner.current_timestamp,
+            current_block_hash: inner.current_miniblock_hash,
+            hard_fork: format!("{:?}", zksync_types::ProtocolVersionId::latest()),
+            transaction_order: "fifo".to_string(),
+            instance_id: inner.instance_id,
+            environment: NodeEnvironment {
+                base_fee: U256::from(L2_GAS_PRICE),
+                chain_id: inner.fork_storage.chain_id.as_u64(),
+                gas_limit: U256::from(inner.block_gas_limit),
+                gas_price: U256::from(L2_GAS_PRICE),
+            },
+            mining: NodeMining {
+                automine: inner.automine,
+                pending_transactions: inner.pending_transactions.len(),
+                block_timestamp_interval: inner.block_timestamp_interval,
+            },
+            fork_config,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_fork_source::HttpForkSource, node::TEST_NODE_NETWORK_ID};
+
+    #[test]
+    fn test_hardhat_metadata_of_non_forked_node() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let metadata = node.hardhat_metadata().expect("failed getting metadata");
+
+        assert_eq!(TEST_NODE_NETWORK_ID as u64, metadata.chain_id);
+        assert_eq!(0, metadata.latest_block_number);
+        assert_eq!(None, metadata.forked_network);
+        assert_eq!(metadata.instance_id, node.node_info().unwrap().instance_id);
+        assert_ne!(H256::zero(), metadata.instance_id);
+    }
+
+    #[test]
+    fn test_era_node_info_of_non_forked_node() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let info = node.era_node_info().expect("failed getting node info");
+
+        assert_eq!(env!("CARGO_PKG_VERSION"), info.version);
+        assert_eq!(TEST_NODE_NETWORK_ID as u64, info.chain_id);
+        assert_eq!(None, info.fork);
+        assert!(info.features.contains(&"automine".to_string()));
+        assert_eq!(36, info.instance_id.len());
+        assert_eq!(
+            info.instance_id,
+            node.era_node_info().unwrap().instance_id,
+            "instance id changed"
+        );
+    }
+
+    #[test]
+    fn test_instance_uuid() {
+        assert_eq!(
+            "00000000-0000-4000-8000-000000000000",
+            instance_uuid(H256::zero())
+        );
+        assert_eq!(
+            "ffffffff-ffff-4fff-bfff-ffffffffffff",
+            instance_uuid(H256::repeat_byte(0xff))
+        );
+    }
+}
@@ -1,16 +1,56 @@
 //! In-memory node, that supports forking other networks.
 
+mod anvil;
+mod bytecode;
 mod config;
+mod coverage;
 mod debug;
+mod era;
+mod era_pubsub;
 mod eth;
+mod eth_pubsub;
 mod evm;
-mod fee_model;
+mod factory_deps;
+pub mod fee_model;
+mod gas_report;
 mod hardhat;
+mod history;
+mod hooks;
 mod in_memory;
 mod in_memory_ext;
+mod metadata;
 mod net;
+mod sender_recovery;
+mod signers;
+mod state_diff;
+mod state_root;
 mod storage_logs;
+mod trace;
+mod tx_envelope;
+mod tx_filter;
 mod web3;
+mod workers;
 mod zks;
 
+pub use bytecode::{validate_bytecode, BytecodeValidation, MAX_BYTECODE_LENGTH};
+pub use coverage::{ContractCoverage, CoverageTracer, ExecutionCoverage, InstructionCoverage};
+pub use debug::{StorageEntry, StorageRangeResult};
+pub use era::{BundleCallResult, DecodedTransaction, VmStats};
+pub use factory_deps::{FactoryDep, FactoryDeps, FactoryDepsTarget};
+pub use gas_report::{FunctionGas, GasChange, GasComparison, GasReport, GasReportWindow};
+pub use history::{HistoryEntry, HistoryFormat};
+pub use hooks::NodeHook;
 pub use in_memory::*;
+pub use metadata::{
+    EraForkInfo, EraNodeInfo, HardhatForkedNetwork, HardhatMetadata, NodeEnvironment,
+    NodeForkConfig, NodeInfo, NodeMining,
+};
+pub use sender_recovery::{Eip712Digest, SenderRecovery};
+pub use state_diff::{AccountDiff, StateDiff, ValueDiff};
+pub use state_root::compute_state_root;
+pub use trace::{
+    CallAction, CreateAction, Trace, TraceAction, TraceFilter, TraceResult, TraceType,
+};
+pub use tx_envelope::{decode_raw_transaction, DecodedEnvelope};
+pub use tx_filter::{TxFilterAction, TxFilterRule};
+pub use workers::VmWorkers;
@@ -1,16 +1,21 @@
 use zksync_basic_types::U256;
+use zksync_web3_decl::error::Web3Error;
 
 use crate::{
     fork::ForkSource,
     namespaces::{NetNamespaceT, Result},
-    node::{InMemoryNode, TEST_NODE_NETWORK_ID},
+    node::InMemoryNode,
+    utils::into_jsrpc_error,
 };
 
 impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> NetNamespaceT
     for InMemoryNode<S>
 {
     fn net_version(&self) -> Result<String> {
-        Ok(TEST_NODE_NETWORK_ID.to_string())
+        self.get_inner()
+            .read()
+            .map(|inner| inner.fork_storage.chain_id.as_u64().to_string())
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
     }
 
     fn net_peer_count(&self) -> Result<U256> {
@@ -18,6 +23,34 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> NetNamespa
     }
 
     fn net_listening(&self) -> Result<bool> {
-        Ok(false)
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http_fork_source::HttpForkSource,
+        node::{InMemoryNodeConfig, TEST_NODE_NETWORK_ID},
+    };
+
+    #[test]
+    fn test_net_version_returns_chain_id() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        assert_eq!(
+            TEST_NODE_NETWORK_ID.to_string(),
+            node.net_version().unwrap()
+        );
+
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                chain_id: Some(1337),
+                ..Default::default()
+            },
+        );
+        assert_eq!("1337", node.net_version().unwrap());
     }
 }
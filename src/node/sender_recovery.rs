@@ -0,0 +1,191 @@
+//! Recovery of the signer of a raw transaction with `era_recoverSender`, to diagnose the transactions rejected for
+//! an invalid signature or a wrong chain id, e.g. by custom signers.
+use std::convert::TryFrom;
+
+use anyhow::anyhow;
+use serde::Serialize;
+use zksync_basic_types::{Address, L2ChainId, H256};
+use zksync_types::{
+    transaction_request::{SerializationTransactionError, TransactionRequest},
+    EIP712TypedStructure, Eip712Domain, PackedEthSignature,
+};
+use zksync_utils::u256_to_h256;
+
+use crate::{fork::ForkSource, node::InMemoryNode};
+
+/// Signer of a raw transaction, as returned by `era_recoverSender`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SenderRecovery {
+    /// The EIP-2718 type of the transaction, 0 for the legacy ones and 113 (`0x71`) for the EIP-712 ones.
+    pub transaction_type: u8,
+    pub hash: Option<H256>,
+    /// The account that signed the transaction.
+    pub signer: Option<Address>,
+    /// The sender declared in the EIP-712 transactions, which only differs from the signer for the smart contract
+    /// accounts. The same as the signer for the other transactions.
+    pub from: Option<Address>,
+    /// The chain id the transaction is signed for, missing for the legacy transactions without replay protection.
+    pub chain_id: Option<u64>,
+    pub node_chain_id: u64,
+    pub chain_id_matches: bool,
+    /// The hashes the EIP-712 transactions are signed over.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eip712: Option<Eip712Digest>,
+    /// Why the node rejects the transaction, if it does.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Hashes of an EIP-712 transaction. The signed digest is `keccak256(0x1901 ++ domainSeparator ++ structHash)`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Eip712Digest {
+    /// The hash of the `zkSync` domain, version 2, on the chain id of the transaction.
+    pub domain_separator: H256,
+    pub struct_hash: H256,
+    pub digest: H256,
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Decodes the raw transaction and recovers its signer. A transaction signed for another chain is decoded
+    /// with the chain id it is signed for, so that its signer is recovered as well.
+    pub fn recover_sender(&self, tx_bytes: &[u8]) -> anyhow::Result<SenderRecovery> {
+        let node_chain_id = self
+            .get_inner()
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?
+            .fork_storage
+            .chain_id;
+        let transaction_type = match tx_bytes.first() {
+            Some(byte) if *byte < 0x7f => *byte,
+            Some(_) => 0,
+            None => return Err(anyhow!("empty transaction")),
+        };
+        let mut recovery = SenderRecovery {
+            transaction_type,
+            node_chain_id: node_chain_id.as_u64(),
+            ..Default::default()
+        };
+
+        let (request, hash, chain_id) =
+            match TransactionRequest::from_bytes(tx_bytes, node_chain_id) {
+                Ok((request, hash)) => (request, hash, node_chain_id),
+                Err(err) => {
+                    recovery.error = Some(err.to_string());
+                    let signed_chain_id = match err {
+                        SerializationTransactionError::WrongChainId(Some(chain_id)) => {
+                            u32::try_from(chain_id).ok().map(L2ChainId::from)
+                        }
+                        _ => None,
+                    };
+                    let decoded = signed_chain_id.and_then(|chain_id| {
+                        TransactionRequest::from_bytes(tx_bytes, chain_id)
+                            .ok()
+                            .map(|(request, hash)| (request, hash, chain_id))
+                    });
+                    match decoded {
+                        Some(decoded) => decoded,
+                        None => return Ok(recovery),
+                    }
+                }
+            };
+
+        recovery.hash = Some(hash);
+        recovery.from = request.from;
+        recovery.chain_id = request.chain_id;
+        recovery.chain_id_matches = request
+            .chain_id
+            .map_or(true, |chain_id| chain_id == node_chain_id.as_u64());
+        if request.is_eip712_tx() {
+            let domain = Eip712Domain::new(chain_id);
+            let digest = PackedEthSignature::typed_data_to_signed_bytes(&domain, &request);
+            recovery.signer = eip712_signature(&request)
+                .and_then(|signature| PackedEthSignature::deserialize_packed(&signature).ok())
+                .and_then(|signature| signature.signature_recover_signer(&digest).ok());
+            recovery.eip712 = Some(Eip712Digest {
+                domain_separator: domain.hash_struct(),
+                struct_hash: request.hash_struct(),
+                digest,
+            });
+        } else {
+            recovery.signer = request.from;
+        }
+        Ok(recovery)
+    }
+}
+
+/// Returns the packed signature of an EIP-712 transaction: the custom signature if set, otherwise the one made of
+/// its `r`, `s` and `v` fields.
+fn eip712_signature(request: &TransactionRequest) -> Option<Vec<u8>> {
+    let custom_signature = request
+        .eip712_meta
+        .as_ref()
+        .and_then(|meta| meta.custom_signature.clone())
+        .filter(|signature| !signature.is_empty());
+    if custom_signature.is_some() {
+        return custom_signature;
+    }
+
+    let (r, s, v) = (request.r?, request.s?, request.v?);
+    let mut signature = [u256_to_h256(r).as_bytes(), u256_to_h256(s).as_bytes()].concat();
+    signature.push(v.as_u64() as u8);
+    Some(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+    use zksync_basic_types::{Nonce, U256};
+    use zksync_types::{fee::Fee, l2::L2Tx};
+
+    fn raw_tx(chain_id: u32, private_key: &H256) -> Vec<u8> {
+        let tx = L2Tx::new_signed(
+            Address::repeat_byte(0x1),
+            vec![],
+            Nonce(0),
+            Fee {
+                gas_limit: U256::from(1_000_000),
+                max_fee_per_gas: U256::from(250_000_000),
+                max_priority_fee_per_gas: U256::from(250_000_000),
+                gas_per_pubdata_limit: U256::from(20000),
+            },
+            U256::from(1),
+            L2ChainId::from(chain_id),
+            private_key,
+            None,
+            Default::default(),
+        )
+        .expect("failed signing transaction");
+        tx.common_data.input.expect("missing raw transaction").data
+    }
+
+    #[test]
+    fn test_recover_sender_of_transaction_for_other_chain() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let private_key = H256::repeat_byte(0x2);
+        let sender = PackedEthSignature::address_from_private_key(&private_key).unwrap();
+
+        let recovery = node
+            .recover_sender(&raw_tx(260, &private_key))
+            .expect("failed recovering sender");
+        assert_eq!(0x71, recovery.transaction_type);
+        assert_eq!(Some(sender), recovery.signer);
+        assert_eq!(Some(sender), recovery.from);
+        assert_eq!(Some(260), recovery.chain_id);
+        assert!(recovery.chain_id_matches);
+        assert!(recovery.eip712.is_some());
+        assert_eq!(None, recovery.error);
+
+        let recovery = node
+            .recover_sender(&raw_tx(9, &private_key))
+            .expect("failed recovering sender");
+        assert_eq!(Some(sender), recovery.signer);
+        assert_eq!(Some(9), recovery.chain_id);
+        assert!(!recovery.chain_id_matches);
+        assert!(recovery.error.is_some());
+
+        assert!(node.recover_sender(&[]).is_err());
+    }
+}
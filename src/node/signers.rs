@@ -0,0 +1,95 @@
+//! Local signers imported with `--import-key` or `era_importAccount`, so that teams can send transactions with
+//! `eth_sendTransaction` and sign messages with `eth_sign` from their existing development keys.
+use anyhow::anyhow;
+use zksync_basic_types::{Address, H256};
+use zksync_types::{Bytes, PackedEthSignature};
+
+use crate::{fork::ForkSource, node::InMemoryNode};
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Registers the private key as a local signer, funding its account like the rich wallets if `fund` is set.
+    ///
+    /// # Returns
+    /// The address of the imported account.
+    pub fn import_account(&self, private_key: H256, fund: bool) -> anyhow::Result<Address> {
+        let address = PackedEthSignature::address_from_private_key(&private_key)
+            .map_err(|err| anyhow!("invalid private key: {:?}", err))?;
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?
+            .signers
+            .insert(address, private_key);
+        if fund {
+            self.set_rich_account(address);
+        }
+        Ok(address)
+    }
+
+    /// Signs the message with the key of the local signer, prefixed as a personal message (EIP-191).
+    ///
+    /// # Returns
+    /// The 65 bytes signature, or an error if the address is not a local signer.
+    pub fn sign_message(&self, address: Address, message: &[u8]) -> anyhow::Result<Bytes> {
+        let private_key = self
+            .get_inner()
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?
+            .signers
+            .get(&address)
+            .copied()
+            .ok_or_else(|| anyhow!("no private key for account {:?}", address))?;
+        let signature = PackedEthSignature::sign(&private_key, message)
+            .map_err(|err| anyhow!("failed signing message: {:?}", err))?;
+        Ok(Bytes(signature.serialize_packed().to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+
+    #[test]
+    fn test_import_account_signs_messages() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let private_key = H256::repeat_byte(0xef);
+        let address = node
+            .import_account(private_key, false)
+            .expect("failed importing account");
+        assert_eq!(
+            PackedEthSignature::address_from_private_key(&private_key).unwrap(),
+            address
+        );
+        assert!(!node
+            .get_inner()
+            .read()
+            .unwrap()
+            .rich_accounts
+            .contains(&address));
+
+        let signature = node
+            .sign_message(address, b"hello")
+            .expect("failed signing message");
+        let signer = PackedEthSignature::deserialize_packed(&signature.0)
+            .expect("invalid signature")
+            .signature_recover_signer(&PackedEthSignature::message_to_signed_bytes(b"hello"))
+            .expect("failed recovering signer");
+        assert_eq!(address, signer);
+
+        assert!(node
+            .sign_message(Address::repeat_byte(0x1), b"hello")
+            .is_err());
+    }
+
+    #[test]
+    fn test_import_account_funds_account() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let address = node
+            .import_account(H256::repeat_byte(0xef), true)
+            .expect("failed importing account");
+
+        let reader = node.get_inner().read().unwrap();
+        assert!(reader.rich_accounts.contains(&address));
+        assert!(reader.signers.contains_key(&address));
+    }
+}
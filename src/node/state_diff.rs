@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use zksync_basic_types::{Address, H256, U256, U64};
+use zksync_types::{
+    get_nonce_key,
+    utils::{decompose_full_nonce, storage_key_for_eth_balance},
+    StorageKey, ACCOUNT_CODE_STORAGE_ADDRESS,
+};
+use zksync_utils::h256_to_u256;
+
+use crate::{
+    fork::ForkSource,
+    node::{InMemoryNode, Snapshot},
+};
+
+/// Differences between the states of two snapshots, as returned by `era_diffSnapshots`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateDiff {
+    /// The accounts whose balance, nonce, code or storage differ, keyed by address.
+    pub accounts: BTreeMap<Address, AccountDiff>,
+    /// The hashes of the bytecodes that are only known in the second snapshot.
+    pub new_bytecodes: BTreeSet<H256>,
+}
+
+/// Differences of a single account in a [StateDiff]. The fields that did not change are omitted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDiff {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<ValueDiff<U256>>,
+    /// The transaction nonce of the account.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<ValueDiff<U256>>,
+    /// The hash of the bytecode deployed at the account, zero if none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<ValueDiff<H256>>,
+    /// The storage slots of the account, keyed by slot.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<H256, ValueDiff<H256>>,
+}
+
+impl AccountDiff {
+    fn is_empty(&self) -> bool {
+        self.balance.is_none()
+            && self.nonce.is_none()
+            && self.code_hash.is_none()
+            && self.storage.is_empty()
+    }
+}
+
+/// Value in the first and in the second snapshot.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueDiff<T> {
+    pub from: T,
+    pub to: T,
+}
+
+impl<T: PartialEq> ValueDiff<T> {
+    fn changed(from: T, to: T) -> Option<Self> {
+        if from == to {
+            None
+        } else {
+            Some(Self { from, to })
+        }
+    }
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Compares the states of two snapshots taken with `evm_snapshot`, neither of which is consumed.
+    ///
+    /// The storage slots are reported under the contract they belong to, including the balances and nonces kept
+    /// by the system contracts. The balance and nonce are additionally decoded for the senders and recipients of
+    /// the transactions, the rich and the impersonated accounts of either snapshot and for the accounts with other
+    /// differences, as the addresses of the other balances and nonces can't be recovered from their hashed
+    /// storage keys.
+    pub fn diff_snapshots(&self, from_id: U64, to_id: U64) -> Result<StateDiff> {
+        // the locks are taken in the same order as when snapshotting
+        let inner = self.get_inner();
+        let reader = inner
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        let snapshots = self
+            .snapshots
+            .read()
+            .map_err(|err| anyhow!("failed acquiring read lock for snapshots: {:?}", err))?;
+        let snapshot = |id: U64| {
+            id.as_usize()
+                .checked_sub(1)
+                .and_then(|index| snapshots.get(index))
+                .ok_or_else(|| anyhow!("no snapshot exists for the id '{}'", id))
+        };
+        let (from, to) = (snapshot(from_id)?, snapshot(to_id)?);
+
+        // the slots that were never written locally hold the value at the fork point
+        let value =
+            |snapshot: &Snapshot, key: &StorageKey| match snapshot.raw_storage.state.get(key) {
+                Some(value) if !value.is_zero() => *value,
+                _ => reader.fork_storage.read_fork_value(key),
+            };
+
+        let mut diff = StateDiff::default();
+        let keys: HashSet<&StorageKey> = from
+            .raw_storage
+            .state
+            .keys()
+            .chain(to.raw_storage.state.keys())
+            .collect();
+        for key in keys {
+            if let Some(change) = ValueDiff::changed(value(from, key), value(to, key)) {
+                if *key.address() == ACCOUNT_CODE_STORAGE_ADDRESS {
+                    let account = Address::from_slice(&key.key().as_bytes()[12..]);
+                    diff.accounts.entry(account).or_default().code_hash = Some(change.clone());
+                }
+                diff.accounts
+                    .entry(*key.address())
+                    .or_default()
+                    .storage
+                    .insert(*key.key(), change);
+            }
+        }
+
+        let mut accounts: BTreeSet<Address> = diff.accounts.keys().copied().collect();
+        for snapshot in &[from, to] {
+            for result in snapshot.tx_results.values() {
+                accounts.insert(result.receipt.from);
+                accounts.extend(result.receipt.to);
+            }
+            accounts.extend(snapshot.rich_accounts.iter());
+            accounts.extend(snapshot.impersonated_accounts.iter());
+        }
+        for account in accounts {
+            let balance_key = storage_key_for_eth_balance(&account);
+            let balance = ValueDiff::changed(
+                h256_to_u256(value(from, &balance_key)),
+                h256_to_u256(value(to, &balance_key)),
+            );
+            let nonce_key = get_nonce_key(&account);
+            let nonce = ValueDiff::changed(
+                decompose_full_nonce(h256_to_u256(value(from, &nonce_key))).0,
+                decompose_full_nonce(h256_to_u256(value(to, &nonce_key))).0,
+            );
+            if balance.is_some() || nonce.is_some() {
+                let account_diff = diff.accounts.entry(account).or_default();
+                account_diff.balance = balance;
+                account_diff.nonce = nonce;
+            }
+        }
+        diff.accounts
+            .retain(|_, account_diff| !account_diff.is_empty());
+
+        diff.new_bytecodes = to
+            .raw_storage
+            .factory_deps
+            .keys()
+            .filter(|hash| !from.raw_storage.factory_deps.contains_key(hash))
+            .copied()
+            .collect();
+
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+    use zksync_types::get_code_key;
+
+    #[test]
+    fn test_diff_snapshots_reports_changed_accounts() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let account = Address::repeat_byte(0x1);
+        let contract = Address::repeat_byte(0x2);
+        let untouched = Address::repeat_byte(0x3);
+        node.set_balance(untouched, U256::from(7))
+            .expect("failed setting balance");
+
+        let before = node.snapshot().expect("failed creating snapshot");
+        node.set_rich_account(account);
+        node.set_nonce(account, U256::from(3))
+            .expect("failed setting nonce");
+        node.set_code(contract, vec![0x1u8; 32])
+            .expect("failed setting code");
+        let after = node.snapshot().expect("failed creating snapshot");
+
+        let diff = node
+            .diff_snapshots(before, after)
+            .expect("failed diffing snapshots");
+
+        let account_diff = &diff.accounts[&account];
+        assert_eq!(
+            Some(ValueDiff {
+                from: U256::zero(),
+                to: U256::from(10u128.pow(30))
+            }),
+            account_diff.balance
+        );
+        assert_eq!(U256::from(3), account_diff.nonce.as_ref().unwrap().to);
+        let code_hash = diff.accounts[&contract].code_hash.as_ref().unwrap();
+        assert_eq!(H256::zero(), code_hash.from);
+        assert!(diff.new_bytecodes.contains(&code_hash.to));
+        assert!(diff.accounts[&ACCOUNT_CODE_STORAGE_ADDRESS]
+            .storage
+            .contains_key(get_code_key(&contract).key()));
+        assert!(!diff.accounts.contains_key(&untouched));
+
+        assert!(node.diff_snapshots(before, U64::from(3)).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! State roots of the blocks, computed with `--state-roots` so that the tools checking the state root linkage of the
+//! blocks see values that follow the state of the node. By default the blocks are sealed with a zero state root, as
+//! hashing the whole storage slows down every block.
+use anyhow::anyhow;
+use zksync_basic_types::H256;
+use zksync_types::{web3::signing::keccak256, StorageKey, StorageValue};
+
+use crate::{fork::ForkSource, node::InMemoryNodeInner};
+
+/// Computes the root of the binary Merkle tree over the non-zero storage slots, ordered by hashed key. Each leaf is
+/// the hash of the hashed key followed by the value, and each node the hash of its two children, an odd node being
+/// carried up as is. The root of the empty state is the hash of no data.
+pub fn compute_state_root<'a>(
+    slots: impl IntoIterator<Item = (&'a StorageKey, &'a StorageValue)>,
+) -> H256 {
+    let mut leaves: Vec<_> = slots
+        .into_iter()
+        .filter(|(_, value)| !value.is_zero())
+        .map(|(key, value)| (key.hashed_key(), *value))
+        .collect();
+    leaves.sort_unstable_by_key(|(hashed_key, _)| *hashed_key);
+
+    let mut level: Vec<H256> = leaves
+        .into_iter()
+        .map(|(hashed_key, value)| {
+            H256(keccak256(
+                &[hashed_key.as_bytes(), value.as_bytes()].concat(),
+            ))
+        })
+        .collect();
+    if level.is_empty() {
+        return H256(keccak256(&[]));
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => H256(keccak256(&[left.as_bytes(), right.as_bytes()].concat())),
+                [node] => *node,
+                _ => unreachable!("chunks of at most 2 nodes"),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+impl<S: std::fmt::Debug + ForkSource> InMemoryNodeInner<S> {
+    /// Computes the state root of the local storage. When forking, the storage only holds the slots changed locally,
+    /// so the hash of the forked block is folded into the root.
+    pub(crate) fn state_root(&self) -> anyhow::Result<H256> {
+        let storage = self
+            .fork_storage
+            .inner
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        let root = compute_state_root(&storage.raw_storage.state);
+        Ok(match &storage.fork {
+            Some(fork) => H256(keccak256(
+                &[fork.l2_miniblock_hash.as_bytes(), root.as_bytes()].concat(),
+            )),
+            None => root,
+        })
+    }
+
+    /// Sets the state root of a new block, once its state was committed and its parent hash set. The state root and
+    /// the parent hash are then folded into the block hash, so that the hash of a block commits to its state and to the
+    /// chain it extends. Must be called before the receipts of the block are built, as they hold its hash.
+    ///
+    /// Does nothing unless the state roots are computed. A failure is only logged, leaving the block hash and the zero
+    /// state root, so that it never prevents sealing the block.
+    pub(crate) fn seal_state_root<TX>(&self, block: &mut zksync_types::api::Block<TX>) {
+        if !self.state_roots {
+            return;
+        }
+        match self.state_root() {
+            Ok(root) => {
+                block.hash = H256(keccak256(
+                    &[
+                        block.hash.as_bytes(),
+                        block.parent_hash.as_bytes(),
+                        root.as_bytes(),
+                    ]
+                    .concat(),
+                ));
+                block.state_root = root;
+            }
+            Err(err) => tracing::error!(
+                "failed computing the state root of block {}: {:?}",
+                block.number,
+                err
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http_fork_source::HttpForkSource,
+        node::{compute_hash, InMemoryNode, InMemoryNodeConfig},
+        testing,
+    };
+    use zksync_basic_types::{AccountTreeId, Address};
+
+    #[test]
+    fn test_compute_state_root_ignores_order_and_zero_slots() {
+        let key =
+            |byte| StorageKey::new(AccountTreeId::new(Address::repeat_byte(byte)), H256::zero());
+        let (a, b, c) = (key(0x1), key(0x2), key(0x3));
+        let one = H256::from_low_u64_be(1);
+        let zero = H256::zero();
+
+        let root = compute_state_root(vec![(&a, &one), (&b, &one), (&c, &zero)]);
+        assert_eq!(root, compute_state_root(vec![(&b, &one), (&a, &one)]));
+        assert_ne!(root, compute_state_root(vec![(&a, &one)]));
+        assert_eq!(H256(keccak256(&[])), compute_state_root(vec![(&c, &zero)]));
+    }
+
+    #[test]
+    fn test_state_roots_follow_the_state() {
+        let node = InMemoryNode::<HttpForkSource>::new(
+            None,
+            None,
+            InMemoryNodeConfig {
+                state_roots: true,
+                ..Default::default()
+            },
+        );
+        let (genesis_hash, genesis_root) = {
+            let reader = node.get_inner().read().unwrap();
+            let genesis = &reader.blocks[&reader.current_miniblock_hash];
+            assert_eq!(genesis.hash, reader.block_hashes[&0]);
+            (genesis.hash, genesis.state_root)
+        };
+        assert_ne!(H256::zero(), genesis_root);
+        assert_eq!(
+            H256(keccak256(
+                &[
+                    compute_hash(0, H256::zero()).as_bytes(),
+                    H256::zero().as_bytes(),
+                    genesis_root.as_bytes()
+                ]
+                .concat()
+            )),
+            genesis_hash
+        );
+
+        let (block_hash, _) = testing::apply_tx(&node, H256::repeat_byte(0x1));
+        let reader = node.get_inner().read().unwrap();
+        let block = &reader.blocks[&block_hash];
+        let state_root = block.state_root;
+        assert_eq!(
+            reader.state_root().expect("failed computing state root"),
+            state_root
+        );
+        assert_ne!(genesis_root, state_root);
+        assert_eq!(genesis_hash, block.parent_hash);
+        assert_eq!(
+            H256(keccak256(
+                &[
+                    compute_hash(1, H256::repeat_byte(0x1)).as_bytes(),
+                    genesis_hash.as_bytes(),
+                    state_root.as_bytes()
+                ]
+                .concat()
+            )),
+            block_hash
+        );
+        let receipt = &reader.tx_results[&H256::repeat_byte(0x1)].receipt;
+        assert_eq!(block_hash, receipt.block_hash);
+        assert_eq!(
+            state_root,
+            reader.blocks[&reader.current_miniblock_hash].state_root
+        );
+
+        let default_node = InMemoryNode::<HttpForkSource>::default();
+        let (block_hash, _) = testing::apply_tx(&default_node, H256::repeat_byte(0x1));
+        let reader = default_node.get_inner().read().unwrap();
+        assert_eq!(H256::zero(), reader.blocks[&block_hash].state_root);
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::formatter::{self, PubdataBytesInfo};
+use crate::formatter::{self, AddressLabels, PubdataBytesInfo};
 
 use super::ShowStorageLogs;
 use multivm::vm_latest::VmExecutionResultAndLogs;
@@ -63,6 +63,7 @@ fn compute_and_update_pubdata_cost(
 
 pub fn print_storage_logs_details(
     show_storage_logs: &ShowStorageLogs,
+    labels: &AddressLabels,
     result: &VmExecutionResultAndLogs,
 ) {
     tracing::info!("");
@@ -88,7 +89,7 @@ pub fn print_storage_logs_details(
                     log_query.log_type,
                     StorageLogQueryType::RepeatedWrite | StorageLogQueryType::InitialWrite
                 ) {
-                    formatter::print_logs(log_query, pubdata_bytes_info);
+                    formatter::print_logs(log_query, labels, pubdata_bytes_info);
                 }
             }
             ShowStorageLogs::Paid => {
@@ -98,16 +99,16 @@ pub fn print_storage_logs_details(
                     .map(|x| x.does_cost())
                     .unwrap_or_default()
                 {
-                    formatter::print_logs(log_query, pubdata_bytes_info);
+                    formatter::print_logs(log_query, labels, pubdata_bytes_info);
                 }
             }
             ShowStorageLogs::Read => {
                 if log_query.log_type == StorageLogQueryType::Read {
-                    formatter::print_logs(log_query, pubdata_bytes_info);
+                    formatter::print_logs(log_query, labels, pubdata_bytes_info);
                 }
             }
             ShowStorageLogs::All => {
-                formatter::print_logs(log_query, pubdata_bytes_info);
+                formatter::print_logs(log_query, labels, pubdata_bytes_info);
             }
 
             _ => {}
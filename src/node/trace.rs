@@ -0,0 +1,391 @@
+use serde::{Deserialize, Serialize};
+use zksync_basic_types::{Address, Bytes, H256, U256, U64};
+use zksync_types::api::{BlockNumber, DebugCall, DebugCallType, TransactionVariant};
+use zksync_web3_decl::error::Web3Error;
+
+use crate::{
+    fork::ForkSource,
+    namespaces::{RpcResult, TraceNamespaceT},
+    node::{InMemoryNode, InMemoryNodeInner},
+    utils::{into_jsrpc_error, to_real_block_number, IntoBoxedFuture},
+};
+
+/// Single call of a transaction in the Parity/OpenEthereum flat trace format.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trace {
+    pub action: TraceAction,
+    pub block_hash: H256,
+    pub block_number: U64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The result of the call, missing if the call failed.
+    pub result: Option<TraceResult>,
+    /// The number of direct subcalls.
+    pub subtraces: usize,
+    /// The path of the call in the call tree, e.g. `[0, 1]` is the second subcall of the first subcall.
+    pub trace_address: Vec<usize>,
+    pub transaction_hash: H256,
+    pub transaction_position: U64,
+    #[serde(rename = "type")]
+    pub trace_type: TraceType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceType {
+    Call,
+    Create,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum TraceAction {
+    Call(CallAction),
+    Create(CreateAction),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallAction {
+    pub call_type: String,
+    pub from: Address,
+    pub to: Address,
+    pub gas: U256,
+    pub input: Bytes,
+    pub value: U256,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAction {
+    pub from: Address,
+    pub gas: U256,
+    pub init: Bytes,
+    pub value: U256,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum TraceResult {
+    Call {
+        #[serde(rename = "gasUsed")]
+        gas_used: U256,
+        output: Bytes,
+    },
+    Create {
+        address: Address,
+        code: Bytes,
+        #[serde(rename = "gasUsed")]
+        gas_used: U256,
+    },
+}
+
+/// Filter of `trace_filter`. Traces match if their sender is one of `fromAddress` and their recipient
+/// (or created contract) is one of `toAddress`; missing address lists match any address.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceFilter {
+    /// Defaults to the earliest block.
+    pub from_block: Option<BlockNumber>,
+    /// Defaults to the latest block.
+    pub to_block: Option<BlockNumber>,
+    pub from_address: Option<Vec<Address>>,
+    pub to_address: Option<Vec<Address>>,
+    /// The number of matching traces to skip.
+    pub after: Option<usize>,
+    /// The maximum number of traces to return.
+    pub count: Option<usize>,
+}
+
+impl Trace {
+    fn from(&self) -> Address {
+        match &self.action {
+            TraceAction::Call(action) => action.from,
+            TraceAction::Create(action) => action.from,
+        }
+    }
+
+    fn to(&self) -> Option<Address> {
+        match (&self.action, &self.result) {
+            (TraceAction::Call(action), _) => Some(action.to),
+            (TraceAction::Create(_), Some(TraceResult::Create { address, .. })) => Some(*address),
+            _ => None,
+        }
+    }
+}
+
+impl TraceFilter {
+    fn matches(&self, trace: &Trace) -> bool {
+        let from_matches = self
+            .from_address
+            .as_ref()
+            .map_or(true, |addresses| addresses.contains(&trace.from()));
+        let to_matches = self.to_address.as_ref().map_or(true, |addresses| {
+            trace.to().map_or(false, |to| addresses.contains(&to))
+        });
+        from_matches && to_matches
+    }
+}
+
+/// Block and transaction details shared by all the traces of a transaction.
+struct TraceContext {
+    block_hash: H256,
+    block_number: U64,
+    transaction_hash: H256,
+    transaction_position: U64,
+}
+
+/// Appends the trace of the call, followed by the traces of its subcalls (depth-first), to `traces`.
+fn flatten_call(
+    call: &DebugCall,
+    trace_address: Vec<usize>,
+    context: &TraceContext,
+    traces: &mut Vec<Trace>,
+) {
+    let error = call
+        .error
+        .clone()
+        .or_else(|| call.revert_reason.as_ref().map(|_| "Reverted".to_string()));
+    let (trace_type, action, result) = match call.r#type {
+        DebugCallType::Call => (
+            TraceType::Call,
+            TraceAction::Call(CallAction {
+                call_type: "call".to_string(),
+                from: call.from,
+                to: call.to,
+                gas: call.gas,
+                input: call.input.clone(),
+                value: call.value,
+            }),
+            TraceResult::Call {
+                gas_used: call.gas_used,
+                output: call.output.clone(),
+            },
+        ),
+        DebugCallType::Create => (
+            TraceType::Create,
+            TraceAction::Create(CreateAction {
+                from: call.from,
+                gas: call.gas,
+                init: call.input.clone(),
+                value: call.value,
+            }),
+            TraceResult::Create {
+                address: call.to,
+                code: call.output.clone(),
+                gas_used: call.gas_used,
+            },
+        ),
+    };
+
+    traces.push(Trace {
+        action,
+        block_hash: context.block_hash,
+        block_number: context.block_number,
+        result: if error.is_none() { Some(result) } else { None },
+        error,
+        subtraces: call.calls.len(),
+        trace_address: trace_address.clone(),
+        transaction_hash: context.transaction_hash,
+        transaction_position: context.transaction_position,
+        trace_type,
+    });
+
+    for (index, subcall) in call.calls.iter().enumerate() {
+        let mut subcall_address = trace_address.clone();
+        subcall_address.push(index);
+        flatten_call(subcall, subcall_address, context, traces);
+    }
+}
+
+impl<S> InMemoryNodeInner<S> {
+    /// Returns the flat traces of a transaction executed by this node.
+    fn transaction_traces(&self, tx_hash: H256) -> Option<Vec<Trace>> {
+        let tx_result = self.tx_results.get(&tx_hash)?;
+        let context = TraceContext {
+            block_hash: tx_result.receipt.block_hash,
+            block_number: U64::from(tx_result.info.miniblock_number),
+            transaction_hash: tx_hash,
+            transaction_position: tx_result.receipt.transaction_index,
+        };
+
+        let mut traces = vec![];
+        flatten_call(&tx_result.debug, vec![], &context, &mut traces);
+        Some(traces)
+    }
+
+    /// Returns the flat traces of all the transactions in a block.
+    fn block_traces(&self, block_number: u64) -> Option<Vec<Trace>> {
+        let block = self
+            .block_hashes
+            .get(&block_number)
+            .and_then(|hash| self.blocks.get(hash))?;
+
+        Some(
+            block
+                .transactions
+                .iter()
+                .filter_map(|tx| {
+                    let tx_hash = match tx {
+                        TransactionVariant::Full(tx) => tx.hash,
+                        TransactionVariant::Hash(hash) => *hash,
+                    };
+                    self.transaction_traces(tx_hash)
+                })
+                .flatten()
+                .collect(),
+        )
+    }
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> TraceNamespaceT
+    for InMemoryNode<S>
+{
+    fn trace_transaction(&self, tx_hash: H256) -> RpcResult<Option<Vec<Trace>>> {
+        self.get_inner()
+            .read()
+            .map(|reader| reader.transaction_traces(tx_hash))
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
+            .into_boxed_future()
+    }
+
+    fn trace_block(&self, block: BlockNumber) -> RpcResult<Option<Vec<Trace>>> {
+        self.get_inner()
+            .read()
+            .map(|reader| {
+                let number = to_real_block_number(block, U64::from(reader.current_miniblock));
+                reader.block_traces(number.as_u64())
+            })
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
+            .into_boxed_future()
+    }
+
+    fn trace_filter(&self, filter: TraceFilter) -> RpcResult<Vec<Trace>> {
+        self.get_inner()
+            .read()
+            .map(|reader| {
+                let latest = U64::from(reader.current_miniblock);
+                let from_block = filter
+                    .from_block
+                    .map_or(0, |block| to_real_block_number(block, latest).as_u64());
+                let to_block = filter
+                    .to_block
+                    .map_or(latest, |block| to_real_block_number(block, latest))
+                    .as_u64();
+
+                (from_block..=to_block)
+                    .filter_map(|number| reader.block_traces(number))
+                    .flatten()
+                    .filter(|trace| filter.matches(trace))
+                    .skip(filter.after.unwrap_or_default())
+                    .take(filter.count.unwrap_or(usize::MAX))
+                    .collect()
+            })
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
+            .into_boxed_future()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zksync_types::api::{Block, TransactionReceipt};
+
+    use super::*;
+    use crate::{
+        http_fork_source::HttpForkSource,
+        node::TransactionResult,
+        testing::{self},
+    };
+
+    fn insert_transaction(node: &InMemoryNode<HttpForkSource>, block_number: u64) -> H256 {
+        let mut writer = node.get_inner().write().unwrap();
+        let tx = zksync_types::api::Transaction {
+            hash: H256::repeat_byte(block_number as u8),
+            ..Default::default()
+        };
+        let tx_hash = tx.hash;
+        let block_hash = H256::repeat_byte(0xa0 + block_number as u8);
+        let mut block = Block::<TransactionVariant>::default();
+        block.transactions.push(TransactionVariant::Full(tx));
+        writer.blocks.insert(block_hash, block);
+        writer.block_hashes.insert(block_number, block_hash);
+        writer.tx_results.insert(
+            tx_hash,
+            TransactionResult {
+                info: testing::default_tx_execution_info(),
+                receipt: TransactionReceipt {
+                    block_hash,
+                    ..Default::default()
+                },
+                debug: DebugCall {
+                    from: Address::repeat_byte(0x1),
+                    to: Address::repeat_byte(0x2),
+                    ..testing::default_tx_debug_info()
+                },
+            },
+        );
+        tx_hash
+    }
+
+    #[tokio::test]
+    async fn test_trace_transaction_flattens_calls() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let tx_hash = insert_transaction(&node, 0);
+
+        let traces = node
+            .trace_transaction(tx_hash)
+            .await
+            .unwrap()
+            .expect("traces exist");
+
+        assert_eq!(2, traces.len());
+        assert_eq!(Vec::<usize>::new(), traces[0].trace_address);
+        assert_eq!(1, traces[0].subtraces);
+        assert_eq!(vec![0], traces[1].trace_address);
+        assert_eq!(0, traces[1].subtraces);
+        assert_eq!(tx_hash, traces[1].transaction_hash);
+        assert_eq!(TraceType::Call, traces[0].trace_type);
+        assert!(traces[0].result.is_some());
+
+        assert_eq!(
+            None,
+            node.trace_transaction(H256::repeat_byte(0xff))
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trace_filter_by_address_and_block_range() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        insert_transaction(&node, 0);
+        let tx_hash = insert_transaction(&node, 1);
+        node.get_inner().write().unwrap().current_miniblock = 1;
+
+        let traces = node
+            .trace_filter(TraceFilter {
+                from_block: Some(BlockNumber::Number(U64::from(1))),
+                from_address: Some(vec![Address::repeat_byte(0x1)]),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(1, traces.len());
+        assert_eq!(tx_hash, traces[0].transaction_hash);
+        assert_eq!(Vec::<usize>::new(), traces[0].trace_address);
+
+        let traces = node
+            .trace_filter(TraceFilter {
+                after: Some(1),
+                count: Some(2),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(2, traces.len());
+        assert_eq!(vec![0], traces[0].trace_address);
+    }
+}
@@ -0,0 +1,172 @@
+//! Decoding of the raw transactions sent with `eth_sendRawTransaction` in the envelopes of the Ethereum wallets, i.e.
+//! the legacy, EIP-2930 and EIP-1559 transactions, including the ones with an access list, which zkSync rejects.
+use ethers::{
+    types::{transaction::eip2718::TypedTransaction, U256 as EthersU256},
+    utils::rlp::Rlp,
+};
+use zksync_basic_types::{web3::signing::keccak256, Address, L2ChainId, H256, U256, U64};
+use zksync_types::{
+    transaction_request::{SerializationTransactionError, TransactionRequest},
+    Bytes, EIP_1559_TX_TYPE, EIP_2930_TX_TYPE,
+};
+
+/// Raw transaction decoded by [decode_raw_transaction].
+#[derive(Debug, Clone)]
+pub struct DecodedEnvelope {
+    pub request: TransactionRequest,
+    pub hash: H256,
+    /// Whether the access list of the transaction was ignored. The signature of such a transaction covers its access
+    /// list, unlike the one the accounts verify, so it is verified by the node instead.
+    pub access_list_ignored: bool,
+}
+
+/// Decodes the raw transaction into a request, the way zkSync does. The Ethereum envelopes zkSync rejects, e.g. the
+/// ones with an access list, are decoded with ethers instead: the access list is ignored, the signer is recovered
+/// from the whole envelope, and the gas price of the legacy and EIP-2930 transactions is used as both their max fee
+/// and max priority fee per gas.
+pub fn decode_raw_transaction(
+    tx_bytes: &[u8],
+    chain_id: L2ChainId,
+) -> Result<DecodedEnvelope, SerializationTransactionError> {
+    let err = match TransactionRequest::from_bytes(tx_bytes, chain_id) {
+        Ok((request, hash)) => {
+            return Ok(DecodedEnvelope {
+                request,
+                hash,
+                access_list_ignored: false,
+            })
+        }
+        Err(err) => err,
+    };
+    let transaction_type = match tx_bytes.first() {
+        Some(byte) if *byte == EIP_2930_TX_TYPE || *byte == EIP_1559_TX_TYPE => Some(*byte),
+        Some(byte) if *byte >= 0xc0 => None,
+        _ => return Err(err),
+    };
+    let (tx, signature) = match TypedTransaction::decode_signed(&Rlp::new(tx_bytes)) {
+        Ok(decoded) => decoded,
+        Err(_) => return Err(err),
+    };
+
+    if let Some(tx_chain_id) = tx.chain_id() {
+        if tx_chain_id.as_u64() != chain_id.as_u64() {
+            return Err(SerializationTransactionError::WrongChainId(Some(
+                tx_chain_id.as_u64(),
+            )));
+        }
+    }
+    let signer = signature
+        .recover(tx.sighash())
+        .map_err(|_| SerializationTransactionError::MalformedSignature)?;
+    let max_priority_fee_per_gas = match &tx {
+        TypedTransaction::Eip1559(tx) => tx.max_priority_fee_per_gas,
+        _ => tx.gas_price(),
+    };
+    // the accounts expect the `v` of the signatures to be 27 or 28, rather than the y parity or an EIP-155 value
+    let v = match signature.v {
+        v @ (0 | 1) => v + 27,
+        v @ (27 | 28) => v,
+        v => 27 + (v + 1) % 2,
+    };
+
+    let request = TransactionRequest {
+        nonce: to_u256(tx.nonce().copied().unwrap_or_default()),
+        from: Some(Address::from(signer.0)),
+        to: tx.to_addr().map(|to| Address::from(to.0)),
+        value: to_u256(tx.value().copied().unwrap_or_default()),
+        gas_price: to_u256(tx.gas_price().unwrap_or_default()),
+        gas: to_u256(tx.gas().copied().unwrap_or_default()),
+        max_priority_fee_per_gas: Some(to_u256(max_priority_fee_per_gas.unwrap_or_default())),
+        input: Bytes(tx.data().map(|data| data.to_vec()).unwrap_or_default()),
+        v: Some(U64::from(v)),
+        r: Some(to_u256(signature.r)),
+        s: Some(to_u256(signature.s)),
+        raw: Some(Bytes(tx_bytes.to_vec())),
+        transaction_type: transaction_type.map(U64::from),
+        chain_id: tx.chain_id().map(|chain_id| chain_id.as_u64()),
+        ..Default::default()
+    };
+    Ok(DecodedEnvelope {
+        request,
+        hash: H256(keccak256(tx_bytes)),
+        access_list_ignored: tx.access_list().map_or(false, |list| !list.0.is_empty()),
+    })
+}
+
+fn to_u256(value: EthersU256) -> U256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    U256::from_big_endian(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::{
+            transaction::eip2930::{AccessList, AccessListItem},
+            Eip1559TransactionRequest, Eip2930TransactionRequest,
+            TransactionRequest as EthersRequest, H256 as EthersH256,
+        },
+    };
+
+    #[test]
+    fn test_decode_raw_transaction_ignores_access_lists() {
+        let wallet = LocalWallet::from_bytes(&[0x2; 32])
+            .expect("invalid private key")
+            .with_chain_id(260u64);
+        let access_list = AccessList(vec![AccessListItem {
+            address: Default::default(),
+            storage_keys: vec![EthersH256::repeat_byte(0x1)],
+        }]);
+        let legacy = EthersRequest::new()
+            .to(ethers::types::Address::repeat_byte(0x1))
+            .value(1)
+            .gas(1_000_000)
+            .gas_price(250_000_000)
+            .nonce(0)
+            .chain_id(260u64);
+        let transactions: Vec<TypedTransaction> = vec![
+            Eip1559TransactionRequest::new()
+                .to(ethers::types::Address::repeat_byte(0x1))
+                .value(1)
+                .gas(1_000_000)
+                .max_fee_per_gas(250_000_000)
+                .max_priority_fee_per_gas(100_000_000)
+                .nonce(0)
+                .chain_id(260u64)
+                .access_list(access_list.clone())
+                .into(),
+            Eip2930TransactionRequest::new(legacy, access_list).into(),
+        ];
+
+        for tx in transactions {
+            let signature = wallet.sign_transaction_sync(&tx).expect("failed signing");
+            let tx_bytes = tx.rlp_signed(&signature);
+
+            let decoded = decode_raw_transaction(&tx_bytes, L2ChainId::from(260))
+                .expect("failed decoding transaction");
+            assert_eq!(
+                Some(Address::from(wallet.address().0)),
+                decoded.request.from
+            );
+            assert_eq!(H256(keccak256(&tx_bytes)), decoded.hash);
+            assert!(decoded.access_list_ignored);
+            assert_eq!(U256::from(250_000_000), decoded.request.gas_price);
+            let expected_priority_fee = match tx {
+                TypedTransaction::Eip1559(_) => 100_000_000,
+                _ => 250_000_000,
+            };
+            assert_eq!(
+                Some(U256::from(expected_priority_fee)),
+                decoded.request.max_priority_fee_per_gas
+            );
+
+            assert!(matches!(
+                decode_raw_transaction(&tx_bytes, L2ChainId::from(9)),
+                Err(SerializationTransactionError::WrongChainId(Some(260)))
+            ));
+        }
+    }
+}
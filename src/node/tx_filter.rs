@@ -0,0 +1,173 @@
+//! Filtering of the submitted transactions set with `era_setTxFilter`, simulating an operator that censors or
+//! holds back some of them, so that dapps can test their retry and fallback behavior.
+use std::{thread, time::Duration};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use zksync_basic_types::Address;
+use zksync_types::{l2::L2Tx, Bytes};
+
+use crate::{
+    fork::ForkSource,
+    node::{InMemoryNode, RejectedTransaction},
+};
+
+/// Rule of the transaction filter. A transaction matches the rule if it matches all of its set criteria.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxFilterRule {
+    /// The sender of the transaction.
+    #[serde(default)]
+    pub from: Option<Address>,
+    /// The recipient of the transaction.
+    #[serde(default)]
+    pub to: Option<Address>,
+    /// The first 4 bytes of the calldata of the transaction.
+    #[serde(default)]
+    pub selector: Option<Bytes>,
+    pub action: TxFilterAction,
+    /// The time the matching transactions are held back for, in milliseconds, with [TxFilterAction::Delay].
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+impl TxFilterRule {
+    fn matches(&self, tx: &L2Tx) -> bool {
+        self.from
+            .map_or(true, |from| from == tx.initiator_account())
+            && self.to.map_or(true, |to| to == tx.recipient_account())
+            && self.selector.as_ref().map_or(true, |selector| {
+                tx.execute.calldata().get(..4) == Some(selector.0.as_slice())
+            })
+    }
+}
+
+/// What happens to the transactions matching a [TxFilterRule].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TxFilterAction {
+    /// The transaction is rejected.
+    Reject,
+    /// The transaction is accepted, but only submitted to the node after [TxFilterRule::delay_ms].
+    Delay,
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Replaces the rules of the transaction filter. An empty list disables the filter.
+    pub fn set_tx_filter(&self, rules: Vec<TxFilterRule>) -> anyhow::Result<()> {
+        for selector in rules.iter().filter_map(|rule| rule.selector.as_ref()) {
+            if selector.0.len() != 4 {
+                return Err(anyhow!("selector {:?} is not 4 bytes long", selector));
+            }
+        }
+        self.get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .map(|mut writer| writer.tx_filter = rules)
+    }
+
+    /// Submits the transaction with [InMemoryNode::submit_l2_tx], unless it matches a rule of the transaction
+    /// filter. The first matching rule applies. A delayed transaction is submitted on a separate thread, and
+    /// recorded as rejected if its submission fails.
+    pub fn submit_filtered_l2_tx(&self, l2_tx: L2Tx) -> Result<(), String> {
+        let rule = self
+            .get_inner()
+            .read()
+            .map_err(|e| format!("Failed to acquire read lock: {}", e))?
+            .tx_filter
+            .iter()
+            .find(|rule| rule.matches(&l2_tx))
+            .cloned();
+        let rule = match rule {
+            Some(rule) => rule,
+            None => return self.submit_l2_tx(l2_tx),
+        };
+
+        match rule.action {
+            TxFilterAction::Reject => {
+                let reason = "transaction rejected by the transaction filter".to_string();
+                tracing::info!("Rejected {:?}: {}", l2_tx.hash(), reason);
+                self.reject_transaction(RejectedTransaction::new(&l2_tx), &reason);
+                Err(reason)
+            }
+            TxFilterAction::Delay => {
+                tracing::info!(
+                    "Delaying {:?} by {}ms with the transaction filter",
+                    l2_tx.hash(),
+                    rule.delay_ms
+                );
+                let node = self.clone();
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_millis(rule.delay_ms));
+                    let hash = l2_tx.hash();
+                    if let Err(err) = node.submit_l2_tx(l2_tx) {
+                        tracing::error!(
+                            "Failed submitting delayed transaction {:?}: {}",
+                            hash,
+                            err
+                        );
+                    }
+                });
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_fork_source::HttpForkSource, testing};
+    use std::time::Instant;
+    use zksync_basic_types::H256;
+
+    #[test]
+    fn test_tx_filter_rejects_and_delays_matching_transactions() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let rejected_tx = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x1))
+            .build();
+        let delayed_tx = testing::TransactionBuilder::new()
+            .set_hash(H256::repeat_byte(0x2))
+            .build();
+        node.set_rich_account(delayed_tx.initiator_account());
+        node.set_tx_filter(vec![
+            TxFilterRule {
+                from: Some(rejected_tx.initiator_account()),
+                to: None,
+                selector: None,
+                action: TxFilterAction::Reject,
+                delay_ms: 0,
+            },
+            TxFilterRule {
+                from: None,
+                to: Some(delayed_tx.recipient_account()),
+                selector: None,
+                action: TxFilterAction::Delay,
+                delay_ms: 100,
+            },
+        ])
+        .expect("failed setting filter");
+
+        assert_eq!(
+            Err("transaction rejected by the transaction filter".to_string()),
+            node.submit_filtered_l2_tx(rejected_tx.clone())
+        );
+        node.submit_filtered_l2_tx(delayed_tx.clone())
+            .expect("failed submitting tx");
+        let is_executed = || {
+            node.get_inner()
+                .read()
+                .unwrap()
+                .tx_results
+                .contains_key(&delayed_tx.hash())
+        };
+        assert!(!is_executed());
+
+        let started_at = Instant::now();
+        while !is_executed() && started_at.elapsed() < Duration::from_secs(10) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(is_executed());
+    }
+}
@@ -1,3 +1,6 @@
+use zksync_basic_types::{web3::signing::keccak256, H256};
+use zksync_types::Bytes;
+
 use crate::{
     fork::ForkSource,
     namespaces::{Result, Web3NamespaceT},
@@ -10,4 +13,31 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> Web3Namesp
     fn web3_client_version(&self) -> Result<String> {
         Ok("zkSync/v2.0".to_string())
     }
+
+    fn web3_sha3(&self, data: Bytes) -> Result<H256> {
+        Ok(H256(keccak256(&data.0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+
+    #[test]
+    fn test_web3_sha3() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let hash = node
+            .web3_sha3(Bytes::from(b"hello world".to_vec()))
+            .unwrap();
+
+        assert_eq!(
+            H256::from_str("0x47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad")
+                .unwrap(),
+            hash
+        );
+    }
 }
@@ -0,0 +1,119 @@
+//! Worker threads running the VM for the read-only RPC methods, so that long executions do not block the
+//! event loop of the RPC server.
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use futures::{channel::oneshot, FutureExt};
+use zksync_web3_decl::error::Web3Error;
+
+use crate::{fork::ForkSource, namespaces::RpcResult, node::InMemoryNode, utils::into_jsrpc_error};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Pool of threads executing the jobs sent to it in order of submission. The clones of the pool share its
+/// threads, which exit once every clone is dropped.
+#[derive(Clone)]
+pub struct VmWorkers {
+    sender: Arc<Mutex<mpsc::Sender<Job>>>,
+}
+
+impl VmWorkers {
+    /// Spawns `count` worker threads, at least one.
+    pub fn new(count: usize) -> Self {
+        let count = count.max(1);
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..count {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("vm-worker-{}", index))
+                .spawn(move || loop {
+                    let job = match receiver.lock().map(|receiver| receiver.recv()) {
+                        Ok(Ok(job)) => job,
+                        _ => break,
+                    };
+                    // a panicking job only fails its own request, the worker keeps running
+                    if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                        tracing::error!("VM worker job panicked");
+                    }
+                })
+                .expect("failed spawning VM worker");
+        }
+
+        Self {
+            sender: Arc::new(Mutex::new(sender)),
+        }
+    }
+
+    /// Runs the job on one of the workers, returning the receiver of its result. The receiver is cancelled
+    /// if the job panics.
+    pub fn run<T: Send + 'static>(
+        &self,
+        job: impl FnOnce() -> T + Send + 'static,
+    ) -> oneshot::Receiver<T> {
+        let (result_sender, result_receiver) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = result_sender.send(job());
+        });
+        if let Ok(sender) = self.sender.lock() {
+            // the receiver is cancelled if the job can't be sent, as it is dropped
+            let _ = sender.send(job);
+        }
+        result_receiver
+    }
+}
+
+impl fmt::Debug for VmWorkers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VmWorkers").finish_non_exhaustive()
+    }
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Runs the handler of an RPC method on the VM workers, with a clone of the node.
+    pub fn run_on_worker<T: Send + 'static>(
+        &self,
+        handler: impl FnOnce(InMemoryNode<S>) -> jsonrpc_core::Result<T> + Send + 'static,
+    ) -> RpcResult<T> {
+        let node = self.clone();
+        self.workers
+            .run(move || handler(node))
+            .map(|result| {
+                result.unwrap_or_else(|_| {
+                    tracing::error!("VM worker failed handling the request");
+                    Err(into_jsrpc_error(Web3Error::InternalError))
+                })
+            })
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_vm_workers_run_jobs_concurrently() {
+        let workers = VmWorkers::new(2);
+        let (sender, receiver) = mpsc::channel();
+
+        // the first job only finishes once the second one ran on the other worker
+        let first = workers.run(move || receiver.recv().expect("failed receiving"));
+        let second = workers.run(move || sender.send(42).expect("failed sending"));
+
+        second.await.expect("second job failed");
+        assert_eq!(42, first.await.expect("first job failed"));
+    }
+
+    #[tokio::test]
+    async fn test_vm_workers_survive_panicking_job() {
+        let workers = VmWorkers::new(1);
+
+        assert!(workers.run(|| panic!("job failed")).await.is_err());
+        assert_eq!(1, workers.run(|| 1).await.expect("job failed"));
+    }
+}
@@ -3,24 +3,24 @@ use std::collections::HashMap;
 use bigdecimal::BigDecimal;
 use colored::Colorize;
 use futures::FutureExt;
-use zksync_basic_types::{AccountTreeId, Address, L1BatchNumber, MiniblockNumber, H256, U256};
+use zksync_basic_types::{AccountTreeId, Address, L1BatchNumber, MiniblockNumber, H256, U256, U64};
 use zksync_state::ReadStorage;
 use zksync_types::{
     api::{
-        BlockDetails, BlockDetailsBase, BlockStatus, BridgeAddresses, Proof, ProtocolVersion,
-        TransactionDetails, TransactionStatus, TransactionVariant,
+        BlockDetails, BlockDetailsBase, BlockStatus, BridgeAddresses, L1BatchDetails, Proof,
+        ProtocolVersion, TransactionDetails, TransactionStatus, TransactionVariant,
     },
     fee::Fee,
     utils::storage_key_for_standard_token_balance,
-    ExecuteTransactionCommon, ProtocolVersionId, Transaction, L2_ETH_TOKEN_ADDRESS,
+    Bytes, ExecuteTransactionCommon, ProtocolVersionId, Transaction, L2_ETH_TOKEN_ADDRESS,
 };
 use zksync_utils::h256_to_u256;
 use zksync_web3_decl::error::Web3Error;
 
 use crate::{
     fork::ForkSource,
-    namespaces::{RpcResult, ZksNamespaceT},
-    node::{InMemoryNode, TransactionResult, L2_GAS_PRICE},
+    namespaces::{EthNamespaceT, RpcResult, ZksNamespaceT},
+    node::{InMemoryNode, TransactionDetailedResult, TransactionResult, L2_GAS_PRICE},
     utils::{
         internal_error, into_jsrpc_error, not_implemented, utc_datetime_from_epoch_ms,
         IntoBoxedFuture,
@@ -129,6 +129,43 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> ZksNamespa
         not_implemented("zks_getProof")
     }
 
+    fn send_raw_transaction_with_detailed_output(
+        &self,
+        tx_bytes: Bytes,
+    ) -> RpcResult<TransactionDetailedResult> {
+        let node = self.clone();
+        Box::pin(async move {
+            let automine = node
+                .get_inner()
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?
+                .automine;
+            // Without automine the transaction is only executed once a block is mined.
+            if !automine {
+                return Err(into_jsrpc_error(Web3Error::SubmitTransactionError(
+                    "zks_sendRawTransactionWithDetailedOutput requires automine to be enabled"
+                        .into(),
+                    vec![],
+                )));
+            }
+
+            let hash = node.send_raw_transaction(tx_bytes).await?;
+
+            node.get_inner()
+                .read()
+                .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?
+                .tx_results
+                .get(&hash)
+                .map(TransactionResult::detailed_output)
+                .ok_or_else(|| {
+                    into_jsrpc_error(internal_error(
+                        "zks_sendRawTransactionWithDetailedOutput",
+                        format!("no result for the executed transaction {:#x}", hash),
+                    ))
+                })
+        })
+    }
+
     fn estimate_gas_l1_to_l2(
         &self,
         _req: zksync_types::transaction_request::CallRequest,
@@ -214,6 +251,17 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> ZksNamespa
     }
 
     fn get_token_price(&self, token_address: zksync_basic_types::Address) -> RpcResult<BigDecimal> {
+        let price = match self.get_inner().read() {
+            Ok(reader) => reader.token_prices.get(&token_address).cloned(),
+            Err(err) => {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                return futures::future::err(into_jsrpc_error(Web3Error::InternalError)).boxed();
+            }
+        };
+        if let Some(price) = price {
+            return Ok(price).into_boxed_future();
+        }
+
         match format!("{:?}", token_address).to_lowercase().as_str() {
             "0x0000000000000000000000000000000000000000" => {
                 // ETH
@@ -267,8 +315,8 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> ZksNamespa
                     let tokens =
                         tokens.map_err(|_err| into_jsrpc_error(Web3Error::InternalError))?;
 
-                    let mut writer = inner
-                        .write()
+                    let reader = inner
+                        .read()
                         .map_err(|_err| into_jsrpc_error(Web3Error::InternalError))?;
 
                     let mut balances = HashMap::new();
@@ -278,7 +326,7 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> ZksNamespa
                             &address,
                         );
 
-                        let balance = writer.fork_storage.read_value(&balance_key);
+                        let balance = (&reader.fork_storage).read_value(&balance_key);
                         if !balance.is_zero() {
                             balances.insert(token.l2_address, h256_to_u256(balance));
                         }
@@ -307,8 +355,13 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> ZksNamespa
         not_implemented("zks_getL2ToL1LogProof")
     }
 
+    /// Returns the L1 batch number of the latest block.
     fn get_l1_batch_number(&self) -> RpcResult<zksync_basic_types::U64> {
-        not_implemented("zks_L1BatchNumber")
+        self.get_inner()
+            .read()
+            .map(|reader| U64::from(reader.latest_l1_batch()))
+            .map_err(|_| into_jsrpc_error(Web3Error::InternalError))
+            .into_boxed_future()
     }
 
     /// Get block details.
@@ -381,13 +434,40 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> ZksNamespa
         })
     }
 
+    /// Returns the numbers of the first and the last block of an L1 batch produced by this node, or of the forked
+    /// network for the batches up to the fork one.
     fn get_miniblock_range(
         &self,
-        _batch: zksync_basic_types::L1BatchNumber,
+        batch: zksync_basic_types::L1BatchNumber,
     ) -> jsonrpc_core::BoxFuture<
         jsonrpc_core::Result<Option<(zksync_basic_types::U64, zksync_basic_types::U64)>>,
     > {
-        not_implemented("zks_getL1BatchBlockRange")
+        let inner = self.get_inner().clone();
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_err| into_jsrpc_error(Web3Error::InternalError))?;
+
+            let fork_storage = reader.fork_storage.inner.read().map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })?;
+            if let Some(fork) = fork_storage
+                .fork
+                .as_ref()
+                .filter(|fork| batch <= fork.l1_block)
+            {
+                return fork.fork_source.get_block_range(batch).map_err(|err| {
+                    into_jsrpc_error(internal_error("zks_getL1BatchBlockRange", err))
+                });
+            }
+
+            let numbers = reader
+                .l1_batch_blocks(batch)
+                .into_iter()
+                .map(|block| block.number);
+            Ok(numbers.clone().min().zip(numbers.max()))
+        })
     }
 
     /// Get transaction details.
@@ -451,23 +531,78 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> ZksNamespa
 
     /// Retrieves details for a given L1 batch.
     ///
-    /// This method is intended to handle queries related to L1 batch details. However, as of the current implementation,
-    /// L1 communication is not supported. Instead of an error or no method found, this method intentionally returns
-    /// `{"jsonrpc":"2.0","result":null,"id":1}` to ensure compatibility with block explorer integration.
+    /// The batches produced by this node are reported as verified once sealed, and as sealed while still open, with
+    /// no L1 transactions. The details of the batches up to the fork one are fetched from the forked network.
     ///
     /// # Parameters
     ///
-    /// * `_batch`: The batch number of type `zksync_basic_types::L1BatchNumber` for which the details are to be fetched.
+    /// * `batch`: The batch number of type `zksync_basic_types::L1BatchNumber` for which the details are to be fetched.
     ///
     /// # Returns
     ///
-    /// A boxed future resolving to a `jsonrpc_core::Result` containing an `Option` of `zksync_types::api::L1BatchDetails`.
-    /// Given the current implementation, this will always be `None`.
+    /// A boxed future resolving to a `jsonrpc_core::Result` containing an `Option` of `zksync_types::api::L1BatchDetails`,
+    /// `None` if the batch is not known.
     fn get_l1_batch_details(
         &self,
-        _batch: zksync_basic_types::L1BatchNumber,
+        batch: zksync_basic_types::L1BatchNumber,
     ) -> RpcResult<Option<zksync_types::api::L1BatchDetails>> {
-        Box::pin(async { Ok(None) })
+        let inner = self.get_inner().clone();
+        Box::pin(async move {
+            let reader = inner
+                .read()
+                .map_err(|_err| into_jsrpc_error(Web3Error::InternalError))?;
+
+            let fork_storage = reader.fork_storage.inner.read().map_err(|err| {
+                tracing::error!("failed acquiring lock: {:?}", err);
+                into_jsrpc_error(Web3Error::InternalError)
+            })?;
+            if let Some(fork) = fork_storage
+                .fork
+                .as_ref()
+                .filter(|fork| batch <= fork.l1_block)
+            {
+                return fork
+                    .fork_source
+                    .get_l1_batch_details(batch)
+                    .map_err(|err| into_jsrpc_error(internal_error("zks_getL1BatchDetails", err)));
+            }
+
+            let blocks = reader.l1_batch_blocks(batch);
+            let (first, last) = match (blocks.first(), blocks.last()) {
+                (Some(first), Some(last)) => (first, last),
+                _ => return Ok(None),
+            };
+            let open = reader.l1_batch_sealer.as_ref().map_or(false, |sealer| {
+                sealer.blocks > 0 && sealer.open_batch == batch.0
+            });
+            Ok(Some(L1BatchDetails {
+                number: batch,
+                base: BlockDetailsBase {
+                    timestamp: first.timestamp.as_u64(),
+                    l1_tx_count: 0,
+                    l2_tx_count: blocks.iter().map(|block| block.transactions.len()).sum(),
+                    // the root of the state the batch ended with, if computed with `--state-roots`
+                    root_hash: Some(last.state_root).filter(|root| !root.is_zero()),
+                    status: if open {
+                        BlockStatus::Sealed
+                    } else {
+                        BlockStatus::Verified
+                    },
+                    commit_tx_hash: None,
+                    committed_at: None,
+                    prove_tx_hash: None,
+                    proven_at: None,
+                    execute_tx_hash: None,
+                    executed_at: None,
+                    l1_gas_price: reader.fee_input_provider.l1_gas_price(),
+                    l2_fair_gas_price: L2_GAS_PRICE,
+                    base_system_contracts_hashes: reader
+                        .system_contracts
+                        .baseline_contracts
+                        .hashes(),
+                },
+            }))
+        })
     }
 
     /// Returns bytecode of a transaction given by its hash.
@@ -482,12 +617,12 @@ impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> ZksNamespa
     fn get_bytecode_by_hash(&self, hash: zksync_basic_types::H256) -> RpcResult<Option<Vec<u8>>> {
         let inner = self.get_inner().clone();
         Box::pin(async move {
-            let mut writer = inner
-                .write()
+            let reader = inner
+                .read()
                 .map_err(|_| into_jsrpc_error(Web3Error::InternalError))?;
 
-            let maybe_bytecode = writer.fork_storage.load_factory_dep(hash).or_else(|| {
-                writer
+            let maybe_bytecode = (&reader.fork_storage).load_factory_dep(hash).or_else(|| {
+                reader
                     .fork_storage
                     .inner
                     .read()
@@ -592,6 +727,28 @@ mod tests {
         assert_eq!(result, BigDecimal::from(1));
     }
 
+    #[tokio::test]
+    async fn test_get_token_price_returns_price_set_with_set_token_price() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let eth = Address::zero();
+        let custom_token = Address::repeat_byte(0x42);
+        let price = BigDecimal::from_str("0.25").unwrap();
+
+        node.set_token_price(custom_token, price.clone())
+            .expect("failed setting token price");
+        node.set_token_price(eth, BigDecimal::from(2_000))
+            .expect("failed setting token price");
+
+        assert_eq!(price, node.get_token_price(custom_token).await.unwrap());
+        assert_eq!(
+            BigDecimal::from(2_000),
+            node.get_token_price(eth).await.unwrap()
+        );
+        assert!(node
+            .set_token_price(custom_token, BigDecimal::from(-1))
+            .is_err());
+    }
+
     #[tokio::test]
     async fn test_get_token_price_given_unknown_address_should_return_error() {
         // Arrange
@@ -775,6 +932,52 @@ mod tests {
         assert_eq!(result.base.timestamp, 1697405098);
     }
 
+    #[tokio::test]
+    async fn test_get_l1_batch_details_local() {
+        use crate::namespaces::{ConfigurationApiNamespaceT, EraNamespaceT};
+
+        let node = InMemoryNode::<HttpForkSource>::default();
+        node.config_set_l1_batch_seal_interval(Some(0))
+            .expect("failed setting interval");
+        testing::apply_tx(&node, H256::repeat_byte(0x1));
+        testing::apply_tx(&node, H256::repeat_byte(0x2));
+        node.seal_l1_batch().await.expect("failed sealing batch");
+        testing::apply_tx(&node, H256::repeat_byte(0x3));
+
+        let sealed = node
+            .get_l1_batch_details(L1BatchNumber(1))
+            .await
+            .expect("get l1 batch details")
+            .expect("l1 batch details");
+        let (_, last_block) = node
+            .get_miniblock_range(L1BatchNumber(1))
+            .await
+            .unwrap()
+            .expect("missing block range");
+        let last_block = node
+            .get_block_by_number(api::BlockNumber::Number(last_block), false)
+            .await
+            .unwrap()
+            .expect("missing block");
+        assert_eq!(L1BatchNumber(1), sealed.number);
+        assert_eq!(2, sealed.base.l2_tx_count);
+        assert_eq!(None, sealed.base.root_hash);
+        assert_eq!(BlockStatus::Verified, sealed.base.status);
+
+        let open = node
+            .get_l1_batch_details(L1BatchNumber(2))
+            .await
+            .expect("get l1 batch details")
+            .expect("l1 batch details");
+        assert_eq!(1, open.base.l2_tx_count);
+        assert_eq!(BlockStatus::Sealed, open.base.status);
+        assert!(node
+            .get_l1_batch_details(L1BatchNumber(3))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
     #[tokio::test]
     async fn test_get_bridge_contracts_uses_default_values_if_local() {
         // Arrange
@@ -1,12 +1,30 @@
 use core::fmt;
-use std::{fs::File, sync::Mutex};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use tracing_subscriber::{
-    filter::LevelFilter, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry,
+    filter::{filter_fn, LevelFilter},
+    layer::SubscriberExt,
+    reload,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
 };
 
+/// Target of the events carrying structured fields (tx hash, block, gas, duration) for machine consumption.
+/// These are only emitted with [LogFormat::Json], as the human readable output already contains the same details.
+pub const STRUCTURED_LOG_TARGET: &str = "era_test_node::structured";
+
+/// Target of the spans covering RPC request handling, VM execution and fork fetches.
+/// These are only exported via OTLP (see the `otlp` feature) and are not printed in the logs.
+pub const SPAN_TARGET: &str = "era_test_node::spans";
+
 /// Log filter level for the node.
 #[derive(Debug, Clone, ValueEnum, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -42,6 +60,123 @@ impl From<LogLevel> for LevelFilter {
     }
 }
 
+/// Output format of the logs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Colored human readable output.
+    #[default]
+    Plain,
+    /// Structured JSON lines.
+    Json,
+}
+
+/// Interval after which the log file is rotated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, ValueEnum)]
+pub enum LogRotationInterval {
+    #[default]
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl LogRotationInterval {
+    fn duration(self) -> Option<Duration> {
+        match self {
+            LogRotationInterval::Never => None,
+            LogRotationInterval::Hourly => Some(Duration::from_secs(60 * 60)),
+            LogRotationInterval::Daily => Some(Duration::from_secs(24 * 60 * 60)),
+        }
+    }
+}
+
+/// Rotation policy of the log file.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogRotation {
+    /// Rotate once the file grows over this many bytes.
+    pub max_size: Option<u64>,
+    /// Rotate once the file has been written to for this long.
+    pub interval: LogRotationInterval,
+    /// Number of rotated files to keep, as `<path>.1` (the most recent) up to `<path>.<max_files>`.
+    pub max_files: usize,
+}
+
+/// Log file that is rotated according to a [LogRotation] policy.
+#[derive(Debug)]
+pub struct RotatingLogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    opened_at: Instant,
+    rotation: LogRotation,
+}
+
+impl RotatingLogFile {
+    /// Creates (or truncates) the log file at `path`.
+    pub fn create<P: AsRef<Path>>(path: P, rotation: LogRotation) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::create(&path)?;
+        Ok(Self {
+            path,
+            file,
+            size: 0,
+            opened_at: Instant::now(),
+            rotation,
+        })
+    }
+
+    fn should_rotate(&self, incoming: usize) -> bool {
+        let size_exceeded = self.rotation.max_size.map_or(false, |max_size| {
+            self.size > 0 && self.size + incoming as u64 > max_size
+        });
+        let interval_elapsed = self
+            .rotation
+            .interval
+            .duration()
+            .map_or(false, |interval| self.opened_at.elapsed() >= interval);
+        size_exceeded || interval_elapsed
+    }
+
+    /// Shifts the rotated files by one, moves the current file to `<path>.1` and starts a new one.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.rotation.max_files > 0 {
+            for index in (1..self.rotation.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        self.opened_at = Instant::now();
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+}
+
+impl Write for RotatingLogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.should_rotate(buf.len()) {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
 /// A sharable reference to the observability stack.
 #[derive(Debug, Default, Clone)]
 pub struct Observability {
@@ -54,7 +189,9 @@ impl Observability {
     pub fn init(
         binary_name: String,
         log_level_filter: LevelFilter,
-        log_file: File,
+        log_format: LogFormat,
+        log_file: RotatingLogFile,
+        otlp_endpoint: Option<String>,
     ) -> Result<Self, anyhow::Error> {
         let filter = Self::parse_filter(&format!(
             "{}={}",
@@ -68,17 +205,46 @@ impl Observability {
         let time_offset = time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC);
         let timer = tracing_subscriber::fmt::time::OffsetTime::new(time_offset, timer_format);
 
+        let (plain, json) = match log_format {
+            LogFormat::Plain => (Some(log_file), None),
+            LogFormat::Json => {
+                // the messages must not contain the color codes
+                colored::control::set_override(false);
+                (None, Some(log_file))
+            }
+        };
+        let skip_structured = || {
+            filter_fn(|metadata| {
+                metadata.target() != STRUCTURED_LOG_TARGET && metadata.target() != SPAN_TARGET
+            })
+        };
+
+        #[cfg(feature = "otlp")]
+        let otlp = otlp_endpoint
+            .map(|endpoint| Self::otlp_layer(&binary_name, &endpoint))
+            .transpose()?;
+        #[cfg(not(feature = "otlp"))]
+        let otlp = match otlp_endpoint {
+            Some(_) => anyhow::bail!(
+                "OTLP export is not available, the node must be built with the `otlp` feature"
+            ),
+            None => None::<tracing_subscriber::layer::Identity>,
+        };
+
         tracing_subscriber::registry()
             .with(filter)
-            .with(
-                tracing_subscriber::fmt::layer().event_format(
-                    tracing_subscriber::fmt::format()
-                        .compact()
-                        .with_timer(timer.clone())
-                        .with_target(false),
-                ),
-            )
-            .with(
+            .with(plain.is_some().then(|| {
+                tracing_subscriber::fmt::layer()
+                    .event_format(
+                        tracing_subscriber::fmt::format()
+                            .compact()
+                            .with_timer(timer.clone())
+                            .with_target(false),
+                    )
+                    .with_ansi(colored::control::SHOULD_COLORIZE.should_colorize())
+                    .with_filter(skip_structured())
+            }))
+            .with(plain.map(|log_file| {
                 tracing_subscriber::fmt::layer()
                     .event_format(
                         tracing_subscriber::fmt::format()
@@ -87,8 +253,23 @@ impl Observability {
                             .with_target(false),
                     )
                     .with_writer(Mutex::new(log_file))
-                    .with_ansi(false),
-            )
+                    .with_ansi(false)
+                    .with_filter(skip_structured())
+            }))
+            .with(json.is_some().then(|| {
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(false)
+                    .with_span_list(false)
+            }))
+            .with(json.map(|log_file| {
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(false)
+                    .with_span_list(false)
+                    .with_writer(Mutex::new(log_file))
+            }))
+            .with(otlp)
             .init();
 
         Ok(Self {
@@ -97,6 +278,41 @@ impl Observability {
         })
     }
 
+    /// Builds the layer exporting the [SPAN_TARGET] spans to the OTLP collector listening at `endpoint`.
+    #[cfg(feature = "otlp")]
+    fn otlp_layer<S>(binary_name: &str, endpoint: &str) -> Result<impl Layer<S>, anyhow::Error>
+    where
+        S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+    {
+        use opentelemetry_otlp::WithExportConfig;
+
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(endpoint),
+            )
+            .with_trace_config(opentelemetry_sdk::trace::config().with_resource(
+                opentelemetry_sdk::Resource::new(vec![opentelemetry::KeyValue::new(
+                    "service.name",
+                    binary_name.to_string(),
+                )]),
+            ))
+            .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+
+        Ok(tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(filter_fn(|metadata| metadata.target() == SPAN_TARGET)))
+    }
+
+    /// Flushes the logs and exports the spans that are still buffered. Called before the node exits.
+    pub fn shutdown(&self) {
+        #[cfg(feature = "otlp")]
+        opentelemetry::global::shutdown_tracer_provider();
+        let _ = io::stdout().flush();
+    }
+
     /// Set the log level for the binary.
     pub fn set_log_level(&self, level: LogLevel) -> Result<(), anyhow::Error> {
         let level = LevelFilter::from(level);
@@ -142,3 +358,40 @@ impl Observability {
         Ok(filter)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_log_file_is_rotated_by_size() {
+        let dir = TempDir::new("log-rotation").unwrap();
+        let path = dir.path().join("node.log");
+        let mut log_file = RotatingLogFile::create(
+            &path,
+            LogRotation {
+                max_size: Some(10),
+                interval: LogRotationInterval::Never,
+                max_files: 2,
+            },
+        )
+        .unwrap();
+
+        for line in ["first\n", "second\n", "third\n", "fourth\n"].iter() {
+            log_file.write_all(line.as_bytes()).unwrap();
+        }
+        log_file.flush().unwrap();
+
+        assert_eq!("fourth\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(
+            "third\n",
+            fs::read_to_string(dir.path().join("node.log.1")).unwrap()
+        );
+        assert_eq!(
+            "second\n",
+            fs::read_to_string(dir.path().join("node.log.2")).unwrap()
+        );
+        assert!(!dir.path().join("node.log.3").exists());
+    }
+}
@@ -0,0 +1,387 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use serde::Deserialize;
+use zksync_basic_types::{Address, H256};
+use zksync_types::Bytes;
+use zksync_utils::bytecode::hash_bytecode;
+
+use crate::{
+    fork::ForkSource,
+    node::{validate_bytecode, InMemoryNode},
+    utils::compute_create2_address,
+};
+
+/// Compiled contract artifact as emitted by `hardhat-zksync-solc` or `foundry-zksync`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Artifact {
+    #[serde(default)]
+    pub contract_name: Option<String>,
+    /// Path of the source file relative to the project, e.g. `contracts/Greeter.sol`.
+    #[serde(default)]
+    pub source_name: Option<String>,
+    pub bytecode: ArtifactBytecode,
+    /// Bytecodes the contract deploys, by hash, as `<source name>:<contract name>` of their artifacts.
+    #[serde(default)]
+    pub factory_deps: BTreeMap<H256, String>,
+}
+
+/// Bytecode of an [Artifact], either as a plain hex string (hardhat) or as an object (foundry).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ArtifactBytecode {
+    Hex(Bytes),
+    Object { object: Bytes },
+}
+
+impl ArtifactBytecode {
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            ArtifactBytecode::Hex(bytes) => &bytes.0,
+            ArtifactBytecode::Object { object } => &object.0,
+        }
+    }
+}
+
+/// Manifest listing the artifacts to deploy and where to deploy them.
+///
+/// Example:
+/// ```json
+/// {
+///   "contracts": [
+///     { "artifact": "artifacts-zk/contracts/Greeter.sol/Greeter.json", "address": "0x1000000000000000000000000000000000000001" },
+///     { "artifact": "artifacts-zk/contracts/Token.sol/Token.json", "salt": "0x0000000000000000000000000000000000000000000000000000000000000001" }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeployManifest {
+    pub contracts: Vec<DeployEntry>,
+}
+
+/// Single contract in the [DeployManifest]. If `address` is not set, the contract is placed at its
+/// `CREATE2` address derived from the zero sender, the given `salt` (zero by default) and empty input.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeployEntry {
+    pub artifact: PathBuf,
+    #[serde(default)]
+    pub address: Option<Address>,
+    #[serde(default)]
+    pub salt: Option<H256>,
+}
+
+/// Contract that was placed in the state by [InMemoryNode::predeploy].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeployedContract {
+    pub name: String,
+    pub address: Address,
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Places the bytecode of the compiled artifacts at deterministic addresses, and stores the bytecodes of
+    /// their `factoryDeps` so that they can deploy them.
+    ///
+    /// The `path` may be either a JSON [DeployManifest] or a directory, in which case every artifact
+    /// found in it (recursively) is deployed to its `CREATE2` address with a zero salt.
+    /// Constructors are not executed - the bytecode is stored the same way as with `hardhat_setCode`, so that the
+    /// address only depends on the bytecode and the salt, and not on a deployer account and its nonce. The storage
+    /// and the immutables a constructor would set are left empty, so only the contracts that do not rely on their
+    /// constructor can be pre-deployed.
+    pub fn predeploy<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<Vec<DeployedContract>> {
+        let path = path.as_ref();
+        let entries = if path.is_dir() {
+            let mut artifacts = vec![];
+            collect_artifacts(path, &mut artifacts)?;
+            artifacts.sort();
+            artifacts
+                .into_iter()
+                .map(|artifact| DeployEntry {
+                    artifact,
+                    address: None,
+                    salt: None,
+                })
+                .collect()
+        } else {
+            let manifest: DeployManifest = serde_json::from_str(
+                &fs::read_to_string(path)
+                    .with_context(|| format!("failed reading manifest {}", path.display()))?,
+            )
+            .with_context(|| format!("failed parsing manifest {}", path.display()))?;
+            let base = path.parent().unwrap_or_else(|| Path::new("."));
+            manifest
+                .contracts
+                .into_iter()
+                .map(|entry| DeployEntry {
+                    artifact: base.join(entry.artifact),
+                    ..entry
+                })
+                .collect()
+        };
+
+        let mut deployed = vec![];
+        for entry in entries {
+            let artifact = read_artifact(&entry.artifact)?
+                .ok_or_else(|| anyhow!("{} has no bytecode", entry.artifact.display()))?;
+            let bytecode = artifact.bytecode.bytes().to_vec();
+            if let Some(message) = validate_bytecode(&bytecode).error_message() {
+                return Err(anyhow!("{}: {}", entry.artifact.display(), message));
+            }
+            let mut factory_deps = BTreeMap::new();
+            collect_factory_deps(&entry.artifact, &artifact, &mut factory_deps)?;
+            let address = entry.address.unwrap_or_else(|| {
+                compute_create2_address(
+                    Address::zero(),
+                    hash_bytecode(&bytecode),
+                    entry.salt.unwrap_or_default(),
+                    &[],
+                )
+            });
+            let name = artifact.contract_name.unwrap_or_else(|| {
+                entry
+                    .artifact
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            });
+
+            self.get_inner()
+                .write()
+                .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+                .map(|mut writer| {
+                    for (hash, bytecode) in factory_deps {
+                        writer.fork_storage.store_factory_dep(hash, bytecode);
+                    }
+                })?;
+            self.set_code(address, bytecode)?;
+            deployed.push(DeployedContract { name, address });
+        }
+
+        Ok(deployed)
+    }
+}
+
+/// Reads an artifact, returning `None` for JSON files that are not deployable artifacts
+/// (e.g. `.dbg.json` files without bytecode or artifacts of interfaces with empty bytecode).
+/// The files that hold a bytecode but fail to parse as an artifact are reported.
+fn read_artifact(path: &Path) -> anyhow::Result<Option<Artifact>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed reading artifact {}", path.display()))?;
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .with_context(|| format!("failed parsing {}", path.display()))?;
+    if value.get("bytecode").is_none() {
+        return Ok(None);
+    }
+    let artifact: Artifact = serde_json::from_value(value)
+        .with_context(|| format!("failed parsing artifact {}", path.display()))?;
+    Ok(Some(artifact).filter(|artifact| !artifact.bytecode.bytes().is_empty()))
+}
+
+/// Reads the bytecodes of the `factoryDeps` of the artifact, and of theirs, from the artifacts next to it. The
+/// artifacts are looked up in the `<source name>/<contract name>.json` layout of hardhat.
+fn collect_factory_deps(
+    path: &Path,
+    artifact: &Artifact,
+    factory_deps: &mut BTreeMap<H256, Vec<u8>>,
+) -> anyhow::Result<()> {
+    if artifact.factory_deps.is_empty() {
+        return Ok(());
+    }
+    let source_name = artifact.source_name.as_ref().ok_or_else(|| {
+        anyhow!(
+            "{} has factory dependencies but no source name to locate them",
+            path.display()
+        )
+    })?;
+    // the artifact is at `<root>/<source name>/<contract name>.json`
+    let root = path
+        .ancestors()
+        .nth(Path::new(source_name).components().count() + 1)
+        .ok_or_else(|| anyhow!("{} is not in an artifacts directory", path.display()))?;
+
+    for (hash, dependency) in &artifact.factory_deps {
+        if factory_deps.contains_key(hash) {
+            continue;
+        }
+        let (source, contract) = dependency.rsplit_once(':').ok_or_else(|| {
+            anyhow!(
+                "invalid factory dependency {} of {}",
+                dependency,
+                path.display()
+            )
+        })?;
+        let dependency_path = root.join(source).join(format!("{}.json", contract));
+        let dependency_artifact = read_artifact(&dependency_path)?
+            .ok_or_else(|| anyhow!("{} has no bytecode", dependency_path.display()))?;
+        let bytecode = dependency_artifact.bytecode.bytes().to_vec();
+        if let Some(message) = validate_bytecode(&bytecode).error_message() {
+            return Err(anyhow!("{}: {}", dependency_path.display(), message));
+        }
+        if hash_bytecode(&bytecode) != *hash {
+            return Err(anyhow!(
+                "the bytecode of {} does not match the hash {:#x} of the factory dependency",
+                dependency_path.display(),
+                hash
+            ));
+        }
+        factory_deps.insert(*hash, bytecode);
+        collect_factory_deps(&dependency_path, &dependency_artifact, factory_deps)?;
+    }
+    Ok(())
+}
+
+fn collect_artifacts(dir: &Path, artifacts: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("failed reading {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_artifacts(&path, artifacts)?;
+        } else if path.extension().map_or(false, |ext| ext == "json")
+            && read_artifact(&path)?.is_some()
+        {
+            artifacts.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        http_fork_source::HttpForkSource,
+        namespaces::{EthNamespaceT, ZksNamespaceT},
+        testing,
+    };
+    use tempdir::TempDir;
+
+    fn write_artifact(dir: &Path, name: &str, bytecode: &str) {
+        fs::write(
+            dir.join(format!("{}.json", name)),
+            format!(
+                r#"{{"contractName": "{}", "abi": [], "bytecode": "0x{}"}}"#,
+                name, bytecode
+            ),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_predeploy_from_directory() {
+        let dir = TempDir::new("predeploy").unwrap();
+        write_artifact(dir.path(), "Storage", testing::STORAGE_CONTRACT_BYTECODE);
+        write_artifact(dir.path(), "IStorage", "");
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let deployed = node.predeploy(dir.path()).expect("failed deploying");
+
+        assert_eq!(1, deployed.len());
+        assert_eq!("Storage", deployed[0].name);
+        let code = node.get_code(deployed[0].address, None).await.unwrap();
+        assert_eq!(
+            hex::decode(testing::STORAGE_CONTRACT_BYTECODE).unwrap(),
+            code.0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_predeploy_from_manifest_uses_explicit_address() {
+        let dir = TempDir::new("predeploy").unwrap();
+        write_artifact(dir.path(), "Storage", testing::STORAGE_CONTRACT_BYTECODE);
+        let address = Address::repeat_byte(0x11);
+        fs::write(
+            dir.path().join("manifest.json"),
+            format!(
+                r#"{{"contracts": [{{"artifact": "Storage.json", "address": "{:#x}"}}]}}"#,
+                address
+            ),
+        )
+        .unwrap();
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let deployed = node
+            .predeploy(dir.path().join("manifest.json"))
+            .expect("failed deploying");
+
+        assert_eq!(
+            vec![DeployedContract {
+                name: "Storage".to_string(),
+                address
+            }],
+            deployed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_predeploy_stores_factory_deps() {
+        let dir = TempDir::new("predeploy").unwrap();
+        let dependency = "ab".repeat(32);
+        let dependency_hash = hash_bytecode(&hex::decode(&dependency).unwrap());
+        for (source, name, bytecode, factory_deps) in [
+            (
+                "contracts/Dep.sol",
+                "Dep",
+                dependency.clone(),
+                "{}".to_string(),
+            ),
+            (
+                "contracts/Factory.sol",
+                "Factory",
+                testing::STORAGE_CONTRACT_BYTECODE.to_string(),
+                format!(r#"{{"{:#x}": "contracts/Dep.sol:Dep"}}"#, dependency_hash),
+            ),
+        ] {
+            let artifact_dir = dir.path().join(source);
+            fs::create_dir_all(&artifact_dir).unwrap();
+            fs::write(
+                artifact_dir.join(format!("{}.json", name)),
+                format!(
+                    r#"{{"contractName": "{}", "sourceName": "{}", "bytecode": "0x{}", "factoryDeps": {}}}"#,
+                    name, source, bytecode, factory_deps
+                ),
+            )
+            .unwrap();
+        }
+        fs::write(
+            dir.path().join("manifest.json"),
+            r#"{"contracts": [{"artifact": "contracts/Factory.sol/Factory.json"}]}"#,
+        )
+        .unwrap();
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        node.predeploy(dir.path().join("manifest.json"))
+            .expect("failed deploying");
+
+        let bytecode = node
+            .get_bytecode_by_hash(dependency_hash)
+            .await
+            .expect("failed fetching bytecode");
+        assert_eq!(Some(hex::decode(&dependency).unwrap()), bytecode);
+    }
+
+    #[tokio::test]
+    async fn test_predeploy_reports_invalid_artifacts() {
+        let dir = TempDir::new("predeploy").unwrap();
+        // 2 words, while the bytecodes must have an odd number of them
+        write_artifact(dir.path(), "Even", &"ab".repeat(64));
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let err = node
+            .predeploy(dir.path())
+            .expect_err("deployed an invalid bytecode");
+        assert!(err
+            .to_string()
+            .contains("the length in 32-byte words is even"));
+
+        let dir = TempDir::new("predeploy").unwrap();
+        fs::write(dir.path().join("Broken.json"), r#"{"bytecode": 42}"#).unwrap();
+
+        let err = node
+            .predeploy(dir.path())
+            .expect_err("skipped a broken artifact");
+        assert!(err.to_string().contains("failed parsing artifact"));
+    }
+}
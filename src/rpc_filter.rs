@@ -0,0 +1,179 @@
+use futures::future::{self, Either};
+use jsonrpc_core::{middleware, Call, Error, ErrorCode, Metadata, Middleware, Output};
+
+/// The methods changing the state or the configuration of the node, rejected in read-only mode. A trailing `*`
+/// matches any method with the given prefix.
+pub const STATE_CHANGING_METHODS: &[&str] = &[
+    "anvil_removeBlockTimestampInterval",
+    "anvil_rollback",
+    "anvil_setBlockTimestampInterval",
+    "anvil_setRpcUrl",
+    "config_set*",
+    "era_beginContext",
+    "era_clearCache",
+    "era_endContext",
+    "era_finishGasReport",
+    "era_importAccount",
+    "era_importContract",
+    "era_reorg",
+    "era_sealL1Batch",
+    "era_setErc20Balance",
+    "era_setTokenPrice",
+    "era_setTxFilter",
+    "era_startGasReport",
+    "era_warpToBlock",
+    "eth_sendRawTransaction",
+    "eth_sendTransaction",
+    "evm_*",
+    "hardhat_impersonateAccount",
+    "hardhat_mine",
+    "hardhat_set*",
+    "hardhat_stopImpersonatingAccount",
+    "zks_sendRawTransactionWithDetailedOutput",
+];
+
+/// Middleware that rejects calls to the RPC methods that are not allowed, e.g. to disable the cheatcodes
+/// of a node shared by several users.
+///
+/// Methods are matched by their exact name, or by a prefix when the pattern ends with `*` (e.g. `hardhat_*`).
+/// A method is allowed if it matches no `deny` pattern and, unless `allow` is empty, at least one `allow` pattern.
+/// In read-only mode, the [STATE_CHANGING_METHODS] are rejected as well.
+#[derive(Debug, Default, Clone)]
+pub struct RpcFilterMiddleware {
+    allow: Vec<String>,
+    deny: Vec<String>,
+    read_only: bool,
+}
+
+impl RpcFilterMiddleware {
+    pub fn new(allow: Vec<String>, deny: Vec<String>) -> Self {
+        Self {
+            allow,
+            deny,
+            read_only: false,
+        }
+    }
+
+    /// Sets whether the methods changing the state or the configuration of the node are rejected.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Returns true if the methods changing the state or the configuration of the node are rejected.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns true if the method may be called.
+    pub fn is_allowed(&self, method: &str) -> bool {
+        self.rejection(method).is_none()
+    }
+
+    /// Returns the reason the method is rejected, or `None` if it may be called.
+    pub fn rejection(&self, method: &str) -> Option<String> {
+        let matches = |pattern: &String| method_matches(pattern, method);
+        if self.read_only
+            && STATE_CHANGING_METHODS
+                .iter()
+                .any(|pattern| method_matches(pattern, method))
+        {
+            Some(format!(
+                "Method {} is not available in read-only mode",
+                method
+            ))
+        } else if self.deny.iter().any(matches)
+            || (!self.allow.is_empty() && !self.allow.iter().any(matches))
+        {
+            Some(format!("Method {} is disabled", method))
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns true if the method matches the pattern, either exactly or by prefix if it ends with `*`.
+pub fn method_matches(pattern: &str, method: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => method.starts_with(prefix),
+        None => method == pattern,
+    }
+}
+
+impl<M: Metadata> Middleware<M> for RpcFilterMiddleware {
+    type Future = middleware::NoopFuture;
+    type CallFuture = middleware::NoopCallFuture;
+
+    fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X>
+    where
+        F: FnOnce(Call, M) -> X + Send,
+        X: future::Future<Output = Option<Output>> + Send + 'static,
+    {
+        match call {
+            Call::MethodCall(method_call) if !self.is_allowed(&method_call.method) => {
+                tracing::warn!("rejected call to disabled method {}", method_call.method);
+                let error = Error {
+                    code: ErrorCode::MethodNotFound,
+                    message: self.rejection(&method_call.method).unwrap_or_default(),
+                    data: None,
+                };
+                let output = Output::from(Err(error), method_call.id, method_call.jsonrpc);
+                Either::Left(Box::pin(future::ready(Some(output))))
+            }
+            Call::Notification(notification) if !self.is_allowed(&notification.method) => {
+                Either::Left(Box::pin(future::ready(None)))
+            }
+            call => Either::Right(next(call, meta)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_allowed() {
+        let filter = RpcFilterMiddleware::default();
+        assert!(filter.is_allowed("hardhat_setBalance"));
+
+        let filter = RpcFilterMiddleware::new(
+            vec![],
+            vec![
+                "hardhat_setBalance".to_string(),
+                "hardhat_impersonateAccount".to_string(),
+            ],
+        );
+        assert!(!filter.is_allowed("hardhat_setBalance"));
+        assert!(filter.is_allowed("hardhat_mine"));
+        assert!(filter.is_allowed("eth_call"));
+
+        let filter = RpcFilterMiddleware::new(
+            vec!["eth_*".to_string(), "net_version".to_string()],
+            vec!["eth_sendTransaction".to_string()],
+        );
+        assert!(filter.is_allowed("eth_call"));
+        assert!(filter.is_allowed("net_version"));
+        assert!(!filter.is_allowed("eth_sendTransaction"));
+        assert!(!filter.is_allowed("hardhat_setBalance"));
+        assert!(!filter.is_allowed("net_peerCount"));
+    }
+
+    #[test]
+    fn test_read_only_rejects_state_changing_methods() {
+        let filter = RpcFilterMiddleware::default().read_only(true);
+        assert!(filter.is_allowed("eth_call"));
+        assert!(filter.is_allowed("config_getShowCalls"));
+        assert!(filter.is_allowed("hardhat_getAutomine"));
+        assert_eq!(
+            Some("Method eth_sendRawTransaction is not available in read-only mode".to_string()),
+            filter.rejection("eth_sendRawTransaction")
+        );
+        assert!(!filter.is_allowed("hardhat_setBalance"));
+        assert!(!filter.is_allowed("evm_snapshot"));
+        assert!(!filter.is_allowed("config_setShowCalls"));
+        assert!(!filter.is_allowed("era_importAccount"));
+        assert!(!filter.is_allowed("era_startGasReport"));
+        assert!(!filter.is_allowed("era_clearCache"));
+    }
+}
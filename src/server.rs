@@ -0,0 +1,520 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::Context;
+use futures::{channel::oneshot, FutureExt};
+use jsonrpc_core::{MetaIoHandler, RemoteProcedure};
+use jsonrpc_http_server::{hyper, RequestMiddlewareAction};
+use jsonrpc_pubsub::Session;
+use jsonrpc_ws_server::{ws, RequestContext};
+use tracing_subscriber::filter::LevelFilter;
+
+use crate::{
+    auth,
+    chaos::ChaosMiddleware,
+    fork::ForkSource,
+    graphql,
+    logging_middleware::{LoggingMiddleware, Meta},
+    metrics::METRICS,
+    namespaces::{
+        AnvilNamespaceT, ConfigurationApiNamespaceT, DebugNamespaceT, EraNamespaceT,
+        EraPubSubNamespaceT, EthNamespaceT, EthPubSubNamespaceT, EthTestNodeNamespaceT,
+        EvmNamespaceT, HardhatNamespaceT, NetNamespaceT, TraceNamespaceT, Web3NamespaceT,
+        ZksNamespaceT,
+    },
+    node::InMemoryNode,
+    rpc_filter::RpcFilterMiddleware,
+    verification::{self, VERIFICATION_PATH},
+    ws_connections::{WsConfig, WsConnections},
+};
+
+/// Single page block explorer, querying the node via its RPC methods.
+const EXPLORER_HTML: &str = include_str!("data/explorer.html");
+
+/// Custom RPC method served next to the built-in namespaces, e.g. one of the methods of an
+/// [jsonrpc_core::IoDelegate] (which converts into an iterator of these).
+pub type RpcMethod = (String, RemoteProcedure<Meta>);
+
+/// Namespace of the built-in RPC methods, named after the prefix of the methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RpcNamespace {
+    Anvil,
+    Config,
+    Debug,
+    /// The `era_*` methods, along with the `era_subscribe` subscriptions.
+    Era,
+    /// The `eth_*` methods, along with the `eth_subscribe` subscriptions.
+    Eth,
+    Evm,
+    Hardhat,
+    Net,
+    Trace,
+    Web3,
+    Zks,
+}
+
+/// Builds the handler of the RPC methods served by the node. Only the methods of the `namespaces` are
+/// registered, or of all of them if empty. The `extensions` are registered last, so they may also override
+/// the built-in methods. The faults of the chaos mode of the node are injected into the responses. The registered
+/// methods are the ones the metrics record the requests under.
+pub fn build_io_handler<
+    S: std::marker::Sync + std::marker::Send + 'static + ForkSource + std::fmt::Debug + Clone,
+>(
+    node: InMemoryNode<S>,
+    logging: LoggingMiddleware,
+    rpc_filter: RpcFilterMiddleware,
+    namespaces: &[RpcNamespace],
+    extensions: Vec<RpcMethod>,
+) -> MetaIoHandler<Meta, (LoggingMiddleware, RpcFilterMiddleware, ChaosMiddleware)> {
+    let mut io = MetaIoHandler::with_middleware((logging, rpc_filter, node.chaos.clone()));
+    let enabled = |namespace| namespaces.is_empty() || namespaces.contains(&namespace);
+
+    if enabled(RpcNamespace::Anvil) {
+        io.extend_with(AnvilNamespaceT::to_delegate(node.clone()));
+    }
+    if enabled(RpcNamespace::Net) {
+        io.extend_with(NetNamespaceT::to_delegate(node.clone()));
+    }
+    if enabled(RpcNamespace::Web3) {
+        io.extend_with(Web3NamespaceT::to_delegate(node.clone()));
+    }
+    if enabled(RpcNamespace::Config) {
+        io.extend_with(ConfigurationApiNamespaceT::to_delegate(node.clone()));
+    }
+    if enabled(RpcNamespace::Debug) {
+        io.extend_with(DebugNamespaceT::to_delegate(node.clone()));
+    }
+    if enabled(RpcNamespace::Era) {
+        io.extend_with(EraNamespaceT::to_delegate(node.clone()));
+        io.extend_with(EraPubSubNamespaceT::to_delegate(node.clone()));
+    }
+    if enabled(RpcNamespace::Eth) {
+        io.extend_with(EthNamespaceT::to_delegate(node.clone()));
+        io.extend_with(EthPubSubNamespaceT::to_delegate(node.clone()));
+        io.extend_with(EthTestNodeNamespaceT::to_delegate(node.clone()));
+    }
+    if enabled(RpcNamespace::Evm) {
+        io.extend_with(EvmNamespaceT::to_delegate(node.clone()));
+    }
+    if enabled(RpcNamespace::Hardhat) {
+        io.extend_with(HardhatNamespaceT::to_delegate(node.clone()));
+    }
+    if enabled(RpcNamespace::Trace) {
+        io.extend_with(TraceNamespaceT::to_delegate(node.clone()));
+    }
+    if enabled(RpcNamespace::Zks) {
+        io.extend_with(ZksNamespaceT::to_delegate(node));
+    }
+    io.extend_with(extensions);
+    METRICS.register_rpc_methods(io.iter().map(|(name, _)| name));
+    io
+}
+
+/// Handle of the JSON-RPC HTTP server started by [build_json_http].
+pub struct ServerHandle {
+    close_handle: jsonrpc_http_server::CloseHandle,
+    /// The address the server listens at, e.g. to find the port it was bound to if started at port `0`.
+    pub addr: SocketAddr,
+    /// Completes once the server stops.
+    pub stopped: tokio::task::JoinHandle<()>,
+}
+
+impl ServerHandle {
+    /// Stops accepting new connections, without waiting for the requests in flight.
+    pub fn close(&self) {
+        self.close_handle.clone().close();
+    }
+
+    /// Stops accepting new connections and waits until the requests in flight are handled.
+    pub async fn shutdown(self) {
+        self.close_handle.close();
+        let _ = self.stopped.await;
+    }
+}
+
+/// Starts the JSON-RPC HTTP server of the node at `addr` in a separate thread. Besides the RPC methods,
+/// the server exposes the `/metrics`, `/health` and `/ready` endpoints, the contract verification API
+/// at `/contract_verification`, if `explorer` is set the block explorer UI at `/explorer`, and if
+/// `graphql` is set the GraphQL endpoint at `/graphql`.
+///
+/// # Returns
+/// A [ServerHandle] to wait for the server to stop, or to shut it down, or an error if the server couldn't be
+/// started, e.g. as the address is in use.
+#[allow(clippy::too_many_arguments)]
+pub async fn build_json_http<
+    S: std::marker::Sync + std::marker::Send + 'static + ForkSource + std::fmt::Debug + Clone,
+>(
+    addr: SocketAddr,
+    log_level_filter: LevelFilter,
+    log_rpc_requests: bool,
+    rpc_filter: RpcFilterMiddleware,
+    auth_token: Option<String>,
+    node: InMemoryNode<S>,
+    namespaces: Vec<RpcNamespace>,
+    extensions: Vec<RpcMethod>,
+    initialized: Arc<AtomicBool>,
+    explorer: bool,
+    graphql: bool,
+) -> anyhow::Result<ServerHandle> {
+    let (sender, recv) = oneshot::channel::<()>();
+    let (close_sender, close_recv) = oneshot::channel();
+    let health_node = node.clone();
+    let verification_node = node.clone();
+    let graphql_schema = graphql.then(|| graphql::build_schema(node.clone(), rpc_filter.clone()));
+    let read_only = rpc_filter.is_read_only();
+    let io_handler = build_io_handler(
+        node,
+        LoggingMiddleware::new(log_level_filter, log_rpc_requests),
+        rpc_filter,
+        &namespaces,
+        extensions,
+    );
+
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .worker_threads(1)
+            .build()
+            .unwrap();
+
+        let server = jsonrpc_http_server::ServerBuilder::new(io_handler)
+            .threads(1)
+            .event_loop_executor(runtime.handle().clone())
+            .request_middleware(move |request: hyper::Request<hyper::Body>| {
+                match request.uri().path() {
+                    path @ ("/health" | "/ready") => {
+                        health_response(&health_node, initialized.load(Ordering::SeqCst), path)
+                            .into()
+                    }
+                    // the page holds no data, its RPC requests are authenticated
+                    "/explorer" if explorer => jsonrpc_http_server::Response {
+                        code: hyper::StatusCode::OK,
+                        content_type: hyper::header::HeaderValue::from_static(
+                            "text/html; charset=utf-8",
+                        ),
+                        content: EXPLORER_HTML.to_string(),
+                    }
+                    .into(),
+                    // CORS preflight requests are sent by browsers without the credentials
+                    _ if request.method() != hyper::Method::OPTIONS
+                        && !auth_token
+                            .as_deref()
+                            .map_or(true, |token| auth::is_authorized(&request, token)) =>
+                    {
+                        jsonrpc_http_server::Response {
+                            code: hyper::StatusCode::UNAUTHORIZED,
+                            content_type: hyper::header::HeaderValue::from_static(
+                                "application/json",
+                            ),
+                            content: serde_json::json!({ "error": "unauthorized" }).to_string(),
+                        }
+                        .into()
+                    }
+                    "/metrics" => jsonrpc_http_server::Response {
+                        code: hyper::StatusCode::OK,
+                        content_type: hyper::header::HeaderValue::from_static(
+                            "text/plain; version=0.0.4",
+                        ),
+                        content: METRICS.encode(),
+                    }
+                    .into(),
+                    "/graphql" if graphql_schema.is_some() => {
+                        graphql_response(graphql_schema.clone().unwrap(), request)
+                    }
+                    // submitting a verification records the verified contract on the node
+                    path if read_only
+                        && request.method() == hyper::Method::POST
+                        && is_verification_path(path) =>
+                    {
+                        jsonrpc_http_server::Response {
+                            code: hyper::StatusCode::FORBIDDEN,
+                            content_type: hyper::header::HeaderValue::from_static(
+                                "application/json",
+                            ),
+                            content: serde_json::json!({
+                                "error": "contract verification is not available in read-only mode"
+                            })
+                            .to_string(),
+                        }
+                        .into()
+                    }
+                    path if is_verification_path(path) => {
+                        verification_response(verification_node.clone(), request)
+                    }
+                    _ => request.into(),
+                }
+            })
+            .start_http(&addr);
+        let server = match server {
+            Ok(server) => server,
+            Err(err) => {
+                let _ = close_sender.send(Err(err));
+                return;
+            }
+        };
+
+        let _ = close_sender.send(Ok((server.close_handle(), *server.address())));
+        server.wait();
+        let _ = sender;
+    });
+
+    let (close_handle, addr) = close_recv
+        .await
+        .map_err(|_| anyhow::anyhow!("server thread exited before starting"))?
+        .with_context(|| format!("failed starting the server at {}", addr))?;
+    Ok(ServerHandle {
+        close_handle,
+        addr,
+        stopped: tokio::spawn(recv.map(drop)),
+    })
+}
+
+/// Handle of the JSON-RPC WebSocket server started by [build_json_ws].
+pub struct WsServerHandle {
+    close_handle: jsonrpc_ws_server::CloseHandle,
+    /// The address the server listens at, e.g. to find the port it was bound to if started at port `0`.
+    pub addr: SocketAddr,
+}
+
+impl WsServerHandle {
+    /// Stops the server, closing the connections.
+    pub fn close(&self) {
+        self.close_handle.clone().close();
+    }
+}
+
+/// Starts the JSON-RPC WebSocket server of the node at `addr` in a separate thread. It serves the same RPC
+/// methods as [build_json_http], including the `extensions`, plus the `eth_subscribe` and `era_subscribe`
+/// subscriptions. The connections
+/// are limited, kept alive and closed once idle according to the `ws_config`.
+///
+/// # Returns
+/// A [WsServerHandle] to shut the server down, or an error if the server couldn't be started.
+#[allow(clippy::too_many_arguments)]
+pub fn build_json_ws<
+    S: std::marker::Sync + std::marker::Send + 'static + ForkSource + std::fmt::Debug + Clone,
+>(
+    addr: SocketAddr,
+    log_level_filter: LevelFilter,
+    log_rpc_requests: bool,
+    rpc_filter: RpcFilterMiddleware,
+    auth_token: Option<String>,
+    node: InMemoryNode<S>,
+    namespaces: Vec<RpcNamespace>,
+    extensions: Vec<RpcMethod>,
+    ws_config: WsConfig,
+) -> anyhow::Result<WsServerHandle> {
+    let (close_sender, close_recv) = std::sync::mpsc::channel();
+    let io_handler = build_io_handler(
+        node,
+        LoggingMiddleware::new(log_level_filter, log_rpc_requests),
+        rpc_filter,
+        &namespaces,
+        extensions,
+    );
+
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .worker_threads(1)
+            .build()
+            .unwrap();
+
+        let connections = WsConnections::default();
+        let session_connections = connections.clone();
+        let server = jsonrpc_ws_server::ServerBuilder::with_meta_extractor(
+            io_handler,
+            move |context: &RequestContext| Meta {
+                session: Some(Arc::new(Session::new(context.sender()))),
+                activity: Some(session_connections.open(context.session_id, context.out.clone())),
+            },
+        )
+        .event_loop_executor(runtime.handle().clone())
+        .max_connections(ws_config.max_connections)
+        .session_stats(connections.clone())
+        .request_middleware(move |request: &ws::Request| {
+            let authorized = auth_token.as_deref().map_or(true, |token| {
+                auth::is_authorized_header(
+                    request
+                        .header("authorization")
+                        .and_then(|header| std::str::from_utf8(header).ok()),
+                    token,
+                )
+            });
+            (!authorized).then(|| {
+                ws::Response::new(
+                    401,
+                    "Unauthorized",
+                    serde_json::json!({ "error": "unauthorized" })
+                        .to_string()
+                        .into_bytes(),
+                )
+            })
+        })
+        .start(&addr);
+        let server = match server {
+            Ok(server) => server,
+            Err(err) => {
+                let _ = close_sender.send(Err(err));
+                return;
+            }
+        };
+        runtime.spawn(connections.run(ws_config));
+
+        let _ = close_sender.send(Ok(WsServerHandle {
+            close_handle: server.close_handle(),
+            addr: *server.addr(),
+        }));
+        let _ = server.wait();
+    });
+
+    close_recv
+        .recv()
+        .map_err(|_| anyhow::anyhow!("server thread exited before starting"))?
+        .with_context(|| format!("failed starting the WebSocket server at {}", addr))
+}
+
+/// Serves the GraphiQL page on GET requests, and executes the GraphQL requests sent via POST.
+fn graphql_response(
+    schema: graphql::GraphQlSchema,
+    request: hyper::Request<hyper::Body>,
+) -> RequestMiddlewareAction {
+    RequestMiddlewareAction::Respond {
+        should_validate_hosts: true,
+        response: Box::pin(async move {
+            let (content_type, content) = if request.method() == hyper::Method::GET {
+                ("text/html; charset=utf-8", graphql::graphiql_html())
+            } else {
+                let body = hyper::body::to_bytes(request.into_body()).await?;
+                ("application/json", graphql::execute(&schema, &body).await)
+            };
+
+            Ok(hyper::Response::builder()
+                .header(hyper::header::CONTENT_TYPE, content_type)
+                .body(hyper::Body::from(content))
+                .expect("valid response"))
+        }),
+    }
+}
+
+/// Returns true if the path is served by the contract verification API.
+fn is_verification_path(path: &str) -> bool {
+    path == VERIFICATION_PATH || path.starts_with(&format!("{}/", VERIFICATION_PATH))
+}
+
+/// Handles the requests to the contract verification API, see [verification::handle_request].
+fn verification_response<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static>(
+    node: InMemoryNode<S>,
+    request: hyper::Request<hyper::Body>,
+) -> RequestMiddlewareAction {
+    RequestMiddlewareAction::Respond {
+        should_validate_hosts: true,
+        response: Box::pin(async move {
+            let method = request.method().clone();
+            let path = request.uri().path().to_string();
+            let body = hyper::body::to_bytes(request.into_body()).await?;
+            let (code, content) = verification::handle_request(&node, &method, &path, &body);
+
+            Ok(hyper::Response::builder()
+                .status(code)
+                .header(hyper::header::CONTENT_TYPE, "application/json")
+                .body(hyper::Body::from(content))
+                .expect("valid response"))
+        }),
+    }
+}
+
+/// Builds the response of the `/health` (liveness) and `/ready` (readiness) endpoints. Both report the
+/// [crate::health::NodeHealth] of the node, but `/ready` responds with `503` until the node is ready.
+fn health_response<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static>(
+    node: &InMemoryNode<S>,
+    initialized: bool,
+    path: &str,
+) -> jsonrpc_http_server::Response {
+    let (code, content) = match node.health(initialized) {
+        Ok(health) => {
+            let code = if path == "/ready" && !health.is_ready() {
+                hyper::StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                hyper::StatusCode::OK
+            };
+            (code, serde_json::to_string(&health).unwrap_or_default())
+        }
+        Err(err) => (
+            hyper::StatusCode::INTERNAL_SERVER_ERROR,
+            serde_json::json!({ "error": err.to_string() }).to_string(),
+        ),
+    };
+
+    jsonrpc_http_server::Response {
+        code,
+        content_type: hyper::header::HeaderValue::from_static("application/json"),
+        content,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+    use jsonrpc_core::{BoxFuture, IoDelegate, Params, Value};
+
+    #[test]
+    fn test_build_io_handler_registers_extensions() {
+        let mut delegate = IoDelegate::<(), Meta>::new(Arc::new(()));
+        delegate.add_method(
+            "custom_hello",
+            |_: &(), _: Params| -> BoxFuture<jsonrpc_core::Result<Value>> {
+                Box::pin(futures::future::ready(Ok(Value::String("hello".into()))))
+            },
+        );
+        let io = build_io_handler(
+            InMemoryNode::<HttpForkSource>::default(),
+            LoggingMiddleware::new(LevelFilter::INFO, false),
+            RpcFilterMiddleware::default(),
+            &[],
+            delegate.into_iter().collect(),
+        );
+
+        let response = io.handle_request_sync(
+            r#"{"jsonrpc":"2.0","method":"custom_hello","params":[],"id":1}"#,
+            Meta::default(),
+        );
+
+        assert_eq!(
+            Some(r#"{"jsonrpc":"2.0","result":"hello","id":1}"#.to_string()),
+            response
+        );
+    }
+
+    #[test]
+    fn test_build_io_handler_registers_enabled_namespaces() {
+        let io = build_io_handler(
+            InMemoryNode::<HttpForkSource>::default(),
+            LoggingMiddleware::new(LevelFilter::INFO, false),
+            RpcFilterMiddleware::default(),
+            &[RpcNamespace::Eth, RpcNamespace::Zks],
+            vec![],
+        );
+        let call = |method: &str| {
+            let request =
+                serde_json::json!({"jsonrpc": "2.0", "method": method, "params": [], "id": 1});
+            let response = io
+                .handle_request_sync(&request.to_string(), Meta::default())
+                .expect("missing response");
+            serde_json::from_str::<serde_json::Value>(&response).expect("invalid response")
+        };
+
+        assert_eq!(serde_json::json!("0x104"), call("eth_chainId")["result"]);
+        assert_eq!(
+            serde_json::json!(-32601),
+            call("config_getShowCalls")["error"]["code"]
+        );
+        assert_eq!(serde_json::json!(-32601), call("evm_mine")["error"]["code"]);
+    }
+}
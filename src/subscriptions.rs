@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+
+use jsonrpc_pubsub::{typed::Sink, SubscriptionId};
+use serde::{Deserialize, Serialize};
+use zksync_basic_types::{Address, H160, H256, U64};
+use zksync_types::api::{Block, BlockNumber, Log, TransactionVariant};
+use zksync_web3_decl::types::ValueOrArray;
+
+use crate::filters::LogFilter;
+
+/// Kind of the `eth_subscribe` subscription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubscriptionKind {
+    /// Headers of the new blocks.
+    NewHeads,
+    /// Logs of the new blocks matching the [LogsParams]. Logs of the blocks that are removed again
+    /// (e.g. by reverting a snapshot) are sent once more, with `removed` set to `true`.
+    Logs,
+    /// Hashes of the transactions added to the pool.
+    NewPendingTransactions,
+}
+
+/// Filter of the `logs` subscription.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LogsParams {
+    /// Addresses the logs must be emitted by, any if not set.
+    pub address: Option<ValueOrArray<H160>>,
+    /// Alternatives of each topic position, any if not set.
+    pub topics: Option<Vec<Option<ValueOrArray<H256>>>>,
+}
+
+/// Notification sent for a subscription.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum SubscriptionResult {
+    Header(Box<Block<TransactionVariant>>),
+    Log(Box<Log>),
+    TransactionHash(H256),
+}
+
+/// Node-level event sent to the `era_subscribe` subscriptions, e.g. for test frameworks to react to
+/// changes of the node state made by other clients.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum NodeEvent {
+    /// A snapshot was taken via `evm_snapshot`.
+    SnapshotTaken { id: U64 },
+    /// The node was reverted to a snapshot via `evm_revert`.
+    SnapshotReverted { id: U64 },
+    /// The impersonation of an account was started or stopped.
+    ImpersonationToggled {
+        address: Address,
+        impersonated: bool,
+    },
+    /// A runtime option of the node was changed, via the `config_*` methods, `anvil_setRpcUrl` or the configuration file.
+    ConfigChanged {
+        option: String,
+        value: serde_json::Value,
+    },
+}
+
+impl NodeEvent {
+    /// Returns the [NodeEvent::ConfigChanged] event for the option, named like its CLI option.
+    pub fn config_changed(option: &str, value: impl Serialize) -> Self {
+        NodeEvent::ConfigChanged {
+            option: option.to_string(),
+            value: serde_json::to_value(value).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Subscription {
+    NewHeads(Sink<SubscriptionResult>),
+    Logs(Box<LogFilter>, Sink<SubscriptionResult>),
+    NewPendingTransactions(Sink<SubscriptionResult>),
+}
+
+impl Subscription {
+    fn sink(&self) -> &Sink<SubscriptionResult> {
+        match self {
+            Subscription::NewHeads(sink)
+            | Subscription::Logs(_, sink)
+            | Subscription::NewPendingTransactions(sink) => sink,
+        }
+    }
+}
+
+/// Keeps track of the `eth_subscribe` subscriptions, and notifies them of the new blocks, logs and
+/// pending transactions, as well as of the `era_subscribe` subscriptions to the [NodeEvent]s. Unlike
+/// the [crate::filters::EthFilters], the subscriptions are kept when a snapshot is reverted.
+#[derive(Debug, Default, Clone)]
+pub struct EthSubscriptions {
+    id_counter: u64,
+    subscriptions: HashMap<SubscriptionId, Subscription>,
+    node_event_subscriptions: HashMap<SubscriptionId, Sink<NodeEvent>>,
+}
+
+impl EthSubscriptions {
+    /// Returns the id for the next subscription.
+    pub fn next_id(&mut self) -> SubscriptionId {
+        self.id_counter += 1;
+        SubscriptionId::String(format!("{:#x}", self.id_counter))
+    }
+
+    /// Adds a subscription of the given kind, notified via the `sink`.
+    pub fn add(
+        &mut self,
+        id: SubscriptionId,
+        kind: SubscriptionKind,
+        params: LogsParams,
+        sink: Sink<SubscriptionResult>,
+    ) {
+        let subscription = match kind {
+            SubscriptionKind::NewHeads => Subscription::NewHeads(sink),
+            SubscriptionKind::Logs => Subscription::Logs(Box::new(log_filter(params)), sink),
+            SubscriptionKind::NewPendingTransactions => Subscription::NewPendingTransactions(sink),
+        };
+        self.subscriptions.insert(id, subscription);
+    }
+
+    /// Adds a subscription to the [NodeEvent]s, notified via the `sink`.
+    pub fn add_node_events(&mut self, id: SubscriptionId, sink: Sink<NodeEvent>) {
+        self.node_event_subscriptions.insert(id, sink);
+    }
+
+    /// Removes the subscription, returns false if it does not exist.
+    pub fn remove(&mut self, id: &SubscriptionId) -> bool {
+        self.subscriptions.remove(id).is_some()
+    }
+
+    /// Removes the subscription to the [NodeEvent]s, returns false if it does not exist.
+    pub fn remove_node_events(&mut self, id: &SubscriptionId) -> bool {
+        self.node_event_subscriptions.remove(id).is_some()
+    }
+
+    /// Notify the `era_subscribe` subscriptions of a node event.
+    pub fn notify_node_event(&mut self, event: NodeEvent) {
+        self.node_event_subscriptions
+            .retain(|id, sink| match sink.notify(Ok(event.clone())) {
+                Ok(()) => true,
+                Err(err) => {
+                    tracing::debug!("dropping subscription {:?}: {:?}", id, err);
+                    false
+                }
+            });
+    }
+
+    /// Notify the `newHeads` subscriptions of a newly produced block.
+    pub fn notify_new_block(&mut self, block: &Block<TransactionVariant>) {
+        let header = SubscriptionResult::Header(Box::new(Block {
+            transactions: vec![],
+            ..block.clone()
+        }));
+        self.notify(|subscription| {
+            matches!(subscription, Subscription::NewHeads(_)).then(|| header.clone())
+        });
+    }
+
+    /// Notify the `newPendingTransactions` subscriptions of a new pending transaction.
+    pub fn notify_new_pending_transaction(&mut self, hash: H256) {
+        self.notify(|subscription| {
+            matches!(subscription, Subscription::NewPendingTransactions(_))
+                .then_some(SubscriptionResult::TransactionHash(hash))
+        });
+    }
+
+    /// Notify the matching `logs` subscriptions of a new transaction log.
+    pub fn notify_new_log(&mut self, log: &Log, latest_block_number: U64) {
+        self.notify(|subscription| match subscription {
+            Subscription::Logs(filter, _) if filter.matches(log, latest_block_number) => {
+                Some(SubscriptionResult::Log(Box::new(log.clone())))
+            }
+            _ => None,
+        });
+    }
+
+    /// Notify the matching `logs` subscriptions of the logs of removed blocks, in the order they were emitted.
+    pub fn notify_removed_logs<'a>(&mut self, logs: impl IntoIterator<Item = &'a Log>) {
+        if !self
+            .subscriptions
+            .values()
+            .any(|subscription| matches!(subscription, Subscription::Logs(..)))
+        {
+            return;
+        }
+
+        let mut logs = logs
+            .into_iter()
+            .map(|log| Log {
+                removed: Some(true),
+                ..log.clone()
+            })
+            .collect::<Vec<_>>();
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+        for log in logs {
+            let block_number = log.block_number.unwrap_or_default();
+            self.notify_new_log(&log, block_number);
+        }
+    }
+
+    /// Sends the notification returned for each subscription, and drops the ones whose client disconnected.
+    fn notify(&mut self, notification: impl Fn(&Subscription) -> Option<SubscriptionResult>) {
+        self.subscriptions
+            .retain(|id, subscription| match notification(subscription) {
+                Some(result) => match subscription.sink().notify(Ok(result)) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        tracing::debug!("dropping subscription {:?}: {:?}", id, err);
+                        false
+                    }
+                },
+                None => true,
+            });
+    }
+}
+
+fn log_filter(params: LogsParams) -> LogFilter {
+    let mut topics: [Option<HashSet<H256>>; 4] = Default::default();
+    for (i, topic_set) in params
+        .topics
+        .unwrap_or_default()
+        .into_iter()
+        .take(4)
+        .enumerate()
+    {
+        topics[i] = topic_set.map(|topic_set| topic_set.0.into_iter().collect());
+    }
+
+    LogFilter::new(
+        BlockNumber::Earliest,
+        BlockNumber::Latest,
+        params.address.unwrap_or_default().0,
+        topics,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::LogBuilder;
+    use futures::StreamExt;
+    use jsonrpc_pubsub::typed::Subscriber;
+
+    #[tokio::test]
+    async fn test_logs_subscription_receives_matching_and_removed_logs() {
+        let mut subscriptions = EthSubscriptions::default();
+        let (subscriber, _id, mut receiver) = Subscriber::new_test("eth_subscription");
+        let id = subscriptions.next_id();
+        let sink = subscriber
+            .assign_id(id.clone())
+            .expect("failed assigning id");
+        subscriptions.add(
+            id,
+            SubscriptionKind::Logs,
+            LogsParams {
+                address: Some(ValueOrArray(vec![H160::repeat_byte(0x1)])),
+                topics: None,
+            },
+            sink,
+        );
+
+        let matching = LogBuilder::new()
+            .set_address(H160::repeat_byte(0x1))
+            .set_block(U64::from(1))
+            .build();
+        let other = LogBuilder::new()
+            .set_address(H160::repeat_byte(0x2))
+            .set_block(U64::from(1))
+            .build();
+        subscriptions.notify_new_log(&matching, U64::from(1));
+        subscriptions.notify_new_log(&other, U64::from(1));
+        subscriptions.notify_removed_logs(vec![&matching, &other]);
+
+        let notifications = receiver.by_ref().take(2).collect::<Vec<_>>().await;
+        assert!(notifications[0].contains(r#""removed":false"#));
+        assert!(notifications[1].contains(r#""removed":true"#));
+    }
+
+    #[tokio::test]
+    async fn test_node_events_subscription() {
+        let mut subscriptions = EthSubscriptions::default();
+        let (subscriber, _id, receiver) = Subscriber::new_test("era_subscription");
+        let id = subscriptions.next_id();
+        let sink = subscriber
+            .assign_id(id.clone())
+            .expect("failed assigning id");
+        subscriptions.add_node_events(id.clone(), sink);
+
+        subscriptions.notify_node_event(NodeEvent::SnapshotTaken { id: U64::from(1) });
+        subscriptions.notify_node_event(NodeEvent::config_changed("show-calls", "user"));
+        assert!(subscriptions.remove_node_events(&id));
+        subscriptions.notify_node_event(NodeEvent::SnapshotReverted { id: U64::from(1) });
+
+        let notifications = receiver.collect::<Vec<_>>().await;
+        assert_eq!(2, notifications.len());
+        assert!(notifications[0].contains(r#""type":"snapshotTaken""#));
+        assert!(notifications[1].contains(r#""type":"configChanged""#));
+        assert!(notifications[1].contains(r#""value":"user""#));
+    }
+}
@@ -6,8 +6,8 @@
 #![cfg(test)]
 
 use crate::deps::InMemoryStorage;
+use crate::fork::ForkSource;
 use crate::node::{InMemoryNode, TxExecutionInfo};
-use crate::{fork::ForkSource, node::compute_hash};
 
 use ethabi::{ParamType, Token};
 use ethers::contract;
@@ -366,6 +366,7 @@ pub struct TransactionBuilder {
     gas_limit: U256,
     max_fee_per_gas: U256,
     max_priority_fee_per_gas: U256,
+    nonce: Nonce,
 }
 
 impl Default for TransactionBuilder {
@@ -376,6 +377,7 @@ impl Default for TransactionBuilder {
             gas_limit: U256::from(1_000_000),
             max_fee_per_gas: U256::from(250_000_000),
             max_priority_fee_per_gas: U256::from(250_000_000),
+            nonce: Nonce(0),
         }
     }
 }
@@ -405,11 +407,16 @@ impl TransactionBuilder {
         self
     }
 
+    pub fn set_nonce(&mut self, nonce: Nonce) -> &mut Self {
+        self.nonce = nonce;
+        self
+    }
+
     pub fn build(&mut self) -> L2Tx {
         let mut tx = L2Tx::new_signed(
             Address::random(),
             vec![],
-            Nonce(0),
+            self.nonce,
             Fee {
                 gas_limit: self.gas_limit,
                 max_fee_per_gas: self.max_fee_per_gas,
@@ -438,13 +445,26 @@ pub fn apply_tx<T: ForkSource + std::fmt::Debug + Clone>(
         .read()
         .map(|reader| reader.current_miniblock.saturating_add(1))
         .expect("failed getting current batch number");
-    let produced_block_hash = compute_hash(next_miniblock, tx_hash);
 
     let tx = TransactionBuilder::new().set_hash(tx_hash).build();
     node.set_rich_account(tx.common_data.initiator_address);
     node.apply_txs(vec![tx]).expect("failed applying tx");
 
-    (produced_block_hash, U64::from(next_miniblock))
+    (
+        produced_block_hash(node, next_miniblock),
+        U64::from(next_miniblock),
+    )
+}
+
+/// Returns the hash of the block with the given number, produced by the node.
+fn produced_block_hash<T: ForkSource + std::fmt::Debug + Clone>(
+    node: &InMemoryNode<T>,
+    block_number: u64,
+) -> H256 {
+    node.get_inner()
+        .read()
+        .map(|reader| reader.block_hashes[&block_number])
+        .expect("failed getting block hash")
 }
 
 /// Deploys a contract with the given bytecode.
@@ -465,7 +485,6 @@ pub fn deploy_contract<T: ForkSource + std::fmt::Debug + Clone>(
         .read()
         .map(|reader| reader.current_miniblock.saturating_add(1))
         .expect("failed getting current batch number");
-    let produced_block_hash = compute_hash(next_miniblock, tx_hash);
 
     let salt = [0u8; 32];
     let bytecode_hash = eip712::hash_bytecode(&bytecode).expect("invalid bytecode");
@@ -526,7 +545,7 @@ pub fn deploy_contract<T: ForkSource + std::fmt::Debug + Clone>(
     tx.set_input(vec![], tx_hash);
     node.apply_txs(vec![tx]).expect("failed deploying contract");
 
-    produced_block_hash
+    produced_block_hash(node, next_miniblock)
 }
 
 /// Builds transaction logs
@@ -6,15 +6,19 @@ use std::pin::Pin;
 use chrono::{DateTime, Utc};
 use futures::Future;
 use jsonrpc_core::{Error, ErrorCode};
-use multivm::interface::{ExecutionResult, VmExecutionResultAndLogs, VmInterface};
+use multivm::interface::{
+    ExecutionResult, Halt, VmExecutionResultAndLogs, VmInterface, VmRevertReason,
+};
 use multivm::vm_latest::HistoryDisabled;
 use multivm::vm_latest::Vm;
-use zksync_basic_types::{H256, U256, U64};
+use serde::Serialize;
+use zksync_basic_types::{Address, H256, U256, U64};
 use zksync_state::WriteStorage;
 use zksync_types::api::{BlockNumber, DebugCall, DebugCallType};
 use zksync_types::l2::L2Tx;
 use zksync_types::vm_trace::Call;
-use zksync_types::CONTRACT_DEPLOYER_ADDRESS;
+use zksync_types::web3::signing::keccak256;
+use zksync_types::{Bytes, CONTRACT_DEPLOYER_ADDRESS};
 use zksync_utils::u256_to_h256;
 use zksync_utils::{bytecode::hash_bytecode, bytes_to_be_words};
 use zksync_web3_decl::error::Web3Error;
@@ -36,6 +40,11 @@ where
 {
 }
 
+/// Whether the address belongs to the system contracts address space (`0x0000...0000` - `0x0000...ffff`).
+pub fn is_system_contract(address: &Address) -> bool {
+    address.as_bytes()[..18].iter().all(|byte| *byte == 0)
+}
+
 /// Takes long integers and returns them in human friendly format with "_".
 /// For example: 12_334_093
 pub fn to_human_size(input: U256) -> String {
@@ -107,6 +116,7 @@ pub fn mine_empty_blocks<S: std::fmt::Debug + ForkSource>(
             (modified_keys, bytecodes, block_ctx)
         };
 
+        node.archive_head_state();
         for (key, value) in keys.iter() {
             node.fork_storage.set_value(*key, *value);
         }
@@ -125,20 +135,21 @@ pub fn mine_empty_blocks<S: std::fmt::Debug + ForkSource>(
             )
         }
 
-        let block = create_empty_block(
+        let mut block = create_empty_block(
             block_ctx.miniblock,
             block_ctx.timestamp,
             block_ctx.batch,
-            None,
+            Some(node.current_miniblock_hash),
         );
-
-        node.block_hashes.insert(block.number.as_u64(), block.hash);
-        node.blocks.insert(block.hash, block);
+        block.hash = node.new_block_hash(block_ctx.miniblock, H256::zero());
+        block.gas_limit = U256::from(node.block_gas_limit);
+        node.seal_state_root(&mut block);
 
         // leave node state ready for next interaction
         node.current_batch = block_ctx.batch;
         node.current_miniblock = block_ctx.miniblock;
         node.current_timestamp = block_ctx.timestamp;
+        node.publish_block(block, true);
     }
 }
 
@@ -262,19 +273,117 @@ pub fn into_jsrpc_error(err: Web3Error) -> Error {
     }
 }
 
+/// The `data` of the error of a reverted or halted execution (e.g. of an `eth_call`), structured like in hardhat so
+/// that `ethers` can decode the custom errors from the revert data.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionErrorData {
+    pub message: String,
+    /// The revert data, empty if the execution halted.
+    pub data: Bytes,
+    /// The revert reason string, if the execution reverted with one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The reason the VM halted the execution for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub halt_reason: Option<String>,
+    /// The call trace of the failed execution, e.g. of the last attempt of `eth_estimateGas`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<DebugCall>,
+}
+
+impl ExecutionErrorData {
+    pub fn revert(message: String, output: &VmRevertReason) -> Self {
+        Self {
+            message,
+            data: output.encoded_data().into(),
+            reason: match output {
+                VmRevertReason::General { msg, .. } => Some(msg.clone()),
+                _ => None,
+            },
+            halt_reason: None,
+            trace: None,
+        }
+    }
+
+    pub fn halt(message: String, reason: &Halt) -> Self {
+        Self {
+            message,
+            data: Default::default(),
+            reason: None,
+            halt_reason: Some(reason.to_string()),
+            trace: None,
+        }
+    }
+
+    /// Returns the JSON-RPC error, with the same code and message as [Web3Error::SubmitTransactionError].
+    pub fn into_jsrpc_error(self) -> Error {
+        Error {
+            code: 3.into(),
+            message: self.message.clone(),
+            data: serde_json::to_value(self).ok(),
+        }
+    }
+}
+
 pub fn internal_error(method_name: &'static str, error: impl fmt::Display) -> Web3Error {
     tracing::error!("Internal error in method {method_name}: {error}");
     Web3Error::InternalError
 }
 
+/// Computes the address of a contract deployed via `CREATE2` on zkSync Era.
+///
+/// The address is derived as
+/// `keccak256(keccak256("zksyncCreate2") ++ sender ++ salt ++ bytecode_hash ++ keccak256(input))[12..]`.
+pub fn compute_create2_address(
+    sender: Address,
+    bytecode_hash: H256,
+    salt: H256,
+    input: &[u8],
+) -> Address {
+    let prefix = keccak256(b"zksyncCreate2");
+    let mut bytes = Vec::with_capacity(5 * 32);
+    bytes.extend_from_slice(&prefix);
+    bytes.extend_from_slice(H256::from(sender).as_bytes());
+    bytes.extend_from_slice(salt.as_bytes());
+    bytes.extend_from_slice(bytecode_hash.as_bytes());
+    bytes.extend_from_slice(&keccak256(input));
+
+    Address::from_slice(&keccak256(&bytes)[12..])
+}
+
 #[cfg(test)]
 mod tests {
+    use std::str::FromStr;
+
     use zksync_basic_types::{H256, U256};
 
     use crate::{http_fork_source::HttpForkSource, node::InMemoryNode, testing};
 
     use super::*;
 
+    #[test]
+    fn test_execution_error_data_holds_revert_data_and_reason() {
+        let output = VmRevertReason::General {
+            msg: "boom".to_string(),
+            data: vec![0x08, 0xc3, 0x79, 0xa0],
+        };
+
+        let err = ExecutionErrorData::revert("execution reverted: boom".to_string(), &output)
+            .into_jsrpc_error();
+
+        assert_eq!(ErrorCode::ServerError(3), err.code);
+        assert_eq!("execution reverted: boom", err.message);
+        assert_eq!(
+            Some(serde_json::json!({
+                "message": "execution reverted: boom",
+                "data": "0x08c379a0",
+                "reason": "boom",
+            })),
+            err.data
+        );
+    }
+
     #[test]
     fn test_utc_datetime_from_epoch_ms() {
         let actual = utc_datetime_from_epoch_ms(1623931200000);
@@ -287,6 +396,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_compute_create2_address_matches_known_vectors() {
+        let sender = Address::repeat_byte(0x1);
+        let bytecode_hash = H256::repeat_byte(0x2);
+
+        assert_eq!(
+            Address::from_str("0x341887e8c4c9cf6ff5ed4680d0451140a94116fb").unwrap(),
+            compute_create2_address(sender, bytecode_hash, H256::zero(), &[])
+        );
+        assert_eq!(
+            Address::from_str("0x5358e3cf39054de9c4c4d46af48b1f9379504a4b").unwrap(),
+            compute_create2_address(sender, bytecode_hash, H256::repeat_byte(0x3), &[0x1])
+        );
+    }
+
     #[test]
     fn test_human_sizes() {
         assert_eq!("123", to_human_size(U256::from(123u64)));
@@ -467,6 +591,37 @@ mod tests {
             assert_eq!(U256::from(2002), tx_block_3.timestamp);
         }
     }
+
+    #[test]
+    fn test_mine_empty_blocks_links_archives_and_notifies_the_blocks() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let inner = node.get_inner();
+        let mut writer = inner.write().expect("failed acquiring write lock");
+        let genesis_hash = writer.current_miniblock_hash;
+        let filter_id = writer
+            .filters
+            .add_block_filter()
+            .expect("failed adding block filter");
+
+        mine_empty_blocks(&mut writer, 2, 1000);
+
+        let (hash_1, hash_2) = (writer.block_hashes[&1], writer.block_hashes[&2]);
+        assert_eq!(hash_2, writer.current_miniblock_hash);
+        assert_eq!(genesis_hash, writer.blocks[&hash_1].parent_hash);
+        assert_eq!(hash_1, writer.blocks[&hash_2].parent_hash);
+        assert!(writer.previous_states.contains_key(&genesis_hash));
+        assert!(writer.previous_states.contains_key(&hash_1));
+        match writer
+            .filters
+            .get_new_changes(filter_id)
+            .expect("failed getting filter changes")
+        {
+            zksync_web3_decl::types::FilterChanges::Hashes(hashes) => {
+                assert_eq!(vec![hash_1, hash_2], hashes)
+            }
+            changes => panic!("unexpected filter changes: {:?}", changes),
+        }
+    }
 }
 
 /// Converts `h256` value as BE into the u64
@@ -0,0 +1,559 @@
+//! Local stub of the zkSync contract verification API, served at `/contract_verification`.
+//!
+//! Submitted sources are not compiled: a request is marked as successful as soon as the contract has code
+//! deployed, so that deployment pipelines verifying their contracts as a post-deploy step run unchanged
+//! against the node. The sources of the verified contracts are used to locate the reverts in the traces
+//! and the RPC errors.
+use std::fmt;
+
+use anyhow::anyhow;
+use ethabi::ParamType;
+use jsonrpc_http_server::hyper;
+use serde::{Deserialize, Serialize};
+use zksync_basic_types::{web3::signing::keccak256, Address};
+use zksync_state::ReadStorage;
+use zksync_types::{get_code_key, Bytes};
+
+use crate::{fork::ForkSource, node::InMemoryNode};
+
+/// Path of the contract verification API.
+pub const VERIFICATION_PATH: &str = "/contract_verification";
+
+/// Verification request, in the format of `POST /contract_verification`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationRequest {
+    pub contract_address: Address,
+    pub contract_name: String,
+    /// Either the flattened source, or the standard JSON input of the compiler.
+    pub source_code: serde_json::Value,
+    #[serde(default)]
+    pub code_format: Option<String>,
+    #[serde(default)]
+    pub compiler_zksolc_version: Option<String>,
+    #[serde(default)]
+    pub compiler_solc_version: Option<String>,
+    #[serde(default)]
+    pub optimization_used: Option<bool>,
+    #[serde(default)]
+    pub constructor_arguments: Option<Bytes>,
+}
+
+/// Status of a verification request, in the format of `GET /contract_verification/{id}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationStatus {
+    /// Either `successful` or `failed`.
+    pub status: String,
+    pub error: Option<String>,
+    pub compilation_errors: Option<Vec<String>>,
+}
+
+/// The verification requests submitted to the node, kept across snapshots. The id of a request is its
+/// index plus one.
+#[derive(Debug, Clone, Default)]
+pub struct VerifiedContracts {
+    requests: Vec<(VerificationRequest, VerificationStatus)>,
+}
+
+impl VerifiedContracts {
+    /// Returns the status of the request with the given id, if any.
+    pub fn status(&self, id: usize) -> Option<&VerificationStatus> {
+        id.checked_sub(1)
+            .and_then(|index| self.requests.get(index))
+            .map(|(_, status)| status)
+    }
+
+    /// Returns the latest successful request for the contract at `address`, if any.
+    pub fn get(&self, address: Address) -> Option<&VerificationRequest> {
+        self.successful()
+            .find(|request| request.contract_address == address)
+    }
+
+    /// Locates the revert with the given revert `data` in the verified sources, looking at the sources of
+    /// the reverting `contract` first. EraVM bytecode carries no source maps, so the location is the line
+    /// of the `Error(string)` message literal, or of the `revert` of the custom error (falling back to its
+    /// declaration).
+    pub fn revert_location(
+        &self,
+        contract: Option<Address>,
+        data: &[u8],
+    ) -> Option<SourceLocation> {
+        let (selector, args) = (data.get(..4)?, &data[4..]);
+        let sources = contract
+            .and_then(|address| self.get(address))
+            .into_iter()
+            .chain(self.successful())
+            .flat_map(sources)
+            .collect::<Vec<_>>();
+
+        if selector == ERROR_STRING_SELECTOR {
+            let message = ethabi::decode(&[ParamType::String], args)
+                .ok()?
+                .pop()?
+                .into_string()?;
+            if message.is_empty() {
+                return None;
+            }
+            return find_line(&sources, |line| {
+                line.contains(&format!("\"{}\"", message))
+                    || line.contains(&format!("'{}'", message))
+            });
+        }
+
+        let name = sources
+            .iter()
+            .flat_map(|(_, content)| content.lines())
+            .filter_map(custom_error_signature)
+            .find(|(_, signature)| keccak256(signature.as_bytes())[..4] == *selector)?
+            .0;
+        find_line(&sources, |line| line.contains(&format!("revert {}(", name))).or_else(|| {
+            find_line(&sources, |line| {
+                custom_error_signature(line).map_or(false, |(declared, _)| declared == name)
+            })
+        })
+    }
+
+    /// Decodes the `output` of a call to the verified `contract` with the given `input`, using the return
+    /// types of the function declared with the called selector. Only the declarations on a single line with
+    /// elementary return types are recognized.
+    pub fn decode_output(&self, contract: Address, input: &[u8], output: &[u8]) -> Option<String> {
+        let selector = input.get(..4)?;
+        let request = self.get(contract)?;
+        let returns = sources(request)
+            .into_iter()
+            .flat_map(|(_, content)| content.lines())
+            .filter_map(function_signature)
+            .find(|(signature, _)| keccak256(signature.as_bytes())[..4] == *selector)?
+            .1;
+        let types = returns
+            .iter()
+            .map(|ty| ethabi::param_type::Reader::read(ty).ok())
+            .collect::<Option<Vec<_>>>()?;
+        let tokens = ethabi::decode(&types, output).ok()?;
+        Some(
+            tokens
+                .iter()
+                .map(format_token)
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    }
+
+    fn insert(&mut self, request: VerificationRequest, status: VerificationStatus) -> usize {
+        self.requests.push((request, status));
+        self.requests.len()
+    }
+
+    fn successful(&self) -> impl Iterator<Item = &VerificationRequest> {
+        self.requests
+            .iter()
+            .rev()
+            .filter(|(_, status)| status.status == "successful")
+            .map(|(request, _)| request)
+    }
+}
+
+/// Selector of the `Error(string)` revert data of `require` and `revert` with a message.
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Location in the sources of a verified contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: String,
+    /// Line number, starting at 1.
+    pub line: usize,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Returns the source files of the request, as (path, content) pairs.
+fn sources(request: &VerificationRequest) -> Vec<(&str, &str)> {
+    match &request.source_code {
+        serde_json::Value::String(content) => {
+            let file = request.contract_name.split(':').next().unwrap_or_default();
+            vec![(file, content.as_str())]
+        }
+        serde_json::Value::Object(input) => input
+            .get("sources")
+            .and_then(|sources| sources.as_object())
+            .map(|sources| {
+                sources
+                    .iter()
+                    .filter_map(|(path, source)| {
+                        Some((path.as_str(), source.get("content")?.as_str()?))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+/// Returns the first line of the sources matching the predicate.
+fn find_line(sources: &[(&str, &str)], predicate: impl Fn(&str) -> bool) -> Option<SourceLocation> {
+    sources.iter().find_map(|(file, content)| {
+        content
+            .lines()
+            .position(&predicate)
+            .map(|index| SourceLocation {
+                file: file.to_string(),
+                line: index + 1,
+            })
+    })
+}
+
+/// Parses a custom error declaration (`error Name(type name, ...);`) on a single line, returning the
+/// name and canonical signature of the error.
+fn custom_error_signature(line: &str) -> Option<(&str, String)> {
+    let declaration = line.trim_start().strip_prefix("error ")?;
+    let (name, rest) = declaration.split_once('(')?;
+    let (params, _) = rest.split_once(')')?;
+    let name = name.trim();
+    Some((
+        name,
+        format!("{}({})", name, canonical_types(params).join(",")),
+    ))
+}
+
+/// Parses a function declaration (`function name(type name, ...) ... returns (type name, ...)`) on a single
+/// line, returning the canonical signature and the return types of the function.
+fn function_signature(line: &str) -> Option<(String, Vec<String>)> {
+    let declaration = line.trim_start().strip_prefix("function ")?;
+    let (name, rest) = declaration.split_once('(')?;
+    let (params, rest) = rest.split_once(')')?;
+    let returns = match rest.split_once("returns") {
+        Some((_, returns)) => {
+            let (_, returns) = returns.split_once('(')?;
+            canonical_types(returns.split_once(')')?.0)
+        }
+        None => vec![],
+    };
+    Some((
+        format!("{}({})", name.trim(), canonical_types(params).join(",")),
+        returns,
+    ))
+}
+
+/// Returns the canonical types of the comma-separated parameters, dropping their names and data locations.
+fn canonical_types(params: &str) -> Vec<String> {
+    params
+        .split(',')
+        .filter_map(|param| param.split_whitespace().next())
+        .map(|ty| {
+            for (alias, canonical) in [("uint", "uint256"), ("int", "int256")] {
+                if let Some(suffix) = ty.strip_prefix(alias) {
+                    if !suffix.starts_with(|c: char| c.is_ascii_digit()) {
+                        return format!("{}{}", canonical, suffix);
+                    }
+                }
+            }
+            ty.to_string()
+        })
+        .collect()
+}
+
+/// Formats a decoded value, with the addresses and bytes in hex.
+fn format_token(token: &ethabi::Token) -> String {
+    match token {
+        ethabi::Token::Address(address) => format!("{:#x}", address),
+        ethabi::Token::Bytes(bytes) | ethabi::Token::FixedBytes(bytes) => {
+            format!("0x{}", hex::encode(bytes))
+        }
+        ethabi::Token::String(value) => format!("{:?}", value),
+        ethabi::Token::Array(tokens) | ethabi::Token::FixedArray(tokens) => format!(
+            "[{}]",
+            tokens
+                .iter()
+                .map(format_token)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        token => token.to_string(),
+    }
+}
+
+impl<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static> InMemoryNode<S> {
+    /// Stores the verification request, which succeeds if the contract has code deployed.
+    ///
+    /// # Returns
+    /// The id of the request.
+    pub fn submit_verification(&self, request: VerificationRequest) -> anyhow::Result<usize> {
+        let mut writer = self
+            .get_inner()
+            .write()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))?;
+        let code_hash = writer
+            .fork_storage
+            .read_value(&get_code_key(&request.contract_address));
+        let status = if code_hash.is_zero() {
+            VerificationStatus {
+                status: "failed".to_string(),
+                error: Some(format!(
+                    "There is no deployed contract on address {:#x}",
+                    request.contract_address
+                )),
+                compilation_errors: None,
+            }
+        } else {
+            tracing::info!(
+                "✅ Verified contract {} at {:#x}",
+                request.contract_name,
+                request.contract_address
+            );
+            VerificationStatus {
+                status: "successful".to_string(),
+                error: None,
+                compilation_errors: None,
+            }
+        };
+
+        Ok(writer.verified_contracts.insert(request, status))
+    }
+
+    /// Returns the status of the verification request with the given id, if any.
+    pub fn verification_status(&self, id: usize) -> anyhow::Result<Option<VerificationStatus>> {
+        self.get_inner()
+            .read()
+            .map_err(|err| anyhow!("failed acquiring lock: {:?}", err))
+            .map(|reader| reader.verified_contracts.status(id).cloned())
+    }
+}
+
+/// Handles a request to the verification API at `path`: `POST /contract_verification` submits a request
+/// and responds with its id, `GET /contract_verification/{id}` responds with its status.
+///
+/// # Returns
+/// The status code and the JSON content of the response.
+pub fn handle_request<S: ForkSource + std::fmt::Debug + Clone + Send + Sync + 'static>(
+    node: &InMemoryNode<S>,
+    method: &hyper::Method,
+    path: &str,
+    body: &[u8],
+) -> (hyper::StatusCode, String) {
+    let id = path
+        .strip_prefix(VERIFICATION_PATH)
+        .and_then(|id| id.strip_prefix('/'));
+    let result = match (method, id) {
+        (&hyper::Method::POST, None) => match serde_json::from_slice(body) {
+            Ok(request) => node
+                .submit_verification(request)
+                .map(|id| (hyper::StatusCode::OK, serde_json::json!(id))),
+            Err(err) => Ok((
+                hyper::StatusCode::BAD_REQUEST,
+                serde_json::json!(format!("invalid verification request: {}", err)),
+            )),
+        },
+        (&hyper::Method::GET, Some(id)) => match id.parse() {
+            Ok(id) => node.verification_status(id).map(|status| match status {
+                Some(status) => (hyper::StatusCode::OK, serde_json::json!(status)),
+                None => (
+                    hyper::StatusCode::NOT_FOUND,
+                    serde_json::json!("verification request not found"),
+                ),
+            }),
+            Err(_) => Ok((
+                hyper::StatusCode::BAD_REQUEST,
+                serde_json::json!(format!("invalid verification request id `{}`", id)),
+            )),
+        },
+        _ => Ok((
+            hyper::StatusCode::METHOD_NOT_ALLOWED,
+            serde_json::json!("method not allowed"),
+        )),
+    };
+
+    match result {
+        Ok((code, content)) => (code, content.to_string()),
+        Err(err) => (
+            hyper::StatusCode::INTERNAL_SERVER_ERROR,
+            serde_json::json!(err.to_string()).to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zksync_basic_types::H160;
+
+    use super::*;
+    use crate::http_fork_source::HttpForkSource;
+
+    fn submit(
+        node: &InMemoryNode<HttpForkSource>,
+        address: Address,
+    ) -> (hyper::StatusCode, String) {
+        let body = serde_json::json!({
+            "contractAddress": format!("{:#x}", address),
+            "contractName": "contracts/Greeter.sol:Greeter",
+            "sourceCode": "contract Greeter {}",
+            "codeFormat": "solidity-single-file",
+            "compilerZksolcVersion": "v1.3.21",
+            "compilerSolcVersion": "0.8.20",
+            "optimizationUsed": true,
+        });
+        handle_request(
+            node,
+            &hyper::Method::POST,
+            VERIFICATION_PATH,
+            body.to_string().as_bytes(),
+        )
+    }
+
+    #[test]
+    fn test_verification_succeeds_for_deployed_contract() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+        let address = H160::repeat_byte(0x1);
+        node.set_code(address, vec![0xab; 32])
+            .expect("failed setting code");
+
+        let (code, id) = submit(&node, address);
+        assert_eq!(hyper::StatusCode::OK, code);
+        assert_eq!("1", id);
+
+        let (code, status) =
+            handle_request(&node, &hyper::Method::GET, "/contract_verification/1", &[]);
+        assert_eq!(hyper::StatusCode::OK, code);
+        assert_eq!(
+            serde_json::json!({"status": "successful", "error": null, "compilationErrors": null}),
+            serde_json::from_str::<serde_json::Value>(&status).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_verification_fails_without_deployed_contract() {
+        let node = InMemoryNode::<HttpForkSource>::default();
+
+        let (_, id) = submit(&node, H160::repeat_byte(0x1));
+        let status = node
+            .verification_status(id.parse().unwrap())
+            .expect("failed getting status")
+            .expect("missing status");
+
+        assert_eq!("failed", status.status);
+        assert!(status.error.is_some());
+    }
+
+    #[test]
+    fn test_revert_location_in_verified_sources() {
+        let address = H160::repeat_byte(0x1);
+        let mut verified_contracts = VerifiedContracts::default();
+        verified_contracts.insert(
+            VerificationRequest {
+                contract_address: address,
+                contract_name: "contracts/Greeter.sol:Greeter".to_string(),
+                source_code: serde_json::json!({
+                    "language": "Solidity",
+                    "sources": {
+                        "contracts/Greeter.sol": {
+                            "content": [
+                                "contract Greeter {",
+                                "    error Unauthorized(address caller, uint amount);",
+                                "    function greet(uint value) public {",
+                                "        require(value > 0, \"Value is zero\");",
+                                "        revert Unauthorized(msg.sender, value);",
+                                "    }",
+                                "}",
+                            ].join("\n"),
+                        },
+                    },
+                }),
+                code_format: Some("solidity-standard-json-input".to_string()),
+                compiler_zksolc_version: None,
+                compiler_solc_version: None,
+                optimization_used: None,
+                constructor_arguments: None,
+            },
+            VerificationStatus {
+                status: "successful".to_string(),
+                error: None,
+                compilation_errors: None,
+            },
+        );
+
+        let error_string = [
+            ERROR_STRING_SELECTOR.to_vec(),
+            ethabi::encode(&[ethabi::Token::String("Value is zero".to_string())]),
+        ]
+        .concat();
+        assert_eq!(
+            Some("contracts/Greeter.sol:4".to_string()),
+            verified_contracts
+                .revert_location(Some(address), &error_string)
+                .map(|location| location.to_string())
+        );
+
+        let custom_error = [
+            keccak256(b"Unauthorized(address,uint256)")[..4].to_vec(),
+            ethabi::encode(&[
+                ethabi::Token::Address(ethabi::Address::repeat_byte(0x1)),
+                ethabi::Token::Uint(1.into()),
+            ]),
+        ]
+        .concat();
+        assert_eq!(
+            Some("contracts/Greeter.sol:5".to_string()),
+            verified_contracts
+                .revert_location(None, &custom_error)
+                .map(|location| location.to_string())
+        );
+        assert_eq!(
+            None,
+            verified_contracts.revert_location(None, &[0xde, 0xad])
+        );
+    }
+
+    #[test]
+    fn test_decode_output_of_verified_function() {
+        let address = H160::repeat_byte(0x1);
+        let mut verified_contracts = VerifiedContracts::default();
+        verified_contracts.insert(
+            VerificationRequest {
+                contract_address: address,
+                contract_name: "Token.sol:Token".to_string(),
+                source_code: serde_json::Value::String(
+                    [
+                        "contract Token {",
+                        "    function balanceOf(address account) public view returns (uint balance, address owner) {",
+                        "        return (42, account);",
+                        "    }",
+                        "}",
+                    ]
+                    .join("\n"),
+                ),
+                code_format: None,
+                compiler_zksolc_version: None,
+                compiler_solc_version: None,
+                optimization_used: None,
+                constructor_arguments: None,
+            },
+            VerificationStatus {
+                status: "successful".to_string(),
+                error: None,
+                compilation_errors: None,
+            },
+        );
+
+        let input = keccak256(b"balanceOf(address)")[..4].to_vec();
+        let output = ethabi::encode(&[
+            ethabi::Token::Uint(42.into()),
+            ethabi::Token::Address(ethabi::Address::repeat_byte(0x2)),
+        ]);
+        assert_eq!(
+            Some(format!("42, {:#x}", H160::repeat_byte(0x2))),
+            verified_contracts.decode_output(address, &input, &output)
+        );
+        assert_eq!(
+            None,
+            verified_contracts.decode_output(address, &[0xde, 0xad, 0xbe, 0xef], &output)
+        );
+        assert_eq!(
+            None,
+            verified_contracts.decode_output(H160::repeat_byte(0x2), &input, &output)
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Limits and keepalive of the connections of the WebSocket server, so that the long-running clients, e.g. dashboards,
+//! are not silently disconnected by proxies, and the resources of the abandoned connections are released.
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use jsonrpc_ws_server::{ws::CloseCode, Sender, SessionId, SessionStats};
+
+/// Default maximum number of the WebSocket connections, the same as the one of the WebSocket server.
+pub const DEFAULT_WS_MAX_CONNECTIONS: usize = 100;
+
+/// JSON-RPC notification keeping the quiet connections alive. The clients ignore it, as it is not a subscription
+/// notification.
+const KEEPALIVE_MESSAGE: &str = r#"{"jsonrpc":"2.0","method":"era_keepalive","params":[]}"#;
+
+/// Limits and keepalive of the WebSocket connections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WsConfig {
+    /// Maximum number of open connections, the new ones are refused beyond it.
+    pub max_connections: usize,
+    /// Interval of the keepalive messages sent to the connections. The pings of the clients are answered with pongs
+    /// regardless.
+    pub keepalive_interval: Option<Duration>,
+    /// Time after which the connections that sent no request are closed.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for WsConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: DEFAULT_WS_MAX_CONNECTIONS,
+            keepalive_interval: None,
+            idle_timeout: None,
+        }
+    }
+}
+
+/// Time of the latest request of a WebSocket connection, for its idle timeout.
+#[derive(Debug, Clone)]
+pub struct WsActivity(Arc<Mutex<Instant>>);
+
+impl WsActivity {
+    fn new() -> Self {
+        Self(Arc::new(Mutex::new(Instant::now())))
+    }
+
+    /// Records a request of the connection.
+    pub fn touch(&self) {
+        if let Ok(mut last_request) = self.0.lock() {
+            *last_request = Instant::now();
+        }
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.0
+            .lock()
+            .map(|last_request| last_request.elapsed())
+            .unwrap_or_default()
+    }
+}
+
+struct WsConnection {
+    out: Sender,
+    activity: WsActivity,
+}
+
+/// The open connections of the WebSocket server, registered when the server extracts the metadata of their session,
+/// and removed once closed.
+#[derive(Clone, Default)]
+pub struct WsConnections {
+    connections: Arc<Mutex<HashMap<SessionId, WsConnection>>>,
+}
+
+impl WsConnections {
+    /// Registers the connection of the session.
+    ///
+    /// # Returns
+    /// The [WsActivity] of the connection, to be touched on each of its requests.
+    pub fn open(&self, id: SessionId, out: Sender) -> WsActivity {
+        let activity = WsActivity::new();
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.insert(
+                id,
+                WsConnection {
+                    out,
+                    activity: activity.clone(),
+                },
+            );
+        }
+        activity
+    }
+
+    /// Sends the keepalive message to the connections if `keepalive` is set, and closes the connections idle for
+    /// longer than the `idle_timeout`. The connections that can't be sent to anymore are dropped.
+    fn check(&self, keepalive: bool, idle_timeout: Option<Duration>) {
+        let mut connections = match self.connections.lock() {
+            Ok(connections) => connections,
+            Err(_) => return,
+        };
+        connections.retain(|id, connection| {
+            if idle_timeout.map_or(false, |timeout| connection.activity.idle_for() > timeout) {
+                tracing::info!("Closing idle WebSocket connection #{}", id);
+                let _ = connection.out.close(CloseCode::Away);
+                return false;
+            }
+            !keepalive || connection.out.send(KEEPALIVE_MESSAGE).is_ok()
+        });
+    }
+
+    /// Sends the keepalive messages and closes the idle connections according to the [WsConfig], until the server
+    /// stops.
+    pub async fn run(self, config: WsConfig) {
+        if config.keepalive_interval.is_none() && config.idle_timeout.is_none() {
+            return;
+        }
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        let mut last_keepalive = Instant::now();
+        loop {
+            interval.tick().await;
+            let keepalive = config
+                .keepalive_interval
+                .map_or(false, |interval| last_keepalive.elapsed() >= interval);
+            if keepalive {
+                last_keepalive = Instant::now();
+            }
+            self.check(keepalive, config.idle_timeout);
+        }
+    }
+}
+
+impl SessionStats for WsConnections {
+    fn open_session(&self, _id: SessionId) {}
+
+    fn close_session(&self, id: SessionId) {
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.remove(&id);
+        }
+    }
+}